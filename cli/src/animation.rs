@@ -0,0 +1,212 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use image::{
+    codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
+        png::PngDecoder,
+    },
+    AnimationDecoder, Frame, ImageFormat,
+};
+
+/// The formats whose animations are filtered frame by frame, rather than only their first frame.
+pub const ANIMATED_FORMATS: [ImageFormat; 2] = [ImageFormat::Gif, ImageFormat::Png];
+
+/// The frames of an animated gif or png, each covering the whole canvas, disposal and blending already applied.
+pub struct Animation {
+    pub frames: Vec<Frame>,
+    /// How many times the animation plays, 0 looping forever.
+    pub plays: u32,
+}
+
+/// Decodes the frames of an animated gif or png, `None` for still images and for the other formats.
+pub fn decode_animation(bytes: &[u8]) -> Result<Option<Animation>> {
+    let (frames, plays) = match image::guess_format(bytes) {
+        Ok(ImageFormat::Gif) => {
+            let frames = GifDecoder::new(Cursor::new(bytes))?
+                .into_frames()
+                .collect_frames()?;
+            (frames, gif_plays(bytes))
+        }
+        Ok(ImageFormat::Png) => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng() {
+                return Ok(None);
+            }
+            let frames = decoder.apng().into_frames().collect_frames()?;
+            (frames, apng_plays(bytes)?)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok((frames.len() > 1).then_some(Animation { frames, plays }))
+}
+
+/// Encodes `animation` as a gif, or as an animated png.
+///
+/// # Errors
+///
+/// If `format` isn't one of [`ANIMATED_FORMATS`], or if encoding fails.
+pub fn encode_animation(animation: Animation, format: ImageFormat) -> Result<Vec<u8>> {
+    match format {
+        ImageFormat::Gif => encode_gif(animation),
+        ImageFormat::Png => encode_apng(animation),
+        format => anyhow::bail!("Animations can't be written as {format:?}"),
+    }
+}
+
+/// The plays of a gif, from the loop count of its NETSCAPE2.0 extension, which repeats the animation that many
+/// times after the first play. Without the extension, it plays once.
+fn gif_plays(bytes: &[u8]) -> u32 {
+    const LOOP_EXTENSION: &[u8] = b"NETSCAPE2.0\x03\x01";
+    bytes
+        .windows(LOOP_EXTENSION.len() + 2)
+        .find(|window| window.starts_with(LOOP_EXTENSION))
+        .map_or(1, |window| {
+            match u16::from_le_bytes([
+                window[LOOP_EXTENSION.len()],
+                window[LOOP_EXTENSION.len() + 1],
+            ]) {
+                0 => 0,
+                loops => u32::from(loops) + 1,
+            }
+        })
+}
+
+fn apng_plays(bytes: &[u8]) -> Result<u32> {
+    let reader = png::Decoder::new(Cursor::new(bytes)).read_info()?;
+    Ok(reader
+        .info()
+        .animation_control
+        .map_or(1, |control| control.num_plays))
+}
+
+fn encode_gif(animation: Animation) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    {
+        // Much faster than the default of 1 when a frame has more than 256 colors, for a slightly worse palette.
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+        match animation.plays {
+            0 => encoder.set_repeat(Repeat::Infinite)?,
+            1 => {}
+            plays => {
+                encoder.set_repeat(Repeat::Finite(u16::try_from(plays - 1).unwrap_or(u16::MAX)))?
+            }
+        }
+        // Each frame covers the whole canvas, and is disposed of before the next one is drawn.
+        encoder.encode_frames(animation.frames)?;
+    }
+    Ok(bytes)
+}
+
+fn encode_apng(animation: Animation) -> Result<Vec<u8>> {
+    let (width, height) = animation.frames[0].buffer().dimensions();
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(animation.frames.len() as u32, animation.plays)?;
+    let mut writer = encoder.write_header()?;
+    for frame in &animation.frames {
+        let (numerator, denominator) = apng_delay(frame);
+        writer.set_frame_delay(numerator, denominator)?;
+        // Each frame covers the whole canvas, replacing the previous one.
+        writer.set_blend_op(png::BlendOp::Source)?;
+        writer.set_dispose_op(png::DisposeOp::None)?;
+        writer.write_image_data(frame.buffer().as_raw())?;
+    }
+    writer.finish()?;
+    Ok(bytes)
+}
+
+/// The delay of `frame` as a fraction of a second, in milliseconds unless it doesn't fit.
+fn apng_delay(frame: &Frame) -> (u16, u16) {
+    let (numerator, denominator) = frame.delay().numer_denom_ms();
+    let ms = (f64::from(numerator) / f64::from(denominator)).round();
+    if ms <= f64::from(u16::MAX) {
+        (ms as u16, 1000)
+    } else {
+        ((ms / 1000.0).round().min(f64::from(u16::MAX)) as u16, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Delay, Frame, ImageFormat, Rgba, RgbaImage};
+
+    use super::{decode_animation, encode_animation, gif_plays, Animation};
+
+    fn animation(plays: u32) -> Animation {
+        Animation {
+            frames: [(255, 0, 40), (0, 255, 80), (0, 0, 120)]
+                .into_iter()
+                .map(|(red, green, delay)| {
+                    Frame::from_parts(
+                        RgbaImage::from_pixel(3, 2, Rgba([red, green, 0, 255])),
+                        0,
+                        0,
+                        Delay::from_numer_denom_ms(delay, 1),
+                    )
+                })
+                .collect(),
+            plays,
+        }
+    }
+
+    fn delays(animation: &Animation) -> Vec<(u32, u32)> {
+        animation
+            .frames
+            .iter()
+            .map(|frame| frame.delay().numer_denom_ms())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips() {
+        for format in [ImageFormat::Gif, ImageFormat::Png] {
+            for plays in [0, 1, 3] {
+                let bytes = encode_animation(animation(plays), format).unwrap();
+                let decoded = decode_animation(&bytes).unwrap().unwrap();
+
+                assert_eq!(plays, decoded.plays, "{format:?}");
+                assert_eq!(delays(&animation(plays)), delays(&decoded), "{format:?}");
+                assert_eq!(
+                    &Rgba([0, 255, 0, 255]),
+                    decoded.frames[1].buffer().get_pixel(2, 1)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn still_images_are_not_animations() {
+        let mut bytes = Vec::new();
+        RgbaImage::new(2, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        assert!(decode_animation(&bytes).unwrap().is_none());
+        assert!(decode_animation(b"not an image").unwrap().is_none());
+    }
+
+    #[test]
+    fn gif_loop_count() {
+        assert_eq!(1, gif_plays(b"GIF89a"));
+        assert_eq!(
+            0,
+            gif_plays(b"GIF89a!\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")
+        );
+        assert_eq!(
+            5,
+            gif_plays(b"GIF89a!\xff\x0bNETSCAPE2.0\x03\x01\x04\x00\x00")
+        );
+    }
+
+    #[test]
+    fn other_formats_are_not_animated() {
+        assert!(encode_animation(animation(0), ImageFormat::Jpeg).is_err());
+    }
+}
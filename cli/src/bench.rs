@@ -0,0 +1,152 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use filters::{FilterChain, FilterStep, Filters, Image, Rgba};
+
+use crate::{adapter_args, create_filters, parse_filter, with_hint};
+
+/// The `bench` subcommand, timing a chain of filters on a generated image.
+pub fn command() -> Command {
+    Command::new("bench")
+        .about(
+            "Time a chain of filters on a generated square image, without reading the result back",
+        )
+        .arg(
+            Arg::new("size")
+                .long("size")
+                .num_args(1)
+                .default_value("2048")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("The width and height of the image, in pixels"),
+        )
+        .arg(
+            Arg::new("iterations")
+                .long("iterations")
+                .num_args(1)
+                .default_value("20")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("How many times the chain runs after the warm-up"),
+        )
+        .arg(
+            Arg::new("filter")
+                .long("filter")
+                .num_args(1)
+                .default_value("gaussianblur")
+                .value_parser(parse_filter)
+                .help("The chain of filters to time, like the --filter of the cli"),
+        )
+        .args(adapter_args())
+}
+
+/// Runs the benchmark, printing the report to stdout.
+///
+/// # Errors
+///
+/// If no adapter matches, or if the image is larger than the adapter supports.
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let size = *matches
+        .get_one::<u32>("size")
+        .expect("The size has a default");
+    let iterations = *matches
+        .get_one::<u32>("iterations")
+        .expect("The iterations have a default");
+    let chain = matches
+        .get_one::<FilterChain>("filter")
+        .expect("The filter has a default");
+    let filters = create_filters(matches)?;
+
+    let image = Image::from_fn(size, size, |x, y| {
+        Rgba::new(x as u8, y as u8, (x ^ y) as u8, 255)
+    });
+    // The first run also compiles the pipelines of the chain, which the next ones reuse.
+    let warm_up_ms = time_chain(&filters, &image, chain)?;
+    let iteration_ms = (0..iterations)
+        .map(|_| time_chain(&filters, &image, chain))
+        .collect::<Result<Vec<_>>>()?;
+
+    let report = Report {
+        size,
+        chain: chain.steps.iter().map(FilterStep::name).collect(),
+        warm_up_ms,
+        iteration_ms,
+    };
+    println!("{}", report.format());
+    Ok(())
+}
+
+/// The milliseconds the gpu takes to run `chain` on `image`, once uploaded.
+fn time_chain(filters: &Filters, image: &Image, chain: &FilterChain) -> Result<f64> {
+    let operation = image.operation(filters).map_err(with_hint)?.wait();
+    let now = Instant::now();
+    chain.apply(operation).map_err(with_hint)?.submit_only();
+    Ok(now.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// The times of a benchmark, in milliseconds.
+struct Report {
+    size: u32,
+    /// The names of the filters of the chain.
+    chain: Vec<&'static str>,
+    /// The first run, compiling the pipelines.
+    warm_up_ms: f64,
+    /// The runs after the warm-up, in order.
+    iteration_ms: Vec<f64>,
+}
+
+impl Report {
+    fn format(&self) -> String {
+        let mut sorted = self.iteration_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let min = sorted[0];
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+        let megapixels = f64::from(self.size) * f64::from(self.size) / 1_000_000.0;
+
+        format!(
+            "chain       {}\n\
+            image       {size}x{size}, {} iterations\n\
+            warm-up     {:>10.2} ms\n\
+            min         {min:>10.2} ms\n\
+            median      {median:>10.2} ms\n\
+            mean        {mean:>10.2} ms\n\
+            throughput  {:>10.2} MP/s",
+            self.chain.join("|"),
+            sorted.len(),
+            self.warm_up_ms,
+            megapixels / (median / 1000.0),
+            size = self.size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Report;
+
+    #[test]
+    fn report() {
+        let report = Report {
+            size: 1000,
+            chain: vec!["grayscale", "gaussianblur"],
+            warm_up_ms: 120.0,
+            iteration_ms: vec![4.0, 2.0, 3.0, 5.0],
+        };
+
+        assert_eq!(
+            "chain       grayscale|gaussianblur\n\
+            image       1000x1000, 4 iterations\n\
+            warm-up         120.00 ms\n\
+            min               2.00 ms\n\
+            median            3.50 ms\n\
+            mean              3.50 ms\n\
+            throughput      285.71 MP/s",
+            report.format()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use filters::ImageDiff;
+
+use crate::{check_extension, load_image};
+
+/// The `compare` subcommand, checking how far an image is from the one expected, like a golden image of a test.
+pub fn command() -> Command {
+    Command::new("compare")
+        .about("Print how much two images of the same dimensions differ")
+        .arg(
+            Arg::new("expected")
+                .required(true)
+                .value_parser(existing_image)
+                .help("The reference image"),
+        )
+        .arg(
+            Arg::new("actual")
+                .required(true)
+                .value_parser(existing_image)
+                .help("The image to compare to the reference"),
+        )
+        .arg(
+            Arg::new("heatmap")
+                .long("heatmap")
+                .num_args(1)
+                .value_parser(|output: &str| {
+                    check_extension(output)?;
+                    Ok::<_, String>(PathBuf::from(output))
+                })
+                .help("Write the differences as an image, brighter where the pixels differ more"),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u8))
+                .help("Fail if a channel of a pixel differs by more than this, from 0 to 255"),
+        )
+}
+
+/// Compares the images, printing the metrics to stdout.
+///
+/// # Errors
+///
+/// If the images can't be read or don't have the same dimensions, or if they differ by more than the threshold.
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let paths = ["expected", "actual"].map(|name| {
+        matches
+            .get_one::<PathBuf>(name)
+            .expect("Both images are required")
+    });
+    let [expected, actual] = [load_image(paths[0])?, load_image(paths[1])?];
+    let cant_compare = |error| {
+        anyhow::anyhow!(
+            "Can't compare {} to {}: {error}",
+            paths[1].display(),
+            paths[0].display()
+        )
+    };
+    let diff = expected.diff(&actual).map_err(cant_compare)?;
+    let ssim = expected.ssim(&actual).map_err(cant_compare)?;
+
+    println!("{}", format_metrics(&diff, ssim));
+    if let Some(heatmap) = matches.get_one::<PathBuf>("heatmap") {
+        diff.to_heatmap().save(heatmap)?;
+    }
+    if let Some(&threshold) = matches.get_one::<u8>("threshold") {
+        let delta = diff.max_channel_delta();
+        if delta > threshold {
+            anyhow::bail!(
+                "The images differ by up to {delta}, more than the threshold of {threshold}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn existing_image(input: &str) -> Result<PathBuf, String> {
+    check_extension(input)?;
+    let path = PathBuf::from(input);
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("Image {input} not found"))
+    }
+}
+
+fn format_metrics(diff: &ImageDiff, ssim: f64) -> String {
+    format!(
+        "max channel delta    {}\n\
+        mean absolute error  {:.3}\n\
+        psnr                 {:.2} dB\n\
+        ssim                 {ssim:.4}",
+        diff.max_channel_delta(),
+        diff.mean_absolute_error(),
+        diff.psnr(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use filters::{Image, Rgba};
+
+    use super::format_metrics;
+
+    #[test]
+    fn metrics() {
+        let image = Image::from_fn(4, 4, |x, y| Rgba::new(x as u8 * 60, y as u8 * 60, 0, 255));
+        let brighter = Image::from_fn(4, 4, |x, y| {
+            Rgba::new(x as u8 * 60 + 4, y as u8 * 60 + 4, 0, 255)
+        });
+
+        assert_eq!(
+            "max channel delta    0\n\
+            mean absolute error  0.000\n\
+            psnr                 inf dB\n\
+            ssim                 1.0000",
+            format_metrics(&image.diff(&image).unwrap(), 1.0)
+        );
+        assert_eq!(
+            "max channel delta    4\n\
+            mean absolute error  2.000\n\
+            psnr                 39.10 dB\n\
+            ssim                 0.9900",
+            format_metrics(&image.diff(&brighter).unwrap(), 0.99)
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Arg, ArgMatches, Command};
+use filters::HashAlgo;
+use pollster::FutureExt;
+
+use crate::{adapter_args, create_filters, load_image, with_hint};
+
+/// The `hash` subcommand, printing perceptual hashes to find duplicate images.
+pub fn command() -> Command {
+    Command::new("hash")
+        .about("Print a perceptual hash of each image, a few bits apart for similar images")
+        .arg(
+            Arg::new("images")
+                .required(true)
+                .num_args(1..)
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("The images to hash"),
+        )
+        .arg(
+            Arg::new("algorithm")
+                .long("algorithm")
+                .num_args(1)
+                .default_value("difference")
+                .value_parser(parse_hash_algo)
+                .help("How to hash: average, difference or perceptual"),
+        )
+        .args(adapter_args())
+}
+
+/// Hashes the images, printing one line per image to stdout, the hash in hex and the path.
+///
+/// # Errors
+///
+/// If no adapter matches, or if an image can't be read.
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let algo = *matches
+        .get_one::<HashAlgo>("algorithm")
+        .expect("The algorithm has a default");
+    let filters = create_filters(matches)?;
+
+    for path in matches
+        .get_many::<PathBuf>("images")
+        .expect("The images are required")
+    {
+        let image = load_image(path)?;
+        let hash = image
+            .operation(&filters)
+            .map_err(with_hint)?
+            .perceptual_hash(algo)
+            .block_on();
+        println!("{hash:016x}  {}", path.display());
+    }
+    Ok(())
+}
+
+fn parse_hash_algo(input: &str) -> Result<HashAlgo, String> {
+    match input.to_lowercase().as_str() {
+        "average" | "ahash" => Ok(HashAlgo::Average),
+        "difference" | "dhash" => Ok(HashAlgo::Difference),
+        "perceptual" | "phash" => Ok(HashAlgo::Perceptual),
+        _ => Err(format!(
+            "Unknown hash algorithm {input}, expecting average, difference or perceptual"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use filters::HashAlgo;
+
+    use super::parse_hash_algo;
+
+    #[test]
+    fn parse_hash_algos() {
+        assert_eq!(Ok(HashAlgo::Average), parse_hash_algo("average"));
+        assert_eq!(Ok(HashAlgo::Difference), parse_hash_algo("dHash"));
+        assert_eq!(Ok(HashAlgo::Perceptual), parse_hash_algo("phash"));
+        assert!(parse_hash_algo("md5").is_err());
+    }
+}
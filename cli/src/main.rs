@@ -1,37 +1,73 @@
 use std::{
+    collections::HashSet,
+    io::{Cursor, Read, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
     time::Instant,
 };
 
+use animation::{decode_animation, encode_animation, Animation, ANIMATED_FORMATS};
 use anyhow::Result;
-use clap::Arg;
-use filters::{Filters, Image, Resize};
-use image::{GenericImageView, ImageBuffer, Rgba};
+use clap::{Arg, ArgAction, ArgMatches};
+use filters::{
+    AvailableAdapter, Backends, BatchProgress, FilterChain, FilterStep, FilterTiming, Filters,
+    FiltersError, FiltersOptions, Image, Image16, MontageLayout, Operation, Resize,
+};
+use image::{
+    DynamicImage, Frame, GenericImageView, ImageBuffer, ImageFormat, ImageOutputFormat, Rgba,
+    RgbaImage,
+};
+use image_webp::{ColorType, WebPEncoder};
+use metadata::{orient, Metadata};
 use pollster::FutureExt;
+use preset::{load_preset, OutputSettings};
+use progress::Progress;
+use timing::{parse_timing_format, FilterClock, StageTiming, Stopwatch, TimingFormat, Timings};
+
+mod animation;
+mod bench;
+mod compare;
+mod hash;
+mod metadata;
+mod preset;
+mod progress;
+mod timing;
 
-const GRAYSCALE: &str = "grayscale";
-const INVERSE: &str = "inverse";
-const HORIZONTAL_FLIP: &str = "hflip";
-const VERTICAL_FLIP: &str = "vflip";
-const HALF: &str = "half";
-const BOX_BLUR: &str = "boxblur";
-const GAUSSIAN_BLUR: &str = "gaussianblur";
+/// The path standing for stdin as input, and for stdout as output.
+const STDIO: &str = "-";
+/// The extensions of the files the cli reads and writes. Only the first frame of a gif is read.
+const SUPPORTED_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "webp", "bmp", "tiff", "tif", "gif"];
+/// How many filter names the default output file name holds, see [`output_file`].
+const MAX_NAMED_FILTERS: usize = 2;
+/// The longest the filter names of the default output file name get, before being shortened.
+const MAX_SUFFIX_LENGTH: usize = 40;
 
 fn main() -> Result<()> {
     let matches = clap::command!()
+        .subcommand(compare::command())
+        .subcommand(bench::command())
+        .subcommand(hash::command())
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
         .arg(
             Arg::new("input")
                 .long("input")
                 .short('i')
-                .required(true)
+                .required_unless_present("input-dir")
                 .num_args(1)
+                .help("The image to filter, or - to read it from stdin")
                 .value_parser(|input: &str| {
-                    if (input.ends_with(".png") || input.ends_with(".jpg"))
-                        && PathBuf::from(&input).exists()
-                    {
+                    if input == STDIO {
+                        return Ok(input.to_owned());
+                    }
+                    check_extension(input)?;
+                    if PathBuf::from(&input).exists() {
                         Ok(input.to_owned())
                     } else {
-                        Err(String::from("Filters only support png or jpg files"))
+                        Err(format!("Input file {input} not found"))
                     }
                 }),
         )
@@ -39,125 +75,1620 @@ fn main() -> Result<()> {
             Arg::new("output")
                 .long("output")
                 .short('o')
-                .required(false)
-                .num_args(1),
+                .required_if_eq("input", STDIO)
+                .num_args(1)
+                .value_parser(|output: &str| {
+                    if output != STDIO {
+                        check_extension(output)?;
+                    }
+                    Ok::<_, String>(output.to_owned())
+                })
+                .help("The file to write, in the format matching its extension, or - to write to stdout"),
+        )
+        .arg(
+            Arg::new("input-dir")
+                .long("input-dir")
+                .num_args(1)
+                .conflicts_with_all(["input", "output", "format"])
+                .requires("output-dir")
+                .value_parser(|input: &str| {
+                    if Path::new(input).is_dir() {
+                        Ok(input.to_owned())
+                    } else {
+                        Err(format!("Input directory {input} not found"))
+                    }
+                })
+                .help("A directory of images to filter, instead of a single input"),
+        )
+        .arg(
+            Arg::new("output-dir")
+                .long("output-dir")
+                .num_args(1)
+                .requires("input-dir")
+                .help("The directory to write the filtered images to, with the names of the input files"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .num_args(1)
+                .requires("input-dir")
+                .value_parser(parse_jobs)
+                .help("How many images of the input directory to decode, filter and encode at the same time, 1 by default"),
+        )
+        .arg(
+            Arg::new("recursive")
+                .long("recursive")
+                .short('r')
+                .action(ArgAction::SetTrue)
+                .requires("input-dir")
+                .help("Also filter the images of the subdirectories, recreating them in the output directory"),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .num_args(1)
+                .requires("recursive")
+                .value_parser(clap::value_parser!(usize))
+                .help("How many levels of subdirectories to walk, all of them by default"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .requires("input-dir")
+                .help("Overwrite the files already in the output directory, instead of skipping their images"),
+        )
+        .arg(
+            Arg::new("respect-exif")
+                .long("respect-exif")
+                .num_args(0..=1)
+                .default_value("true")
+                .default_missing_value("true")
+                .value_parser(clap::value_parser!(bool))
+                .help("Turn the images upright according to their EXIF orientation before filtering them, true by default"),
+        )
+        .arg(
+            Arg::new("keep-metadata")
+                .long("keep-metadata")
+                .action(ArgAction::SetTrue)
+                .help("Copy the EXIF and ICC blocks of the input into jpeg, png and webp outputs"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .action(ArgAction::SetTrue)
+                .help("Only print the images skipped, without the time each image took nor the progress of the input directory"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .required_if_eq("output", STDIO)
+                .num_args(1)
+                .value_parser(parse_format)
+                .help("The format to write, one of png, jpg, webp, bmp, tiff or gif, required when writing to stdout"),
         )
         .arg(
             Arg::new("filter")
                 .long("filter")
-                .value_parser([
-                    GRAYSCALE,
-                    INVERSE,
-                    HORIZONTAL_FLIP,
-                    VERTICAL_FLIP,
-                    HALF,
-                    BOX_BLUR,
-                    GAUSSIAN_BLUR,
-                ])
-                .required(true)
-                .num_args(1..),
+                .value_parser(parse_filter)
+                .required_unless_present_any(["chain", "preset"])
+                .num_args(1..)
+                .help("Filters separated by | or given one by one, like \"grayscale|gaussianblur(3.0)|resize(800,600,linear)\" or \"gaussianblur=4.5 boxblur=21 resize=800x600:nearest\""),
+        )
+        .arg(
+            Arg::new("chain")
+                .long("chain")
+                .required(false)
+                .num_args(1)
+                .conflicts_with("filter")
+                .value_parser(|input: &str| {
+                    if PathBuf::from(&input).exists() {
+                        Ok(input.to_owned())
+                    } else {
+                        Err(format!("Chain file {input} not found"))
+                    }
+                })
+                .help("A toml file listing the filters to apply, as [[steps]] tables"),
+        )
+        .arg(
+            Arg::new("preset")
+                .long("preset")
+                .num_args(1)
+                .conflicts_with("chain")
+                .value_parser(|input: &str| {
+                    if PathBuf::from(&input).exists() {
+                        Ok(input.to_owned())
+                    } else {
+                        Err(format!("Preset file {input} not found"))
+                    }
+                })
+                .help("A toml file naming a chain of filters, applied before the --filter ones, and the output settings"),
+        )
+        .arg(
+            Arg::new("watermark")
+                .long("watermark")
+                .required(false)
+                .num_args(1)
+                .value_parser(|input: &str| {
+                    if PathBuf::from(&input).exists() {
+                        Ok(input.to_owned())
+                    } else {
+                        Err(format!("Watermark file {input} not found"))
+                    }
+                })
+                .help("An image drawn over the filtered one, like a logo, blending through its alpha"),
+        )
+        .arg(
+            Arg::new("at")
+                .long("at")
+                .required(false)
+                .num_args(1)
+                .requires("watermark")
+                .allow_hyphen_values(true)
+                .value_parser(parse_position)
+                .help("Where the top left corner of the watermark lands, in pixels from the top left corner of the image, as x,y like 10,20 or -5,0, 0,0 by default"),
+        )
+        .arg(
+            Arg::new("montage")
+                .long("montage")
+                .num_args(0..=1)
+                .default_missing_value("horizontal")
+                .value_parser(parse_montage_layout)
+                .help("Write the original image next to the filtered one, or above it with --montage vertical"),
+        )
+        .arg(
+            Arg::new("gutter")
+                .long("gutter")
+                .num_args(1)
+                .default_value("8")
+                .requires("montage")
+                .value_parser(clap::value_parser!(u32))
+                .help("How many pixels separate the images of a montage"),
+        )
+        .arg(
+            Arg::new("gutter-color")
+                .long("gutter-color")
+                .num_args(1)
+                .requires("montage")
+                .value_parser(parse_color)
+                .help("The color between the images of a montage, as rrggbb or rrggbbaa hex, white by default"),
+        )
+        .arg(
+            Arg::new("background")
+                .long("background")
+                .num_args(1)
+                .value_parser(parse_color)
+                .help("The color transparent pixels are flattened onto when writing jpegs, which have no alpha, as rrggbb hex, white by default"),
+        )
+        .arg(
+            Arg::new("list-adapters")
+                .long("list-adapters")
+                .action(ArgAction::SetTrue)
+                .exclusive(true)
+                .help("List the gpu adapters available, to pick one with --adapter and --backend"),
+        )
+        .args(adapter_args())
+        .arg(
+            Arg::new("bit-depth")
+                .long("bit-depth")
+                .required(false)
+                .num_args(1)
+                .value_parser(parse_bit_depth)
+                .help("8, or 16 to process and save 16-bit pngs without losing precision"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("bit-depth")
+                .help("Print the time the gpu spent on each filter"),
+        )
+        .arg(
+            Arg::new("timing")
+                .long("timing")
+                .visible_alias("verbose")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("profile")
+                .help("Print the time spent decoding, uploading, on each filter, reading back and encoding each image"),
+        )
+        .arg(
+            Arg::new("timing-format")
+                .long("timing-format")
+                .num_args(1)
+                .requires("timing")
+                .value_parser(parse_timing_format)
+                .help("How --timing prints the times, text or json"),
         )
         .get_matches();
 
+    match matches.subcommand() {
+        Some(("compare", matches)) => return compare::run(matches),
+        Some(("bench", matches)) => return bench::run(matches),
+        Some(("hash", matches)) => return hash::run(matches),
+        _ => {}
+    }
+    if matches.get_flag("list-adapters") {
+        for adapter in Filters::available_adapters() {
+            println!("{}", describe_adapter(&adapter));
+        }
+        return Ok(());
+    }
+
+    let high_bit_depth = matches.get_one::<u8>("bit-depth") == Some(&16);
+    let profile = matches.get_flag("profile");
+    let timing = matches.get_flag("timing").then(|| {
+        matches
+            .get_one::<TimingFormat>("timing-format")
+            .copied()
+            .unwrap_or(TimingFormat::Text)
+    });
+    let watermark = matches
+        .get_one::<String>("watermark")
+        .map(load_image)
+        .transpose()?;
+    let position = matches
+        .get_one::<(i32, i32)>("at")
+        .copied()
+        .unwrap_or((0, 0));
+    let preset = matches
+        .get_one::<String>("preset")
+        .map(load_preset)
+        .transpose()?;
+    let (chain, chain_name, output_settings) = match preset {
+        Some(preset) => (
+            Some(FilterChain::new(preset.steps)),
+            Some(preset.name),
+            preset.output,
+        ),
+        None => {
+            let chain_path = matches.get_one::<String>("chain");
+            (
+                chain_path.map(load_chain).transpose()?,
+                chain_path.and_then(|path| {
+                    Path::new(path)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                }),
+                OutputSettings::default(),
+            )
+        }
+    };
+    let filter_chain = FilterChain::new(
+        matches
+            .get_many::<FilterChain>("filter")
+            .into_iter()
+            .flatten()
+            .flat_map(|chain| chain.steps.iter().cloned())
+            .collect(),
+    );
+
+    let pipeline = Pipeline {
+        filter_chain,
+        chain,
+        watermark,
+        position,
+        high_bit_depth,
+        profile,
+        timing,
+        quiet: matches.get_flag("quiet"),
+        respect_exif: matches.get_one::<bool>("respect-exif") == Some(&true),
+        keep_metadata: matches.get_flag("keep-metadata"),
+        montage: matches
+            .get_one::<MontageLayout>("montage")
+            .map(|&layout| Montage {
+                layout,
+                gutter: *matches
+                    .get_one::<u32>("gutter")
+                    .expect("The gutter has a default"),
+                color: matches
+                    .get_one::<filters::Rgba>("gutter-color")
+                    .copied()
+                    .unwrap_or(filters::Rgba::new(255, 255, 255, 255)),
+            }),
+        background: matches
+            .get_one::<filters::Rgba>("background")
+            .copied()
+            .unwrap_or(filters::Rgba::new(255, 255, 255, 255)),
+        output: output_settings,
+    };
+
+    // The gpu is only set up once the arguments are known to be valid.
+    let new_filters = || {
+        Ok::<_, anyhow::Error>(
+            create_filters(&matches)?.with_profiling(profile || timing.is_some()),
+        )
+    };
+
+    if let Some(input_dir) = matches.get_one::<String>("input-dir") {
+        let output_dir = matches
+            .get_one::<String>("output-dir")
+            .expect("An output directory is required with an input directory");
+        let max_depth = if matches.get_flag("recursive") {
+            matches
+                .get_one::<usize>("max-depth")
+                .copied()
+                .unwrap_or(usize::MAX)
+        } else {
+            0
+        };
+        let batch = Batch {
+            input_dir: Path::new(input_dir),
+            output_dir: Path::new(output_dir),
+            jobs: matches.get_one::<usize>("jobs").copied().unwrap_or(1),
+            max_depth,
+            force: matches.get_flag("force"),
+        };
+        if let Some(format) = pipeline.output.format {
+            pipeline.check_format(format)?;
+        }
+        let filters = new_filters()?;
+        let mut progress = Progress::stderr(pipeline.quiet);
+        return process_directory(&filters, &pipeline, &batch, &mut progress);
+    }
+
     let input = matches
         .get_one::<String>("input")
-        .expect("Input is required");
-    let image = image::open(input)?;
-    let filter_list: Vec<String> = matches
-        .get_many::<String>("filter")
-        .expect("Filter is required")
+        .expect("Input is required without an input directory");
+    let filter_names = chain_name
+        .iter()
         .cloned()
-        .collect();
-
-    let filter_concat = filter_list.join("_");
-    let output = output_file(
+        .chain(
+            pipeline
+                .filter_chain
+                .steps
+                .iter()
+                .map(FilterStep::name)
+                .map(String::from),
+        )
+        .collect::<Vec<_>>();
+    let full_chain = chain_name
+        .into_iter()
+        .chain(Some(pipeline.filter_chain.to_string()))
+        .collect::<Vec<_>>()
+        .join("|");
+    let format = matches
+        .get_one::<ImageFormat>("format")
+        .copied()
+        .or(pipeline.output.format);
+    let mut output = output_file(
         matches.get_one::<String>("output").map(|x| &**x),
         input,
-        &filter_concat,
-    );
+        &filter_names,
+        &full_chain,
+    )?;
+    if let (None, Some(format)) = (matches.get_one::<String>("output"), format) {
+        output.set_extension(format.extensions_str()[0]);
+    }
+    let format = match format {
+        Some(format) => format,
+        None => ImageFormat::from_path(&output)?,
+    };
+    pipeline.check_format(format)?;
+    let filters = new_filters()?;
+    let bytes = pipeline.filter(&filters, Path::new(input), format)?;
+    write_output(&output, &bytes)
+}
 
-    let (width, height) = image.dimensions();
+/// What the arguments ask to do to each image.
+struct Pipeline {
+    filter_chain: FilterChain,
+    chain: Option<FilterChain>,
+    watermark: Option<Image>,
+    position: (i32, i32),
+    high_bit_depth: bool,
+    profile: bool,
+    timing: Option<TimingFormat>,
+    /// Whether to leave out the time each image took.
+    quiet: bool,
+    /// Whether images are turned upright according to their EXIF orientation.
+    respect_exif: bool,
+    /// Whether the EXIF and ICC blocks of the input are copied into the output.
+    keep_metadata: bool,
+    /// How the original image is placed next to the filtered one, if it is.
+    montage: Option<Montage>,
+    /// The color transparent pixels are flattened onto for formats without alpha.
+    background: filters::Rgba,
+    output: OutputSettings,
+}
 
-    let image = Image {
-        width,
-        height,
-        pixels: bytemuck::cast_slice(&image.to_rgba8().into_raw()).to_vec(),
-    };
+/// The layout of `--montage`, showing an image before and after filtering.
+struct Montage {
+    layout: MontageLayout,
+    gutter: u32,
+    color: filters::Rgba,
+}
 
-    let filters = Filters::new().block_on();
-    let now = Instant::now();
-    let mut operation = image.operation(&filters);
-
-    for filter in filter_list {
-        operation = match filter.as_str() {
-            GRAYSCALE => operation.grayscale(),
-            INVERSE => operation.inverse(),
-            HORIZONTAL_FLIP => operation.hflip(),
-            VERTICAL_FLIP => operation.vflip(),
-            HALF => {
-                let (width, height) = operation.dimensions();
-                operation.resize((width / 2, height / 2), Resize::Linear)
+impl Pipeline {
+    /// Checks that images can be written in `format`, which must be png to keep 16 bits per channel.
+    fn check_format(&self, format: ImageFormat) -> Result<()> {
+        if self.high_bit_depth && format != ImageFormat::Png {
+            anyhow::bail!("16-bit output is only supported for png files");
+        }
+        Ok(())
+    }
+
+    /// Filters the image read from `input`, or from stdin for `-`, and encodes the result in `format`. Animations
+    /// are filtered frame by frame when `format` supports them, and only their first frame otherwise.
+    fn filter(&self, filters: &Filters, input: &Path, format: ImageFormat) -> Result<Vec<u8>> {
+        let bytes = read_input(input)?;
+        if ANIMATED_FORMATS.contains(&format) {
+            if let Some(animation) = decode_animation(&bytes)? {
+                let animation = self.apply_animation(filters, animation)?;
+                return encode_animation(animation, format);
             }
-            BOX_BLUR => operation.box_blur(15),
-            GAUSSIAN_BLUR => operation.gaussian_blur(3.0),
-            _ => operation,
+        }
+
+        let mut metadata = Metadata::read(&bytes);
+        let orientation = if self.respect_exif {
+            metadata.orientation()
+        } else {
+            1
         };
+        let encoded = self.apply(filters, || decode_input(input, &bytes), orientation, format)?;
+        if !self.keep_metadata {
+            return Ok(encoded);
+        }
+        if orientation != 1 {
+            metadata.clear_orientation();
+        }
+        metadata.write_to(encoded)
     }
-    let image = operation.execute().block_on();
 
-    println!(
-        "Took {} ms to apply the filter to the image",
-        now.elapsed().as_millis()
-    );
+    /// Filters the frames of `animation` one after the other with the same [`filters::FrameProcessor`], which
+    /// resizes all of them the same way, keeping their delays.
+    fn apply_animation(&self, filters: &Filters, animation: Animation) -> Result<Animation> {
+        if self.high_bit_depth {
+            anyhow::bail!("16-bit output isn't supported for animations");
+        }
+        if self.watermark.is_some() {
+            anyhow::bail!("Watermarks aren't supported for animations");
+        }
+        if self.montage.is_some() {
+            anyhow::bail!("Montages aren't supported for animations");
+        }
+
+        let now = Instant::now();
+        let (width, height) = animation.frames[0].buffer().dimensions();
+        let mut steps = self
+            .chain
+            .iter()
+            .flat_map(|chain| &chain.steps)
+            .chain(&self.filter_chain.steps)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut processor = filters
+            .frame_processor(width, height, FilterChain::new(steps.clone()))
+            .map_err(with_hint)?;
+        if let Some((max_width, max_height)) = self.output.scale_down(processor.output_dimensions())
+        {
+            steps.push(FilterStep::Fit {
+                width: max_width,
+                height: max_height,
+                mode: Resize::Area,
+            });
+            processor = filters
+                .frame_processor(width, height, FilterChain::new(steps))
+                .map_err(with_hint)?;
+        }
+
+        let mut out = Image::new(1, 1, filters::Rgba::new(0, 0, 0, 0));
+        let frames = animation
+            .frames
+            .into_iter()
+            .map(|frame| {
+                processor.process(frame.buffer().as_raw(), &mut out)?;
+                Ok(Frame::from_parts(
+                    RgbaImage::from(&out),
+                    0,
+                    0,
+                    frame.delay(),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !self.quiet {
+            print_elapsed(now);
+        }
+
+        Ok(Animation {
+            frames,
+            plays: animation.plays,
+        })
+    }
+
+    /// Filters the image returned by `decode`, once turned upright according to its EXIF `orientation`, and
+    /// encodes the result in `format`.
+    fn apply<'f>(
+        &self,
+        filters: &'f Filters,
+        decode: impl FnOnce() -> Result<DynamicImage>,
+        orientation: u16,
+        format: ImageFormat,
+    ) -> Result<Vec<u8>> {
+        self.check_format(format)?;
+
+        let mut stopwatch = Stopwatch::start();
+        let source = decode()?;
+        let decode_ms = stopwatch.lap();
+        let now = Instant::now();
+        let mut operation = if self.high_bit_depth {
+            to_image16(source).operation(filters)
+        } else {
+            Image::try_from(source)?.operation(filters)
+        }
+        .map_err(with_hint)?;
+
+        // The gpu times the passes when it can, otherwise the cpu waits for each filter to finish, which only
+        // happens when the stages are timed.
+        let filter_clock = self.timing.map(|_| {
+            if filters.supports_profiling() && !self.high_bit_depth {
+                FilterClock::Gpu
+            } else {
+                FilterClock::Wall
+            }
+        });
+        if filter_clock.is_some() {
+            operation = operation.wait();
+        }
+        let upload_ms = stopwatch.lap();
+        let mut filter_timings = Vec::new();
+        let mut timed = |name: &str, operation: Operation<'f>| -> Operation<'f> {
+            if filter_clock != Some(FilterClock::Wall) {
+                return operation;
+            }
+            let operation = operation.wait();
+            filter_timings.push(StageTiming {
+                name: name.to_owned(),
+                ms: stopwatch.lap(),
+            });
+            operation
+        };
+
+        if orientation != 1 {
+            operation = timed("orientation", orient(operation, orientation));
+        }
+        let original = self.montage.as_ref().map(|_| operation.fork());
+        let steps = self.chain.iter().flat_map(|chain| &chain.steps);
+        for step in steps.chain(&self.filter_chain.steps) {
+            operation = timed(step.name(), step.apply(operation)?);
+        }
+        if let Some(watermark) = &self.watermark {
+            operation = timed(
+                "watermark",
+                operation.composite(watermark, self.position, 1.0)?,
+            );
+        }
+        if let (Some(montage), Some(original)) = (&self.montage, original) {
+            operation = timed(
+                "montage",
+                original
+                    .montage(operation, montage.layout, montage.gutter, montage.color)
+                    .map_err(with_hint)?,
+            );
+        }
+        if let Some(max) = self.output.scale_down(operation.dimensions()) {
+            operation = timed("scale down", operation.resize_fit(max, Resize::Area)?);
+        }
+        if !supports_alpha(format) {
+            operation = timed("flatten", operation.flatten(self.background));
+        }
+        if filter_clock.is_some() {
+            operation = operation.wait();
+            stopwatch.lap();
+        }
+
+        let mut gpu_timings = Vec::new();
+        let filtered = if self.high_bit_depth {
+            Filtered::Deep(operation.execute16().block_on())
+        } else if self.profile || filter_clock == Some(FilterClock::Gpu) {
+            let (image, timings) = operation.execute_profiled().block_on();
+            gpu_timings = timings;
+            Filtered::Rgba(image)
+        } else {
+            Filtered::Rgba(operation.execute().block_on())
+        };
+        let readback_ms = stopwatch.lap();
+        if self.timing.is_none() && !self.quiet {
+            print_elapsed(now);
+        }
+        if self.profile {
+            print_timings(&gpu_timings);
+        }
+        if filter_clock == Some(FilterClock::Gpu) {
+            filter_timings = gpu_timings
+                .into_iter()
+                .map(|timing| StageTiming {
+                    name: timing.name,
+                    ms: timing.gpu_micros / 1000.0,
+                })
+                .collect();
+        }
+
+        let bytes = match filtered {
+            Filtered::Rgba(image) => encode_image(&image, format, self.output.quality)?,
+            Filtered::Deep(image) => encode_image16(&image, format)?,
+        };
+        let encode_ms = stopwatch.lap();
+        if let (Some(timing_format), Some(filter_clock)) = (self.timing, filter_clock) {
+            let timings = Timings {
+                decode_ms,
+                upload_ms,
+                filter_clock,
+                filters: filter_timings,
+                readback_ms,
+                encode_ms,
+                total_ms: stopwatch.total(),
+            };
+            eprintln!("{}", timings.format(timing_format));
+        }
+        Ok(bytes)
+    }
+}
+
+/// The result of a [`Pipeline`], before it is encoded.
+enum Filtered {
+    Rgba(Image),
+    Deep(Image16),
+}
+
+/// Where the batch mode reads and writes images, and how.
+struct Batch<'a> {
+    input_dir: &'a Path,
+    output_dir: &'a Path,
+    /// How many images are processed at the same time.
+    jobs: usize,
+    /// How many levels of subdirectories are walked, 0 only processing the files of `input_dir`.
+    max_depth: usize,
+    /// Whether files already in `output_dir` are overwritten rather than skipped.
+    force: bool,
+}
+
+/// Filters every file of the input directory into a file at the same relative path in the output directory,
+/// skipping with a warning those that can't be decoded or filtered.
+///
+/// The files are shared between `jobs` threads, each decoding a file, filtering it on the shared gpu device and
+/// encoding the result, so that the cpu work of some files overlaps the gpu work of others.
+fn process_directory<W: Write + Send>(
+    filters: &Filters,
+    pipeline: &Pipeline,
+    batch: &Batch,
+    progress: &mut Progress<W>,
+) -> Result<()> {
+    std::fs::create_dir_all(batch.output_dir)?;
+    if batch.input_dir.canonicalize()? == batch.output_dir.canonicalize()? {
+        anyhow::bail!("The output directory must differ from the input directory");
+    }
+    let inputs = list_files(batch.input_dir, batch.output_dir, batch.max_depth)?;
 
-    let buffer =
-        ImageBuffer::<Rgba<u8>, _>::from_raw(image.width, image.height, image.as_raw()).unwrap();
-    buffer.save(output).unwrap();
+    let next = AtomicUsize::new(0);
+    let skipped = AtomicUsize::new(0);
+    // Holding the lock while counting keeps the updates in order.
+    let reporter = Mutex::new((0, progress));
+    std::thread::scope(|scope| {
+        for _ in 0..batch.jobs.min(inputs.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(relative) = inputs.get(index) else {
+                    break;
+                };
+                let input = batch.input_dir.join(relative);
+                let mut output = batch.output_dir.join(relative);
+                if let Some(format) = pipeline.output.format {
+                    output.set_extension(format.extensions_str()[0]);
+                }
+                let result = process_file(filters, pipeline, &input, &output, batch.force);
+
+                let mut reporter = reporter.lock().expect("No thread panics while reporting");
+                let (completed, progress) = &mut *reporter;
+                *completed += 1;
+                if let Err(error) = result {
+                    progress.warn(format_args!("Skipping {}: {error}", input.display()));
+                    skipped.fetch_add(1, Ordering::Relaxed);
+                }
+                progress.update(
+                    BatchProgress {
+                        index,
+                        completed: *completed,
+                        total: inputs.len(),
+                    },
+                    output.display(),
+                );
+            });
+        }
+    });
+    let skipped = skipped.into_inner();
+    let (_, progress) = reporter
+        .into_inner()
+        .expect("No thread panics while reporting");
+    progress.finish(format_args!(
+        "Filtered {} images, skipped {skipped}",
+        inputs.len() - skipped
+    ));
 
     Ok(())
 }
 
-fn output_file(output: Option<&str>, input: &str, filter: &str) -> PathBuf {
-    if let Some(output) = output {
-        Path::new(output).to_owned()
+fn process_file(
+    filters: &Filters,
+    pipeline: &Pipeline,
+    input: &Path,
+    output: &Path,
+    force: bool,
+) -> Result<()> {
+    if !force && output.exists() {
+        anyhow::bail!(
+            "{} already exists, pass --force to overwrite it",
+            output.display()
+        );
+    }
+
+    let format = ImageFormat::from_path(output)?;
+    let bytes = pipeline.filter(filters, input, format)?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(std::fs::write(output, bytes)?)
+}
+
+/// Lists the files of `input_dir` relative to it, sorted, along with those of its subdirectories up to `max_depth`
+/// levels deep. `output_dir` is left out, and directories reached again through symlinks are only walked once.
+fn list_files(input_dir: &Path, output_dir: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    let output_dir = output_dir.canonicalize()?;
+    let mut visited = HashSet::new();
+    let mut files = Vec::new();
+    let mut directories = vec![(PathBuf::new(), 0)];
+    while let Some((relative, depth)) = directories.pop() {
+        let directory = input_dir.join(&relative);
+        let canonical = directory.canonicalize()?;
+        if canonical == output_dir || !visited.insert(canonical) {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_file() {
+                files.push(relative.join(entry.file_name()));
+            } else if path.is_dir() && depth < max_depth {
+                directories.push((relative.join(entry.file_name()), depth + 1));
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn describe_adapter(adapter: &AvailableAdapter) -> String {
+    format!(
+        "{} ({:?}, {:?}), textures up to {} pixels wide",
+        adapter.info.name,
+        adapter.info.backend,
+        adapter.info.device_type,
+        adapter.limits.max_texture_dimension_2d
+    )
+}
+
+/// The `--adapter` and `--backend` arguments, picking the gpu adapter of [`create_filters`].
+fn adapter_args() -> [Arg; 2] {
+    [
+        Arg::new("adapter")
+            .long("adapter")
+            .required(false)
+            .num_args(1)
+            .help("Use the first gpu adapter whose name contains this string"),
+        Arg::new("backend")
+            .long("backend")
+            .required(false)
+            .num_args(1)
+            .value_parser(parse_backend)
+            .help("One of vulkan, metal, dx12, dx11 or gl"),
+    ]
+}
+
+/// Creates the filters on the adapter picked by the [`adapter_args`], listing the available adapters when none
+/// matches.
+fn create_filters(matches: &ArgMatches) -> Result<Filters> {
+    let adapter_name_filter = matches.get_one::<String>("adapter").cloned();
+    let backends = matches
+        .get_one::<Backends>("backend")
+        .copied()
+        .unwrap_or(Backends::all());
+    let options = FiltersOptions {
+        backends,
+        adapter_name_filter: adapter_name_filter.clone(),
+        ..Default::default()
+    };
+    match Filters::with_options(options).block_on() {
+        Err(FiltersError::NoAdapter) if adapter_name_filter.is_some() => {
+            let mut adapters = Filters::available_adapters();
+            adapters.retain(|adapter| backends.contains(adapter.info.backend.into()));
+            Err(no_adapter_error(
+                &adapter_name_filter.unwrap_or_default(),
+                &adapters,
+            ))
+        }
+        filters => Ok(filters?),
+    }
+}
+
+/// The error when no adapter matches `name_filter`, listing the `adapters` of the selected backends.
+fn no_adapter_error(name_filter: &str, adapters: &[AvailableAdapter]) -> anyhow::Error {
+    if adapters.is_empty() {
+        return anyhow::anyhow!("No adapter matching {name_filter}, no adapter is available");
+    }
+
+    let adapters = adapters
+        .iter()
+        .map(|adapter| format!("\n  {}", describe_adapter(adapter)))
+        .collect::<String>();
+    anyhow::anyhow!("No adapter matching {name_filter}, available adapters are:{adapters}")
+}
+
+/// Turns a filters error into a message, suggesting a fix when the user can do something about it.
+fn with_hint(error: FiltersError) -> anyhow::Error {
+    match error {
+        FiltersError::ImageTooLarge { limit, .. } => anyhow::anyhow!(
+            "{error}. Try resizing the image to at most {limit}x{limit} pixels first"
+        ),
+        error => error.into(),
+    }
+}
+
+fn load_image<P: AsRef<Path>>(path: P) -> Result<Image> {
+    Ok(Image::open(path)?)
+}
+
+/// Reads the bytes of `input`, from stdin for `-`.
+fn read_input(input: &Path) -> Result<Vec<u8>> {
+    if input != Path::new(STDIO) {
+        return Ok(std::fs::read(input)?);
+    }
+
+    let mut bytes = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes the `bytes` read from `input`, in the format matching its extension, or guessed from the bytes for
+/// stdin.
+fn decode_input(input: &Path, bytes: &[u8]) -> Result<DynamicImage> {
+    if input != Path::new(STDIO) {
+        let format = ImageFormat::from_path(input)?;
+        return Ok(image::load_from_memory_with_format(bytes, format)?);
+    }
+
+    image::load_from_memory(bytes)
+        .map_err(|error| anyhow::anyhow!("Couldn't decode the image read from stdin: {error}"))
+}
+
+/// Encodes the image in `format`, with the given `quality` if it is jpeg, webp being encoded losslessly.
+fn encode_image(image: &Image, format: ImageFormat, quality: Option<u8>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    if format == ImageFormat::WebP {
+        WebPEncoder::new(&mut bytes).encode(
+            image.as_raw(),
+            image.width,
+            image.height,
+            ColorType::Rgba8,
+        )?;
     } else {
-        let path = Path::new(input);
-        let parent = path.parent();
-        let stem = path
-            .file_stem()
-            .expect("Expecting .jpg or .png files")
-            .to_string_lossy();
-        let extension = path
-            .extension()
-            .expect("Expecting .jpg or .png files")
-            .to_string_lossy();
-
-        let filename = format!("{}_{}.{}", stem, filter, extension);
-        let output_path = if let Some(parent) = parent {
-            parent.join(filename)
-        } else {
-            Path::new(&filename).to_path_buf()
+        let format = match (format, quality) {
+            (ImageFormat::Jpeg, Some(quality)) => ImageOutputFormat::Jpeg(quality),
+            (format, _) => format.into(),
         };
+        RgbaImage::from(image).write_to(&mut Cursor::new(&mut bytes), format)?;
+    }
+    Ok(bytes)
+}
+
+fn encode_image16(image: &Image16, format: ImageFormat) -> Result<Vec<u8>> {
+    let buffer = ImageBuffer::<Rgba<u16>, _>::from_raw(
+        image.width,
+        image.height,
+        bytemuck::cast_slice(&image.pixels),
+    )
+    .unwrap();
+    let mut bytes = Vec::new();
+    buffer.write_to(&mut Cursor::new(&mut bytes), format)?;
+    Ok(bytes)
+}
+
+/// Writes the encoded image to the file at `output`, or to stdout if it is `-`.
+fn write_output(output: &Path, bytes: &[u8]) -> Result<()> {
+    if output == Path::new(STDIO) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(output, bytes)?;
+    }
+    Ok(())
+}
+
+/// Whether images written in `format` keep their alpha, jpeg being the only supported format without it.
+fn supports_alpha(format: ImageFormat) -> bool {
+    format != ImageFormat::Jpeg
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|actual| actual.eq_ignore_ascii_case(extension))
+}
+
+/// Checks that the cli can read and write files like `path`, listing the supported extensions otherwise.
+fn check_extension(path: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    if SUPPORTED_EXTENSIONS
+        .iter()
+        .any(|extension| has_extension(path, extension))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported file {}, expecting one of the extensions {}",
+            path.display(),
+            SUPPORTED_EXTENSIONS.join(", ")
+        ))
+    }
+}
+
+fn to_image16(image: DynamicImage) -> Image16 {
+    let (width, height) = image.dimensions();
+
+    Image16 {
+        width,
+        height,
+        pixels: bytemuck::cast_slice(&image.to_rgba16().into_raw()).to_vec(),
+    }
+}
+
+fn load_chain<P: AsRef<Path>>(path: P) -> Result<FilterChain> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+
+    toml::from_str(&content)
+        .map_err(|error| anyhow::anyhow!("Invalid chain file {}: {error}", path.display()))
+}
+
+fn print_elapsed(start: Instant) {
+    eprintln!(
+        "Took {} ms to apply the filter to the image",
+        start.elapsed().as_millis()
+    );
+}
+
+fn print_timings(timings: &[FilterTiming]) {
+    if timings.is_empty() {
+        eprintln!("No gpu timings: the adapter doesn't support timestamp queries");
+        return;
+    }
+
+    let width = timings
+        .iter()
+        .map(|timing| timing.name.len())
+        .max()
+        .unwrap_or_default();
+    for timing in timings {
+        eprintln!("{:<width$}  {:>10.1} µs", timing.name, timing.gpu_micros);
+    }
+    eprintln!(
+        "{:<width$}  {:>10.1} µs",
+        "total",
+        timings.iter().map(|timing| timing.gpu_micros).sum::<f64>()
+    );
+}
+
+fn parse_filter(input: &str) -> Result<FilterChain, String> {
+    FilterChain::parse(input).map_err(|error| {
+        format!(
+            "{error}\n  {input}\n  {:>width$}",
+            "^",
+            width = error.position + 1
+        )
+    })
+}
+
+fn parse_format(input: &str) -> Result<ImageFormat, String> {
+    let extension = input.to_ascii_lowercase();
+    SUPPORTED_EXTENSIONS
+        .contains(&extension.as_str())
+        .then(|| ImageFormat::from_extension(&extension))
+        .flatten()
+        .ok_or_else(|| {
+            format!(
+                "Unsupported format {input}, expecting one of {}",
+                SUPPORTED_EXTENSIONS.join(", ")
+            )
+        })
+}
+
+fn parse_position(input: &str) -> Result<(i32, i32), String> {
+    let error = || format!("Expecting a position formatted as x,y, got {input}");
+    let (x, y) = input.split_once(',').ok_or_else(error)?;
+    let x = x.trim().parse().map_err(|_| error())?;
+    let y = y.trim().parse().map_err(|_| error())?;
+
+    Ok((x, y))
+}
 
-        output_path
+fn parse_montage_layout(input: &str) -> Result<MontageLayout, String> {
+    match input.to_lowercase().as_str() {
+        "horizontal" => Ok(MontageLayout::Horizontal),
+        "vertical" => Ok(MontageLayout::Vertical),
+        _ => Err(format!(
+            "Unknown montage layout {input}, expecting horizontal or vertical"
+        )),
     }
 }
 
+fn parse_color(input: &str) -> Result<filters::Rgba, String> {
+    filters::Rgba::from_hex(input).map_err(|error| error.to_string())
+}
+
+fn parse_backend(input: &str) -> Result<Backends, String> {
+    match input.to_lowercase().as_str() {
+        "vulkan" => Ok(Backends::VULKAN),
+        "metal" => Ok(Backends::METAL),
+        "dx12" => Ok(Backends::DX12),
+        "dx11" => Ok(Backends::DX11),
+        "gl" => Ok(Backends::GL),
+        _ => Err(format!(
+            "Unknown backend {input}, expecting one of vulkan, metal, dx12, dx11 or gl"
+        )),
+    }
+}
+
+fn parse_bit_depth(input: &str) -> Result<u8, String> {
+    match input {
+        "8" => Ok(8),
+        "16" => Ok(16),
+        _ => Err(format!("Unknown bit depth {input}, expecting 8 or 16")),
+    }
+}
+
+fn parse_jobs(input: &str) -> Result<usize, String> {
+    match input.parse() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => Err(format!(
+            "Invalid number of jobs {input}, expecting a positive integer"
+        )),
+    }
+}
+
+/// Returns `output` if given, otherwise names the output after the input and the filters applied to it, like
+/// `sunflower_grayscale.png`.
+///
+/// Only the first [`MAX_NAMED_FILTERS`] filter names make it into the name, followed by a hash of `full_chain` when
+/// there are more, or when they are too long, so that the name stays short but differs for each chain.
+///
+/// # Arguments
+///
+/// * `output` - The output given on the command line, if any.
+/// * `input` - The path of the input image.
+/// * `filter_names` - The names of the filters, along with the chain or preset they come from.
+/// * `full_chain` - The chain with all its parameters.
+///
+/// # Errors
+///
+/// If `output` isn't given and `input` has no extension to give to the output.
+fn output_file(
+    output: Option<&str>,
+    input: &str,
+    filter_names: &[String],
+    full_chain: &str,
+) -> Result<PathBuf> {
+    if let Some(output) = output {
+        return Ok(Path::new(output).to_owned());
+    }
+
+    let path = Path::new(input);
+    let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+        anyhow::bail!("Can't name the output after {input}, which has no extension: pass --output");
+    };
+    let names = filter_names
+        .iter()
+        .map(|name| sanitize_file_name(name))
+        .collect::<Vec<_>>();
+    let mut suffix = names.join("_");
+    if names.len() > MAX_NAMED_FILTERS || suffix.len() > MAX_SUFFIX_LENGTH {
+        suffix = names[..names.len().min(MAX_NAMED_FILTERS)]
+            .iter()
+            .map(|name| name.chars().take(MAX_SUFFIX_LENGTH / 2).collect::<String>())
+            .chain(Some(format!("{:08x}", fnv1a(full_chain))))
+            .collect::<Vec<_>>()
+            .join("_");
+    }
+
+    let filename = format!(
+        "{}_{suffix}.{}",
+        stem.to_string_lossy(),
+        extension.to_string_lossy()
+    );
+    Ok(match path.parent() {
+        Some(parent) => parent.join(filename),
+        None => PathBuf::from(filename),
+    })
+}
+
+/// Replaces the characters that aren't allowed in file names on some systems, or that would be awkward in a shell,
+/// with underscores.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|character| {
+            if character.is_alphanumeric() || matches!(character, '-' | '.') {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The 32 bits FNV-1a hash of `text`, which unlike the hasher of the standard library doesn't change between
+/// versions of Rust, so that the same chain keeps naming its outputs the same way.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::output_file;
+    use std::path::Path;
+
+    use filters::{
+        AdapterInfo, AvailableAdapter, Backend, Backends, DeviceType, FilterChain, FilterStep,
+        Filters, Image, Limits, MontageLayout, Resize, Rgba,
+    };
+    use image::ImageFormat;
+    use pollster::FutureExt;
+
+    use crate::{
+        check_extension, describe_adapter, encode_image, fnv1a, load_chain, load_image,
+        no_adapter_error, output_file, parse_backend, parse_bit_depth, parse_color, parse_filter,
+        parse_format, parse_jobs, parse_montage_layout, parse_position, process_directory, Batch,
+        Pipeline, Progress,
+    };
+
+    fn fixture() -> Image {
+        Image::from_fn(7, 5, |x, y| {
+            Rgba::new((x * 30) as u8, (y * 50) as u8, 90, 255)
+        })
+    }
 
     #[test]
     fn output_file_name_no_specified() {
-        let file_path = output_file(None, "sunflower.png", "grayscale");
+        let file_path = output_file(None, "sunflower.png", &names(&["grayscale"]), "grayscale");
 
-        assert_eq!("sunflower_grayscale.png", file_path.to_string_lossy());
+        assert_eq!(
+            "sunflower_grayscale.png",
+            file_path.unwrap().to_string_lossy()
+        );
     }
 
     #[test]
     fn output_file_name_output_specified() {
-        let file_path = output_file(Some("output.png"), "sunflower.png", "grayscale");
+        let file_path = output_file(
+            Some("output.png"),
+            "sunflower",
+            &names(&["grayscale"]),
+            "grayscale",
+        );
+
+        assert_eq!("output.png", file_path.unwrap().to_string_lossy());
+    }
+
+    #[test]
+    fn output_file_name_long_chain() {
+        let names = names(&["grayscale", "gaussianblur", "resize", "sharpen", "inverse"]);
+        let chain = "grayscale|gaussianblur(3)|resize(800,600,linear)|sharpen(1)|inverse";
+        let file_path = output_file(None, "photos/sunflower.jpg", &names, chain).unwrap();
+        let other_parameters = output_file(
+            None,
+            "photos/sunflower.jpg",
+            &names,
+            &chain.replace("(3)", "(4)"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Path::new("photos/sunflower_grayscale_gaussianblur_a432f1cb.jpg"),
+            file_path
+        );
+        assert_ne!(file_path, other_parameters);
+        // A single name is shortened too when it is too long.
+        let long = "a".repeat(50);
+        let file_path =
+            output_file(None, "sunflower.png", std::slice::from_ref(&long), &long).unwrap();
+        assert_eq!(
+            format!("sunflower_{}_{:08x}.png", "a".repeat(20), fnv1a(&long)),
+            file_path.to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn output_file_name_sanitized() {
+        let file_path = output_file(
+            None,
+            "sunflower.png",
+            &names(&["web: large/1", "grayscale"]),
+            "",
+        );
+
+        assert_eq!(
+            "sunflower_web__large_1_grayscale.png",
+            file_path.unwrap().to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn output_file_name_without_extension() {
+        let error =
+            output_file(None, "sunflower", &names(&["grayscale"]), "grayscale").unwrap_err();
+
+        assert_eq!(
+            "Can't name the output after sunflower, which has no extension: pass --output",
+            error.to_string()
+        );
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_position_negative() {
+        let position = parse_position("-12,40");
+
+        assert_eq!(Ok((-12, 40)), position);
+    }
+
+    #[test]
+    fn parse_position_invalid() {
+        assert!(parse_position("12").is_err());
+        assert!(parse_position("12,a").is_err());
+    }
+
+    #[test]
+    fn parse_filter_chains() {
+        assert_eq!(
+            Ok(FilterChain::new(vec![
+                FilterStep::Grayscale,
+                FilterStep::Fit {
+                    width: 800,
+                    height: 600,
+                    mode: Resize::Linear
+                },
+                FilterStep::Thumbnail { size: 256 }
+            ])),
+            parse_filter("grayscale|fit(800,600)|thumbnail(256)")
+        );
+        assert!(parse_filter("half").is_err());
+        assert!(parse_filter("grayscale(2)").is_err());
+        assert!(parse_filter("resize(800)").is_err());
+    }
+
+    #[test]
+    fn parse_filter_assigned_parameters() {
+        assert_eq!(
+            Ok(FilterChain::new(vec![
+                FilterStep::GaussianBlur { sigma: 4.5 },
+                FilterStep::BoxBlur { size: 21 },
+                FilterStep::Resize {
+                    width: 800,
+                    height: 600,
+                    mode: Resize::Nearest
+                }
+            ])),
+            parse_filter("gaussianblur=4.5|boxblur=21|resize=800x600:nearest")
+        );
+        assert_eq!(
+            Ok(FilterChain::new(vec![
+                FilterStep::BoxBlur { size: 15 },
+                FilterStep::GaussianBlur { sigma: 3.0 }
+            ])),
+            parse_filter("boxblur|gaussianblur")
+        );
+        assert_eq!(
+            Err(
+                "Invalid number 4.5.1 at position 13\n  gaussianblur=4.5.1\n               ^"
+                    .to_owned()
+            ),
+            parse_filter("gaussianblur=4.5.1")
+        );
+        assert_eq!(
+            Err("Expecting dimensions formatted as WIDTHxHEIGHT, got 800 at position 7\n  resize=800\n         ^".to_owned()),
+            parse_filter("resize=800")
+        );
+    }
+
+    #[test]
+    fn filter_fixture_image() {
+        let path = std::env::temp_dir().join("filters_filter_fixture_image.png");
+        Image::from_fn(24, 16, |x, y| {
+            Rgba::new((x * 10) as u8, (y * 15) as u8, 90, 255)
+        })
+        .save(&path)
+        .unwrap();
+        let image = load_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let filters = Filters::new().block_on().unwrap();
+
+        let chain = parse_filter("gaussianblur=1.5|boxblur=3|resize=12x8:nearest").unwrap();
+        let filtered = chain
+            .apply(image.operation(&filters).unwrap())
+            .unwrap()
+            .execute()
+            .block_on();
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.5)
+            .box_blur(3)
+            .resize((12, 8), Resize::Nearest)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((12, 8), (filtered.width, filtered.height));
+        assert_eq!(expected, filtered);
+    }
+
+    #[test]
+    fn parse_filter_points_at_error() {
+        assert_eq!(
+            Err("Unknown filter sepia at position 10\n  grayscale|sepia\n            ^".to_owned()),
+            parse_filter("grayscale|sepia")
+        );
+        assert_eq!(
+            Err("Invalid color #08080, expecting #rrggbb or #rrggbbaa at position 10\n  splittone(#08080,#ff8000)\n            ^".to_owned()),
+            parse_filter("splittone(#08080,#ff8000)")
+        );
+    }
+
+    #[test]
+    fn supported_extensions() {
+        for path in [
+            "photo.png",
+            "photo.jpg",
+            "photo.JPEG",
+            "photo.webp",
+            "photo.bmp",
+            "photo.tif",
+            "photo.tiff",
+            "dir/photo.gif",
+        ] {
+            assert_eq!(Ok(()), check_extension(path), "{path}");
+        }
+        assert_eq!(
+            Err("Unsupported file photo.psd, expecting one of the extensions png, jpg, jpeg, webp, bmp, tiff, tif, gif".to_owned()),
+            check_extension("photo.psd")
+        );
+        assert!(check_extension("photo").is_err());
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert_eq!(Ok(ImageFormat::Png), parse_format("png"));
+        assert_eq!(Ok(ImageFormat::Jpeg), parse_format("JPG"));
+        assert_eq!(Ok(ImageFormat::WebP), parse_format("webp"));
+        assert_eq!(Ok(ImageFormat::Tiff), parse_format("tif"));
+        assert_eq!(
+            Err("Unsupported format tga, expecting one of png, jpg, jpeg, webp, bmp, tiff, tif, gif".to_owned()),
+            parse_format("tga")
+        );
+    }
+
+    fn round_trip(extension: &str) -> Image {
+        let path = std::env::temp_dir().join(format!("filters_round_trip.{extension}"));
+        let format = parse_format(extension).unwrap();
+        std::fs::write(&path, encode_image(&fixture(), format, None).unwrap()).unwrap();
+        let image = load_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        image
+    }
+
+    #[test]
+    fn bmp_round_trip() {
+        assert_eq!(fixture(), round_trip("bmp"));
+    }
+
+    #[test]
+    fn webp_round_trip() {
+        assert_eq!(fixture(), round_trip("webp"));
+    }
+
+    #[test]
+    fn tiff_round_trip() {
+        assert_eq!(fixture(), round_trip("tiff"));
+    }
+
+    #[test]
+    fn gif_first_frame() {
+        let path = std::env::temp_dir().join("filters_gif_first_frame.gif");
+        let frame = |value| {
+            image::Frame::new(image::RgbaImage::from_pixel(
+                3,
+                2,
+                image::Rgba([value, value, value, 255]),
+            ))
+        };
+        let mut encoder =
+            image::codecs::gif::GifEncoder::new(std::fs::File::create(&path).unwrap());
+        encoder.encode_frames([frame(0), frame(255)]).unwrap();
+        drop(encoder);
+
+        let image = load_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Image::new(3, 2, Rgba::new(0, 0, 0, 255)), image);
+    }
+
+    fn adapter(name: &str, backend: Backend) -> AvailableAdapter {
+        AvailableAdapter {
+            info: AdapterInfo {
+                name: name.to_owned(),
+                vendor: 0,
+                device: 0,
+                device_type: DeviceType::DiscreteGpu,
+                driver: String::new(),
+                driver_info: String::new(),
+                backend,
+            },
+            limits: Limits::default(),
+        }
+    }
+
+    #[test]
+    fn describe_adapters() {
+        assert_eq!(
+            "Radeon (Vulkan, DiscreteGpu), textures up to 8192 pixels wide",
+            describe_adapter(&adapter("Radeon", Backend::Vulkan))
+        );
+        for adapter in Filters::available_adapters() {
+            assert!(describe_adapter(&adapter).starts_with(&adapter.info.name));
+        }
+    }
+
+    #[test]
+    fn no_adapter_error_lists_adapters() {
+        let adapters = [
+            adapter("Radeon", Backend::Vulkan),
+            adapter("llvmpipe", Backend::Gl),
+        ];
+
+        assert_eq!(
+            "No adapter matching nvidia, available adapters are:\n  \
+            Radeon (Vulkan, DiscreteGpu), textures up to 8192 pixels wide\n  \
+            llvmpipe (Gl, DiscreteGpu), textures up to 8192 pixels wide",
+            no_adapter_error("nvidia", &adapters).to_string()
+        );
+        assert_eq!(
+            "No adapter matching nvidia, no adapter is available",
+            no_adapter_error("nvidia", &[]).to_string()
+        );
+    }
+
+    #[test]
+    fn parse_montage_layouts_and_colors() {
+        assert_eq!(
+            Ok(MontageLayout::Vertical),
+            parse_montage_layout("Vertical")
+        );
+        assert!(parse_montage_layout("diagonal").is_err());
+        assert_eq!(Ok(Rgba::new(255, 0, 128, 255)), parse_color("#ff0080"));
+        assert_eq!(Ok(Rgba::new(0, 0, 0, 16)), parse_color("00000010"));
+        assert!(parse_color("fff").is_err());
+        assert!(parse_color("gg0000").is_err());
+    }
+
+    #[test]
+    fn parse_backend_names() {
+        assert_eq!(Ok(Backends::VULKAN), parse_backend("vulkan"));
+        assert_eq!(Ok(Backends::GL), parse_backend("GL"));
+        assert!(parse_backend("opengl es").is_err());
+    }
+
+    #[test]
+    fn parse_bit_depth_values() {
+        assert_eq!(Ok(8), parse_bit_depth("8"));
+        assert_eq!(Ok(16), parse_bit_depth("16"));
+        assert!(parse_bit_depth("32").is_err());
+    }
+
+    #[test]
+    fn parse_jobs_values() {
+        assert_eq!(Ok(4), parse_jobs("4"));
+        assert!(parse_jobs("0").is_err());
+        assert!(parse_jobs("-2").is_err());
+        assert!(parse_jobs("many").is_err());
+    }
+
+    #[test]
+    fn load_chain_from_toml() {
+        let path = std::env::temp_dir().join("filters_load_chain_from_toml.toml");
+        std::fs::write(
+            &path,
+            "[[steps]]\nfilter = \"grayscale\"\n\n\
+            [[steps]]\nfilter = \"resize\"\nwidth = 800\nheight = 600\nmode = \"linear\"\n",
+        )
+        .unwrap();
+
+        let chain = load_chain(&path).unwrap();
+
+        assert_eq!(
+            FilterChain::new(vec![
+                FilterStep::Grayscale,
+                FilterStep::Resize {
+                    width: 800,
+                    height: 600,
+                    mode: Resize::Linear
+                }
+            ]),
+            chain
+        );
+    }
+
+    #[test]
+    fn load_chain_unknown_step() {
+        let path = std::env::temp_dir().join("filters_load_chain_unknown_step.toml");
+        std::fs::write(&path, "[[steps]]\nfilter = \"sepia\"\n").unwrap();
+
+        let message = load_chain(&path).unwrap_err().to_string();
+
+        assert!(message.contains("unknown variant `sepia`"), "{message}");
+    }
+
+    #[test]
+    fn batch_progress_bar() {
+        let directory = std::env::temp_dir().join("filters_batch_progress_bar");
+        let _ = std::fs::remove_dir_all(&directory);
+        let input_dir = directory.join("input");
+        std::fs::create_dir_all(&input_dir).unwrap();
+        for name in ["a", "b", "c"] {
+            Image::from_fn(4, 4, |x, y| Rgba::new(x as u8, y as u8, 0, 255))
+                .save(input_dir.join(format!("{name}.png")))
+                .unwrap();
+        }
+        let filters = Filters::new().block_on().unwrap();
+        let pipeline = Pipeline {
+            filter_chain: parse_filter("inverse").unwrap(),
+            chain: None,
+            watermark: None,
+            position: (0, 0),
+            high_bit_depth: false,
+            profile: false,
+            timing: None,
+            quiet: false,
+            respect_exif: true,
+            keep_metadata: false,
+            montage: None,
+            background: Rgba::new(255, 255, 255, 255),
+            output: Default::default(),
+        };
+        let batch = Batch {
+            input_dir: &input_dir,
+            output_dir: &directory.join("output"),
+            jobs: 2,
+            max_depth: 0,
+            force: false,
+        };
+        // Draws as it would on a terminal.
+        let mut progress = Progress::new(Vec::new(), true, false);
+
+        process_directory(&filters, &pipeline, &batch, &mut progress).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        let output = String::from_utf8(progress.into_inner()).unwrap();
 
-        assert_eq!("output.png", file_path.to_string_lossy());
+        assert_eq!(3, output.matches("\r\x1b[2K").count(), "{output:?}");
+        for completed in 1..=3 {
+            assert!(output.contains(&format!("] {completed}/3 ")), "{output:?}");
+        }
+        assert!(
+            output.ends_with("\nFiltered 3 images, skipped 0\n"),
+            "{output:?}"
+        );
     }
 }
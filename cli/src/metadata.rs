@@ -0,0 +1,197 @@
+use anyhow::Result;
+use filters::Operation;
+use img_parts::{Bytes, DynImage, ImageEXIF, ImageICC};
+
+/// The tag of the EXIF orientation entry.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// The EXIF and ICC blocks of a jpeg, png or webp, which decoding the pixels drops.
+#[derive(Debug, Default)]
+pub struct Metadata {
+    /// The TIFF structure of the EXIF block, without the `Exif` prefix of jpegs.
+    pub exif: Option<Bytes>,
+    pub icc_profile: Option<Bytes>,
+}
+
+impl Metadata {
+    /// Reads the metadata of an encoded image, finding none in the formats other than jpeg, png and webp.
+    pub fn read(bytes: &[u8]) -> Self {
+        match DynImage::from_bytes(Bytes::copy_from_slice(bytes)) {
+            Ok(Some(image)) => Self {
+                exif: image.exif(),
+                icc_profile: image.icc_profile(),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// The EXIF orientation, from 1 to 8, 1 meaning the pixels are already upright.
+    pub fn orientation(&self) -> u16 {
+        self.exif
+            .as_deref()
+            .and_then(|exif| {
+                let (offset, big_endian) = orientation_offset(exif)?;
+                read_u16(exif, offset, big_endian)
+            })
+            .filter(|orientation| (1..=8).contains(orientation))
+            .unwrap_or(1)
+    }
+
+    /// Marks the pixels as upright, once the orientation was applied to them, so that viewers don't turn them again.
+    pub fn clear_orientation(&mut self) {
+        let Some(exif) = &self.exif else {
+            return;
+        };
+        if let Some((offset, big_endian)) = orientation_offset(exif) {
+            let mut exif = exif.to_vec();
+            let upright = if big_endian {
+                1u16.to_be_bytes()
+            } else {
+                1u16.to_le_bytes()
+            };
+            exif[offset..offset + 2].copy_from_slice(&upright);
+            self.exif = Some(exif.into());
+        }
+    }
+
+    /// Copies the metadata into `encoded`, if it is a jpeg, png or webp, returning the other formats unchanged.
+    pub fn write_to(&self, encoded: Vec<u8>) -> Result<Vec<u8>> {
+        let mut image = match DynImage::from_bytes(encoded.clone().into())? {
+            Some(image) => image,
+            None => return Ok(encoded),
+        };
+        image.set_exif(self.exif.clone());
+        image.set_icc_profile(self.icc_profile.clone());
+        Ok(image.encoder().bytes().to_vec())
+    }
+}
+
+/// Turns `operation` upright according to an EXIF `orientation`, which tells how its pixels are mirrored and
+/// rotated.
+pub fn orient(operation: Operation, orientation: u16) -> Operation {
+    match orientation {
+        2 => operation.hflip(),
+        3 => operation.hflip().vflip(),
+        4 => operation.vflip(),
+        5 => operation.transpose(),
+        6 => operation.transpose().hflip(),
+        7 => operation.transpose().hflip().vflip(),
+        8 => operation.transpose().vflip(),
+        _ => operation,
+    }
+}
+
+/// Finds the value of the orientation entry in the first directory of the TIFF structure `exif`, returning its
+/// offset and whether the structure is big endian.
+fn orientation_offset(exif: &[u8]) -> Option<(usize, bool)> {
+    let big_endian = match exif.get(..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+    let directory = read_u32(exif, 4, big_endian)? as usize;
+    let entries = read_u16(exif, directory, big_endian)? as usize;
+
+    (0..entries)
+        .map(|index| directory + 2 + index * 12)
+        .find(|&entry| read_u16(exif, entry, big_endian) == Some(ORIENTATION_TAG))
+        // The value, a single short, is stored in place of the offset to the values.
+        .map(|entry| (entry + 8, big_endian))
+        .filter(|(offset, _)| offset + 2 <= exif.len())
+}
+
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use img_parts::Bytes;
+
+    use super::Metadata;
+
+    /// A TIFF structure holding a single orientation entry.
+    fn exif(orientation: u16, big_endian: bool) -> Bytes {
+        // Offsets and counts take 32 bits, written as two 16 bits words.
+        let long = |value: u16| if big_endian { [0, value] } else { [value, 0] };
+        let words = [
+            &[42][..],
+            &long(8),
+            // One entry, the orientation tag, of type short, with one value.
+            &[1, 0x0112, 3],
+            &long(1),
+            &[orientation, 0],
+            // No next directory.
+            &long(0),
+        ]
+        .concat();
+
+        let mut exif = if big_endian { b"MM" } else { b"II" }.to_vec();
+        for word in words {
+            exif.extend(if big_endian {
+                word.to_be_bytes()
+            } else {
+                word.to_le_bytes()
+            });
+        }
+        exif.into()
+    }
+
+    #[test]
+    fn read_orientation() {
+        for big_endian in [false, true] {
+            let mut metadata = Metadata {
+                exif: Some(exif(6, big_endian)),
+                icc_profile: None,
+            };
+            assert_eq!(6, metadata.orientation());
+
+            metadata.clear_orientation();
+            assert_eq!(1, metadata.orientation());
+            assert_eq!(Some(exif(1, big_endian)), metadata.exif);
+        }
+    }
+
+    #[test]
+    fn missing_or_invalid_orientation() {
+        assert_eq!(1, Metadata::default().orientation());
+        let broken = Metadata {
+            exif: Some(Bytes::from_static(b"II*\0\xff\xff\xff\xff")),
+            icc_profile: None,
+        };
+        assert_eq!(1, broken.orientation());
+        let invalid = Metadata {
+            exif: Some(exif(9, false)),
+            icc_profile: None,
+        };
+        assert_eq!(1, invalid.orientation());
+    }
+
+    #[test]
+    fn other_formats_keep_no_metadata() {
+        let metadata = Metadata {
+            exif: Some(exif(3, false)),
+            icc_profile: Some(Bytes::from_static(b"profile")),
+        };
+
+        assert!(Metadata::read(b"BM not a bitmap").exif.is_none());
+        assert_eq!(
+            b"BM not a bitmap".to_vec(),
+            metadata.write_to(b"BM not a bitmap".to_vec()).unwrap()
+        );
+    }
+}
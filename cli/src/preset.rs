@@ -0,0 +1,178 @@
+use std::path::Path;
+
+use anyhow::Result;
+use filters::FilterStep;
+use image::ImageFormat;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::parse_format;
+
+/// A named chain of filters, along with how to write the result, stored in a TOML file like:
+///
+/// ```toml
+/// name = "web"
+///
+/// [[steps]]
+/// filter = "gaussianblur"
+/// sigma = 1.5
+///
+/// [output]
+/// format = "jpg"
+/// quality = 85
+/// max_width = 1920
+/// max_height = 1080
+/// ```
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    /// Names the output files, like the filters do on the command line.
+    pub name: String,
+    #[serde(default)]
+    pub steps: Vec<FilterStep>,
+    #[serde(default)]
+    pub output: OutputSettings,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSettings {
+    /// The format to write, in place of the one matching the extension of the output.
+    #[serde(default, deserialize_with = "deserialize_format")]
+    pub format: Option<ImageFormat>,
+    /// The quality of jpeg outputs, from 1 to 100.
+    #[serde(default, deserialize_with = "deserialize_quality")]
+    pub quality: Option<u8>,
+    /// Wider images are scaled down to this width, keeping their aspect ratio.
+    pub max_width: Option<u32>,
+    /// Taller images are scaled down to this height, keeping their aspect ratio.
+    pub max_height: Option<u32>,
+}
+
+impl OutputSettings {
+    /// The box an image of `dimensions` should be scaled down to fit in, if it is larger than the maximum
+    /// dimensions.
+    pub fn scale_down(&self, (width, height): (u32, u32)) -> Option<(u32, u32)> {
+        let max = (
+            self.max_width.unwrap_or(width),
+            self.max_height.unwrap_or(height),
+        );
+        (width > max.0 || height > max.1).then_some(max)
+    }
+}
+
+fn deserialize_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<ImageFormat>, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    parse_format(&format).map(Some).map_err(de::Error::custom)
+}
+
+fn deserialize_quality<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+    match u8::deserialize(deserializer)? {
+        quality @ 1..=100 => Ok(Some(quality)),
+        quality => Err(de::Error::custom(format!(
+            "Invalid quality {quality}, expecting 1 to 100"
+        ))),
+    }
+}
+
+pub fn load_preset<P: AsRef<Path>>(path: P) -> Result<Preset> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+
+    toml::from_str(&content)
+        .map_err(|error| anyhow::anyhow!("Invalid preset file {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use filters::{FilterStep, Resize};
+    use image::ImageFormat;
+
+    use super::{load_preset, OutputSettings, Preset};
+
+    fn parse(content: &str) -> Result<Preset, String> {
+        toml::from_str(content).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn parse_preset() {
+        let preset = parse(
+            "name = \"web\"\n\n\
+            [[steps]]\nfilter = \"grayscale\"\n\n\
+            [[steps]]\nfilter = \"fit\"\nwidth = 800\nheight = 600\nmode = \"lanczos3\"\n\n\
+            [output]\nformat = \"JPG\"\nquality = 85\nmax_width = 1920\n",
+        );
+
+        assert_eq!(
+            Ok(Preset {
+                name: "web".to_owned(),
+                steps: vec![
+                    FilterStep::Grayscale,
+                    FilterStep::Fit {
+                        width: 800,
+                        height: 600,
+                        mode: Resize::Lanczos3
+                    }
+                ],
+                output: OutputSettings {
+                    format: Some(ImageFormat::Jpeg),
+                    quality: Some(85),
+                    max_width: Some(1920),
+                    max_height: None,
+                },
+            }),
+            preset
+        );
+    }
+
+    #[test]
+    fn parse_preset_defaults() {
+        assert_eq!(
+            Ok(Preset {
+                name: "nothing".to_owned(),
+                steps: Vec::new(),
+                output: OutputSettings::default(),
+            }),
+            parse("name = \"nothing\"")
+        );
+    }
+
+    #[test]
+    fn unknown_keys_have_line_numbers() {
+        let path = std::env::temp_dir().join("filters_unknown_keys_have_line_numbers.toml");
+        std::fs::write(
+            &path,
+            "name = \"web\"\n\n[output]\nformat = \"png\"\ncompression = 9\n",
+        )
+        .unwrap();
+
+        let error = load_preset(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(error.contains("line 5"), "{error}");
+        assert!(error.contains("unknown field `compression`"), "{error}");
+    }
+
+    #[test]
+    fn invalid_output_settings() {
+        let error = parse("name = \"web\"\n[output]\nquality = 0\n").unwrap_err();
+        assert!(error.contains("line 3"), "{error}");
+        assert!(error.contains("Invalid quality 0"), "{error}");
+
+        let error = parse("name = \"web\"\n[output]\nformat = \"psd\"\n").unwrap_err();
+        assert!(error.contains("Unsupported format psd"), "{error}");
+    }
+
+    #[test]
+    fn scale_down_larger_images() {
+        let output = OutputSettings {
+            max_width: Some(100),
+            ..Default::default()
+        };
+
+        assert_eq!(Some((100, 50)), output.scale_down((200, 50)));
+        assert_eq!(None, output.scale_down((100, 500)));
+        assert_eq!(None, OutputSettings::default().scale_down((4000, 3000)));
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fmt::Display,
+    io::{IsTerminal, Stderr, Write},
+    time::{Duration, Instant},
+};
+
+use filters::BatchProgress;
+
+/// How many characters wide the bar itself is, without the counts and the ETA.
+const BAR_WIDTH: usize = 30;
+/// Moves back to the start of the line and clears it, to draw over the previous bar.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// A bar redrawn in place, for terminals.
+    Bar,
+    /// A line per file, for logs and pipes.
+    Lines,
+    Quiet,
+}
+
+/// Reports how far a batch is, as a bar with an ETA on terminals, and as plain lines without control characters
+/// elsewhere.
+pub struct Progress<W> {
+    out: W,
+    style: Style,
+    start: Instant,
+    /// The bar last drawn, drawn again below the warnings printed over it.
+    bar: Option<String>,
+}
+
+impl Progress<Stderr> {
+    /// Reports on stderr, with a bar if it is a terminal, so that the images written to stdout are left alone.
+    pub fn stderr(quiet: bool) -> Self {
+        let terminal = std::io::stderr().is_terminal();
+        Self::new(std::io::stderr(), terminal, quiet)
+    }
+}
+
+impl<W: Write> Progress<W> {
+    /// # Arguments
+    ///
+    /// * `out` - Where to write the progress.
+    /// * `terminal` - Whether `out` is a terminal, drawing a bar in place of lines.
+    /// * `quiet` - Only print the warnings, no progress nor summary.
+    pub fn new(out: W, terminal: bool, quiet: bool) -> Self {
+        let style = match (quiet, terminal) {
+            (true, _) => Style::Quiet,
+            (false, true) => Style::Bar,
+            (false, false) => Style::Lines,
+        };
+        Self {
+            out,
+            style,
+            start: Instant::now(),
+            bar: None,
+        }
+    }
+
+    /// Reports that the file `name` is done.
+    pub fn update(&mut self, progress: BatchProgress, name: impl Display) {
+        match self.style {
+            Style::Bar => {
+                let bar = format_bar(progress, self.start.elapsed(), &name);
+                let _ = write!(self.out, "{CLEAR_LINE}{bar}");
+                let _ = self.out.flush();
+                self.bar = Some(bar);
+            }
+            Style::Lines => {
+                let _ = writeln!(
+                    self.out,
+                    "[{}/{}] {name}",
+                    progress.completed, progress.total
+                );
+            }
+            Style::Quiet => {}
+        }
+    }
+
+    /// Prints `message` on its own line, even when quiet, keeping the bar below it.
+    pub fn warn(&mut self, message: impl Display) {
+        match &self.bar {
+            Some(bar) => {
+                let _ = write!(self.out, "{CLEAR_LINE}{message}\n{bar}");
+                let _ = self.out.flush();
+            }
+            None => {
+                let _ = writeln!(self.out, "{message}");
+            }
+        }
+    }
+
+    /// Ends the bar, and prints `summary` unless quiet.
+    pub fn finish(&mut self, summary: impl Display) {
+        if self.bar.take().is_some() {
+            let _ = writeln!(self.out);
+        }
+        if self.style != Style::Quiet {
+            let _ = writeln!(self.out, "{summary}");
+        }
+    }
+
+    #[cfg(test)]
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Formats a bar like `[=========>          ] 3/10 00:04 ETA 00:09 photo.jpg`, the ETA assuming the remaining
+/// files take as long as the done ones.
+fn format_bar(progress: BatchProgress, elapsed: Duration, name: impl Display) -> String {
+    let BatchProgress {
+        completed, total, ..
+    } = progress;
+    let filled = (BAR_WIDTH * completed)
+        .checked_div(total)
+        .unwrap_or(BAR_WIDTH);
+    let bar = if filled < BAR_WIDTH {
+        format!(
+            "{}>{}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled - 1)
+        )
+    } else {
+        "=".repeat(BAR_WIDTH)
+    };
+    let remaining = total.saturating_sub(completed) as f64;
+    let eta = elapsed.mul_f64(remaining / completed.max(1) as f64);
+
+    format!(
+        "[{bar}] {completed}/{total} {} ETA {} {name}",
+        format_duration(elapsed),
+        format_duration(eta)
+    )
+}
+
+/// Formats `duration` as minutes and seconds, with hours only when there are some.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use filters::BatchProgress;
+
+    use super::{format_bar, format_duration, Progress};
+
+    fn progress(completed: usize, total: usize) -> BatchProgress {
+        BatchProgress {
+            index: completed - 1,
+            completed,
+            total,
+        }
+    }
+
+    fn run(terminal: bool, quiet: bool) -> String {
+        let mut reporter = Progress::new(Vec::new(), terminal, quiet);
+        reporter.update(progress(1, 3), "a.png");
+        reporter.warn("Skipping b.png: unsupported");
+        reporter.update(progress(2, 3), "b.png");
+        reporter.update(progress(3, 3), "c.png");
+        reporter.finish("Filtered 2 images, skipped 1");
+        String::from_utf8(reporter.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn bar_format() {
+        assert_eq!(
+            "[==========>                   ] 1/3 00:04 ETA 00:08 a.png",
+            format_bar(progress(1, 3), Duration::from_secs(4), "a.png")
+        );
+        assert_eq!(
+            "[==============================] 3/3 01:02 ETA 00:00 c.png",
+            format_bar(progress(3, 3), Duration::from_secs(62), "c.png")
+        );
+    }
+
+    #[test]
+    fn duration_format() {
+        assert_eq!("00:00", format_duration(Duration::from_millis(999)));
+        assert_eq!("02:05", format_duration(Duration::from_secs(125)));
+        assert_eq!("1:00:01", format_duration(Duration::from_secs(3601)));
+    }
+
+    #[test]
+    fn bar_on_terminals() {
+        let output = run(true, false);
+
+        // Three updates, and the first bar drawn again below the warning.
+        assert_eq!(4, output.matches(" ETA ").count(), "{output:?}");
+        assert!(output.contains("\x1b[2KSkipping b.png: unsupported\n[==========>"));
+        assert!(output.ends_with(" c.png\nFiltered 2 images, skipped 1\n"));
+    }
+
+    #[test]
+    fn lines_elsewhere() {
+        assert_eq!(
+            "[1/3] a.png\nSkipping b.png: unsupported\n[2/3] b.png\n[3/3] c.png\nFiltered 2 images, skipped 1\n",
+            run(false, false)
+        );
+    }
+
+    #[test]
+    fn quiet_only_warns() {
+        assert_eq!("Skipping b.png: unsupported\n", run(true, true));
+        assert_eq!("Skipping b.png: unsupported\n", run(false, true));
+    }
+}
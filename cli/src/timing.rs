@@ -0,0 +1,184 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+/// How `--timing` prints the time each stage took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingFormat {
+    Text,
+    Json,
+}
+
+pub fn parse_timing_format(input: &str) -> Result<TimingFormat, String> {
+    match input {
+        "text" => Ok(TimingFormat::Text),
+        "json" => Ok(TimingFormat::Json),
+        _ => Err(format!(
+            "Unknown timing format {input}, expecting text or json"
+        )),
+    }
+}
+
+/// How the time of each filter was measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterClock {
+    /// On the gpu, with timestamp queries around each pass.
+    Gpu,
+    /// On the cpu, waiting for the gpu after each filter, which makes the whole a bit slower.
+    Wall,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageTiming {
+    pub name: String,
+    pub ms: f64,
+}
+
+/// How long each stage of filtering an image took, in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Timings {
+    pub decode_ms: f64,
+    pub upload_ms: f64,
+    pub filter_clock: FilterClock,
+    /// The filters in order, or their passes when timed on the gpu.
+    pub filters: Vec<StageTiming>,
+    pub readback_ms: f64,
+    pub encode_ms: f64,
+    pub total_ms: f64,
+}
+
+impl Timings {
+    pub fn format(&self, format: TimingFormat) -> String {
+        if format == TimingFormat::Json {
+            return serde_json::to_string(self).expect("Timings are serializable");
+        }
+
+        let clock = match self.filter_clock {
+            FilterClock::Gpu => "gpu",
+            FilterClock::Wall => "wall clock",
+        };
+        let mut lines = vec![
+            ("decode", self.decode_ms, ""),
+            ("upload", self.upload_ms, ""),
+        ];
+        lines.extend(
+            self.filters
+                .iter()
+                .map(|filter| (filter.name.as_str(), filter.ms, clock)),
+        );
+        lines.extend([
+            ("readback", self.readback_ms, ""),
+            ("encode", self.encode_ms, ""),
+            ("total", self.total_ms, ""),
+        ]);
+
+        let width = lines.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+        lines
+            .iter()
+            .map(|(name, ms, clock)| {
+                format!("{name:<width$}  {ms:>10.2} ms {clock}")
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Measures the time between successive laps, and since it started.
+pub struct Stopwatch {
+    start: Instant,
+    last: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last: now,
+        }
+    }
+
+    /// The milliseconds since the previous lap, or since the start for the first one.
+    pub fn lap(&mut self) -> f64 {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        elapsed.as_secs_f64() * 1000.0
+    }
+
+    pub fn total(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_timing_format, FilterClock, StageTiming, TimingFormat, Timings};
+
+    fn timings() -> Timings {
+        Timings {
+            decode_ms: 1.5,
+            upload_ms: 0.25,
+            filter_clock: FilterClock::Wall,
+            filters: vec![
+                StageTiming {
+                    name: "grayscale".to_owned(),
+                    ms: 0.5,
+                },
+                StageTiming {
+                    name: "gaussianblur".to_owned(),
+                    ms: 12.0,
+                },
+            ],
+            readback_ms: 2.0,
+            encode_ms: 3.0,
+            total_ms: 19.25,
+        }
+    }
+
+    #[test]
+    fn parse_timing_formats() {
+        assert_eq!(Ok(TimingFormat::Text), parse_timing_format("text"));
+        assert_eq!(Ok(TimingFormat::Json), parse_timing_format("json"));
+        assert!(parse_timing_format("csv").is_err());
+    }
+
+    #[test]
+    fn format_text() {
+        assert_eq!(
+            "decode              1.50 ms\n\
+            upload              0.25 ms\n\
+            grayscale           0.50 ms wall clock\n\
+            gaussianblur       12.00 ms wall clock\n\
+            readback            2.00 ms\n\
+            encode              3.00 ms\n\
+            total              19.25 ms",
+            timings().format(TimingFormat::Text)
+        );
+    }
+
+    #[test]
+    fn format_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&timings().format(TimingFormat::Json)).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "decode_ms": 1.5,
+                "upload_ms": 0.25,
+                "filter_clock": "wall",
+                "filters": [
+                    { "name": "grayscale", "ms": 0.5 },
+                    { "name": "gaussianblur", "ms": 12.0 }
+                ],
+                "readback_ms": 2.0,
+                "encode_ms": 3.0,
+                "total_ms": 19.25
+            }),
+            json
+        );
+    }
+}
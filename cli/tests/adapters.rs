@@ -0,0 +1,39 @@
+use std::process::Command;
+
+use image::RgbaImage;
+
+#[test]
+fn list_adapters() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("--list-adapters")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.lines().count() > 0);
+    assert!(
+        stdout.lines().all(|line| line.contains("textures up to")),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn unknown_adapter() {
+    let input = std::env::temp_dir().join("filters_unknown_adapter.png");
+    RgbaImage::new(2, 2).save(&input).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap(), "--filter", "grayscale"])
+        .args(["--adapter", "no such adapter, surely"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&input).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("No adapter matching no such adapter, surely, available adapters are:"),
+        "{stderr}"
+    );
+}
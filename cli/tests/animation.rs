@@ -0,0 +1,104 @@
+use std::{io::Cursor, path::Path, process::Command};
+
+use image::{
+    codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
+        png::PngDecoder,
+    },
+    AnimationDecoder, Delay, Frame, Frames, Rgba, RgbaImage,
+};
+
+fn run(input: &Path, output: &Path, filter: &str) {
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--filter", filter])
+        .output()
+        .unwrap();
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+}
+
+/// Three frames of 6 by 4 pixels, red, green then blue, shown for 50, 100 and 200 ms, looping forever.
+fn write_fixture(path: &Path) {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        encoder.set_repeat(Repeat::Infinite).unwrap();
+        let frames = [
+            ([255, 0, 0, 255], 50),
+            ([0, 255, 0, 255], 100),
+            ([0, 0, 255, 255], 200),
+        ]
+        .into_iter()
+        .map(|(color, delay)| {
+            Frame::from_parts(
+                RgbaImage::from_pixel(6, 4, Rgba(color)),
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay, 1),
+            )
+        });
+        encoder.encode_frames(frames).unwrap();
+    }
+    std::fs::write(path, bytes).unwrap();
+}
+
+fn check_frames(frames: Frames, dimensions: (u32, u32)) {
+    let frames = frames.collect_frames().unwrap();
+
+    assert_eq!(3, frames.len());
+    let delays = frames
+        .iter()
+        .map(|frame| frame.delay().numer_denom_ms())
+        .collect::<Vec<_>>();
+    assert_eq!(vec![(50, 1), (100, 1), (200, 1)], delays);
+    for frame in &frames {
+        assert_eq!(dimensions, frame.buffer().dimensions());
+        let Rgba([red, green, blue, alpha]) = *frame.buffer().get_pixel(1, 1);
+        assert_eq!((red, red, 255), (green, blue, alpha));
+    }
+    // Red, green and blue don't have the same luminance.
+    assert_ne!(
+        frames[0].buffer().get_pixel(0, 0),
+        frames[1].buffer().get_pixel(0, 0)
+    );
+}
+
+#[test]
+fn grayscale_gif() {
+    let directory = std::env::temp_dir().join("filters_grayscale_gif");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let (input, output) = (directory.join("input.gif"), directory.join("output.gif"));
+    write_fixture(&input);
+
+    run(&input, &output, "grayscale");
+    let bytes = std::fs::read(&output).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    check_frames(
+        GifDecoder::new(Cursor::new(&bytes)).unwrap().into_frames(),
+        (6, 4),
+    );
+}
+
+#[test]
+fn resized_apng() {
+    let directory = std::env::temp_dir().join("filters_resized_apng");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let (input, output) = (directory.join("input.gif"), directory.join("output.png"));
+    write_fixture(&input);
+
+    run(&input, &output, "grayscale|resize=3x2");
+    let bytes = std::fs::read(&output).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let decoder = PngDecoder::new(Cursor::new(&bytes)).unwrap();
+    assert!(decoder.is_apng());
+    check_frames(decoder.apng().into_frames(), (3, 2));
+}
@@ -0,0 +1,236 @@
+use std::{path::Path, process::Command};
+
+use image::{Rgba, RgbaImage};
+
+#[test]
+fn filters_directory() {
+    let root = std::env::temp_dir().join("filters_batch");
+    let (input_dir, output_dir) = (root.join("input"), root.join("output"));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&input_dir).unwrap();
+    for (index, name) in ["a.png", "b.bmp", "c.tiff"].into_iter().enumerate() {
+        RgbaImage::from_pixel(4, 3, Rgba([index as u8 * 80, 10, 20, 255]))
+            .save(input_dir.join(name))
+            .unwrap();
+    }
+    std::fs::write(input_dir.join("junk.png"), "not an image").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--input-dir", input_dir.to_str().unwrap()])
+        .args(["--output-dir", output_dir.to_str().unwrap()])
+        .args(["--filter", "inverse"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let mut outputs = std::fs::read_dir(&output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    outputs.sort();
+    let inverted = image::open(output_dir.join("b.bmp")).unwrap().into_rgba8();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(vec!["a.png", "b.bmp", "c.tiff"], outputs);
+    assert_eq!(&Rgba([175, 245, 235, 255]), inverted.get_pixel(3, 2));
+    assert_eq!(1, stderr.matches("Skipping").count(), "{stderr}");
+    assert!(stderr.contains("junk.png"), "{stderr}");
+    assert!(stderr.contains("Filtered 3 images, skipped 1"), "{stderr}");
+}
+
+/// Filters `input_dir` into `output_dir` on `jobs` threads, returning the names and contents of the outputs.
+fn filter_with_jobs(input_dir: &Path, output_dir: &Path, jobs: &str) -> Vec<(String, Vec<u8>)> {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--input-dir", input_dir.to_str().unwrap()])
+        .args(["--output-dir", output_dir.to_str().unwrap()])
+        .args(["--jobs", jobs])
+        .args(["--filter", "grayscale|gaussianblur(1.5)"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut outputs = std::fs::read_dir(output_dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name().into_string().unwrap(),
+                std::fs::read(entry.path()).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    outputs.sort();
+    outputs
+}
+
+#[test]
+fn parallel_jobs_match_sequential() {
+    let root = std::env::temp_dir().join("filters_batch_jobs");
+    let input_dir = root.join("input");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&input_dir).unwrap();
+    for index in 0..20u32 {
+        RgbaImage::from_fn(16 + index, 12, |x, y| {
+            Rgba([(x * 15) as u8, (y * 20) as u8, (index * 12) as u8, 255])
+        })
+        .save(input_dir.join(format!("image{index:02}.png")))
+        .unwrap();
+    }
+
+    let sequential = filter_with_jobs(&input_dir, &root.join("sequential"), "1");
+    let parallel = filter_with_jobs(&input_dir, &root.join("parallel"), "4");
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(20, parallel.len());
+    assert_eq!(sequential, parallel);
+}
+
+/// Creates an image at each of `paths`, relative to `root`, creating directories as needed.
+fn create_tree(root: &Path, paths: &[&str]) {
+    for path in paths {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        RgbaImage::from_pixel(3, 2, Rgba([40, 80, 120, 255]))
+            .save(path)
+            .unwrap();
+    }
+}
+
+/// Lists the files under `root`, relative to it, with `/` separators.
+fn list_tree(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut directories = vec![root.to_owned()];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                directories.push(path);
+            } else {
+                let relative = path.strip_prefix(root).unwrap();
+                files.push(relative.to_str().unwrap().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn filter_tree(input_dir: &Path, output_dir: &Path, extra_args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--input-dir", input_dir.to_str().unwrap()])
+        .args(["--output-dir", output_dir.to_str().unwrap()])
+        .args(extra_args)
+        .args(["--filter", "inverse"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(output.status.success(), "{stderr}");
+    stderr
+}
+
+#[test]
+fn recursive_mirrors_structure() {
+    let root = std::env::temp_dir().join("filters_batch_recursive");
+    let input_dir = root.join("input");
+    let _ = std::fs::remove_dir_all(&root);
+    create_tree(
+        &input_dir,
+        &["a.png", "sub/b.png", "sub/deeper/c.bmp", "other/d.png"],
+    );
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&input_dir, input_dir.join("sub/loop")).unwrap();
+
+    filter_tree(&input_dir, &root.join("flat"), &[]);
+    filter_tree(&input_dir, &root.join("all"), &["--recursive"]);
+    filter_tree(
+        &input_dir,
+        &root.join("shallow"),
+        &["--recursive", "--max-depth", "1"],
+    );
+    let trees = ["flat", "all", "shallow"].map(|tree| list_tree(&root.join(tree)));
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(vec!["a.png"], trees[0]);
+    assert_eq!(
+        vec!["a.png", "other/d.png", "sub/b.png", "sub/deeper/c.bmp"],
+        trees[1]
+    );
+    assert_eq!(vec!["a.png", "other/d.png", "sub/b.png"], trees[2]);
+}
+
+#[test]
+fn existing_outputs_need_force() {
+    let root = std::env::temp_dir().join("filters_batch_force");
+    let (input_dir, output_dir) = (root.join("input"), root.join("output"));
+    let _ = std::fs::remove_dir_all(&root);
+    create_tree(&input_dir, &["a.png", "b.png"]);
+    std::fs::create_dir_all(&output_dir).unwrap();
+    std::fs::write(output_dir.join("a.png"), "existing").unwrap();
+
+    let skipping = filter_tree(&input_dir, &output_dir, &[]);
+    let kept = std::fs::read(output_dir.join("a.png")).unwrap();
+    let forcing = filter_tree(&input_dir, &output_dir, &["--force"]);
+    let overwritten = image::open(output_dir.join("a.png")).unwrap().into_rgba8();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(skipping.contains("already exists"), "{skipping}");
+    assert!(
+        skipping.contains("Filtered 1 images, skipped 1"),
+        "{skipping}"
+    );
+    assert_eq!(b"existing".to_vec(), kept);
+    assert!(
+        forcing.contains("Filtered 2 images, skipped 0"),
+        "{forcing}"
+    );
+    assert_eq!(&Rgba([215, 175, 135, 255]), overwritten.get_pixel(0, 0));
+}
+
+#[test]
+fn input_dir_conflicts_with_input() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--input-dir", ".", "--output-dir", "out", "-i", "a.png"])
+        .args(["--filter", "inverse"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn quiet_prints_only_warnings() {
+    let root = std::env::temp_dir().join("filters_batch_quiet");
+    let (input_dir, output_dir) = (root.join("input"), root.join("output"));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&input_dir).unwrap();
+    for name in ["a.png", "b.png"] {
+        RgbaImage::new(3, 3).save(input_dir.join(name)).unwrap();
+    }
+    std::fs::write(input_dir.join("junk.png"), "not an image").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--input-dir", input_dir.to_str().unwrap()])
+        .args(["--output-dir", output_dir.to_str().unwrap()])
+        .args(["--filter", "grayscale", "--quiet"])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        !stdout
+            .chars()
+            .chain(stderr.chars())
+            .any(|c| c.is_control() && c != '\n'),
+        "{stdout:?} {stderr:?}"
+    );
+    assert_eq!("", stdout);
+    assert_eq!(1, stderr.lines().count(), "{stderr}");
+    assert!(stderr.starts_with("Skipping"), "{stderr}");
+}
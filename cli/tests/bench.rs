@@ -0,0 +1,29 @@
+use std::process::Command;
+
+#[test]
+fn tiny_bench() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["bench", "--size", "64", "--iterations", "2"])
+        .args(["--filter", "grayscale|boxblur=3"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!("chain       grayscale|boxblur", lines[0]);
+    assert_eq!("image       64x64, 2 iterations", lines[1]);
+    for (line, label) in lines[2..].iter().zip(["warm-up", "min", "median", "mean"]) {
+        assert!(line.starts_with(label), "{stdout}");
+        let ms = line[label.len()..].trim().strip_suffix(" ms").unwrap();
+        assert!(ms.parse::<f64>().unwrap() >= 0.0, "{stdout}");
+    }
+    let throughput = lines[6].strip_prefix("throughput").unwrap();
+    let throughput = throughput.trim().strip_suffix(" MP/s").unwrap();
+    assert!(throughput.parse::<f64>().unwrap() > 0.0, "{stdout}");
+    assert_eq!(7, lines.len(), "{stdout}");
+}
@@ -0,0 +1,56 @@
+use std::{collections::HashSet, path::Path, process::Command};
+
+use image::{ImageBuffer, Rgba};
+
+fn run(input: &Path, output: &Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--filter", "inverse", "--bit-depth", "16"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn inverse_16_bit_gradient() {
+    let directory = std::env::temp_dir().join("filters_inverse_16_bit_gradient");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("gradient.png");
+    // A thousand levels of red, four times as many as 8 bits hold.
+    ImageBuffer::from_fn(1000, 2, |x, _| Rgba([x as u16 * 65, 32768, 0, u16::MAX]))
+        .save(&input)
+        .unwrap();
+
+    let png = run(&input, &directory.join("inverted.png"));
+    let jpeg = run(&input, &directory.join("inverted.jpg"));
+    let inverted = image::open(directory.join("inverted.png"));
+    let jpeg_written = directory.join("inverted.jpg").exists();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(
+        png.status.success(),
+        "{}",
+        String::from_utf8_lossy(&png.stderr)
+    );
+    let inverted = inverted.unwrap().to_rgba16();
+    let levels = inverted
+        .pixels()
+        .map(|Rgba([red, ..])| *red)
+        .collect::<HashSet<_>>();
+    assert!(levels.len() > 256, "Only {} levels", levels.len());
+    for (x, _, Rgba([red, _, _, alpha])) in inverted.enumerate_pixels() {
+        let expected = u16::MAX - x as u16 * 65;
+        // The passes work on half floats, which keep 11 bits of precision.
+        assert!(red.abs_diff(expected) <= 32, "{red} for {expected}");
+        assert_eq!(u16::MAX, *alpha);
+    }
+
+    let stderr = String::from_utf8_lossy(&jpeg.stderr);
+    assert!(!jpeg.status.success());
+    assert!(
+        stderr.contains("16-bit output is only supported for png files"),
+        "{stderr}"
+    );
+    assert!(!jpeg_written);
+}
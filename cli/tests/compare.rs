@@ -0,0 +1,62 @@
+use std::{path::Path, process::Command};
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+fn compare(args: &[&Path], options: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("compare")
+        .args(args)
+        .args(options)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn compare_brightness_shift() {
+    let directory = std::env::temp_dir().join("filters_compare_brightness_shift");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let (expected, actual, heatmap, small) = (
+        directory.join("expected.png"),
+        directory.join("actual.png"),
+        directory.join("heatmap.png"),
+        directory.join("small.png"),
+    );
+    let gradient = |shift: u8| {
+        RgbaImage::from_fn(12, 10, move |x, y| {
+            Rgba([x as u8 * 20 + shift, y as u8 * 20 + shift, 100 + shift, 255])
+        })
+    };
+    gradient(0).save(&expected).unwrap();
+    gradient(8).save(&actual).unwrap();
+    RgbaImage::new(6, 5).save(&small).unwrap();
+
+    let within = compare(&[&expected, &actual], &["--threshold", "8"]);
+    let beyond = compare(
+        &[&expected, &actual],
+        &["--threshold", "7", "--heatmap", heatmap.to_str().unwrap()],
+    );
+    let mismatched = compare(&[&expected, &small], &[]);
+    let heatmap_dimensions = image::open(&heatmap).unwrap().dimensions();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let stdout = String::from_utf8_lossy(&within.stdout);
+    assert!(within.status.success(), "{stdout}");
+    assert!(stdout.contains("max channel delta    8\n"), "{stdout}");
+    assert!(stdout.contains("ssim"), "{stdout}");
+
+    let stderr = String::from_utf8_lossy(&beyond.stderr);
+    assert!(!beyond.status.success());
+    assert!(
+        stderr.contains("differ by up to 8, more than the threshold of 7"),
+        "{stderr}"
+    );
+    assert_eq!((12, 10), heatmap_dimensions);
+
+    let stderr = String::from_utf8_lossy(&mismatched.stderr);
+    assert!(!mismatched.status.success());
+    assert!(
+        stderr.contains("Expected dimensions 12x10, got 6x5"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,68 @@
+use std::{path::Path, process::Command};
+
+use image::{Rgba, RgbaImage};
+
+fn run(input: &Path, output: &Path, background: Option<&str>) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--filter", "hflip"])
+        .args(
+            background
+                .map(|color| ["--background", color])
+                .into_iter()
+                .flatten(),
+        )
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn jpeg_flattened_onto_background() {
+    let directory = std::env::temp_dir().join("filters_jpeg_flattened_onto_background");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("transparent.png");
+    // Half transparent red on the left, fully transparent black on the right.
+    RgbaImage::from_fn(32, 16, |x, _| {
+        if x < 16 {
+            Rgba([255, 0, 0, 128])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+    .save(&input)
+    .unwrap();
+
+    let white = run(&input, &directory.join("white.jpg"), None);
+    let navy = run(&input, &directory.join("navy.jpg"), Some("#000080"));
+    let png = run(&input, &directory.join("kept.png"), None);
+    let images =
+        ["white.jpg", "navy.jpg", "kept.png"].map(|name| image::open(directory.join(name)));
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    for output in [&white, &navy, &png] {
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let [white, navy, png] = images.map(|image| image.unwrap().to_rgba8());
+    let close = |actual: &Rgba<u8>, expected: [u8; 3]| {
+        actual.0[..3]
+            .iter()
+            .zip(expected)
+            .all(|(actual, expected)| actual.abs_diff(expected) <= 4)
+    };
+    // Flipped, the transparent half is now on the left.
+    let (transparent, red) = (white.get_pixel(4, 8), white.get_pixel(28, 8));
+    assert!(close(transparent, [255, 255, 255]), "{transparent:?}");
+    assert!(close(red, [255, 127, 127]), "{red:?}");
+    let (transparent, red) = (navy.get_pixel(4, 8), navy.get_pixel(28, 8));
+    assert!(close(transparent, [0, 0, 128]), "{transparent:?}");
+    assert!(close(red, [128, 0, 64]), "{red:?}");
+    // Formats with alpha keep it.
+    assert_eq!(&Rgba([0, 0, 0, 0]), png.get_pixel(4, 8));
+    assert_eq!(&Rgba([255, 0, 0, 128]), png.get_pixel(28, 8));
+}
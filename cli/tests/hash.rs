@@ -0,0 +1,67 @@
+use std::process::Command;
+
+use image::{Rgba, RgbaImage};
+
+#[test]
+fn hash_finds_duplicates() {
+    let directory = std::env::temp_dir().join("filters_hash_finds_duplicates");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let paths = ["original.png", "brightened.png", "stripes.png"].map(|name| directory.join(name));
+    let gradient = |shift: u8| {
+        RgbaImage::from_fn(64, 48, move |x, y| {
+            let value = if x > 40 && y < 20 {
+                230
+            } else {
+                (x * 2 + y) as u8
+            };
+            Rgba([
+                value.saturating_add(shift),
+                value.saturating_add(shift),
+                value,
+                255,
+            ])
+        })
+    };
+    gradient(0).save(&paths[0]).unwrap();
+    gradient(10).save(&paths[1]).unwrap();
+    RgbaImage::from_fn(64, 48, |x, _| {
+        let value = if (x / 8) % 2 == 0 { 20 } else { 240 };
+        Rgba([value, value, value, 255])
+    })
+    .save(&paths[2])
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("hash")
+        .args(&paths)
+        .output()
+        .unwrap();
+    let unknown = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["hash", "--algorithm", "md5"])
+        .arg(&paths[0])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hashes = stdout
+        .lines()
+        .map(|line| {
+            let (hash, path) = line.split_once("  ").unwrap();
+            assert!(path.ends_with(".png"), "{line}");
+            u64::from_str_radix(hash, 16).unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(3, hashes.len(), "{stdout}");
+    assert!((hashes[0] ^ hashes[1]).count_ones() <= 4, "{stdout}");
+    assert!((hashes[0] ^ hashes[2]).count_ones() >= 16, "{stdout}");
+
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("Unknown hash algorithm md5"));
+}
@@ -0,0 +1,84 @@
+use std::{io::Cursor, path::Path, process::Command};
+
+use image::{GenericImageView, ImageOutputFormat, Rgba, RgbaImage};
+use img_parts::{jpeg::Jpeg, png::Png, Bytes, ImageEXIF, ImageICC};
+
+const ICC_PROFILE: &[u8] = b"not quite an icc profile, but as opaque to the cli";
+
+/// A little endian TIFF structure with a single orientation entry.
+fn exif(orientation: u8) -> Vec<u8> {
+    let mut exif = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0".to_vec();
+    exif.extend([orientation, 0, 0, 0, 0, 0, 0, 0]);
+    exif
+}
+
+/// A jpeg of 16 by 8 pixels, red on the left and blue on the right, to be turned a quarter clockwise.
+fn write_fixture(path: &Path) {
+    let mut bytes = Vec::new();
+    RgbaImage::from_fn(16, 8, |x, _| {
+        if x < 8 {
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([0, 0, 255, 255])
+        }
+    })
+    .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Jpeg(95))
+    .unwrap();
+    let mut jpeg = Jpeg::from_bytes(bytes.into()).unwrap();
+    jpeg.set_exif(Some(exif(6).into()));
+    jpeg.set_icc_profile(Some(Bytes::from_static(ICC_PROFILE)));
+    std::fs::write(path, jpeg.encoder().bytes()).unwrap();
+}
+
+fn run(directory: &Path, args: &[&str]) -> Vec<u8> {
+    let (input, output) = (directory.join("photo.jpg"), directory.join("output.png"));
+    write_fixture(&input);
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--filter", "inverse"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    std::fs::read(output).unwrap()
+}
+
+#[test]
+fn orientation_and_metadata() {
+    let directory = std::env::temp_dir().join("filters_orientation_and_metadata");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let upright = run(&directory, &["--keep-metadata"]);
+    let ignored = run(&directory, &["--respect-exif=false"]);
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let image = image::load_from_memory(&upright).unwrap();
+    assert_eq!((8, 16), image.dimensions());
+    // Turned clockwise, the left half ends up on top, inverted from red to cyan.
+    let Rgba([red, green, blue, _]) = image.get_pixel(4, 3);
+    assert!(
+        red < 30 && green > 225 && blue > 225,
+        "{red} {green} {blue}"
+    );
+    let Rgba([red, green, blue, _]) = image.get_pixel(4, 12);
+    assert!(
+        red > 225 && green > 225 && blue < 30,
+        "{red} {green} {blue}"
+    );
+
+    let png = Png::from_bytes(upright.into()).unwrap();
+    assert_eq!(Some(Bytes::from_static(ICC_PROFILE)), png.icc_profile());
+    // The pixels being upright, viewers must not turn them again.
+    assert_eq!(Some(Bytes::from(exif(1))), png.exif());
+
+    let image = image::load_from_memory(&ignored).unwrap();
+    assert_eq!((16, 8), image.dimensions());
+    let png = Png::from_bytes(ignored.into()).unwrap();
+    assert_eq!(None, png.icc_profile());
+}
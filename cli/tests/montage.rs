@@ -0,0 +1,53 @@
+use std::{path::Path, process::Command};
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+fn run(directory: &Path, args: &[&str]) -> RgbaImage {
+    let (input, output) = (directory.join("input.png"), directory.join("output.png"));
+    RgbaImage::from_fn(12, 10, |x, y| Rgba([x as u8 * 20, y as u8 * 25, 100, 255]))
+        .save(&input)
+        .unwrap();
+    let result = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--filter", "inverse"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    image::open(output).unwrap().to_rgba8()
+}
+
+#[test]
+fn original_next_to_filtered() {
+    let directory = std::env::temp_dir().join("filters_montage");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let horizontal = run(
+        &directory,
+        &["--montage", "--gutter", "3", "--gutter-color", "ff00ff"],
+    );
+    let vertical = run(&directory, &["--montage", "vertical", "--gutter", "2"]);
+    let original = image::open(directory.join("input.png")).unwrap().to_rgba8();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let mut inverted = original.clone();
+    image::imageops::invert(&mut inverted);
+    assert_eq!((12 * 2 + 3, 10), horizontal.dimensions());
+    assert_eq!(original, horizontal.view(0, 0, 12, 10).to_image());
+    assert_eq!(inverted, horizontal.view(15, 0, 12, 10).to_image());
+    assert_eq!(
+        RgbaImage::from_pixel(3, 10, Rgba([255, 0, 255, 255])),
+        horizontal.view(12, 0, 3, 10).to_image()
+    );
+
+    assert_eq!((12, 10 * 2 + 2), vertical.dimensions());
+    assert_eq!(original, vertical.view(0, 0, 12, 10).to_image());
+    assert_eq!(inverted, vertical.view(0, 12, 12, 10).to_image());
+    assert_eq!(&Rgba([255, 255, 255, 255]), vertical.get_pixel(0, 11));
+}
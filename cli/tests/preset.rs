@@ -0,0 +1,96 @@
+use std::{path::Path, process::Command};
+
+use image::{GenericImageView, Rgba, RgbaImage};
+
+fn run(args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+#[test]
+fn preset_matches_command_line() {
+    let directory = std::env::temp_dir().join("filters_preset_matches_command_line");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("fixture.png");
+    RgbaImage::from_fn(20, 14, |x, y| {
+        Rgba([(x * 12) as u8, (y * 18) as u8, 60, 255])
+    })
+    .save(&input)
+    .unwrap();
+    let preset = directory.join("soft.toml");
+    std::fs::write(
+        &preset,
+        "name = \"soft\"\n\n\
+        [[steps]]\nfilter = \"grayscale\"\n\n\
+        [[steps]]\nfilter = \"gaussianblur\"\nsigma = 1.5\n\n\
+        [output]\nformat = \"bmp\"\n",
+    )
+    .unwrap();
+
+    run(&[
+        "-i",
+        path(&input),
+        "--preset",
+        path(&preset),
+        "--filter",
+        "inverse",
+    ]);
+    run(&[
+        "-i",
+        path(&input),
+        "-o",
+        path(&directory.join("explicit.bmp")),
+        "--filter",
+        "grayscale|gaussianblur(1.5)",
+        "inverse",
+    ]);
+    // The preset names the output and picks its format.
+    let from_preset = std::fs::read(directory.join("fixture_soft_inverse.bmp")).unwrap();
+    let explicit = std::fs::read(directory.join("explicit.bmp")).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(explicit, from_preset);
+}
+
+#[test]
+fn preset_scales_down_larger_images() {
+    let directory = std::env::temp_dir().join("filters_preset_scales_down");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("fixture.png");
+    RgbaImage::from_pixel(40, 20, Rgba([10, 20, 30, 255]))
+        .save(&input)
+        .unwrap();
+    let preset = directory.join("small.toml");
+    std::fs::write(
+        &preset,
+        "name = \"small\"\n\n[output]\nformat = \"jpg\"\nquality = 90\nmax_width = 10\n",
+    )
+    .unwrap();
+    let output = directory.join("small.jpg");
+
+    run(&[
+        "-i",
+        path(&input),
+        "-o",
+        path(&output),
+        "--preset",
+        path(&preset),
+    ]);
+    let dimensions = image::open(&output).unwrap().dimensions();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!((10, 5), dimensions);
+}
@@ -0,0 +1,102 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use image::{Rgba, RgbaImage};
+
+fn cli(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn stdin_to_stdout_matches_files() {
+    let directory = std::env::temp_dir();
+    let input = directory.join("filters_stdio_input.png");
+    let output = directory.join("filters_stdio_output.png");
+    RgbaImage::from_fn(9, 6, |x, y| {
+        Rgba([(x * 25) as u8, (y * 40) as u8, 120, 255])
+    })
+    .save(&input)
+    .unwrap();
+
+    let from_files = cli(
+        &[
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--filter",
+            "grayscale",
+        ],
+        &[],
+    );
+    assert!(from_files.status.success(), "{from_files:?}");
+    let expected = std::fs::read(&output).unwrap();
+    let piped = cli(
+        &[
+            "--input",
+            "-",
+            "--output",
+            "-",
+            "--format",
+            "png",
+            "--filter",
+            "grayscale",
+        ],
+        &std::fs::read(&input).unwrap(),
+    );
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&output).unwrap();
+
+    assert!(piped.status.success(), "{piped:?}");
+    assert_eq!(expected, piped.stdout);
+}
+
+#[test]
+fn stdout_requires_format() {
+    let output = cli(
+        &["--input", "-", "--output", "-", "--filter", "grayscale"],
+        &[],
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--format"));
+}
+
+#[test]
+fn stdin_requires_output() {
+    let output = cli(&["--input", "-", "--filter", "grayscale"], &[]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--output"));
+}
+
+#[test]
+fn undecodable_stdin() {
+    let output = cli(
+        &[
+            "-i",
+            "-",
+            "-o",
+            "-",
+            "--format",
+            "png",
+            "--filter",
+            "grayscale",
+        ],
+        b"not an image",
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Couldn't decode the image read from stdin"));
+}
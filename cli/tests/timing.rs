@@ -0,0 +1,55 @@
+use std::process::Command;
+
+use image::{Rgba, RgbaImage};
+
+#[test]
+fn json_timings() {
+    let directory = std::env::temp_dir().join("filters_json_timings");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let input = directory.join("fixture.png");
+    RgbaImage::from_fn(32, 24, |x, y| {
+        Rgba([(x * 8) as u8, (y * 10) as u8, 50, 255])
+    })
+    .save(&input)
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", directory.join("output.png").to_str().unwrap()])
+        .args(["--filter", "grayscale|boxblur(3)|inverse"])
+        .args(["--timing", "--timing-format", "json"])
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .unwrap_or_else(|| panic!("No JSON in {stderr}"));
+    let timings: serde_json::Value = serde_json::from_str(line).unwrap();
+    for stage in [
+        "decode_ms",
+        "upload_ms",
+        "readback_ms",
+        "encode_ms",
+        "total_ms",
+    ] {
+        assert!(timings[stage].as_f64().unwrap() >= 0.0, "{stage}");
+    }
+    let names = timings["filters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|filter| filter["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    // Timed on the gpu, a filter can take several passes, like the two of a box blur.
+    for filter in ["grayscale", "inverse"] {
+        assert!(names.contains(&filter), "{names:?}");
+    }
+    if timings["filter_clock"] == "wall" {
+        assert_eq!(vec!["grayscale", "boxblur", "inverse"], names);
+    }
+}
@@ -0,0 +1,258 @@
+//! Times the upload, the passes and the readback separately, on square images of a few sizes, along with frames
+//! going through a `FrameProcessor` and operations building their pipelines.
+//!
+//! The passes are timed on an image already on the gpu, with [`Operation::submit_only`] waiting for them to
+//! finish without reading the result back. Run with `cargo bench -p filters`, or `cargo bench -p filters -- 512`
+//! for the smallest size only.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use filters::{FilterChain, Filters, FiltersOptions, Image, Operation, Resize, Rgba};
+use pollster::FutureExt;
+use wgpu::{
+    DeviceDescriptor, Instance, RequestAdapterOptions, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages,
+};
+
+const SIZES: [u32; 3] = [512, 2048, 4096];
+
+fn test_image(size: u32) -> Image {
+    Image::from_fn(size, size, |x, y| {
+        Rgba::new(x as u8, y as u8, (x ^ y) as u8, 255)
+    })
+}
+
+/// Uploads `image` and waits for it to be on the gpu, so that the operation only times its passes.
+fn uploaded<'a>(filters: &'a Filters, image: &Image) -> Operation<'a> {
+    let (texture, size) = image.operation(filters).unwrap().into_texture();
+    filters.wait_idle();
+    let descriptor = TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+    };
+    Operation::from_texture(filters, texture, &descriptor).unwrap()
+}
+
+/// Many small passes, whose settings go through push constants when the device supports them, or through uniform
+/// buffers otherwise.
+fn many_brightness_passes(operation: Operation<'_>) -> Operation<'_> {
+    (0..100).fold(operation, |operation, _| operation.brightness(0.001))
+}
+
+/// Times `filter` on each size, excluding the upload.
+fn bench_passes<F>(criterion: &mut Criterion, filters: &Filters, name: &str, filter: F)
+where
+    F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+{
+    let mut group = criterion.benchmark_group(name);
+    group.sample_size(10);
+    for size in SIZES {
+        let image = test_image(size);
+        group.throughput(Throughput::Elements(size as u64 * size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |bencher| {
+            bencher.iter_batched(
+                || uploaded(filters, &image),
+                |operation| filter(operation).submit_only(),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn benches(criterion: &mut Criterion) {
+    let filters = Filters::new().block_on().unwrap();
+
+    let mut group = criterion.benchmark_group("upload");
+    group.sample_size(10);
+    for size in SIZES {
+        let image = test_image(size);
+        group.throughput(Throughput::Bytes(image.as_raw().len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |bencher| {
+            bencher.iter(|| image.operation(&filters).unwrap().submit_only())
+        });
+    }
+    group.finish();
+
+    let mut group = criterion.benchmark_group("readback");
+    group.sample_size(10);
+    for size in SIZES {
+        let image = test_image(size);
+        group.throughput(Throughput::Bytes(image.as_raw().len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |bencher| {
+            bencher.iter_batched(
+                || uploaded(&filters, &image),
+                |operation| operation.execute().block_on(),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+
+    bench_passes(criterion, &filters, "grayscale", |operation| {
+        operation.grayscale()
+    });
+    bench_passes(criterion, &filters, "inverse", |operation| {
+        operation.inverse()
+    });
+    bench_passes(criterion, &filters, "hflip", |operation| operation.hflip());
+    bench_passes(criterion, &filters, "vflip", |operation| operation.vflip());
+    bench_passes(criterion, &filters, "brightness", |operation| {
+        operation.brightness(0.1)
+    });
+    bench_passes(
+        criterion,
+        &filters,
+        "brightness 100 times",
+        many_brightness_passes,
+    );
+    bench_passes(criterion, &filters, "contrast", |operation| {
+        operation.contrast(1.2)
+    });
+    bench_passes(criterion, &filters, "sharpen", |operation| {
+        operation.sharpen(1.0)
+    });
+
+    // The running sums make the cost of the box blur independent of the size of its window.
+    for filter_size in [5, 9, 151] {
+        bench_passes(
+            criterion,
+            &filters,
+            &format!("box blur {filter_size}"),
+            move |operation| operation.box_blur(filter_size),
+        );
+    }
+    // The three box blurs of the fast gaussian blur catch up with the exact one for sigmas above about 15.
+    for sigma in [1.0, 4.0, 16.0, 25.0] {
+        bench_passes(
+            criterion,
+            &filters,
+            &format!("gaussian blur {sigma}"),
+            move |operation| operation.gaussian_blur(sigma),
+        );
+        bench_passes(
+            criterion,
+            &filters,
+            &format!("fast gaussian blur {sigma}"),
+            move |operation| operation.fast_gaussian_blur(sigma),
+        );
+    }
+
+    // With the default workgroup sizes, the kernel of a sigma of 145 still fits in workgroup memory, while the one
+    // of 155 is read from the texture instead.
+    for sigma in [145.0, 155.0] {
+        bench_passes(
+            criterion,
+            &filters,
+            &format!("gaussian blur {sigma}"),
+            move |operation| operation.gaussian_blur(sigma),
+        );
+    }
+
+    bench_passes(criterion, &filters, "resize up", |operation| {
+        let (width, height) = operation.dimensions();
+        operation
+            .resize((width * 3 / 2, height * 3 / 2), Resize::Linear)
+            .unwrap()
+    });
+    bench_passes(criterion, &filters, "resize down", |operation| {
+        let (width, height) = operation.dimensions();
+        operation
+            .resize((width / 2, height / 2), Resize::Lanczos3)
+            .unwrap()
+    });
+
+    bench_passes(criterion, &filters, "chain", |operation| {
+        let (width, height) = operation.dimensions();
+        operation
+            .resize((width / 2, height / 2), Resize::Linear)
+            .unwrap()
+            .gaussian_blur(2.0)
+            .contrast(1.1)
+            .sharpen(0.5)
+    });
+
+    // Frames of a 1080p video, going through the one-shot path or a `FrameProcessor`.
+    let mut group = criterion.benchmark_group("frames");
+    group.sample_size(10);
+    let frame = Image::from_fn(1920, 1080, |x, y| {
+        Rgba::new(x as u8, y as u8, (x ^ y) as u8, 255)
+    });
+    let chain = FilterChain::parse("grayscale|brightness(0.1)").unwrap();
+    group.bench_function("one shot", |bencher| {
+        bencher.iter(|| {
+            chain
+                .apply(frame.operation(&filters).unwrap())
+                .unwrap()
+                .execute()
+                .block_on()
+        })
+    });
+    let mut processor = filters.frame_processor(1920, 1080, chain).unwrap();
+    let mut output = Image::new(0, 0, Rgba::new(0, 0, 0, 0));
+    group.bench_function("frame processor", |bencher| {
+        bencher.iter(|| processor.process(frame.as_raw(), &mut output).unwrap())
+    });
+    group.finish();
+
+    // The same passes without push constants, on filters of their own. The gl backend doesn't cope with two
+    // instances at once, so the first one goes away before.
+    drop(processor);
+    drop(filters);
+    let filters = Filters::with_options(FiltersOptions {
+        push_constants: false,
+        ..Default::default()
+    })
+    .block_on()
+    .unwrap();
+    bench_passes(
+        criterion,
+        &filters,
+        "brightness 100 times with uniforms",
+        many_brightness_passes,
+    );
+    drop(filters);
+
+    // A small operation on filters that already built its pipelines, or on new filters sharing the same device,
+    // whose pipeline cache starts empty.
+    let instance = Instance::new(wgpu::Backends::all());
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions::default())
+        .block_on()
+        .unwrap();
+    let (device, queue) = adapter
+        .request_device(&DeviceDescriptor::default(), None)
+        .block_on()
+        .unwrap();
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    let image = test_image(64);
+    let grayscale = |filters: &Filters| {
+        image
+            .operation(filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on()
+    };
+    let mut group = criterion.benchmark_group("pipeline cache");
+    group.sample_size(10);
+    let filters = Filters::from_device(device.clone(), queue.clone());
+    group.bench_function("cached", |bencher| bencher.iter(|| grayscale(&filters)));
+    group.bench_function("uncached", |bencher| {
+        bencher.iter_batched(
+            || Filters::from_device(device.clone(), queue.clone()),
+            |filters| grayscale(&filters),
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(filters_benches, benches);
+criterion_main!(filters_benches);
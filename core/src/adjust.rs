@@ -0,0 +1,351 @@
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::{
+    util::BufferInitDescriptor, BindGroupDescriptor, BindGroupEntry, BindingResource,
+    ComputePassDescriptor, ComputePipeline, Extent3d, Sampler, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, capitalize, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Operation,
+};
+
+const BRIGHTNESS_SHADER: &str = include_str!("shaders/brightness.wgsl");
+const CONTRAST_SHADER: &str = include_str!("shaders/contrast.wgsl");
+const SET_ALPHA_SHADER: &str = include_str!("shaders/set_alpha.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Adds `amount` to the red, green and blue channels, clamping the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Between -1.0, turning everything black, and 1.0, turning everything white. 0.0 changes nothing.
+    pub fn brightness(self, amount: f32) -> Self {
+        self.adjust("brightness", BRIGHTNESS_SHADER, amount)
+    }
+
+    /// Scales the red, green and blue channels around the middle gray, clamping the result.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - The scale: 1.0 changes nothing, lower values flatten the image, higher values increase the contrast.
+    pub fn contrast(self, amount: f32) -> Self {
+        self.adjust("contrast", CONTRAST_SHADER, amount)
+    }
+
+    /// Overwrites the alpha of every pixel with `value`, leaving the red, green and blue channels as they are, see
+    /// [`Operation::flatten`] to blend them with a background instead.
+    pub fn set_alpha(self, value: u8) -> Self {
+        self.adjust("set alpha", SET_ALPHA_SHADER, f32::from(value) / 255.0)
+    }
+
+    /// Applies a per pixel adjustment whose shader takes a single `amount` setting, passed as a push constant
+    /// when the device supports it.
+    fn adjust(self, name: &'static str, shader_string: &str, amount: f32) -> Self {
+        let settings = bytemuck::bytes_of(&amount);
+        if self.pipelines.fits_push_constants(settings.len()) {
+            let pipeline = self.pipelines.get_push_constants(
+                self.device,
+                name,
+                shader_string,
+                self.format,
+                settings.len() as u32,
+            );
+            self.push_constants_filter(name, &pipeline, settings)
+        } else {
+            let pipeline = self.pipeline(name, shader_string, Bindings::Uniform);
+            self.settings_filter(name, &pipeline, settings)
+        }
+    }
+
+    /// Applies a filter whose shader takes its `settings` as push constants, and its input and output textures
+    /// at group 0, see [`crate::cache::PipelineCache::get_push_constants`].
+    fn push_constants_filter(
+        mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        settings: &[u8],
+    ) -> Self {
+        let capitalized_filter_name = capitalize(name);
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_push_constants(0, settings);
+            compute_pass.set_bind_group(0, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        self
+    }
+
+    /// Applies a filter whose shader takes its `settings` as a uniform buffer at group 0, if there are any,
+    /// and its input and output textures at group 1.
+    pub(crate) fn settings_filter(
+        self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        settings: &[u8],
+    ) -> Self {
+        let output_size = self.texture_size;
+        self.settings_pass(name, pipeline, settings, None, output_size)
+    }
+
+    /// Like [`Operation::settings_filter`], but writes to an output texture of `output_size`, with the `sampler`
+    /// bound at group 0, binding 1 if there is one.
+    pub(crate) fn settings_pass(
+        mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        settings: &[u8],
+        sampler: Option<&Sampler>,
+        output_size: Extent3d,
+    ) -> Self {
+        let capitalized_filter_name = capitalize(name);
+
+        let output_texture = self
+            .pool
+            .take(self.device, output_size, STORAGE_TEXTURE_USAGES);
+
+        let settings = (!settings.is_empty()).then(|| {
+            self.device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(format!("{} settings", capitalized_filter_name).as_str()),
+                contents: settings,
+                usage: BufferUsages::UNIFORM,
+            })
+        });
+
+        let mut entries = Vec::new();
+        if let Some(settings) = &settings {
+            entries.push(BindGroupEntry {
+                binding: 0,
+                resource: settings.as_entire_binding(),
+            });
+        }
+        if let Some(sampler) = sampler {
+            entries.push(BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(sampler),
+            });
+        }
+        // Without settings nor sampler, the layout derived from the shader still has an empty group 0, which must
+        // be bound.
+        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute constants"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (output_size.width, output_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, output_size, STORAGE_TEXTURE_USAGES);
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersOptions, Image, Rgba};
+
+    fn test_image() -> Image {
+        Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([0, 64, 128, 255]),
+                Rgba([200, 230, 255, 128]),
+                Rgba([10, 20, 30, 0]),
+            ],
+        }
+    }
+
+    #[test]
+    fn brightness_adds_and_clamps() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([51, 115, 179, 255]),
+                Rgba([251, 255, 255, 128]),
+                Rgba([61, 71, 81, 0]),
+            ],
+        };
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .brightness(0.2)
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn set_alpha_keeps_colors() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .set_alpha(200)
+            .execute()
+            .block_on();
+
+        let expected = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([0, 64, 128, 200]),
+                Rgba([200, 230, 255, 200]),
+                Rgba([10, 20, 30, 200]),
+            ],
+        };
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn contrast_one_is_identity() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .contrast(1.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn contrast_zero_is_middle_gray() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .contrast(0.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(
+            vec![128, 128, 128, 255, 128, 128, 128, 128, 128, 128, 128, 0],
+            output.as_raw()
+        );
+    }
+
+    fn brightness_and_contrast(filters: &Filters) -> Image {
+        test_image()
+            .operation(filters)
+            .unwrap()
+            .brightness(0.2)
+            .contrast(1.5)
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn push_constants_match_uniforms() {
+        let mut filters = Filters::new().block_on().unwrap();
+
+        let push_constants = brightness_and_contrast(&filters);
+        filters.pipelines.disable_push_constants();
+        let uniforms = brightness_and_contrast(&filters);
+
+        assert_eq!(uniforms, push_constants);
+    }
+
+    #[test]
+    fn uniforms_when_push_constants_are_disabled() {
+        let filters = Filters::with_options(FiltersOptions {
+            push_constants: false,
+            ..Default::default()
+        })
+        .block_on()
+        .unwrap();
+
+        assert!(!filters.pipelines.fits_push_constants(4));
+        assert_eq!(
+            vec![
+                Rgba([13, 109, 205, 255]),
+                Rgba([255, 255, 255, 128]),
+                Rgba([28, 43, 58, 0]),
+            ],
+            brightness_and_contrast(&filters).pixels
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{FilterChain, Filters, FiltersError, Image, Rgba};
+
+impl Filters {
+    /// Applies a chain of filters to many small images at once, packing them in a single atlas image so that each
+    /// filter runs a single pass for all of them, then cutting the results back out, in the same order.
+    ///
+    /// Only filters that work on each pixel on its own, like grayscale, inverse or brightness, can be used:
+    /// blurs and the like would bleed from an image into its neighbors, and flips or resizes would move them.
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - The images to process, which can have different sizes.
+    /// * `chain` - The filter chain, applied once to the atlas.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::NotPackable`] if a filter of the chain looks at neighboring pixels or moves them,
+    /// [`FiltersError::ImageTooLarge`] if the atlas doesn't fit in a texture of the gpu, and
+    /// [`FiltersError::UnsupportedSize`] for empty images.
+    pub async fn pack_and_process(
+        &self,
+        images: &[Image],
+        chain: &FilterChain,
+    ) -> Result<Vec<Image>, FiltersError> {
+        if images.is_empty() {
+            return Ok(Vec::new());
+        }
+        for image in images {
+            image.check_pixel_count()?;
+            if image.width == 0 || image.height == 0 {
+                return Err(FiltersError::UnsupportedSize {
+                    width: image.width,
+                    height: image.height,
+                });
+            }
+        }
+
+        let sizes = images
+            .iter()
+            .map(|image| (image.width, image.height))
+            .collect::<Vec<_>>();
+        let limit = self.device.limits().max_texture_dimension_2d;
+        let Packing {
+            positions,
+            size: (width, height),
+        } = shelf_pack(&sizes, limit)?;
+        let mut atlas = Image::new(width, height, Rgba([0, 0, 0, 0]));
+        for (image, &(x, y)) in images.iter().zip(&positions) {
+            atlas.copy_from(image, x, y)?;
+        }
+
+        let operation = chain.apply(atlas.operation(self)?)?;
+        if !operation.tileable || operation.radius > 0 || operation.dimensions() != (width, height)
+        {
+            return Err(FiltersError::NotPackable);
+        }
+        let atlas = operation.execute().await;
+
+        sizes
+            .iter()
+            .zip(positions)
+            .map(|(&(width, height), (x, y))| atlas.sub_image(x, y, width, height))
+            .collect()
+    }
+}
+
+/// Where images are placed in an atlas.
+struct Packing {
+    /// The top left corner of each image.
+    positions: Vec<(u32, u32)>,
+    /// The width and height of the atlas.
+    size: (u32, u32),
+}
+
+/// Places rectangles of the given sizes on shelves, from the tallest to the shortest, left to right, starting a
+/// new shelf below when a rectangle doesn't fit in the width of the atlas anymore.
+fn shelf_pack(sizes: &[(u32, u32)], limit: u32) -> Result<Packing, FiltersError> {
+    let area = sizes
+        .iter()
+        .map(|&(width, height)| u64::from(width) * u64::from(height))
+        .sum::<u64>();
+    let widest = sizes.iter().map(|&(width, _)| width).max().unwrap_or(0);
+    // A square atlas if the rectangles packed perfectly, wide enough for the widest one.
+    let target_width = ((area as f64).sqrt().ceil() as u32).min(limit).max(widest);
+
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index].1));
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height, mut atlas_width) = (0u32, 0u32, 0u32, 0u32);
+    for index in order {
+        let (width, height) = sizes[index];
+        if x > 0 && x.saturating_add(width) > target_width {
+            y = y.saturating_add(shelf_height);
+            (x, shelf_height) = (0, 0);
+        }
+        positions[index] = (x, y);
+        x += width;
+        shelf_height = shelf_height.max(height);
+        atlas_width = atlas_width.max(x);
+    }
+
+    let atlas_height = y.saturating_add(shelf_height);
+    if atlas_width > limit || atlas_height > limit {
+        return Err(FiltersError::ImageTooLarge {
+            dimension: (atlas_width, atlas_height),
+            limit,
+        });
+    }
+    Ok(Packing {
+        positions,
+        size: (atlas_width, atlas_height),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::{shelf_pack, Packing};
+    use crate::{FilterChain, FilterStep, Filters, FiltersError, Image, Rgba};
+
+    fn images() -> Vec<Image> {
+        (0..10)
+            .map(|index| {
+                Image::from_fn(3 + index * 2, 12 - index, |x, y| {
+                    Rgba::new((x * 20) as u8, (y * 20) as u8, (index * 25) as u8, 255)
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn packed_grayscale_matches_individual() {
+        let filters = Filters::new().block_on().unwrap();
+        let images = images();
+        let chain = FilterChain::new(vec![FilterStep::Grayscale, FilterStep::Inverse]);
+
+        let packed = filters
+            .pack_and_process(&images, &chain)
+            .block_on()
+            .unwrap();
+        let individual = filters
+            .batch(&images, |operation| operation.grayscale().inverse())
+            .unwrap();
+
+        assert_eq!(individual, packed);
+    }
+
+    #[test]
+    fn blurs_are_rejected() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for step in [FilterStep::BoxBlur { size: 3 }, FilterStep::HFlip] {
+            let result = filters
+                .pack_and_process(&images(), &FilterChain::new(vec![step]))
+                .block_on();
+            assert!(matches!(result, Err(FiltersError::NotPackable)));
+        }
+    }
+
+    #[test]
+    fn shelves_dont_overlap() {
+        let sizes = [(4, 2), (3, 5), (6, 1), (2, 2), (5, 5), (1, 7)];
+
+        let Packing {
+            positions,
+            size: (width, height),
+        } = shelf_pack(&sizes, 64).unwrap();
+
+        let rectangles = positions
+            .iter()
+            .zip(&sizes)
+            .map(|(&(x, y), &(w, h))| (x, y, x + w, y + h))
+            .collect::<Vec<_>>();
+        for (index, &(left, top, right, bottom)) in rectangles.iter().enumerate() {
+            assert!(right <= width && bottom <= height);
+            for &(other_left, other_top, other_right, other_bottom) in &rectangles[index + 1..] {
+                let apart = right <= other_left
+                    || other_right <= left
+                    || bottom <= other_top
+                    || other_bottom <= top;
+                assert!(apart, "{rectangles:?}");
+            }
+        }
+        assert!(matches!(
+            shelf_pack(&sizes, 6),
+            Err(FiltersError::ImageTooLarge { limit: 6, .. })
+        ));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use wgpu::Buffer;
+
+use crate::{
+    create_readback_buffer, encode_copy_to_buffer, read_mapped_buffer, BatchError, BatchProgress,
+    CancellationToken, Filters, FiltersError, Image, Operation, Rgba,
+};
+
+/// How many images are submitted to the gpu before waiting for their results.
+const IMAGES_IN_FLIGHT: usize = 8;
+
+impl Filters {
+    /// Applies the same chain of filters to many images, returning the results in the same order.
+    ///
+    /// Compared to calling [`Operation::execute`] for each image, the pipelines are shared, the readback
+    /// buffers are reused for images of the same output size, and several images are submitted before
+    /// waiting on any of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - The images to process, which can have different sizes.
+    /// * `chain` - The filter chain, applied to each image.
+    pub fn batch<F>(&self, images: &[Image], chain: F) -> Result<Vec<Image>, FiltersError>
+    where
+        F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+    {
+        let mut results = Vec::with_capacity(images.len());
+        self.run_batch(images, chain, &CancellationToken::new(), |_, image| {
+            results.push(image)
+        })?;
+
+        Ok(results)
+    }
+
+    /// Like [`Filters::batch`], but reports each finished image to `on_progress`, and stops as soon as `token`
+    /// is cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `images` - The images to process, which can have different sizes.
+    /// * `chain` - The filter chain, applied to each image.
+    /// * `token` - Cancels the batch: no image is submitted after that, and the images finished afterwards are
+    ///   dropped.
+    /// * `on_progress` - Called in order each time an image is done, which can cancel `token` itself.
+    ///
+    /// # Errors
+    ///
+    /// A [`BatchError`] holding the images finished so far, along with [`FiltersError::Cancelled`] if the batch
+    /// was cancelled, or the error of the image that failed.
+    pub fn batch_with_progress<F, P>(
+        &self,
+        images: &[Image],
+        chain: F,
+        token: &CancellationToken,
+        on_progress: P,
+    ) -> Result<Vec<Image>, BatchError>
+    where
+        F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+        P: Fn(BatchProgress),
+    {
+        let total = images.len();
+        let mut results = Vec::with_capacity(total);
+        let result = self.run_batch(images, chain, token, |index, image| {
+            results.push(image);
+            on_progress(BatchProgress {
+                index,
+                completed: results.len(),
+                total,
+            });
+        });
+
+        match result {
+            Ok(()) => Ok(results),
+            Err(error) => Err(BatchError {
+                completed: results,
+                error,
+            }),
+        }
+    }
+
+    /// Like [`Filters::batch`], but hands each result to `on_result` along with its index as soon as it is
+    /// read back, instead of collecting all of them.
+    pub async fn batch_each<F, R>(
+        &self,
+        images: &[Image],
+        chain: F,
+        on_result: R,
+    ) -> Result<(), FiltersError>
+    where
+        F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+        R: FnMut(usize, Image),
+    {
+        self.run_batch(images, chain, &CancellationToken::new(), on_result)
+    }
+
+    fn run_batch<F, R>(
+        &self,
+        images: &[Image],
+        chain: F,
+        token: &CancellationToken,
+        mut on_result: R,
+    ) -> Result<(), FiltersError>
+    where
+        F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+        R: FnMut(usize, Image),
+    {
+        let mut buffers: HashMap<(u32, u32), Vec<Buffer>> = HashMap::new();
+
+        for (chunk_index, chunk) in images.chunks(IMAGES_IN_FLIGHT).enumerate() {
+            let mut pending = Vec::with_capacity(chunk.len());
+            for image in chunk {
+                token.check()?;
+                let mut operation = chain(image.operation(self)?);
+                operation.convert_to_rgba8();
+                let (width, height) = operation.dimensions();
+                let buffer = buffers
+                    .get_mut(&(width, height))
+                    .and_then(Vec::pop)
+                    .unwrap_or_else(|| create_readback_buffer::<Rgba>(&self.device, width, height));
+                encode_copy_to_buffer::<Rgba>(
+                    &mut operation.encoder,
+                    width,
+                    height,
+                    &operation.texture,
+                    &buffer,
+                );
+                operation.submit();
+                pending.push((width, height, buffer));
+            }
+
+            for (_, _, buffer) in &pending {
+                buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+            }
+            self.device.poll(wgpu::Maintain::Wait);
+
+            for (index, (width, height, buffer)) in pending.into_iter().enumerate() {
+                token.check()?;
+                on_result(
+                    chunk_index * IMAGES_IN_FLIGHT + index,
+                    read_mapped_buffer(width, height, &buffer),
+                );
+                buffers.entry((width, height)).or_default().push(buffer);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use pollster::FutureExt;
+
+    use crate::{BatchProgress, CancellationToken, Filters, FiltersError, Image, Rgba};
+
+    fn image(width: u32, height: u32, seed: u8) -> Image {
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| Rgba([seed, (index % 256) as u8, 255 - seed, 255]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn batch_matches_individual_operations() {
+        let images: Vec<Image> = (0..20).map(|seed| image(5, 3, seed * 10)).collect();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected: Vec<Image> = images
+            .iter()
+            .map(|image| {
+                image
+                    .operation(&filters)
+                    .unwrap()
+                    .grayscale()
+                    .hflip()
+                    .execute()
+                    .block_on()
+            })
+            .collect();
+        let output = filters
+            .batch(&images, |operation| operation.grayscale().hflip())
+            .unwrap();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn batch_mixed_sizes() {
+        let images: Vec<Image> = (0..11)
+            .map(|seed| image(1 + seed as u32 % 4, 2 + seed as u32 % 3, seed))
+            .collect();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected: Vec<Image> = images
+            .iter()
+            .map(|image| {
+                image
+                    .operation(&filters)
+                    .unwrap()
+                    .inverse()
+                    .execute()
+                    .block_on()
+            })
+            .collect();
+        let mut output: Vec<Option<Image>> = (0..images.len()).map(|_| None).collect();
+        filters
+            .batch_each(
+                &images,
+                |operation| operation.inverse(),
+                |index, image| output[index] = Some(image),
+            )
+            .block_on()
+            .unwrap();
+
+        assert_eq!(
+            expected,
+            output.into_iter().map(Option::unwrap).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn batch_with_progress_reports_each_image() {
+        let images: Vec<Image> = (0..3).map(|seed| image(4, 2, seed * 50)).collect();
+        let filters = Filters::new().block_on().unwrap();
+        let progress = RefCell::new(Vec::new());
+
+        let output = filters
+            .batch_with_progress(
+                &images,
+                |operation| operation.inverse(),
+                &CancellationToken::new(),
+                |update| progress.borrow_mut().push(update),
+            )
+            .unwrap();
+
+        assert_eq!(3, output.len());
+        assert_eq!(
+            vec![
+                BatchProgress {
+                    index: 0,
+                    completed: 1,
+                    total: 3
+                },
+                BatchProgress {
+                    index: 1,
+                    completed: 2,
+                    total: 3
+                },
+                BatchProgress {
+                    index: 2,
+                    completed: 3,
+                    total: 3
+                },
+            ],
+            progress.into_inner()
+        );
+    }
+
+    #[test]
+    fn batch_cancelled_after_first_image() {
+        let images: Vec<Image> = (0..3).map(|seed| image(4, 2, seed * 50)).collect();
+        let filters = Filters::new().block_on().unwrap();
+        let token = CancellationToken::new();
+
+        let error = filters
+            .batch_with_progress(
+                &images,
+                |operation| operation.inverse(),
+                &token,
+                |_| token.cancel(),
+            )
+            .unwrap_err();
+
+        assert!(matches!(error.error, FiltersError::Cancelled));
+        assert_eq!(
+            vec![images[0]
+                .operation(&filters)
+                .unwrap()
+                .inverse()
+                .execute()
+                .block_on()],
+            error.completed
+        );
+    }
+}
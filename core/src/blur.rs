@@ -2,84 +2,146 @@ use wgpu::util::DeviceExt;
 use wgpu::BufferUsages;
 use wgpu::{
     util::BufferInitDescriptor, BindGroupDescriptor, BindGroupEntry, BindingResource,
-    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
-    ShaderModuleDescriptor, ShaderSource, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureUsages, TextureViewDescriptor,
+    ComputePassDescriptor, Device, TextureFormat, TextureViewDescriptor,
 };
 
-use crate::{capitalize, compute_work_group_count, Operation};
+use crate::{
+    cache::Bindings, capitalize, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES,
+    FiltersError, Operation,
+};
 
 const BOX_BLUR_SHADER: &str = include_str!("shaders/box_blur.wgsl");
+/// The box blur summing the whole window for each pixel, which is slower for large windows.
+const BOX_BLUR_DIRECT_SHADER: &str = include_str!("shaders/box_blur_direct.wgsl");
 const GAUSSIAN_BLUR_SHADER: &str = include_str!("shaders/gaussian_blur.wgsl");
+const GAUSSIAN_BLUR_GLOBAL_SHADER: &str = include_str!("shaders/gaussian_blur_global.wgsl");
+
+/// The most pixels the gaussian blur keeps in workgroup memory, 16KiB of them.
+const MAX_TILE_LENGTH: u32 = 1024;
 
-struct Kernel {
-    sum: f32,
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct BoxBlurSettings {
+    filter_size: u32,
+    /// How many steps the values of 8-bit textures have, keeping the running sums exact, or 0.
+    steps: f32,
+}
+
+/// The weights of a separable filter, applied vertically then horizontally by
+/// [`Operation::apply_separable_kernel`]. The middle value is centered on each pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
     values: Vec<f32>,
 }
 
 impl Kernel {
-    fn new(values: Vec<f32>) -> Self {
-        let sum = values.iter().sum();
-        Self { sum, values }
+    /// A kernel of `values`, used as they are, see [`Kernel::normalized`].
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidKernelSize`] if there are no values, or an even number of them, which can't be centered.
+    pub fn new(values: Vec<f32>) -> Result<Self, FiltersError> {
+        if values.len().is_multiple_of(2) {
+            return Err(FiltersError::InvalidKernelSize(values.len()));
+        }
+
+        Ok(Self { values })
+    }
+
+    /// A normalized gaussian reaching 3 sigmas on each side, like the one of [`Operation::gaussian_blur`].
+    /// A sigma of 0 or less gives a kernel leaving the image as is.
+    pub fn gaussian(sigma: f32) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return Self { values: vec![1.0] };
+        }
+        Self::gaussian_of_size(sigma, 2 * (sigma * 3.0).ceil() as usize + 1)
     }
 
-    fn packed_data(&self) -> Vec<f32> {
-        let mut data = vec![0.0; self.values.len() + 1];
-        data[0] = self.sum;
-        data[1..].copy_from_slice(&self.values);
-        data
+    /// A normalized kernel averaging `size` pixels. Like for [`Operation::box_blur`], even sizes are rounded up.
+    pub fn box_filter(size: u32) -> Self {
+        let size = (size | 1) as usize;
+        Self {
+            values: vec![1.0 / size as f32; size],
+        }
+    }
+
+    /// Scales the values so that they add up to 1, which keeps the brightness of the image. Kernels adding up to 0,
+    /// like derivatives, are returned as they are.
+    pub fn normalized(self) -> Self {
+        let sum: f32 = self.values.iter().sum();
+        if sum == 0.0 {
+            return self;
+        }
+
+        Self {
+            values: self.values.into_iter().map(|value| value / sum).collect(),
+        }
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
     }
 
     fn size(&self) -> usize {
         self.values.len()
     }
+
+    /// The normalized gaussian of `sigma`, over `size` values.
+    pub(crate) fn gaussian_of_size(sigma: f32, size: usize) -> Self {
+        let radius = (size - 1) / 2;
+        let values = (0..size)
+            .map(|index| {
+                let x = index.abs_diff(radius) as f32;
+                normalized_probablility_density_function(x, sigma)
+            })
+            .collect();
+
+        Self { values }.normalized()
+    }
 }
 
 impl<'a> Operation<'a> {
-    pub fn box_blur(mut self, filter_size: u32) -> Self {
+    /// Blurs the image by averaging `filter_size` pixels, vertically then horizontally.
+    ///
+    /// Each column, then each row, is blurred by a single invocation keeping a running sum of the window, so the
+    /// cost doesn't depend on `filter_size`.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter_size` - The width of the window centered on each pixel. 0 and 1 leave the image as is, even sizes
+    ///   are rounded up to the next odd one, and sizes above twice the longest side of the image are clamped to it.
+    pub fn box_blur(self, filter_size: u32) -> Self {
+        self.box_blur_with(filter_size, true)
+    }
+
+    fn box_blur_with(mut self, filter_size: u32, running_sums: bool) -> Self {
+        let filter_size = box_filter_size(filter_size, self.dimensions());
+        if filter_size == 1 {
+            return self;
+        }
         let name = "box blur";
         let capitalized_filter_name = capitalize(name);
 
-        let vertical_pass_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
-        let horizontal_pass_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
-
-        let shader = self.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some(format!("{} shader", capitalized_filter_name).as_str()),
-            source: ShaderSource::Wgsl(BOX_BLUR_SHADER.into()),
-        });
-
-        let pipeline = self
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some(format!("{} pipeline", capitalized_filter_name).as_str()),
-                layout: None,
-                module: &shader,
-                entry_point: "main",
-            });
-
+        let vertical_pass_texture =
+            self.pool
+                .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let horizontal_pass_texture =
+            self.pool
+                .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = if running_sums {
+            self.pipeline(name, BOX_BLUR_SHADER, Bindings::Derived)
+        } else {
+            self.pipeline("box blur direct", BOX_BLUR_DIRECT_SHADER, Bindings::Derived)
+        };
+
+        let steps = match self.pipelines.texture_format(self.format) {
+            TextureFormat::Rgba8Unorm | TextureFormat::R8Unorm => 255.0,
+            _ => 0.0,
+        };
         let settings = self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Image info"),
-            contents: bytemuck::cast_slice(&[filter_size]),
+            contents: bytemuck::bytes_of(&BoxBlurSettings { filter_size, steps }),
             usage: BufferUsages::UNIFORM,
         });
 
@@ -149,88 +211,145 @@ impl<'a> Operation<'a> {
             ],
         });
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        let pass = self.begin_pass(name);
         {
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some(format!("{} pass", capitalized_filter_name).as_str()),
             });
             compute_pass.set_pipeline(&pipeline);
             compute_pass.set_bind_group(0, &compute_constants, &[]);
+            // With running sums, there is one invocation per column, then one per row.
+            let (width, height) = if running_sums {
+                (self.texture_size.width, 1)
+            } else {
+                (self.texture_size.width, self.texture_size.height)
+            };
             compute_pass.set_bind_group(1, &vertical_bind_group, &[]);
-            let (dispatch_with, dispatch_height) = compute_work_group_count(
-                (self.texture_size.width, self.texture_size.height),
-                (128, 1),
-            );
-            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+            let (dispatch_width, dispatch_height) =
+                line_work_group_count((width, height), self.pipelines.workgroups().line, false);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+
+            let (width, height) = if running_sums {
+                (1, self.texture_size.height)
+            } else {
+                (self.texture_size.width, self.texture_size.height)
+            };
             compute_pass.set_bind_group(1, &horizontal_bind_group, &[]);
-            let (dispatch_height, dispatch_with) = compute_work_group_count(
-                (self.texture_size.width, self.texture_size.height),
-                (1, 128),
-            );
-            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+            let (dispatch_width, dispatch_height) =
+                line_work_group_count((width, height), self.pipelines.workgroups().line, true);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
         }
+        self.end_pass(pass);
 
-        self.queue.submit(Some(encoder.finish()));
-        self.texture = horizontal_pass_texture;
+        self.pool.release(
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+            vertical_pass_texture,
+        );
+        self.set_texture(
+            horizontal_pass_texture,
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+        );
+        self.radius += filter_size.saturating_sub(1) / 2;
 
         self
     }
 
-    pub fn gaussian_blur(mut self, sigma: f32) -> Self {
-        let name = "gaussian blur";
-        let capitalized_filter_name = capitalize(name);
+    /// Blurs the image with a gaussian kernel, vertically then horizontally.
+    ///
+    /// Each workgroup reads the pixels it needs once into workgroup memory. Kernels too large to fit there, for a
+    /// sigma beyond about 149 with the default workgroup sizes, read them from the texture instead, which is slower.
+    ///
+    /// # Arguments
+    ///
+    /// * `sigma` - The standard deviation of the gaussian, in pixels. A sigma of 0 or less leaves the image as is.
+    ///   The kernel reaches 3 sigmas around each pixel, but no further than the longest side of the image.
+    pub fn gaussian_blur(self, sigma: f32) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return self;
+        }
+        let kernel_size = kernel_size_for_sigma(sigma, self.dimensions());
+        let shared_memory = self.fits_in_shared_memory(kernel_size);
+        self.gaussian_blur_with(sigma, shared_memory)
+    }
 
-        let kernel = kernel(sigma);
-        let kernel_size = kernel.size() as u32;
+    /// Approximates [`Operation::gaussian_blur`] with three box blurs, which is faster for a sigma above about 15,
+    /// like for a background blur. A sigma of 0 or less leaves the image as is.
+    pub fn fast_gaussian_blur(self, sigma: f32) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return self;
+        }
+        box_sizes_for_gaussian(sigma)
+            .into_iter()
+            .fold(self, |operation, size| operation.box_blur(size))
+    }
 
-        let vertical_pass_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
-        let horizontal_pass_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
+    /// Applies `kernel` vertically, then horizontally, the same way as [`Operation::gaussian_blur`]. The results are
+    /// clamped between 0 and 1, so derivative kernels lose their negative values.
+    pub fn apply_separable_kernel(self, kernel: &Kernel) -> Self {
+        // From any pixel, the values further than the longest side of the image only reach the transparent pixels
+        // around it, so they can be left out.
+        let (width, height) = self.dimensions();
+        let max_size = 2 * width.max(height) as usize + 1;
+        let values = if kernel.size() > max_size {
+            let start = (kernel.size() - max_size) / 2;
+            &kernel.values[start..start + max_size]
+        } else {
+            &kernel.values[..]
+        };
+        let shared_memory = self.fits_in_shared_memory(values.len() as u32);
+        self.separable_filter("separable kernel", values, shared_memory)
+    }
 
-        let shader = self.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some(format!("{} shader", capitalized_filter_name).as_str()),
-            source: ShaderSource::Wgsl(GAUSSIAN_BLUR_SHADER.into()),
-        });
+    /// Whether the segments of a kernel of `kernel_size` fit in workgroup memory.
+    fn fits_in_shared_memory(&self, kernel_size: u32) -> bool {
+        self.pipelines.workgroups().line + kernel_size - 1 <= tile_length(self.device)
+    }
 
-        let pipeline = self
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some(format!("{} pipeline", capitalized_filter_name).as_str()),
-                layout: None,
-                module: &shader,
-                entry_point: "main",
-            });
+    fn gaussian_blur_with(self, sigma: f32, shared_memory: bool) -> Self {
+        let kernel_size = kernel_size_for_sigma(sigma, self.dimensions());
+        let kernel = Kernel::gaussian_of_size(sigma, kernel_size as usize);
+        self.separable_filter("gaussian blur", &kernel.values, shared_memory)
+    }
+
+    /// Applies the kernel of `values` with the gaussian blur shaders, in a pass named `name`.
+    fn separable_filter(mut self, name: &str, values: &[f32], shared_memory: bool) -> Self {
+        let capitalized_filter_name = capitalize(name);
+
+        let kernel_size = values.len() as u32;
+        let workgroup_length = self.pipelines.workgroups().line;
+
+        let vertical_pass_texture =
+            self.pool
+                .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let horizontal_pass_texture =
+            self.pool
+                .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = if shared_memory {
+            let shader_string = GAUSSIAN_BLUR_SHADER.replace(
+                "array<vec4<f32>, 1024>",
+                &format!("array<vec4<f32>, {}>", tile_length(self.device)),
+            );
+            self.pipeline("gaussian blur", &shader_string, Bindings::Derived)
+        } else {
+            self.pipeline(
+                "gaussian blur global",
+                GAUSSIAN_BLUR_GLOBAL_SHADER,
+                Bindings::Derived,
+            )
+        };
 
         let settings = self.device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Image info"),
-            contents: bytemuck::cast_slice(&[kernel_size]),
+            contents: bytemuck::cast_slice(&[kernel_size, workgroup_length]),
             usage: BufferUsages::UNIFORM,
         });
 
         let kernel = self.device.create_buffer_init(&BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(&kernel.packed_data()[..]),
+            contents: bytemuck::cast_slice(values),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
@@ -306,51 +425,96 @@ impl<'a> Operation<'a> {
             ],
         });
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        let pass = self.begin_pass(name);
         {
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some(format!("{} pass", capitalized_filter_name).as_str()),
             });
             compute_pass.set_pipeline(&pipeline);
             compute_pass.set_bind_group(0, &compute_constants, &[]);
+            // The workgroups follow the columns for the vertical pass, and the rows for the horizontal one.
+            let size = (self.texture_size.width, self.texture_size.height);
             compute_pass.set_bind_group(1, &vertical_bind_group, &[]);
-            let (dispatch_with, dispatch_height) = compute_work_group_count(
-                (self.texture_size.width, self.texture_size.height),
-                (128, 1),
-            );
-            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+            let (dispatch_width, dispatch_height) =
+                line_work_group_count(size, workgroup_length, true);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
             compute_pass.set_bind_group(1, &horizontal_bind_group, &[]);
-            let (dispatch_height, dispatch_with) = compute_work_group_count(
-                (self.texture_size.width, self.texture_size.height),
-                (1, 128),
-            );
-            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+            let (dispatch_width, dispatch_height) =
+                line_work_group_count(size, workgroup_length, false);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
         }
+        self.end_pass(pass);
 
-        self.queue.submit(Some(encoder.finish()));
-        self.texture = horizontal_pass_texture;
+        self.pool.release(
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+            vertical_pass_texture,
+        );
+        self.set_texture(
+            horizontal_pass_texture,
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+        );
+        self.radius += (kernel_size - 1) / 2;
 
         self
     }
 }
 
-fn kernel_size_for_sigma(sigma: f32) -> u32 {
-    2 * (sigma * 3.0).ceil() as u32 + 1
+/// The workgroups to dispatch for a blur pass over `size`.
+///
+/// The blur shaders declare `@workgroup_size(128)`, swapped for `line`, the line length of the
+/// [`WorkgroupConfig`](crate::WorkgroupConfig), which is a workgroup of `(line, 1)` invocations along `global_id.x`.
+/// When `transposed`, the shader swaps `global_id.xy`, so that `global_id.x` counts the rows of the image and
+/// `global_id.y` its columns, and the counts are computed for the transposed size.
+fn line_work_group_count((width, height): (u32, u32), line: u32, transposed: bool) -> (u32, u32) {
+    let size = if transposed {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    compute_work_group_count(size, (line, 1))
+}
+
+/// How many pixels fit in the workgroup memory of `device`, up to [`MAX_TILE_LENGTH`].
+fn tile_length(device: &Device) -> u32 {
+    let pixel_size = std::mem::size_of::<[f32; 4]>() as u32;
+    (device.limits().max_compute_workgroup_storage_size / pixel_size).min(MAX_TILE_LENGTH)
+}
+
+/// The window of a box blur of `filter_size` over an image of `dimensions`. Even sizes are rounded up, so that the
+/// window stays centered on each pixel, and a window wider than twice the longest side would only add more of the
+/// transparent pixels around the image, so it is clamped to that.
+pub(crate) fn box_filter_size(filter_size: u32, (width, height): (u32, u32)) -> u32 {
+    (filter_size | 1).min(2 * width.max(height) + 1)
 }
 
-fn kernel(sigma: f32) -> Kernel {
-    let kernel_size = kernel_size_for_sigma(sigma);
-    let mut values = vec![0.0; kernel_size as usize];
-    let kernel_radius = (kernel_size as usize - 1) / 2;
-    for index in 0..=kernel_radius {
-        let normpdf = normalized_probablility_density_function(index as f32, sigma);
-        values[kernel_radius + index] = normpdf;
-        values[kernel_radius - index] = normpdf;
+/// The sizes of three successive box blurs approximating a gaussian blur of `sigma`, odd so that they are centered,
+/// as described in "Fast Almost-Gaussian Filtering" by Peter Kovesi.
+pub(crate) fn box_sizes_for_gaussian(sigma: f32) -> [u32; 3] {
+    let variance = 12.0 * sigma * sigma;
+    let ideal_size = (variance / 3.0 + 1.0).sqrt();
+    // The largest odd size below the ideal one, which is at least 1.
+    let lower = (ideal_size.floor() as u32 - 1) / 2 * 2 + 1;
+    let lower_f = lower as f32;
+    // How many of the boxes use the lower size, the others being 2 pixels larger, for the variance to be closest.
+    let lower_count = ((variance - 3.0 * lower_f * lower_f - 12.0 * lower_f - 9.0)
+        / (-4.0 * lower_f - 4.0))
+        .round()
+        .max(0.0) as usize;
+
+    let mut sizes = [lower.saturating_add(2); 3];
+    for size in sizes.iter_mut().take(lower_count) {
+        *size = lower;
     }
+    sizes
+}
 
-    Kernel::new(values)
+/// The size of the kernel reaching 3 sigmas around each pixel, for an image of `dimensions`. Beyond the longest side
+/// of the image, the kernel would only reach the transparent pixels around it, so it is capped there.
+pub(crate) fn kernel_size_for_sigma(sigma: f32, (width, height): (u32, u32)) -> u32 {
+    let radius = ((sigma * 3.0).ceil() as u32).min(width.max(height));
+    2 * radius + 1
 }
 
 fn normalized_probablility_density_function(x: f32, sigma: f32) -> f32 {
@@ -359,34 +523,414 @@ fn normalized_probablility_density_function(x: f32, sigma: f32) -> f32 {
 
 #[cfg(test)]
 mod tests {
-    use super::{kernel, kernel_size_for_sigma};
+    use std::time::{Duration, Instant};
+
+    use pollster::FutureExt;
+
+    use super::{box_sizes_for_gaussian, kernel_size_for_sigma, Kernel};
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    fn test_image(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| {
+                    let value = index * 37 % 256;
+                    Rgba([value as u8, (255 - value) as u8, (index * 7) as u8, 200])
+                })
+                .collect(),
+        }
+    }
+
+    fn box_blur(filters: &Filters, image: &Image, filter_size: u32, running_sums: bool) -> Image {
+        image
+            .operation(filters)
+            .unwrap()
+            .box_blur_with(filter_size, running_sums)
+            .execute()
+            .block_on()
+    }
+
+    fn gaussian_blur(filters: &Filters, image: &Image, sigma: f32, shared_memory: bool) -> Image {
+        image
+            .operation(filters)
+            .unwrap()
+            .gaussian_blur_with(sigma, shared_memory)
+            .execute()
+            .block_on()
+    }
 
     #[test]
     fn kernel_size_sigma_2_dot_2() {
-        let kernel_size = kernel_size_for_sigma(2.2);
+        let kernel_size = kernel_size_for_sigma(2.2, (100, 100));
 
         assert_eq!(15, kernel_size);
     }
 
+    #[test]
+    fn kernel_size_is_capped_to_the_image() {
+        assert_eq!(201, kernel_size_for_sigma(500.0, (100, 60)));
+        assert_eq!(201, kernel_size_for_sigma(f32::MAX, (60, 100)));
+    }
+
     #[test]
     fn kernel_sigma_1_dot_2() {
-        let kernel = kernel(1.2);
+        let kernel = Kernel::gaussian(1.2);
 
         assert_eq!(
             kernel.values,
             [
-                0.0012852254,
-                0.014606836,
-                0.08289714,
-                0.23492521,
-                0.33244997,
-                0.23492521,
-                0.08289714,
-                0.014606836,
-                0.0012852254
+                0.0012853811,
+                0.014608607,
+                0.082907185,
+                0.23495369,
+                0.33249027,
+                0.23495369,
+                0.082907185,
+                0.014608607,
+                0.0012853811
             ]
         );
+        assert!((kernel.values.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn separable_gaussian_kernel_matches_gaussian_blur() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        for sigma in [0.8, 3.0] {
+            let expected = image
+                .operation(&filters)
+                .unwrap()
+                .gaussian_blur(sigma)
+                .execute()
+                .block_on();
+            let output = image
+                .operation(&filters)
+                .unwrap()
+                .apply_separable_kernel(&Kernel::gaussian(sigma))
+                .execute()
+                .block_on();
+
+            assert_eq!(expected, output, "sigma {sigma}");
+        }
+    }
+
+    #[test]
+    fn separable_identity_kernel() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .apply_separable_kernel(&Kernel::new(vec![1.0]).unwrap())
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn separable_kernel_shifts() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        // Taking the value of the previous pixel moves the image one pixel right and down.
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .apply_separable_kernel(&Kernel::new(vec![1.0, 0.0, 0.0]).unwrap())
+            .execute()
+            .block_on();
+
+        for y in 1..33 {
+            for x in 1..40 {
+                assert_eq!(
+                    image.pixels[(y - 1) * 40 + x - 1],
+                    output.pixels[y * 40 + x],
+                    "{x},{y}"
+                );
+            }
+        }
+        assert_eq!(Rgba([0, 0, 0, 0]), output.pixels[0]);
+    }
+
+    #[test]
+    fn kernel_constructors() {
+        assert!(matches!(
+            Kernel::new(vec![]),
+            Err(FiltersError::InvalidKernelSize(0))
+        ));
+        assert!(matches!(
+            Kernel::new(vec![0.5, 0.5]),
+            Err(FiltersError::InvalidKernelSize(2))
+        ));
+        assert_eq!([1.0], Kernel::gaussian(0.0).values());
+        assert_eq!([0.2; 5], Kernel::box_filter(4).values());
+        assert_eq!(
+            [0.25, 0.5, 0.25],
+            Kernel::new(vec![1.0, 2.0, 1.0])
+                .unwrap()
+                .normalized()
+                .values()
+        );
+        assert_eq!(
+            [-1.0, 0.0, 1.0],
+            Kernel::new(vec![-1.0, 0.0, 1.0])
+                .unwrap()
+                .normalized()
+                .values()
+        );
+    }
+
+    #[test]
+    fn non_positive_sigma_is_identity() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        for sigma in [0.0, -2.0, f32::NAN] {
+            let output = image
+                .operation(&filters)
+                .unwrap()
+                .gaussian_blur(sigma)
+                .fast_gaussian_blur(sigma)
+                .execute()
+                .block_on();
+
+            assert_eq!(image, output, "sigma {sigma}");
+        }
+    }
+
+    #[test]
+    fn uniform_image_is_unchanged() {
+        let image = Image {
+            width: 160,
+            height: 160,
+            pixels: vec![Rgba([37, 150, 201, 255]); 160 * 160],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        for sigma in [0.5, 3.0, 20.0] {
+            for shared_memory in [true, false] {
+                let output = gaussian_blur(&filters, &image, sigma, shared_memory);
+
+                // Near the edges, the kernel reaches the transparent pixels around the image.
+                let radius = (kernel_size_for_sigma(sigma, (160, 160)) - 1) / 2;
+                for (index, (input, output)) in image.pixels.iter().zip(&output.pixels).enumerate()
+                {
+                    let (x, y) = (index as u32 % 160, index as u32 / 160);
+                    if (radius..160 - radius).contains(&x) && (radius..160 - radius).contains(&y) {
+                        assert_eq!(input, output, "sigma {sigma} at {x},{y}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn enormous_sigma_is_capped() {
+        let image = test_image(100, 100);
+        let filters = Filters::new().block_on().unwrap();
+
+        let start = Instant::now();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(500.0)
+            .execute()
+            .block_on();
+
+        assert_eq!((100, 100), (output.width, output.height));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn running_sums_match_direct() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for (width, height) in [(1, 1), (7, 5), (40, 33), (200, 90)] {
+            let image = test_image(width, height);
+            for filter_size in [1, 3, 9, 51, 151] {
+                assert_eq!(
+                    box_blur(&filters, &image, filter_size, false),
+                    box_blur(&filters, &image, filter_size, true),
+                    "{width}x{height}, filter size {filter_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn box_blur_of_one_is_identity() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        for filter_size in [0, 1] {
+            assert_eq!(image, box_blur(&filters, &image, filter_size, true));
+            assert_eq!(image, box_blur(&filters, &image, filter_size, false));
+        }
+    }
+
+    #[test]
+    fn box_blur_even_size_rounds_up() {
+        let mut image = Image {
+            width: 5,
+            height: 5,
+            pixels: vec![Rgba([0, 0, 0, 0]); 25],
+        };
+        image.pixels[12] = Rgba([255, 255, 255, 255]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let three = box_blur(&filters, &image, 3, true);
+        assert_eq!(three, box_blur(&filters, &image, 2, true));
+        assert_eq!(three, box_blur(&filters, &image, 2, false));
+
+        // The window stays centered: the pixel spreads evenly to its 8 neighbours, without shifting.
+        let spread = Rgba([28, 28, 28, 28]);
+        for (index, pixel) in three.pixels.iter().enumerate() {
+            let (x, y) = (index % 5, index / 5);
+            if (1..=3).contains(&x) && (1..=3).contains(&y) {
+                assert_eq!(spread, *pixel, "{x},{y}");
+            } else {
+                assert_eq!(Rgba([0, 0, 0, 0]), *pixel, "{x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn box_blur_enormous_size_is_clamped() {
+        let image = test_image(40, 33);
+        let filters = Filters::new().block_on().unwrap();
+
+        let clamped = box_blur(&filters, &image, 81, true);
+        assert_eq!(clamped, box_blur(&filters, &image, u32::MAX, true));
+        assert_eq!(clamped, box_blur(&filters, &image, u32::MAX, false));
+    }
+
+    #[test]
+    fn shared_memory_matches_global() {
+        let image = test_image(300, 200);
+        let filters = Filters::new().block_on().unwrap();
+
+        for sigma in [0.5, 2.2, 20.0, 60.0] {
+            let shared = gaussian_blur(&filters, &image, sigma, true);
+            let global = gaussian_blur(&filters, &image, sigma, false);
+
+            assert!(shared.approx_eq(&global, 1), "sigma {sigma}");
+        }
+    }
 
-        assert_eq!(kernel.sum, 0.9998788);
+    /// Checks that the pixels of the last row and of the last column were blurred, rather than left black, or
+    /// copied from the input, by a dispatch too small for the image.
+    fn assert_edges_blurred(input: &Image, output: &Image, name: &str) {
+        let (width, height) = (input.width, input.height);
+        let last_row = (0..width).map(|x| (x, height - 1));
+        let last_column = (0..height).map(|y| (width - 1, y));
+        for (x, y) in last_row.chain(last_column) {
+            let index = (y * width + x) as usize;
+            let (input, output) = (input.pixels[index], output.pixels[index]);
+            assert_ne!(
+                Rgba([0, 0, 0, 0]),
+                output,
+                "{name} {width}x{height} at {x},{y}"
+            );
+            assert_ne!(input, output, "{name} {width}x{height} at {x},{y}");
+        }
+    }
+
+    #[test]
+    fn blurs_reach_the_edges_of_non_square_images() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for (width, height) in [(512, 16), (16, 512)] {
+            let image = test_image(width, height);
+
+            let outputs = [
+                ("box blur", box_blur(&filters, &image, 5, true)),
+                ("box blur direct", box_blur(&filters, &image, 5, false)),
+                ("gaussian blur", gaussian_blur(&filters, &image, 2.0, true)),
+                (
+                    "gaussian blur global",
+                    gaussian_blur(&filters, &image, 2.0, false),
+                ),
+            ];
+            for (name, output) in outputs {
+                assert_edges_blurred(&image, &output, name);
+            }
+        }
+    }
+
+    #[test]
+    fn box_sizes_sigma_5() {
+        // Boxes of 9 and 11 pixels have variances of 80 / 12 and 120 / 12, which add up to about 5² = 25.
+        assert_eq!([9, 9, 11], box_sizes_for_gaussian(5.0));
+        assert_eq!([1, 1, 1], box_sizes_for_gaussian(0.1));
+    }
+
+    /// Squares of 32 pixels, with sharp edges for the blurs to soften.
+    fn checkerboard(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| {
+                    let (x, y) = (index % width / 32, index / width / 32);
+                    if (x + y) % 2 == 0 {
+                        Rgba([255, 200, 0, 255])
+                    } else {
+                        Rgba([0, 50, 255, 255])
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The root mean square error of the channels of `a` and `b`, leaving out `border` pixels on each side.
+    fn root_mean_square_error(a: &Image, b: &Image, border: u32) -> f64 {
+        let inside = |index: &usize| {
+            let (x, y) = (*index as u32 % a.width, *index as u32 / a.width);
+            (border..a.width - border).contains(&x) && (border..a.height - border).contains(&y)
+        };
+        let squares: Vec<f64> = a
+            .pixels
+            .iter()
+            .zip(&b.pixels)
+            .enumerate()
+            .filter(|(index, _)| inside(index))
+            .flat_map(|(_, (a, b))| {
+                a.0.iter()
+                    .zip(b.0)
+                    .map(|(a, b)| (*a as f64 - b as f64).powi(2))
+            })
+            .collect();
+        (squares.iter().sum::<f64>() / squares.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn fast_gaussian_blur_is_close_to_gaussian_blur() {
+        let image = checkerboard(256, 256);
+        let filters = Filters::new().block_on().unwrap();
+
+        for sigma in [5.0, 15.0, 25.0] {
+            let exact = image
+                .operation(&filters)
+                .unwrap()
+                .gaussian_blur(sigma)
+                .execute()
+                .block_on();
+            let fast = image
+                .operation(&filters)
+                .unwrap()
+                .fast_gaussian_blur(sigma)
+                .execute()
+                .block_on();
+
+            // Each of the box blurs pulls in the transparent pixels around the image again, so the edges are darker.
+            let error = root_mean_square_error(&exact, &fast, 3 * sigma as u32);
+            assert!(error < 3.0, "sigma {sigma}, error {error}");
+        }
     }
 }
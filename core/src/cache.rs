@@ -0,0 +1,504 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use wgpu::{
+    BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType,
+    BufferBindingType, ComputePipeline, ComputePipelineDescriptor, Device, PipelineLayout,
+    PipelineLayoutDescriptor, PushConstantRange, SamplerBindingType, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StorageTextureAccess, TextureFormat,
+    TextureSampleType, TextureViewDimension,
+};
+
+use crate::{capitalize, PixelFormat, WorkgroupConfig};
+
+/// What a shader binds, to pick the bind group layouts of its pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Bindings {
+    /// The input texture at group 0, binding 0, and the output storage texture at group 0, binding 1.
+    Textures,
+    /// A uniform buffer at group 0, binding 0, and the textures of [`Bindings::Textures`] at group 1.
+    Uniform,
+    /// A sampler at group 0, binding 0, and the textures of [`Bindings::Textures`] at group 1.
+    Sampler,
+    /// Anything else, with the layouts derived from the shader.
+    Derived,
+}
+
+/// The bind group layouts shared by the pipelines of the filters, so that they can share bind groups too.
+struct BindGroupLayouts {
+    uniform: BindGroupLayout,
+    sampler: BindGroupLayout,
+    /// The input and output textures, for each format the passes work with.
+    textures: HashMap<PixelFormat, BindGroupLayout>,
+}
+
+impl BindGroupLayouts {
+    fn new(device: &Device, single_channel_storage: bool) -> Self {
+        let single_entry = |label: &str, ty: BindingType| {
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty,
+                    count: None,
+                }],
+            })
+        };
+        let uniform = single_entry(
+            "Uniform layout",
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        );
+        let sampler = single_entry(
+            "Sampler layout",
+            BindingType::Sampler(SamplerBindingType::Filtering),
+        );
+
+        let textures = [
+            PixelFormat::Rgba8,
+            PixelFormat::Rgba16,
+            PixelFormat::Float,
+            PixelFormat::Luma,
+        ]
+        .into_iter()
+        .map(|format| {
+            let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some(format!("Textures layout ({:?})", format).as_str()),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::StorageTexture {
+                            access: StorageTextureAccess::WriteOnly,
+                            format: format.texture_format(single_channel_storage),
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+            (format, layout)
+        })
+        .collect();
+
+        Self {
+            uniform,
+            sampler,
+            textures,
+        }
+    }
+}
+
+/// The compute pipelines built so far, keyed by filter name and pixel format, so that applying a filter again
+/// doesn't compile its shader again.
+pub(crate) struct PipelineCache {
+    pipelines: Mutex<HashMap<(&'static str, PixelFormat), Arc<ComputePipeline>>>,
+    layouts: BindGroupLayouts,
+    /// The workgroup sizes the shaders are compiled with, already clamped to the limits of the device.
+    workgroups: WorkgroupConfig,
+    /// The point workgroup sizes picked by auto-tuning, by filter name.
+    tuned_workgroup_sizes: Mutex<HashMap<&'static str, (u32, u32)>>,
+    /// Whether the device can write R8Unorm textures, which [`PixelFormat::Luma`] passes then use.
+    single_channel_storage: bool,
+    /// How many bytes of push constants the pipelines can use, 0 if the device doesn't support them.
+    max_push_constant_size: u32,
+}
+
+impl PipelineCache {
+    pub(crate) fn new(
+        device: &Device,
+        single_channel_storage: bool,
+        max_push_constant_size: u32,
+    ) -> Self {
+        Self {
+            pipelines: Mutex::new(HashMap::new()),
+            layouts: BindGroupLayouts::new(device, single_channel_storage),
+            workgroups: WorkgroupConfig::default().clamp(&device.limits()),
+            tuned_workgroup_sizes: Mutex::new(HashMap::new()),
+            single_channel_storage,
+            max_push_constant_size,
+        }
+    }
+
+    /// Whether settings of `size` bytes fit in push constants on this device.
+    pub(crate) fn fits_push_constants(&self, size: usize) -> bool {
+        size as u64 <= self.max_push_constant_size as u64
+    }
+
+    /// The format of the textures the passes working in `format` read and write on this device.
+    pub(crate) fn texture_format(&self, format: PixelFormat) -> TextureFormat {
+        format.texture_format(self.single_channel_storage)
+    }
+
+    pub(crate) fn workgroups(&self) -> WorkgroupConfig {
+        self.workgroups
+    }
+
+    /// Changes the workgroup sizes, which must already be clamped, forgetting the pipelines built with the previous
+    /// ones.
+    pub(crate) fn set_workgroups(&mut self, workgroups: WorkgroupConfig) {
+        self.workgroups = workgroups;
+        self.pipelines
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.tuned_workgroup_sizes
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// The workgroup size of the filter `name`, working on each pixel: the one picked by auto-tuning if there is one,
+    /// the configured one otherwise. Passes must be dispatched with the size their pipeline was built with.
+    pub(crate) fn workgroup_size(&self, name: &str) -> (u32, u32) {
+        self.tuned_workgroup_sizes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .copied()
+            .unwrap_or(self.workgroups.point)
+    }
+
+    pub(crate) fn is_tuned(&self, name: &str) -> bool {
+        self.tuned_workgroup_sizes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(name)
+    }
+
+    /// Keeps `size` as the workgroup size of `name`, unless another thread tuned it first.
+    pub(crate) fn set_tuned_workgroup_size(&self, name: &'static str, size: (u32, u32)) {
+        self.tuned_workgroup_sizes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name)
+            .or_insert(size);
+    }
+
+    /// Returns the pipeline of the filter `name`, building it from `shader_string` the first time, with the shared
+    /// bind group layouts matching `bindings`.
+    ///
+    /// Shaders are written for Rgba8Unorm output textures, their storage format is swapped for the one of `format`.
+    /// They are written for workgroups of 16×16, or of 128 for passes along a line, swapped for the configured sizes.
+    pub(crate) fn get(
+        &self,
+        device: &Device,
+        name: &'static str,
+        shader_string: &str,
+        format: PixelFormat,
+        bindings: Bindings,
+    ) -> Arc<ComputePipeline> {
+        self.cached(name, format, || {
+            let workgroup_size = self.workgroup_size(name);
+            self.build_with_workgroup_size(
+                device,
+                name,
+                shader_string,
+                format,
+                bindings,
+                workgroup_size,
+            )
+        })
+    }
+
+    /// Like [`PipelineCache::get`], but with the entries of the bind group 0 given rather than shared or derived
+    /// from the shader. Derived layouts expect filterable textures, so this is needed to read textures that can't
+    /// be filtered.
+    pub(crate) fn get_with_layout(
+        &self,
+        device: &Device,
+        name: &'static str,
+        shader_string: &str,
+        format: PixelFormat,
+        entries: &[BindGroupLayoutEntry],
+    ) -> Arc<ComputePipeline> {
+        self.cached(name, format, || {
+            let capitalized_filter_name = capitalize(name);
+            let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some(format!("{} layout", capitalized_filter_name).as_str()),
+                entries,
+            });
+            let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(format!("{} layout", capitalized_filter_name).as_str()),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            self.build(device, name, shader_string, format, Some(&layout))
+        })
+    }
+
+    /// Returns the pipeline of the filter `name` whose settings, `size` bytes long, are passed as push constants,
+    /// which must fit, see [`PipelineCache::fits_push_constants`].
+    ///
+    /// The shader is written like for [`Operation::settings_filter`]: the uniform at group 0, binding 0 becomes
+    /// the push constants, and the textures of group 1 move to group 0.
+    ///
+    /// [`Operation::settings_filter`]: crate::Operation::settings_filter
+    pub(crate) fn get_push_constants(
+        &self,
+        device: &Device,
+        name: &'static str,
+        shader_string: &str,
+        format: PixelFormat,
+        size: u32,
+    ) -> Arc<ComputePipeline> {
+        self.cached(name, format, || {
+            let shader_string = shader_string
+                .replace("@group(0) @binding(0) var<uniform>", "var<push_constant>")
+                .replace("@group(1)", "@group(0)");
+            let range = PushConstantRange {
+                stages: ShaderStages::COMPUTE,
+                range: 0..size,
+            };
+            let layout = self.layout(device, name, format, Bindings::Textures, &[range]);
+            self.build(device, name, &shader_string, format, layout.as_ref())
+        })
+    }
+
+    /// Builds a pipeline without caching it. Without a `layout`, the bind group layouts are derived from the shader.
+    pub(crate) fn build(
+        &self,
+        device: &Device,
+        name: &str,
+        shader_string: &str,
+        format: PixelFormat,
+        layout: Option<&PipelineLayout>,
+    ) -> ComputePipeline {
+        let shader = self.shader(
+            device,
+            name,
+            shader_string,
+            format,
+            self.workgroup_size(name),
+        );
+
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(format!("{} pipeline", capitalize(name)).as_str()),
+            layout,
+            module: &shader,
+            entry_point: "main",
+        })
+    }
+
+    /// Builds a pipeline without caching it, dispatched in workgroups of `workgroup_size` rather than the configured
+    /// size.
+    pub(crate) fn build_with_workgroup_size(
+        &self,
+        device: &Device,
+        name: &str,
+        shader_string: &str,
+        format: PixelFormat,
+        bindings: Bindings,
+        workgroup_size: (u32, u32),
+    ) -> ComputePipeline {
+        let layout = self.layout(device, name, format, bindings, &[]);
+        let shader = self.shader(device, name, shader_string, format, workgroup_size);
+
+        device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some(format!("{} pipeline", capitalize(name)).as_str()),
+            layout: layout.as_ref(),
+            module: &shader,
+            entry_point: "main",
+        })
+    }
+
+    /// Returns the cached pipeline of `name` and `format`, calling `build` to create it the first time.
+    fn cached(
+        &self,
+        name: &'static str,
+        format: PixelFormat,
+        build: impl FnOnce() -> ComputePipeline,
+    ) -> Arc<ComputePipeline> {
+        let mut pipelines = self
+            .pipelines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        pipelines
+            .entry((name, format))
+            .or_insert_with(|| Arc::new(build()))
+            .clone()
+    }
+
+    /// Creates the pipeline layout made of the shared bind group layouts matching `bindings`, or `None` for
+    /// [`Bindings::Derived`].
+    fn layout(
+        &self,
+        device: &Device,
+        name: &str,
+        format: PixelFormat,
+        bindings: Bindings,
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Option<PipelineLayout> {
+        let textures = &self.layouts.textures[&format];
+        let bind_group_layouts = match bindings {
+            Bindings::Textures => vec![textures],
+            Bindings::Uniform => vec![&self.layouts.uniform, textures],
+            Bindings::Sampler => vec![&self.layouts.sampler, textures],
+            Bindings::Derived => return None,
+        };
+
+        Some(device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some(format!("{} layout", capitalize(name)).as_str()),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges,
+        }))
+    }
+
+    /// Compiles `shader_string`, with its storage format swapped for the one of `format`, and its workgroup size for
+    /// `workgroup_size`, or for the configured line size.
+    fn shader(
+        &self,
+        device: &Device,
+        name: &str,
+        shader_string: &str,
+        format: PixelFormat,
+        (workgroup_width, workgroup_height): (u32, u32),
+    ) -> ShaderModule {
+        device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(format!("{} shader", capitalize(name)).as_str()),
+            source: ShaderSource::Wgsl(
+                shader_string
+                    .replace(
+                        "rgba8unorm",
+                        format.storage_format(self.single_channel_storage),
+                    )
+                    .replace(
+                        "@workgroup_size(16, 16)",
+                        &format!("@workgroup_size({workgroup_width}, {workgroup_height})"),
+                    )
+                    .replace(
+                        "@workgroup_size(128)",
+                        &format!("@workgroup_size({})", self.workgroups.line),
+                    )
+                    .into(),
+            ),
+        })
+    }
+
+    /// Makes the filters pass their settings in uniform buffers, as on devices without push constants.
+    #[cfg(test)]
+    pub(crate) fn disable_push_constants(&mut self) {
+        self.max_push_constant_size = 0;
+        self.clear();
+    }
+
+    /// How many pipelines were built so far.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.pipelines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Forgets all the pipelines, forcing them to be built again.
+    #[cfg(test)]
+    pub(crate) fn clear(&self) {
+        self.pipelines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::Bindings;
+    use crate::{Filters, Image, PixelFormat, Rgba};
+
+    #[test]
+    fn cached_and_uncached_pipelines_match() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([255, 0, 0, 255]), Rgba([20, 40, 60, 128])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let uncached = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .gaussian_blur(1.0)
+            .execute()
+            .block_on();
+        let cached = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .gaussian_blur(1.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(uncached, cached);
+    }
+
+    #[test]
+    fn pipelines_are_built_once() {
+        let image = Image {
+            width: 4,
+            height: 4,
+            pixels: vec![Rgba([10, 20, 30, 255]); 16],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let run = || {
+            image
+                .operation(&filters)
+                .unwrap()
+                .grayscale()
+                .gaussian_blur(1.0)
+                .execute()
+                .block_on()
+        };
+
+        run();
+        let built = filters.pipelines.len();
+        run();
+
+        assert!(built > 0);
+        assert_eq!(built, filters.pipelines.len());
+
+        filters.pipelines.clear();
+        run();
+        assert_eq!(built, filters.pipelines.len());
+    }
+
+    #[test]
+    fn point_filters_share_bind_group_layout() {
+        let filters = Filters::new().block_on().unwrap();
+        let layout = |name, shader| {
+            let pipeline = filters.pipelines.get(
+                &filters.device,
+                name,
+                shader,
+                PixelFormat::Rgba8,
+                Bindings::Textures,
+            );
+            // The debug output of a bind group layout holds its id.
+            format!("{:?}", pipeline.get_bind_group_layout(0))
+        };
+
+        let grayscale = layout("grayscale", crate::GRAYSCALE_SHADER);
+        let inverse = layout("inverse", crate::INVERSE_SHADER);
+
+        assert_eq!(grayscale, inverse);
+    }
+}
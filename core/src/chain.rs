@@ -0,0 +1,263 @@
+use crate::{FiltersError, Operation, Resize, Rgba};
+
+/// A filter with its settings, one of the steps of a [`FilterChain`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "filter", rename_all = "lowercase", deny_unknown_fields)
+)]
+pub enum FilterStep {
+    Grayscale,
+    Inverse,
+    HFlip,
+    VFlip,
+    Resize {
+        width: u32,
+        height: u32,
+        mode: Resize,
+    },
+    /// Resizes the image to fit in `width` by `height`, keeping its aspect ratio, see [`Operation::resize_fit`].
+    Fit {
+        width: u32,
+        height: u32,
+        mode: Resize,
+    },
+    /// See [`Operation::thumbnail`].
+    Thumbnail {
+        size: u32,
+    },
+    BoxBlur {
+        size: u32,
+    },
+    GaussianBlur {
+        sigma: f32,
+    },
+    /// See [`Operation::fast_gaussian_blur`].
+    FastBlur {
+        sigma: f32,
+    },
+    Brightness {
+        amount: f32,
+    },
+    Contrast {
+        amount: f32,
+    },
+    Sharpen {
+        amount: f32,
+    },
+    /// See [`Operation::split_tone`].
+    SplitTone {
+        shadows: Rgba,
+        highlights: Rgba,
+        balance: f32,
+    },
+}
+
+/// A list of filters to apply in order, which can be stored, with the `serde` feature, and replayed later.
+///
+/// In TOML, each step is a table naming its filter along with its settings:
+///
+/// ```toml
+/// [[steps]]
+/// filter = "grayscale"
+///
+/// [[steps]]
+/// filter = "resize"
+/// width = 800
+/// height = 600
+/// mode = "linear"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct FilterChain {
+    pub steps: Vec<FilterStep>,
+}
+
+impl FilterChain {
+    pub fn new(steps: Vec<FilterStep>) -> Self {
+        Self { steps }
+    }
+
+    /// Applies the steps of the chain to `operation`, in order.
+    ///
+    /// # Errors
+    ///
+    /// The error of the first step that fails, like a resize to a zero width.
+    pub fn apply<'a>(&self, operation: Operation<'a>) -> Result<Operation<'a>, FiltersError> {
+        self.steps
+            .iter()
+            .try_fold(operation, |operation, step| step.apply(operation))
+    }
+}
+
+impl FilterStep {
+    /// The name of the filter, as written in the strings parsed by [`FilterChain::parse`] and in serialized chains.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterStep::Grayscale => "grayscale",
+            FilterStep::Inverse => "inverse",
+            FilterStep::HFlip => "hflip",
+            FilterStep::VFlip => "vflip",
+            FilterStep::Resize { .. } => "resize",
+            FilterStep::Fit { .. } => "fit",
+            FilterStep::Thumbnail { .. } => "thumbnail",
+            FilterStep::BoxBlur { .. } => "boxblur",
+            FilterStep::GaussianBlur { .. } => "gaussianblur",
+            FilterStep::FastBlur { .. } => "fastblur",
+            FilterStep::Brightness { .. } => "brightness",
+            FilterStep::Contrast { .. } => "contrast",
+            FilterStep::Sharpen { .. } => "sharpen",
+            FilterStep::SplitTone { .. } => "splittone",
+        }
+    }
+
+    /// Applies this filter to `operation`.
+    pub fn apply<'a>(&self, operation: Operation<'a>) -> Result<Operation<'a>, FiltersError> {
+        Ok(match *self {
+            FilterStep::Grayscale => operation.grayscale(),
+            FilterStep::Inverse => operation.inverse(),
+            FilterStep::HFlip => operation.hflip(),
+            FilterStep::VFlip => operation.vflip(),
+            FilterStep::Resize {
+                width,
+                height,
+                mode,
+            } => operation.resize((width, height), mode)?,
+            FilterStep::Fit {
+                width,
+                height,
+                mode,
+            } => operation.resize_fit((width, height), mode)?,
+            FilterStep::Thumbnail { size } => operation.thumbnail(size)?,
+            FilterStep::BoxBlur { size } => operation.box_blur(size),
+            FilterStep::GaussianBlur { sigma } => operation.gaussian_blur(sigma),
+            FilterStep::FastBlur { sigma } => operation.fast_gaussian_blur(sigma),
+            FilterStep::Brightness { amount } => operation.brightness(amount),
+            FilterStep::Contrast { amount } => operation.contrast(amount),
+            FilterStep::Sharpen { amount } => operation.sharpen(amount),
+            FilterStep::SplitTone {
+                shadows,
+                highlights,
+                balance,
+            } => operation.split_tone(shadows, highlights, balance),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{FilterChain, FilterStep, Filters, FiltersError, Image, Resize, Rgba};
+
+    fn test_image() -> Image {
+        Image {
+            width: 6,
+            height: 4,
+            pixels: (0..24)
+                .map(|index| Rgba([index * 10, 255 - index * 10, 64, 255]))
+                .collect(),
+        }
+    }
+
+    fn test_chain() -> FilterChain {
+        FilterChain::new(vec![
+            FilterStep::Grayscale,
+            FilterStep::HFlip,
+            FilterStep::Resize {
+                width: 3,
+                height: 2,
+                mode: Resize::Nearest,
+            },
+            FilterStep::GaussianBlur { sigma: 1.5 },
+            FilterStep::Inverse,
+        ])
+    }
+
+    #[test]
+    fn chain_matches_builder_calls() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .hflip()
+            .resize((3, 2), Resize::Nearest)
+            .unwrap()
+            .gaussian_blur(1.5)
+            .inverse()
+            .execute()
+            .block_on();
+        let output = test_chain()
+            .apply(image.operation(&filters).unwrap())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn chain_resize_error() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = FilterChain::new(vec![FilterStep::Resize {
+            width: 0,
+            height: 2,
+            mode: Resize::Linear,
+        }])
+        .apply(image.operation(&filters).unwrap());
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 2
+            })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn chain_serde_round_trip() {
+        let image = test_image();
+        let chain = test_chain();
+        let filters = Filters::new().block_on().unwrap();
+
+        let json = serde_json::to_string(&chain).unwrap();
+        let parsed: FilterChain = serde_json::from_str(&json).unwrap();
+        assert_eq!(chain, parsed);
+
+        let expected = chain
+            .apply(image.operation(&filters).unwrap())
+            .unwrap()
+            .execute()
+            .block_on();
+        let output = parsed
+            .apply(image.operation(&filters).unwrap())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn chain_unknown_step() {
+        let result = serde_json::from_str::<FilterChain>(
+            r#"{"steps": [{"filter": "grayscale"}, {"filter": "sepia"}]}"#,
+        );
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("unknown variant `sepia`"), "{message}");
+    }
+}
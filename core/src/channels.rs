@@ -0,0 +1,196 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    TextureViewDescriptor,
+};
+
+use crate::{cache::Bindings, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Operation};
+
+const BLUR_CHANNELS_SHADER: &str = include_str!("shaders/blur_channels.wgsl");
+
+/// The channels a filter like [`Operation::gaussian_blur_channels`] changes, the others being kept as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChannelMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ChannelMask {
+    pub const ALL: Self = Self {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: true,
+    };
+    pub const RGB: Self = Self {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: false,
+    };
+    pub const ALPHA: Self = Self {
+        red: false,
+        green: false,
+        blue: false,
+        alpha: true,
+    };
+
+    /// The mask as the 1s and 0s of a uniform, since booleans can't be stored in one.
+    fn to_uniform(self) -> [u32; 4] {
+        [self.red, self.green, self.blue, self.alpha].map(u32::from)
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Like [`Operation::gaussian_blur`], but only blurs the `channels` of the mask, copying the others untouched,
+    /// like to soften the alpha of a cut out while keeping its colors sharp. Single channel images are blurred
+    /// according to the red of the mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `sigma` - The standard deviation of the gaussian, in pixels. A sigma of 0 or less leaves the image as is.
+    /// * `channels` - The channels to blur.
+    pub fn gaussian_blur_channels(mut self, sigma: f32, channels: ChannelMask) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 || channels == ChannelMask::default() {
+            return self;
+        }
+        if channels == ChannelMask::ALL {
+            return self.gaussian_blur(sigma);
+        }
+
+        let name = "blur channels";
+        let (operation, blurred, blurred_usage) = self.blurred_copy(sigma);
+        self = operation;
+
+        let pipeline = self.pipeline(name, BLUR_CHANNELS_SHADER, Bindings::Derived);
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Blur channels settings"),
+            contents: bytemuck::cast_slice(&channels.to_uniform()),
+            usage: BufferUsages::UNIFORM,
+        });
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Blur channels bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &blurred.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Blur channels pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.pool.release(self.texture_size, blurred_usage, blurred);
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{ChannelMask, Filters, Image, Rgba};
+
+    /// A fine checkerboard of colors, inside an opaque disc on a transparent background.
+    fn cut_out() -> Image {
+        Image::from_fn(48, 48, |x, y| {
+            let (dx, dy) = (x as f32 - 24.0, y as f32 - 24.0);
+            let alpha = if dx * dx + dy * dy < 15.0 * 15.0 {
+                255
+            } else {
+                0
+            };
+            if (x + y) % 2 == 0 {
+                Rgba::new(250, 20, (x * 5) as u8, alpha)
+            } else {
+                Rgba::new(10, 230, (y * 5) as u8, alpha)
+            }
+        })
+    }
+
+    fn blur(filters: &Filters, image: &Image, channels: ChannelMask) -> Image {
+        image
+            .operation(filters)
+            .unwrap()
+            .gaussian_blur_channels(2.0, channels)
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn blurs_only_alpha() {
+        let image = cut_out();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = blur(&filters, &image, ChannelMask::ALPHA);
+        let blurred = blur(&filters, &image, ChannelMask::ALL);
+
+        for ((input, output), blurred) in
+            image.pixels.iter().zip(&output.pixels).zip(&blurred.pixels)
+        {
+            assert_eq!(input.0[..3], output.0[..3]);
+            assert_eq!(blurred.a(), output.a());
+        }
+        assert_ne!(image, output);
+    }
+
+    #[test]
+    fn blurs_only_colors() {
+        let image = cut_out();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = blur(&filters, &image, ChannelMask::RGB);
+        let blurred = blur(&filters, &image, ChannelMask::ALL);
+
+        for ((input, output), blurred) in
+            image.pixels.iter().zip(&output.pixels).zip(&blurred.pixels)
+        {
+            assert_eq!(blurred.0[..3], output.0[..3]);
+            assert_eq!(input.a(), output.a());
+        }
+    }
+
+    #[test]
+    fn no_channels_is_identity() {
+        let image = cut_out();
+        let filters = Filters::new().block_on().unwrap();
+
+        assert_eq!(image, blur(&filters, &image, ChannelMask::default()));
+    }
+}
@@ -0,0 +1,203 @@
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindingResource, ComputePassDescriptor,
+    TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Operation, PixelFormat,
+};
+
+const CHROMA_SPLIT_SHADER: &str = include_str!("shaders/chroma_split.wgsl");
+const CHROMA_DENOISE_SHADER: &str = include_str!("shaders/chroma_denoise.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Removes the color noise of the image, like the blotches of a photo taken in low light, while keeping it
+    /// sharp: the chroma of the image is split off on a copy and blurred by a gaussian, then recombined with the
+    /// luma, the Y of YCbCr, of each pixel, left untouched.
+    ///
+    /// Grayscale images have no chroma and are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `sigma` - The standard deviation of the blur of the chroma, in pixels, like 2.0 to 5.0 for the noise of a
+    ///   photo. A sigma of 0 or less leaves the image as is.
+    pub fn chroma_denoise(mut self, sigma: f32) -> Self {
+        if sigma.is_nan() || sigma <= 0.0 || self.format == PixelFormat::Luma {
+            return self;
+        }
+
+        let name = "chroma denoise";
+        let (operation, chroma, chroma_usage) = self.filtered_copy(|copy| {
+            copy.simple_filter("chroma split", CHROMA_SPLIT_SHADER)
+                .gaussian_blur(sigma)
+        });
+        self = operation;
+
+        let pipeline = self.pipeline(name, CHROMA_DENOISE_SHADER, Bindings::Derived);
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Chroma denoise bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &chroma.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Chroma denoise pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.pool.release(self.texture_size, chroma_usage, chroma);
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    /// A xorshift generator, so that the test is reproducible.
+    fn random(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    fn luma(pixel: &Rgba) -> f32 {
+        0.299 * f32::from(pixel.r()) + 0.587 * f32::from(pixel.g()) + 0.114 * f32::from(pixel.b())
+    }
+
+    /// The Cb and Cr of the pixel, from -127.5 to 127.5.
+    fn chroma(pixel: &Rgba) -> [f32; 2] {
+        let luma = luma(pixel);
+        [
+            (f32::from(pixel.b()) - luma) / 1.772,
+            (f32::from(pixel.r()) - luma) / 1.402,
+        ]
+    }
+
+    /// The variance of the Cb and Cr of the image, summed.
+    fn chroma_variance(image: &Image) -> f32 {
+        let chromas = image.pixels.iter().map(chroma).collect::<Vec<_>>();
+        (0..2)
+            .map(|channel| {
+                let mean = chromas.iter().map(|c| c[channel]).sum::<f32>() / chromas.len() as f32;
+                chromas
+                    .iter()
+                    .map(|c| (c[channel] - mean).powi(2))
+                    .sum::<f32>()
+                    / chromas.len() as f32
+            })
+            .sum()
+    }
+
+    /// Blocks of grays, from 48 to 208, with noise only in their chroma.
+    fn noisy_chart() -> Image {
+        let mut state = 0x2545_f491;
+        Image::from_fn(64, 64, |x, y| {
+            let gray = 48.0 + 32.0 * ((x / 16 + y / 16) % 6) as f32;
+            let noise = |state: &mut u32| (random(state) % 41) as f32 - 20.0;
+            let (cb, cr) = (noise(&mut state), noise(&mut state));
+            let red = gray + 1.402 * cr;
+            let blue = gray + 1.772 * cb;
+            let green = (gray - 0.299 * red - 0.114 * blue) / 0.587;
+            Rgba::new(
+                red.round() as u8,
+                green.round() as u8,
+                blue.round() as u8,
+                255,
+            )
+        })
+    }
+
+    #[test]
+    fn removes_chroma_noise_keeping_luma() {
+        let image = noisy_chart();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .chroma_denoise(3.0)
+            .execute()
+            .block_on();
+
+        assert!(
+            chroma_variance(&output) < chroma_variance(&image) * 0.05,
+            "{} from {}",
+            chroma_variance(&output),
+            chroma_variance(&image)
+        );
+        for (input, output) in image.pixels.iter().zip(&output.pixels) {
+            assert!(
+                (luma(input) - luma(output)).abs() <= 1.0,
+                "{input:?} became {output:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn gray_is_unchanged() {
+        let image = Image::from_fn(32, 32, |x, y| {
+            let gray = (x * 8 + y) as u8;
+            Rgba::new(gray, gray, gray, 255)
+        });
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .chroma_denoise(3.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn zero_sigma_is_identity() {
+        let image = noisy_chart();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .chroma_denoise(0.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+}
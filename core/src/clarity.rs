@@ -0,0 +1,145 @@
+use crate::{Operation, PixelFormat};
+
+const CLARITY_SHADER: &str = include_str!("shaders/clarity.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ClaritySettings {
+    amount: f32,
+    single_channel: u32,
+}
+
+impl<'a> Operation<'a> {
+    /// Boosts the local contrast of the midtones, like an unsharp mask with a large radius: the difference between
+    /// the luminance of each pixel and the one of its blurred surroundings is added back, weighted so that it fades
+    /// out towards black and white, which keeps the shadows and the highlights from clipping.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - How much of the local contrast is added: 0.0 leaves the image untouched, 0.5 is a strong
+    ///   boost. Negative amounts soften the image for a dreamy look, -1.0 replacing the luminance of the midtones by
+    ///   its blurred version.
+    /// * `radius` - The sigma of the gaussian blur the local contrast is measured against, in pixels, large compared
+    ///   to the details, like 20 or more for a photo.
+    pub fn clarity(self, amount: f32, radius: f32) -> Self {
+        if amount == 0.0 {
+            return self;
+        }
+
+        let settings = ClaritySettings {
+            amount,
+            single_channel: u32::from(self.format == PixelFormat::Luma),
+        };
+        self.blurred_pass(
+            "clarity",
+            CLARITY_SHADER,
+            bytemuck::bytes_of(&settings),
+            &[radius],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    /// Soft waves of gray, from about 30 to 225, with some finer ripples on them.
+    fn waves() -> Image {
+        Image::from_fn(96, 64, |x, y| {
+            let (x, y) = (x as f32, y as f32);
+            let value = 128.0 + 80.0 * (x / 9.0).sin() * (y / 11.0).cos() + 15.0 * (x / 2.0).sin();
+            let value = value.round() as u8;
+            Rgba::new(value, value, value, 255)
+        })
+    }
+
+    fn standard_deviation(image: &Image) -> f32 {
+        let luminances = image
+            .pixels
+            .iter()
+            .map(|pixel| {
+                0.299 * f32::from(pixel.r())
+                    + 0.587 * f32::from(pixel.g())
+                    + 0.114 * f32::from(pixel.b())
+            })
+            .collect::<Vec<_>>();
+        let mean = luminances.iter().sum::<f32>() / luminances.len() as f32;
+        let variance = luminances
+            .iter()
+            .map(|luminance| (luminance - mean).powi(2))
+            .sum::<f32>()
+            / luminances.len() as f32;
+        variance.sqrt()
+    }
+
+    fn clipped(image: &Image) -> usize {
+        image
+            .pixels
+            .iter()
+            .filter(|pixel| {
+                pixel.0[..3]
+                    .iter()
+                    .any(|&channel| channel == 0 || channel == 255)
+            })
+            .count()
+    }
+
+    #[test]
+    fn positive_amount_adds_contrast() {
+        let image = waves();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .clarity(0.8, 12.0)
+            .execute()
+            .block_on();
+
+        assert!(
+            standard_deviation(&output) > standard_deviation(&image) * 1.1,
+            "{} from {}",
+            standard_deviation(&output),
+            standard_deviation(&image)
+        );
+        assert!(clipped(&output) <= 10, "{} clipped", clipped(&output));
+    }
+
+    #[test]
+    fn negative_amount_softens() {
+        let image = waves();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .clarity(-0.8, 12.0)
+            .execute()
+            .block_on();
+
+        assert!(
+            standard_deviation(&output) < standard_deviation(&image) * 0.9,
+            "{} from {}",
+            standard_deviation(&output),
+            standard_deviation(&image)
+        );
+        assert!(clipped(&output) <= 10, "{} clipped", clipped(&output));
+    }
+
+    #[test]
+    fn no_amount_is_identity() {
+        let image = waves();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .clarity(0.0, 12.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+}
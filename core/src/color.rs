@@ -0,0 +1,234 @@
+use std::str::FromStr;
+
+use crate::{FiltersError, Rgba};
+
+impl Rgba {
+    /// Parses a color written as `#rrggbb`, or `#rrggbbaa` with an alpha, the `#` being optional. Colors without
+    /// alpha are opaque.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidColor`] if the string has another length, or characters other than hex digits.
+    pub fn from_hex(input: &str) -> Result<Self, FiltersError> {
+        let hex = input.strip_prefix('#').unwrap_or(input);
+        if !matches!(hex.len(), 6 | 8) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(FiltersError::InvalidColor(input.to_owned()));
+        }
+
+        let mut channels = [u8::MAX; 4];
+        for (channel, index) in channels.iter_mut().zip((0..hex.len()).step_by(2)) {
+            *channel =
+                u8::from_str_radix(&hex[index..index + 2], 16).expect("The digits were checked");
+        }
+        Ok(Self(channels))
+    }
+
+    /// Writes the color as `#rrggbbaa`, which [`Rgba::from_hex`] reads back.
+    pub fn to_hex(&self) -> String {
+        let Self([r, g, b, a]) = self;
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+
+    /// The hue in degrees, from 0 to 360, and the saturation and lightness, from 0 to 1, of the color. The alpha is
+    /// left out.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let [r, g, b] = self.unit_rgb();
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let lightness = (max + min) / 2.0;
+        let chroma = max - min;
+        let saturation = if chroma == 0.0 {
+            0.0
+        } else {
+            chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+
+        (hue([r, g, b], max, chroma), saturation, lightness)
+    }
+
+    /// The color of the given hue in degrees, wrapped to 0 to 360, and saturation and lightness, clamped from 0
+    /// to 1.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: u8) -> Self {
+        let (saturation, lightness) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        from_hue_chroma(hue, chroma, lightness - chroma / 2.0, alpha)
+    }
+
+    /// The hue in degrees, from 0 to 360, and the saturation and value, from 0 to 1, of the color. The alpha is
+    /// left out.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let [r, g, b] = self.unit_rgb();
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let chroma = max - min;
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        (hue([r, g, b], max, chroma), saturation, max)
+    }
+
+    /// The color of the given hue in degrees, wrapped to 0 to 360, and saturation and value, clamped from 0 to 1.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: u8) -> Self {
+        let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let chroma = value * saturation;
+        from_hue_chroma(hue, chroma, value - chroma, alpha)
+    }
+
+    /// Decodes the sRGB channels to linear values from 0 to 1, like [`crate::Operation::assume_srgb`] does on the
+    /// gpu. The alpha, which isn't encoded, is only scaled.
+    pub fn to_linear_f32(&self) -> [f32; 4] {
+        let [r, g, b] = self.unit_rgb().map(|channel| {
+            if channel <= 0.04045 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        [r, g, b, f32::from(self.a()) / 255.0]
+    }
+
+    /// Encodes linear values, clamped from 0 to 1, back to sRGB, the reverse of [`Rgba::to_linear_f32`].
+    pub fn from_linear_f32(linear: [f32; 4]) -> Self {
+        let [r, g, b, a] = linear.map(|channel| channel.clamp(0.0, 1.0));
+        let [r, g, b] = [r, g, b].map(|channel| {
+            if channel <= 0.0031308 {
+                channel * 12.92
+            } else {
+                1.055 * channel.powf(1.0 / 2.4) - 0.055
+            }
+        });
+        Self([r, g, b, a].map(to_byte))
+    }
+
+    /// The luminance of the color, from 0 to 1, weighting the channels like [`crate::Operation::grayscale`].
+    pub fn luminance(&self) -> f32 {
+        let [r, g, b] = self.unit_rgb();
+        0.299 * r + 0.587 * g + 0.114 * b
+    }
+
+    fn unit_rgb(&self) -> [f32; 3] {
+        let Self([r, g, b, _]) = self;
+        [r, g, b].map(|channel| f32::from(*channel) / 255.0)
+    }
+}
+
+/// Parses a color with [`Rgba::from_hex`].
+impl FromStr for Rgba {
+    type Err = FiltersError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(input)
+    }
+}
+
+/// The hue shared by HSL and HSV, in degrees, of channels whose largest is `max`, `chroma` above the smallest.
+fn hue([r, g, b]: [f32; 3], max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        return 0.0;
+    }
+    let sector = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    sector * 60.0
+}
+
+/// The color of `hue`, whose largest channel is `chroma` above the smallest one, `min`.
+fn from_hue_chroma(hue: f32, chroma: f32, min: f32, alpha: u8) -> Rgba {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let second = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let [r, g, b] = [r, g, b].map(|channel| to_byte(channel + min));
+    Rgba([r, g, b, alpha])
+}
+
+fn to_byte(unit: f32) -> u8 {
+    (unit.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FiltersError, Rgba};
+
+    /// Every color whose channels are multiples of 5, 52³ of them, black and white included.
+    fn colors() -> impl Iterator<Item = Rgba> {
+        (0..=255).step_by(5).flat_map(|r| {
+            (0..=255)
+                .step_by(5)
+                .flat_map(move |g| (0..=255).step_by(5).map(move |b| Rgba::new(r, g, b, 200)))
+        })
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for color in colors() {
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert!((0.0..360.0).contains(&hue), "{color:?}");
+            assert_eq!(color, Rgba::from_hsl(hue, saturation, lightness, 200));
+        }
+        assert_eq!((0.0, 1.0, 0.5), Rgba::new(255, 0, 0, 255).to_hsl());
+        assert_eq!(
+            Rgba::new(0, 0, 255, 255),
+            Rgba::from_hsl(-120.0, 1.0, 0.5, 255)
+        );
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        for color in colors() {
+            let (hue, saturation, value) = color.to_hsv();
+            assert!((0.0..360.0).contains(&hue), "{color:?}");
+            assert_eq!(color, Rgba::from_hsv(hue, saturation, value, 200));
+        }
+        assert_eq!((120.0, 1.0, 1.0), Rgba::new(0, 255, 0, 255).to_hsv());
+        assert_eq!(
+            Rgba::new(128, 128, 128, 255),
+            Rgba::from_hsv(42.0, 0.0, 128.0 / 255.0, 255)
+        );
+    }
+
+    #[test]
+    fn linear_round_trip() {
+        for value in 0..=255 {
+            let color = Rgba::new(value, value, value, value);
+            assert_eq!(color, Rgba::from_linear_f32(color.to_linear_f32()));
+        }
+        let [gray, _, _, alpha] = Rgba::new(188, 188, 188, 255).to_linear_f32();
+        assert!((gray - 0.5).abs() < 0.005, "{gray}");
+        assert_eq!(1.0, alpha);
+    }
+
+    #[test]
+    fn luminance() {
+        assert_eq!(0.0, Rgba::new(0, 0, 0, 255).luminance());
+        assert!((Rgba::new(255, 255, 255, 0).luminance() - 1.0).abs() < 1e-6);
+        assert!(Rgba::new(0, 255, 0, 255).luminance() > Rgba::new(255, 0, 0, 255).luminance());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(
+            Rgba::new(255, 0, 128, 255),
+            Rgba::from_hex("#ff0080").unwrap()
+        );
+        assert_eq!(Rgba::new(0, 0, 0, 16), "00000010".parse().unwrap());
+        assert_eq!("#12abcdef", Rgba::new(0x12, 0xab, 0xcd, 0xef).to_hex());
+
+        for invalid in ["", "#", "#fff", "#ff00801", "gg0000", "#ff+080", "ff00éé"] {
+            assert!(
+                matches!(
+                    Rgba::from_hex(invalid),
+                    Err(FiltersError::InvalidColor(input)) if input == invalid
+                ),
+                "{invalid}"
+            );
+        }
+    }
+}
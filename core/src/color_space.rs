@@ -0,0 +1,152 @@
+use crate::{cache::Bindings, Operation, PixelFormat, Rgba};
+
+const SRGB_TO_LINEAR_SHADER: &str = include_str!("shaders/srgb_to_linear.wgsl");
+const LINEAR_TO_SRGB_SHADER: &str = include_str!("shaders/linear_to_srgb.wgsl");
+
+/// How the values the filters work on relate to light intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// The filters work directly on the stored values, which is how they behave by default.
+    Linear,
+    /// The stored values are sRGB encoded: the filters work on the linear values decoded from them,
+    /// and the result is encoded back to sRGB before being read.
+    Srgb,
+}
+
+impl<'a> Operation<'a> {
+    /// Declares the image as sRGB encoded, like most 8-bit images are, so that the following filters blend light
+    /// correctly: blurring a black and white checkerboard gives a gray of 188 rather than a darker 128.
+    ///
+    /// The image is decoded to linear values right away, with at least 16 bits of precision so that dark tones don't
+    /// band, and encoded back to sRGB when the result is read. Does nothing if the image already is decoded.
+    pub fn assume_srgb(mut self) -> Self {
+        if self.color_space == ColorSpace::Srgb {
+            return self;
+        }
+
+        let format = match self.format {
+            PixelFormat::Rgba8 => PixelFormat::Rgba16,
+            format => format,
+        };
+        self.convert_format(format, "srgb_to_linear", SRGB_TO_LINEAR_SHADER);
+        self.color_space = ColorSpace::Srgb;
+
+        self
+    }
+
+    /// Makes the following filters work on the stored values again, encoding the image back to sRGB if it was
+    /// decoded by [`Operation::assume_srgb`].
+    pub fn assume_linear(mut self) -> Self {
+        self.encode_srgb();
+        self
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The channels of `color` from 0 to 1, decoded to linear values if the image is, to pass to the shaders.
+    pub(crate) fn color_values(&self, color: Rgba) -> [f32; 4] {
+        match self.color_space {
+            ColorSpace::Srgb => color.to_linear_f32(),
+            ColorSpace::Linear => color.0.map(|channel| f32::from(channel) / 255.0),
+        }
+    }
+
+    /// Records the encoding back to sRGB of an image decoded by [`Operation::assume_srgb`], before reading it.
+    pub(crate) fn encode_srgb(&mut self) {
+        if self.color_space == ColorSpace::Linear {
+            return;
+        }
+
+        let pipeline = self.pipeline("linear_to_srgb", LINEAR_TO_SRGB_SHADER, Bindings::Textures);
+        self.convert_input("linear_to_srgb", &pipeline);
+        self.color_space = ColorSpace::Linear;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{ColorSpace, Filters, Image, PixelFormat, Resize, Rgba};
+
+    fn checkerboard(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| {
+                    if (index % width + index / width).is_multiple_of(2) {
+                        Rgba([0, 0, 0, 255])
+                    } else {
+                        Rgba([255, 255, 255, 255])
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip_is_identity() {
+        let image = Image {
+            width: 256,
+            height: 1,
+            pixels: (0..=255)
+                .map(|value| Rgba([value, value, 255 - value, value]))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image.operation(&filters).unwrap().assume_srgb();
+        assert_eq!(ColorSpace::Srgb, operation.color_space());
+        assert_eq!(PixelFormat::Rgba16, operation.pixel_format());
+        let output = operation.execute().block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn srgb_blur_of_checkerboard() {
+        let image = checkerboard(32, 32);
+        let filters = Filters::new().block_on().unwrap();
+
+        let gamma = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(2.0)
+            .execute()
+            .block_on();
+        let linear = image
+            .operation(&filters)
+            .unwrap()
+            .assume_srgb()
+            .gaussian_blur(2.0)
+            .execute()
+            .block_on();
+
+        let center = (16 * 32 + 16) as usize;
+        assert!(gamma.pixels[center].0[0].abs_diff(128) <= 3);
+        assert!(linear.pixels[center].0[0].abs_diff(188) <= 3);
+    }
+
+    #[test]
+    fn srgb_resize_of_checkerboard() {
+        let image = checkerboard(4, 4);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .assume_srgb()
+            .resize((2, 2), Resize::Area)
+            .unwrap()
+            .assume_linear()
+            .execute()
+            .block_on();
+
+        for pixel in output.pixels {
+            assert!(pixel.0[0].abs_diff(188) <= 1);
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    ComputePipeline, TextureFormat, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    compute_work_group_count, encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    read_mapped_buffer_into, wait_for_mapping, Operation, PixelFormat,
+};
+
+const COMPONENTS_INIT_SHADER: &str = include_str!("shaders/components_init.wgsl");
+const COMPONENTS_PROPAGATE_SHADER: &str = include_str!("shaders/components_propagate.wgsl");
+
+/// How many times the labels spread to their neighbors on the gpu. Small components are fully labeled by then,
+/// and the cpu merges what is left of the larger ones, so this only trades gpu passes for cpu work.
+const PROPAGATION_PASSES: u32 = 16;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ThresholdSettings {
+    threshold: f32,
+    single_channel: u32,
+}
+
+/// A region of connected pixels found by [`Operation::connected_components`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Component {
+    /// The label of the pixels of the component in [`LabeledImage::labels`], starting from 1.
+    pub label: u32,
+    /// The left edge of the bounding box.
+    pub x: u32,
+    /// The top edge of the bounding box.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// How many pixels the component has.
+    pub area: u32,
+}
+
+/// The connected components of an image, see [`Operation::connected_components`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabeledImage {
+    pub width: u32,
+    pub height: u32,
+    /// The label of each pixel, row by row: 0 for the background, and the label of its component otherwise.
+    pub labels: Vec<u32>,
+    /// The components, in the order of their first pixel, row by row, so that the component labeled `n` is at
+    /// index `n - 1`.
+    pub components: Vec<Component>,
+}
+
+impl LabeledImage {
+    /// The label of the pixel at `x`, `y`, or `None` outside of the image.
+    pub fn label(&self, x: u32, y: u32) -> Option<u32> {
+        (x < self.width && y < self.height)
+            .then(|| self.labels[y as usize * self.width as usize + x as usize])
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Finds the regions of pixels brighter than `threshold`, like the blobs of a mask, each pixel being connected
+    /// to the four next to it.
+    ///
+    /// The labels spread from pixel to pixel on the gpu for a few passes, then are read back and merged on the
+    /// cpu, so that even components too large for the gpu passes to cover end up with a single label.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The luminance, from 0 to 1, pixels must be above to be part of a component. Single channel
+    ///   images use their value as it is.
+    pub async fn connected_components(mut self, threshold: f32) -> LabeledImage {
+        let (width, height) = self.dimensions();
+        let mut labels_pool = TexturePool::new(TextureFormat::R32Uint);
+        let mut labels = labels_pool.take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let mut spread = labels_pool.take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Threshold settings"),
+            contents: bytemuck::bytes_of(&ThresholdSettings {
+                threshold,
+                single_channel: u32::from(self.format == PixelFormat::Luma),
+            }),
+            usage: BufferUsages::UNIFORM,
+        });
+        let init = self.pipeline("components init", COMPONENTS_INIT_SHADER, Bindings::Derived);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Components init bind group"),
+            layout: &init.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &labels.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+        self.encode_components_pass("components init", &init, &bind_group);
+
+        let propagate = self.pipeline(
+            "components propagate",
+            COMPONENTS_PROPAGATE_SHADER,
+            Bindings::Derived,
+        );
+        for _ in 0..PROPAGATION_PASSES {
+            let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Components propagate bind group"),
+                layout: &propagate.get_bind_group_layout(0),
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(
+                            &labels.create_view(&TextureViewDescriptor::default()),
+                        ),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(
+                            &spread.create_view(&TextureViewDescriptor::default()),
+                        ),
+                    },
+                ],
+            });
+            self.encode_components_pass("components propagate", &propagate, &bind_group);
+            std::mem::swap(&mut labels, &mut spread);
+        }
+
+        let device = self.device;
+        let buffer =
+            encode_texture_to_buffer::<u32>(device, &mut self.encoder, width, height, &labels);
+        self.submit();
+        wait_for_mapping(device, &buffer).await;
+        let mut pixel_labels = Vec::new();
+        read_mapped_buffer_into(width, height, &buffer, &mut pixel_labels);
+
+        label_components(width, height, pixel_labels)
+    }
+
+    fn encode_components_pass(
+        &mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor { label: Some(name) });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+    }
+}
+
+/// Merges the labels that touch each other, which the gpu passes didn't get to, and numbers the components from 1,
+/// row by row.
+///
+/// # Arguments
+///
+/// * `labels` - 0 for the background, and for the other pixels the index plus one of a pixel of their component.
+fn label_components(width: u32, height: u32, mut labels: Vec<u32>) -> LabeledImage {
+    // A union find of the labels, each pointing to a smaller label of the same component, or to itself.
+    let mut parents = (0..=labels.len() as u32).collect::<Vec<_>>();
+
+    let (width_usize, height_usize) = (width as usize, height as usize);
+    for y in 0..height_usize {
+        for x in 0..width_usize {
+            let label = labels[y * width_usize + x];
+            if label == 0 {
+                continue;
+            }
+            let right = (x + 1 < width_usize).then(|| labels[y * width_usize + x + 1]);
+            let below = (y + 1 < height_usize).then(|| labels[(y + 1) * width_usize + x]);
+            for neighbor in [right, below].into_iter().flatten() {
+                if neighbor == 0 {
+                    continue;
+                }
+                let (a, b) = (root(&mut parents, label), root(&mut parents, neighbor));
+                parents[a.max(b) as usize] = a.min(b);
+            }
+        }
+    }
+
+    // The final labels of the roots, by root, numbered as they come.
+    let mut numbers = vec![0u32; parents.len()];
+    let mut components = Vec::<Component>::new();
+    for (index, label) in labels.iter_mut().enumerate() {
+        if *label == 0 {
+            continue;
+        }
+        let root = root(&mut parents, *label) as usize;
+        let (x, y) = ((index % width_usize) as u32, (index / width_usize) as u32);
+        if numbers[root] == 0 {
+            components.push(Component {
+                label: components.len() as u32 + 1,
+                x,
+                y,
+                width: 1,
+                height: 1,
+                area: 0,
+            });
+            numbers[root] = components.len() as u32;
+        }
+        *label = numbers[root];
+
+        let component = &mut components[*label as usize - 1];
+        let (left, right) = (
+            component.x.min(x),
+            (component.x + component.width).max(x + 1),
+        );
+        component.x = left;
+        component.width = right - left;
+        component.height = y + 1 - component.y;
+        component.area += 1;
+    }
+
+    LabeledImage {
+        width,
+        height,
+        labels,
+        components,
+    }
+}
+
+/// The smallest label of the component of `label`, shortening the path to it along the way.
+fn root(parents: &mut [u32], mut label: u32) -> u32 {
+    while parents[label as usize] != label {
+        let parent = parents[label as usize];
+        parents[label as usize] = parents[parent as usize];
+        label = parent;
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::{label_components, Component};
+    use crate::{Filters, Image, ImageLuma, Rgba};
+
+    /// Three white squares on black, the largest too wide for the gpu passes to label it on their own.
+    fn squares() -> Image {
+        Image::from_fn(60, 40, |x, y| {
+            let inside = (2..4).contains(&x) && (2..4).contains(&y)
+                || (10..13).contains(&x) && (5..8).contains(&y)
+                || (20..55).contains(&x) && (3..38).contains(&y);
+            if inside {
+                Rgba::new(255, 255, 255, 255)
+            } else {
+                Rgba::new(0, 0, 0, 255)
+            }
+        })
+    }
+
+    #[test]
+    fn three_squares() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let labeled = squares()
+            .operation(&filters)
+            .unwrap()
+            .connected_components(0.5)
+            .block_on();
+
+        let component = |label, x, y, width, height| Component {
+            label,
+            x,
+            y,
+            width,
+            height,
+            area: width * height,
+        };
+        assert_eq!(
+            vec![
+                component(1, 2, 2, 2, 2),
+                component(2, 20, 3, 35, 35),
+                component(3, 10, 5, 3, 3),
+            ],
+            labeled.components
+        );
+        assert_eq!(Some(0), labeled.label(0, 0));
+        assert_eq!(Some(1), labeled.label(3, 3));
+        assert_eq!(Some(2), labeled.label(54, 37));
+        assert_eq!(Some(3), labeled.label(11, 6));
+        assert_eq!(None, labeled.label(60, 0));
+    }
+
+    #[test]
+    fn luma_threshold() {
+        let luma = ImageLuma {
+            width: 5,
+            height: 1,
+            pixels: vec![200, 100, 200, 200, 0],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let labeled = luma
+            .operation(&filters)
+            .unwrap()
+            .connected_components(0.5)
+            .block_on();
+
+        assert_eq!(vec![1, 0, 2, 2, 0], labeled.labels);
+        assert_eq!(2, labeled.components[1].area);
+    }
+
+    #[test]
+    fn shapes_merged_on_cpu() {
+        // A U whose arms got different labels, as if the gpu passes had stopped early, and a diagonal neighbor
+        // which isn't connected.
+        let labels = vec![
+            1, 0, 3, 0, //
+            1, 0, 3, 0, //
+            1, 1, 1, 0, //
+            0, 0, 0, 16, //
+        ];
+
+        let labeled = label_components(4, 4, labels);
+
+        assert_eq!(
+            vec![
+                1, 0, 1, 0, //
+                1, 0, 1, 0, //
+                1, 1, 1, 0, //
+                0, 0, 0, 2, //
+            ],
+            labeled.labels
+        );
+        assert_eq!(
+            vec![
+                Component {
+                    label: 1,
+                    x: 0,
+                    y: 0,
+                    width: 3,
+                    height: 3,
+                    area: 7
+                },
+                Component {
+                    label: 2,
+                    x: 3,
+                    y: 3,
+                    width: 1,
+                    height: 1,
+                    area: 1
+                },
+            ],
+            labeled.components
+        );
+    }
+}
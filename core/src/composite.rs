@@ -0,0 +1,286 @@
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::{
+    util::BufferInitDescriptor, BindGroupDescriptor, BindGroupEntry, BindingResource,
+    ComputePassDescriptor, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, capitalize, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES,
+    texture_from_image, FiltersError, Image, Operation, Rgba,
+};
+
+const COMPOSITE_SHADER: &str = include_str!("shaders/composite.wgsl");
+const FLATTEN_SHADER: &str = include_str!("shaders/flatten.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct CompositeSettings {
+    position: [i32; 2],
+    opacity: f32,
+    _padding: u32,
+}
+
+impl<'a> Operation<'a> {
+    /// Draws `overlay` on top of the current image, using source-over alpha compositing.
+    ///
+    /// # Arguments
+    ///
+    /// * `overlay` - The image to draw on top, like a watermark.
+    /// * `position` - Where the top left corner of the overlay lands. It can be negative, or push the overlay
+    ///   partially out of the image: the parts of the overlay that fall outside are simply clipped.
+    /// * `opacity` - Multiplies the alpha of the overlay, clamped between 0.0 and 1.0.
+    pub fn composite(
+        mut self,
+        overlay: &Image,
+        position: (i32, i32),
+        opacity: f32,
+    ) -> Result<Self, FiltersError> {
+        let name = "composite";
+        let capitalized_filter_name = capitalize(name);
+
+        let (overlay_texture, _) = texture_from_image(self.device, self.queue, overlay)?;
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline(name, COMPOSITE_SHADER, Bindings::Derived);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Composite settings"),
+            contents: bytemuck::cast_slice(&[CompositeSettings {
+                position: [position.0, position.1],
+                opacity: opacity.clamp(0.0, 1.0),
+                _padding: 0,
+            }]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute constants"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &overlay_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+
+        Ok(self)
+    }
+
+    /// Draws the image over a solid `background`, leaving every pixel opaque, like before saving to a format
+    /// without alpha such as jpeg, where transparent pixels would otherwise turn black.
+    ///
+    /// # Arguments
+    ///
+    /// * `background` - The color showing through the transparent pixels. Its alpha is ignored.
+    pub fn flatten(self, background: Rgba) -> Self {
+        let name = "flatten";
+        let pipeline = self.pipeline(name, FLATTEN_SHADER, Bindings::Uniform);
+        let background = self.color_values(background);
+        self.settings_filter(name, &pipeline, bytemuck::cast_slice(&background))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    #[test]
+    fn composite_semi_transparent_patch() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 0, 0, 0]),
+                Rgba([0, 0, 0, 255]),
+            ],
+        };
+        let overlay = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 255, 255, 128]),
+                Rgba([0, 0, 255, 0]),
+                Rgba([255, 0, 0, 128]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 128, 128, 255]),
+                Rgba([255, 0, 0, 255]),
+                Rgba([255, 0, 0, 128]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image
+            .operation(&filters)
+            .unwrap()
+            .composite(&overlay, (0, 0), 1.0)
+            .unwrap();
+        let output = operation.execute().block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn composite_opacity() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([0, 0, 0, 255])],
+        };
+        let overlay = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([255, 255, 255, 255])],
+        };
+
+        let expected = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([51, 51, 51, 255])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image
+            .operation(&filters)
+            .unwrap()
+            .composite(&overlay, (0, 0), 0.2)
+            .unwrap();
+        let output = operation.execute().block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn composite_partially_clipped() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 255]); 6],
+        };
+        let overlay = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([10, 0, 0, 255]),
+                Rgba([20, 0, 0, 255]),
+                Rgba([30, 0, 0, 255]),
+                Rgba([40, 0, 0, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([40, 0, 0, 255]),
+                Rgba([0, 0, 0, 255]),
+                Rgba([0, 0, 0, 255]),
+                Rgba([0, 0, 0, 255]),
+                Rgba([0, 0, 0, 255]),
+                Rgba([10, 0, 0, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image
+            .operation(&filters)
+            .unwrap()
+            .composite(&overlay, (-1, -1), 1.0)
+            .unwrap()
+            .composite(&overlay, (2, 1), 1.0)
+            .unwrap();
+        let output = operation.execute().block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn flatten_over_background() {
+        let image = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([255, 0, 0, 128]),
+                Rgba([0, 0, 0, 0]),
+                Rgba([10, 20, 30, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .flatten(Rgba([255, 255, 255, 0]))
+            .execute()
+            .block_on();
+
+        let expected = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([255, 127, 127, 255]),
+                Rgba([255, 255, 255, 255]),
+                Rgba([10, 20, 30, 255]),
+            ],
+        };
+        assert!(expected.approx_eq(&output, 1), "{:?}", output.pixels);
+    }
+}
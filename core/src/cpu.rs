@@ -0,0 +1,391 @@
+//! Cpu implementations of some of the filters, following the shaders as closely as possible.
+//!
+//! They are much slower than the gpu, but give a reference to check the gpu results against, see
+//! [`assert_gpu_matches_cpu`] and [`assert_image_approx_eq`], and a fallback for machines without any compatible
+//! adapter.
+
+use pollster::FutureExt;
+
+use crate::{
+    blur::{box_filter_size, kernel_size_for_sigma},
+    FilterChain, FilterStep, Filters, FiltersError, Image, Kernel, Resize, Rgba,
+};
+
+/// Converts the image to grayscale, using the same luminance weights as [`crate::Operation::grayscale`].
+pub fn grayscale(image: &Image) -> Image {
+    map_pixels(image, |Rgba([r, g, b, a])| {
+        let gray = 0.299 * unorm(r) + 0.587 * unorm(g) + 0.114 * unorm(b);
+        let gray = to_unorm(gray);
+        Rgba([gray, gray, gray, a])
+    })
+}
+
+/// Inverts the red, green and blue channels, like [`crate::Operation::inverse`].
+pub fn inverse(image: &Image) -> Image {
+    map_pixels(image, |Rgba([r, g, b, a])| {
+        Rgba([255 - r, 255 - g, 255 - b, a])
+    })
+}
+
+/// Mirrors the image horizontally, like [`crate::Operation::hflip`].
+pub fn hflip(image: &Image) -> Image {
+    Image {
+        width: image.width,
+        height: image.height,
+        pixels: image
+            .pixels
+            .chunks(image.width as usize)
+            .flat_map(|row| row.iter().rev().copied())
+            .collect(),
+    }
+}
+
+/// Mirrors the image vertically, like [`crate::Operation::vflip`].
+pub fn vflip(image: &Image) -> Image {
+    Image {
+        width: image.width,
+        height: image.height,
+        pixels: image
+            .pixels
+            .rchunks(image.width as usize)
+            .flatten()
+            .copied()
+            .collect(),
+    }
+}
+
+/// Resizes the image with nearest neighbor sampling, like [`crate::Operation::resize`] with [`Resize::Nearest`].
+///
+/// # Errors
+///
+/// [`FiltersError::UnsupportedSize`] if the new width or height is 0.
+pub fn resize_nearest(image: &Image, new_dimension: (u32, u32)) -> Result<Image, FiltersError> {
+    let (width, height) = new_dimension;
+    if width == 0 || height == 0 {
+        return Err(FiltersError::UnsupportedSize { width, height });
+    }
+
+    // Same as the shader, which samples at the top left corner of each output pixel.
+    let source = |position: u32, size: u32, source_size: u32| {
+        ((position as u64 * source_size as u64 / size as u64) as u32).min(source_size - 1)
+    };
+    let pixels = (0..height)
+        .flat_map(|y| {
+            let source_y = source(y, height, image.height);
+            (0..width).map(move |x| {
+                let source_x = source(x, width, image.width);
+                image.pixels[(source_y * image.width + source_x) as usize]
+            })
+        })
+        .collect();
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// Blurs the image by averaging `filter_size` pixels, vertically then horizontally, like
+/// [`crate::Operation::box_blur`], which also documents how `filter_size` is rounded. Pixels outside of the image
+/// count as transparent black.
+pub fn box_blur(image: &Image, filter_size: u32) -> Image {
+    let filter_size = box_filter_size(filter_size, (image.width, image.height));
+    separable_filter(image, &vec![1.0 / filter_size as f32; filter_size as usize])
+}
+
+/// Blurs the image with a gaussian kernel, vertically then horizontally, like [`crate::Operation::gaussian_blur`].
+/// Pixels outside of the image count as transparent black.
+pub fn gaussian_blur(image: &Image, sigma: f32) -> Image {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return map_pixels(image, |pixel| pixel);
+    }
+    let kernel_size = kernel_size_for_sigma(sigma, (image.width, image.height));
+    separable_filter(
+        image,
+        Kernel::gaussian_of_size(sigma, kernel_size as usize).values(),
+    )
+}
+
+/// Applies the centered `weights` vertically, then horizontally, rounding to 8 bits between the passes like the
+/// intermediate texture of the gpu does.
+fn separable_filter(image: &Image, weights: &[f32]) -> Image {
+    let (width, height) = (image.width as i64, image.height as i64);
+    let radius = (weights.len().saturating_sub(1) / 2) as i64;
+    let pass = |input: &[Rgba], vertical: bool| -> Vec<Rgba> {
+        let mut output = Vec::with_capacity(input.len());
+        for y in 0..height {
+            for x in 0..width {
+                let mut color = [0.0f32; 4];
+                for (offset, weight) in (-radius..=radius).zip(weights) {
+                    let (sample_x, sample_y) = if vertical {
+                        (x, y + offset)
+                    } else {
+                        (x + offset, y)
+                    };
+                    if sample_x < 0 || sample_y < 0 || sample_x >= width || sample_y >= height {
+                        continue;
+                    }
+                    let Rgba(sample) = input[(sample_y * width + sample_x) as usize];
+                    for (channel, value) in color.iter_mut().zip(sample) {
+                        *channel += weight * unorm(value);
+                    }
+                }
+                output.push(Rgba(color.map(to_unorm)));
+            }
+        }
+        output
+    };
+
+    let vertical = pass(&image.pixels, true);
+    Image {
+        width: image.width,
+        height: image.height,
+        pixels: pass(&vertical, false),
+    }
+}
+
+/// Applies the steps of `chain` to `image` on the cpu.
+///
+/// # Errors
+///
+/// [`FiltersError::NoCpuImplementation`] for the first step without a cpu implementation, or the error of a step
+/// that fails, like a resize to a zero width.
+pub fn apply(chain: &FilterChain, image: &Image) -> Result<Image, FiltersError> {
+    let mut output = map_pixels(image, |pixel| pixel);
+    for step in &chain.steps {
+        output = match *step {
+            FilterStep::Grayscale => grayscale(&output),
+            FilterStep::Inverse => inverse(&output),
+            FilterStep::HFlip => hflip(&output),
+            FilterStep::VFlip => vflip(&output),
+            FilterStep::Resize {
+                width,
+                height,
+                mode: Resize::Nearest,
+            } => resize_nearest(&output, (width, height))?,
+            FilterStep::BoxBlur { size } => box_blur(&output, size),
+            FilterStep::GaussianBlur { sigma } => gaussian_blur(&output, sigma),
+            _ => return Err(FiltersError::NoCpuImplementation(step.name())),
+        };
+    }
+    Ok(output)
+}
+
+/// Applies `chain` to `image` both on the gpu and on the cpu, and panics if the results differ by more than
+/// `tolerance` on any channel of any pixel.
+///
+/// # Panics
+///
+/// If the results have different dimensions, if a channel differs by more than `tolerance`, or if the chain fails.
+pub fn assert_gpu_matches_cpu(
+    filters: &Filters,
+    image: &Image,
+    chain: &FilterChain,
+    tolerance: u8,
+) {
+    let expected = apply(chain, image).unwrap();
+    let output = chain
+        .apply(image.operation(filters).unwrap())
+        .unwrap()
+        .execute()
+        .block_on();
+
+    if let Some(differences) = describe_differences(&expected, &output, tolerance) {
+        panic!("The gpu and the cpu differ for {chain}: {differences}");
+    }
+}
+
+/// Panics if `actual` doesn't have the dimensions of `expected`, or if a channel of one of its pixels differs by
+/// more than `tolerance`, which absorbs the rounding of the different gpus. The message sums up the differences
+/// rather than listing every pixel: how many pixels differ, by how much, and where the first ones are.
+///
+/// # Panics
+///
+/// If the images differ beyond `tolerance`.
+pub fn assert_image_approx_eq(expected: &Image, actual: &Image, tolerance: u8) {
+    if let Some(differences) = describe_differences(expected, actual, tolerance) {
+        panic!("{differences}");
+    }
+}
+
+/// How many pixels of the differences [`assert_image_approx_eq`] lists.
+const LISTED_DIFFERENCES: usize = 5;
+
+/// Sums up how `actual` differs from `expected` beyond `tolerance`, or `None` if it doesn't.
+fn describe_differences(expected: &Image, actual: &Image, tolerance: u8) -> Option<String> {
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Some(format!(
+            "Expected an image of {}x{}, got {}x{}",
+            expected.width, expected.height, actual.width, actual.height
+        ));
+    }
+
+    let deltas = expected
+        .pixels
+        .iter()
+        .zip(&actual.pixels)
+        .map(|(a, b)| {
+            a.0.iter()
+                .zip(b.0)
+                .map(|(a, b)| a.abs_diff(b))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let differing = deltas
+        .iter()
+        .enumerate()
+        .filter(|(_, &delta)| delta > tolerance)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if differing.is_empty() {
+        return None;
+    }
+
+    let max = deltas.iter().copied().max().unwrap_or(0);
+    let mean = deltas.iter().map(|&delta| f64::from(delta)).sum::<f64>() / deltas.len() as f64;
+    let mut summary = format!(
+        "{} of {} pixels differ by more than {tolerance}, by up to {max}, {mean:.3} on average",
+        differing.len(),
+        deltas.len(),
+    );
+    for &index in differing.iter().take(LISTED_DIFFERENCES) {
+        summary.push_str(&format!(
+            "\n  at {},{}: expected {:?}, got {:?}",
+            index as u32 % expected.width,
+            index as u32 / expected.width,
+            expected.pixels[index].0,
+            actual.pixels[index].0
+        ));
+    }
+    if differing.len() > LISTED_DIFFERENCES {
+        summary.push_str(&format!(
+            "\n  and {} more",
+            differing.len() - LISTED_DIFFERENCES
+        ));
+    }
+    Some(summary)
+}
+
+fn map_pixels(image: &Image, f: impl Fn(Rgba) -> Rgba) -> Image {
+    Image {
+        width: image.width,
+        height: image.height,
+        pixels: image.pixels.iter().copied().map(f).collect(),
+    }
+}
+
+fn unorm(value: u8) -> f32 {
+    value as f32 / 255.0
+}
+
+fn to_unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image() -> Image {
+        Image {
+            width: 7,
+            height: 5,
+            pixels: (0..35u32)
+                .map(|index| {
+                    let value = index * 37 % 256;
+                    Rgba([value as u8, (255 - value) as u8, (index * 7) as u8, 200])
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn grayscale_inverse_of_transparent_black() {
+        // Same as the grayscale test of the gpu.
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 0]); 4],
+        };
+
+        let expected = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([255, 255, 255, 0]); 4],
+        };
+
+        assert_eq!(expected, grayscale(&inverse(&image)));
+    }
+
+    #[test]
+    fn hflip_matches_gpu_test() {
+        let image = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([0, 0, 255, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([255, 0, 0, 255]),
+            ],
+        };
+
+        assert_eq!(expected, hflip(&image));
+    }
+
+    #[test]
+    fn resize_nearest_zero_size() {
+        let result = resize_nearest(&test_image(), (0, 2));
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn apply_without_cpu_implementation() {
+        let chain = FilterChain::new(vec![FilterStep::Sharpen { amount: 1.0 }]);
+
+        let result = apply(&chain, &test_image());
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::NoCpuImplementation("sharpen"))
+        ));
+    }
+
+    #[test]
+    fn gpu_matches_cpu() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        for chain in [
+            "grayscale",
+            "inverse",
+            "hflip",
+            "vflip",
+            "resize(3,2,nearest)",
+            "resize(16,11,nearest)",
+            "boxblur(3)",
+            "boxblur(4)",
+            "grayscale|vflip|boxblur(5)|inverse|resize(4,4,nearest)",
+        ] {
+            assert_gpu_matches_cpu(&filters, &image, &chain.parse().unwrap(), 1);
+        }
+    }
+}
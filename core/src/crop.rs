@@ -0,0 +1,120 @@
+use wgpu::{Extent3d, ImageCopyTexture, Origin3d, TextureAspect};
+
+use crate::{pool::COPY_TEXTURE_USAGES, FiltersError, Operation};
+
+impl<'a> Operation<'a> {
+    /// Keeps only a rectangular region of the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The top left corner of the region to keep.
+    /// * `dimension` - The width and height of the region to keep, which must fit in the image.
+    pub fn crop(mut self, origin: (u32, u32), dimension: (u32, u32)) -> Result<Self, FiltersError> {
+        if dimension.0 == 0 || dimension.1 == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: dimension.0,
+                height: dimension.1,
+            });
+        }
+        if origin.0.saturating_add(dimension.0) > self.texture_size.width
+            || origin.1.saturating_add(dimension.1) > self.texture_size.height
+        {
+            return Err(FiltersError::CropOutOfBounds {
+                origin,
+                dimension,
+                image: self.dimensions(),
+            });
+        }
+
+        let texture_size = Extent3d {
+            width: dimension.0,
+            height: dimension.1,
+            depth_or_array_layers: 1,
+        };
+
+        let output_texture = self
+            .pool
+            .take(self.device, texture_size, COPY_TEXTURE_USAGES);
+
+        self.encoder.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: origin.0,
+                    y: origin.1,
+                    z: 0,
+                },
+                aspect: TextureAspect::All,
+            },
+            output_texture.as_image_copy(),
+            texture_size,
+        );
+
+        self.set_texture(output_texture, texture_size, COPY_TEXTURE_USAGES);
+        self.tileable = false;
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    #[test]
+    fn crop_region() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([1, 0, 0, 255]),
+                Rgba([2, 0, 0, 255]),
+                Rgba([3, 0, 0, 255]),
+                Rgba([4, 0, 0, 255]),
+                Rgba([5, 0, 0, 255]),
+                Rgba([6, 0, 0, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([5, 0, 0, 255]), Rgba([6, 0, 0, 255])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .crop((1, 1), (2, 1))
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn crop_out_of_bounds() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 255]); 4],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters).unwrap().crop((1, 1), (2, 2));
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::CropOutOfBounds {
+                origin: (1, 1),
+                dimension: (2, 2),
+                image: (2, 2)
+            })
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+use naga::{
+    front::wgsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+    ShaderStage,
+};
+
+use wgpu::{AddressMode, Extent3d, FilterMode, SamplerDescriptor};
+
+use crate::{check_texture_size, FiltersError, Operation};
+
+impl<'a> Operation<'a> {
+    /// Applies a filter written in WGSL, for the effects this crate doesn't provide.
+    ///
+    /// The shader follows the same conventions as the built-in filters:
+    /// * The entry point is a compute shader named `main`, with a workgroup size written `@workgroup_size(16, 16)`,
+    ///   dispatched once per pixel, rounded up, so it must skip the invocations outside of the image. The size is
+    ///   swapped for the one of the filters, see [`WorkgroupConfig`](crate::WorkgroupConfig).
+    /// * Group 0, binding 0 is a uniform buffer filled with `uniforms`, only needed if they aren't empty.
+    /// * Group 1, binding 0 is the input, a `texture_2d<f32>`, and binding 1 the output, a
+    ///   `texture_storage_2d<rgba8unorm, write>`. The output format is swapped for the one of the operation,
+    ///   see [`PixelFormat`](crate::PixelFormat), so the shader works with 16-bit or float images too.
+    ///
+    /// The shader is compiled again each time this is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `shader_source` - The WGSL source of the shader.
+    /// * `uniforms` - The raw bytes of the uniform buffer, laid out as the shader expects, or nothing.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidShader`] if the shader doesn't compile, or doesn't have a `main` compute entry point
+    /// with a workgroup size written `@workgroup_size(16, 16)`.
+    pub fn custom(self, shader_source: &str, uniforms: &[u8]) -> Result<Self, FiltersError> {
+        validate_shader(shader_source)?;
+
+        let pipeline =
+            self.pipelines
+                .build(self.device, "custom", shader_source, self.format, None);
+
+        Ok(self.settings_filter("custom", &pipeline, uniforms))
+    }
+
+    /// Like [`Operation::custom`], but for shaders producing an image of another size, like a downsample.
+    ///
+    /// The shader is dispatched once per pixel of the output, and can read the input through a clamp to edge,
+    /// linear sampler at group 0, binding 1, bound when `with_sampler` is true, like the shader of
+    /// [`Operation::resize`] does. The filters following it work on the new size.
+    ///
+    /// # Arguments
+    ///
+    /// * `shader_source` - The WGSL source of the shader.
+    /// * `output_size` - The width and height of the image the shader produces.
+    /// * `uniforms` - The raw bytes of the uniform buffer, laid out as the shader expects, or nothing.
+    /// * `with_sampler` - Whether to bind a sampler at group 0, binding 1.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidShader`] if the shader doesn't compile, or doesn't have a `main` compute entry point
+    /// with a workgroup size written `@workgroup_size(16, 16)`. [`FiltersError::UnsupportedSize`] if the output size
+    /// is zero, and [`FiltersError::ImageTooLarge`] if it is too large for the gpu.
+    pub fn custom_resized(
+        mut self,
+        shader_source: &str,
+        output_size: (u32, u32),
+        uniforms: &[u8],
+        with_sampler: bool,
+    ) -> Result<Self, FiltersError> {
+        if output_size.0 == 0 || output_size.1 == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: output_size.0,
+                height: output_size.1,
+            });
+        }
+        check_texture_size(self.device, output_size)?;
+        validate_shader(shader_source)?;
+
+        let pipeline =
+            self.pipelines
+                .build(self.device, "custom", shader_source, self.format, None);
+        let sampler = with_sampler.then(|| {
+            self.device.create_sampler(&SamplerDescriptor {
+                label: Some("Custom sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                mipmap_filter: FilterMode::Linear,
+                ..Default::default()
+            })
+        });
+
+        self.tileable = false;
+        Ok(self.settings_pass(
+            "custom",
+            &pipeline,
+            uniforms,
+            sampler.as_ref(),
+            Extent3d {
+                width: output_size.0,
+                height: output_size.1,
+                depth_or_array_layers: 1,
+            },
+        ))
+    }
+}
+
+/// Compiles `shader_source` with naga, like wgpu does, but returns its errors instead of panicking.
+fn validate_shader(shader_source: &str) -> Result<(), FiltersError> {
+    let module = wgsl::parse_str(shader_source)
+        .map_err(|error| FiltersError::InvalidShader(error.emit_to_string(shader_source)))?;
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|error| FiltersError::InvalidShader(error.emit_to_string(shader_source)))?;
+
+    let Some(entry_point) = module.entry_points.iter().find(|entry_point| {
+        entry_point.name == "main" && entry_point.stage == ShaderStage::Compute
+    }) else {
+        return Err(FiltersError::InvalidShader(
+            "missing compute entry point `main`".to_string(),
+        ));
+    };
+
+    // The shader is dispatched in workgroups of the size of the filters, which only replaces this exact spelling.
+    if entry_point.workgroup_size != [16, 16, 1]
+        || !shader_source.contains("@workgroup_size(16, 16)")
+    {
+        return Err(FiltersError::InvalidShader(format!(
+            "the workgroup size of `main` must be written @workgroup_size(16, 16), got {:?}",
+            entry_point.workgroup_size
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Resize, Rgba};
+
+    const CUSTOM_INVERSE_SHADER: &str = "
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, vec2<i32>(global_id.xy), 0);
+    textureStore(output_texture, vec2<i32>(global_id.xy), vec4<f32>(1.0 - color.rgb, color.a));
+}
+";
+
+    fn test_image() -> Image {
+        Image {
+            width: 17,
+            height: 3,
+            pixels: (0..17 * 3)
+                .map(|index| Rgba([index as u8 * 5, 255 - index as u8, 128, 200]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn custom_inverse_matches_inverse() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .custom(CUSTOM_INVERSE_SHADER, &[])
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn custom_with_uniforms_matches_brightness() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .brightness(0.2)
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .custom(
+                include_str!("shaders/brightness.wgsl"),
+                bytemuck::cast_slice(&[0.2f32]),
+            )
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn custom_invalid_shader() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .custom("fn main() { let x = ; }", &[]);
+
+        assert!(matches!(result, Err(FiltersError::InvalidShader(_))));
+    }
+
+    #[test]
+    fn custom_other_workgroup_size() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        for workgroup_size in ["(8, 8)", "(16,16)", "(16, 16, 2)", "(256)"] {
+            let shader_source = CUSTOM_INVERSE_SHADER.replace("(16, 16)", workgroup_size);
+            let result = image
+                .operation(&filters)
+                .unwrap()
+                .custom(&shader_source, &[]);
+
+            assert!(
+                matches!(result, Err(FiltersError::InvalidShader(_))),
+                "{workgroup_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn custom_missing_entry_point() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .custom(&CUSTOM_INVERSE_SHADER.replace("fn main(", "fn apply("), &[]);
+
+        assert!(matches!(result, Err(FiltersError::InvalidShader(_))));
+    }
+
+    const CUSTOM_DOWNSAMPLE_SHADER: &str = "
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, vec2<i32>(global_id.xy) * 2, 0);
+    textureStore(output_texture, vec2<i32>(global_id.xy), color);
+}
+";
+
+    const CUSTOM_SAMPLED_DOWNSAMPLE_SHADER: &str = "
+@group(0) @binding(1) var samp : sampler;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let tex_coords = vec2<f32>(global_id.xy) / vec2<f32>(dimensions);
+    let color = textureSampleLevel(input_texture, samp, tex_coords, 0.0);
+    textureStore(output_texture, vec2<i32>(global_id.xy), color);
+}
+";
+
+    #[test]
+    fn custom_resized_downsample_matches_nearest_resize() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .resize((8, 1), Resize::Nearest)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        let operation = image
+            .operation(&filters)
+            .unwrap()
+            .custom_resized(CUSTOM_DOWNSAMPLE_SHADER, (8, 1), &[], false)
+            .unwrap();
+        assert_eq!((8, 1), operation.dimensions());
+        let output = operation.inverse().execute().block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn custom_resized_with_sampler_matches_linear_resize() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .resize((8, 1), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .custom_resized(CUSTOM_SAMPLED_DOWNSAMPLE_SHADER, (8, 1), &[], true)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn custom_resized_zero_size() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters).unwrap().custom_resized(
+            CUSTOM_DOWNSAMPLE_SHADER,
+            (0, 1),
+            &[],
+            false,
+        );
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 1
+            })
+        ));
+    }
+}
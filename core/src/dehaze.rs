@@ -0,0 +1,328 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages,
+    ComputePassDescriptor, ComputePipeline, Texture, TextureFormat, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    compute_work_group_count, encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    read_mapped_buffer_into, wait_for_mapping, Operation, PixelFormat,
+};
+
+const DEHAZE_DARK_SHADER: &str = include_str!("shaders/dehaze_dark.wgsl");
+const DEHAZE_ESTIMATE_SHADER: &str = include_str!("shaders/dehaze_estimate.wgsl");
+const DEHAZE_SHADER: &str = include_str!("shaders/dehaze.wgsl");
+
+/// The radius of the window of the dark channel, of 15×15 pixels like in the paper of the dark channel prior.
+const WINDOW_RADIUS: i32 = 7;
+/// How much of the haze a strength of 1.0 removes, a little less than all of it so that distant objects still look
+/// distant.
+const HAZE_REMOVED: f32 = 0.95;
+/// The share of the haziest pixels, those of the brightest dark channel, the atmospheric light is averaged from.
+const ATMOSPHERE_SHARE: usize = 1000;
+/// The least atmospheric light of a channel, which the dark channel is divided by.
+const MIN_ATMOSPHERE: f32 = 0.05;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct DehazeSettings {
+    atmosphere: [f32; 4],
+    radius: i32,
+    strength: f32,
+    single_channel: u32,
+    _padding: u32,
+}
+
+impl<'a> Operation<'a> {
+    /// Removes the haze of the image, like of a landscape in the fog, with the dark channel prior: in a haze-free
+    /// image, most windows have a pixel with a channel close to black, so how far the darkest channel of a window is
+    /// from black tells how thick the haze is there.
+    ///
+    /// The dark channel is computed on the gpu, and read back along with the colors to pick the atmospheric light,
+    /// the color of the haze, as the average of the haziest pixels. The dark channel of the image relative to that
+    /// light then gives the transmission of each pixel, and the haze is subtracted.
+    ///
+    /// The passes recorded so far are submitted, and the operation can be continued afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `strength` - How much of the haze is removed, from 0.0, leaving the image untouched, to 1.0.
+    pub async fn dehaze(mut self, strength: f32) -> Self {
+        if strength.is_nan() || strength <= 0.0 {
+            return self;
+        }
+
+        let mut settings = DehazeSettings {
+            atmosphere: [1.0; 4],
+            radius: WINDOW_RADIUS,
+            strength: strength.min(1.0) * HAZE_REMOVED,
+            single_channel: u32::from(self.format == PixelFormat::Luma),
+            _padding: 0,
+        };
+        settings.atmosphere = self.atmospheric_light(&settings).await;
+
+        let dark = self.dark_channel_rows(&settings);
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let pipeline = self.pipeline("dehaze", DEHAZE_SHADER, Bindings::Derived);
+        let bind_group = self.dehaze_bind_group(
+            &pipeline,
+            [&self.texture, &dark, &output_texture],
+            &settings,
+        );
+        self.encode_dehaze_pass("dehaze", &pipeline, &bind_group);
+
+        self.pool
+            .release(self.texture_size, STORAGE_TEXTURE_USAGES, dark);
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.radius += WINDOW_RADIUS as u32;
+        self.tileable = false;
+        self
+    }
+
+    /// Computes the dark channel of the image and reads it back, and averages the colors of its brightest pixels.
+    async fn atmospheric_light(&mut self, settings: &DehazeSettings) -> [f32; 4] {
+        let (width, height) = self.dimensions();
+        let dark = self.dark_channel_rows(settings);
+        let estimate = TexturePool::new(TextureFormat::Rgba32Float).take(
+            self.device,
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+        );
+        let pipeline = self.pipeline("dehaze estimate", DEHAZE_ESTIMATE_SHADER, Bindings::Derived);
+        let bind_group =
+            self.dehaze_bind_group(&pipeline, [&self.texture, &dark, &estimate], settings);
+        self.encode_dehaze_pass("dehaze estimate", &pipeline, &bind_group);
+        self.pool
+            .release(self.texture_size, STORAGE_TEXTURE_USAGES, dark);
+
+        let buffer = encode_texture_to_buffer::<[f32; 4]>(
+            self.device,
+            &mut self.encoder,
+            width,
+            height,
+            &estimate,
+        );
+        self.submit_recorded();
+        wait_for_mapping(self.device, &buffer).await;
+        let mut values = Vec::new();
+        read_mapped_buffer_into(width, height, &buffer, &mut values);
+
+        average_of_haziest(values)
+    }
+
+    /// Records the first pass of the dark channel, over the rows of the windows, relative to the atmospheric light
+    /// of `settings`.
+    fn dark_channel_rows(&mut self, settings: &DehazeSettings) -> Texture {
+        let dark = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let pipeline = self.pipeline("dehaze dark", DEHAZE_DARK_SHADER, Bindings::Derived);
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Dehaze settings"),
+            contents: bytemuck::bytes_of(settings),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Dehaze dark bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &dark.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+        self.encode_dehaze_pass("dehaze dark", &pipeline, &bind_group);
+        dark
+    }
+
+    /// The bind group of the dehaze shaders reading the image and the rows of the dark channel, which write the
+    /// last of the `textures`.
+    fn dehaze_bind_group(
+        &self,
+        pipeline: &ComputePipeline,
+        textures: [&Texture; 3],
+        settings: &DehazeSettings,
+    ) -> BindGroup {
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Dehaze settings"),
+            contents: bytemuck::bytes_of(settings),
+            usage: BufferUsages::UNIFORM,
+        });
+        let views = textures.map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Dehaze bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&views[0]),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&views[1]),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&views[2]),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn encode_dehaze_pass(
+        &mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        bind_group: &BindGroup,
+    ) {
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor { label: Some(name) });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+    }
+}
+
+/// The average color of the pixels of the brightest dark channel, from their dark channel and color, as written by
+/// the estimate shader.
+fn average_of_haziest(mut values: Vec<[f32; 4]>) -> [f32; 4] {
+    let count = (values.len() / ATMOSPHERE_SHARE).max(1);
+    values.sort_unstable_by(|a, b| b[0].total_cmp(&a[0]));
+    let mut sum = [0.0; 3];
+    for [_, r, g, b] in &values[..count] {
+        sum[0] += r;
+        sum[1] += g;
+        sum[2] += b;
+    }
+    let [r, g, b] = sum.map(|channel| (channel / count as f32).max(MIN_ATMOSPHERE));
+    [r, g, b, 1.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::average_of_haziest;
+    use crate::{Filters, Image, Rgba};
+
+    const HAZE: f32 = 200.0;
+
+    /// Saturated blocks of color under a white sky, and the same blended 60% towards a light gray haze.
+    fn scene() -> (Image, Image) {
+        let colors = [
+            Rgba::new(200, 30, 0, 255),
+            Rgba::new(0, 140, 60, 255),
+            Rgba::new(20, 40, 180, 255),
+            Rgba::new(30, 20, 10, 255),
+        ];
+        let clear = Image::from_fn(64, 64, |x, y| {
+            if y < 16 {
+                Rgba::new(255, 255, 255, 255)
+            } else {
+                colors[((x / 8 + y / 8) % 4) as usize]
+            }
+        });
+        let hazy = Image::from_fn(64, 64, |x, y| {
+            let Rgba([r, g, b, a]) = clear.pixels[(y * 64 + x) as usize];
+            let haze = |channel: u8| (0.4 * f32::from(channel) + 0.6 * HAZE).round() as u8;
+            Rgba::new(haze(r), haze(g), haze(b), a)
+        });
+        (clear, hazy)
+    }
+
+    /// The standard deviation of the luminance of the blocks, below the sky.
+    fn standard_deviation(image: &Image) -> f32 {
+        let luminances = image.pixels[16 * 64..]
+            .iter()
+            .map(|pixel| {
+                0.299 * f32::from(pixel.r())
+                    + 0.587 * f32::from(pixel.g())
+                    + 0.114 * f32::from(pixel.b())
+            })
+            .collect::<Vec<_>>();
+        let mean = luminances.iter().sum::<f32>() / luminances.len() as f32;
+        let variance = luminances
+            .iter()
+            .map(|luminance| (luminance - mean).powi(2))
+            .sum::<f32>()
+            / luminances.len() as f32;
+        variance.sqrt()
+    }
+
+    #[test]
+    fn restores_contrast() {
+        let (clear, hazy) = scene();
+        let filters = Filters::new().block_on().unwrap();
+
+        let dehazed = hazy
+            .operation(&filters)
+            .unwrap()
+            .dehaze(1.0)
+            .block_on()
+            .execute()
+            .block_on();
+
+        let (clear, hazy, dehazed) = (
+            standard_deviation(&clear),
+            standard_deviation(&hazy),
+            standard_deviation(&dehazed),
+        );
+        assert!(
+            dehazed > hazy * 1.8,
+            "{dehazed} from {hazy}, {clear} without haze"
+        );
+    }
+
+    #[test]
+    fn no_strength_is_identity() {
+        let (_, hazy) = scene();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = hazy
+            .operation(&filters)
+            .unwrap()
+            .dehaze(0.0)
+            .block_on()
+            .execute()
+            .block_on();
+
+        assert_eq!(hazy, output);
+    }
+
+    #[test]
+    fn atmosphere_from_the_haziest_pixels() {
+        // A pixel in 1000 is averaged, the two of the brightest dark channel out of 2001.
+        let mut values = vec![[0.1, 0.0, 0.0, 0.0]; 1999];
+        values.extend([[0.9, 0.75, 0.5, 0.25], [0.8, 0.25, 0.25, 0.0]]);
+
+        assert_eq!([0.5, 0.375, 0.125, 1.0], average_of_haziest(values));
+    }
+}
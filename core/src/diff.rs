@@ -0,0 +1,359 @@
+use crate::{FiltersError, Image, Rgba};
+
+/// The radius of the window SSIM compares, 11 pixels wide.
+const SSIM_RADIUS: usize = 5;
+/// The standard deviation of the gaussian weighting the window.
+const SSIM_SIGMA: f64 = 1.5;
+/// Keep the ratios stable where the means, or the variances, are close to 0.
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// The per channel differences between two images of the same dimensions, see [`Image::diff`].
+///
+/// Gpus round differently, so tests comparing filter results are better off allowing a small difference with
+/// [`Image::approx_eq`], or checking one of these metrics, than comparing images exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageDiff {
+    width: u32,
+    height: u32,
+    /// The absolute difference of each channel of each pixel, alpha included.
+    deltas: Vec<[u8; 4]>,
+}
+
+impl ImageDiff {
+    /// The largest difference of any channel of any pixel, 0 for identical images.
+    pub fn max_channel_delta(&self) -> u8 {
+        self.deltas.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// The average difference of the channels, between 0 and 255.
+    pub fn mean_absolute_error(&self) -> f64 {
+        let count = self.deltas.len() * 4;
+        if count == 0 {
+            return 0.0;
+        }
+        let sum: u64 = self
+            .deltas
+            .iter()
+            .flatten()
+            .map(|&delta| delta as u64)
+            .sum();
+        sum as f64 / count as f64
+    }
+
+    /// The peak signal to noise ratio, in decibels: the higher, the closer the images. Infinite for identical ones.
+    pub fn psnr(&self) -> f64 {
+        let count = self.deltas.len() * 4;
+        let squares: u64 = self
+            .deltas
+            .iter()
+            .flatten()
+            .map(|&delta| delta as u64 * delta as u64)
+            .sum();
+        if squares == 0 {
+            return f64::INFINITY;
+        }
+        let mean_square_error = squares as f64 / count as f64;
+        10.0 * (255.0 * 255.0 / mean_square_error).log10()
+    }
+
+    /// Renders the largest channel difference of each pixel in red, stretched so that the largest difference of the
+    /// image is the brightest. Identical pixels are black.
+    pub fn to_heatmap(&self) -> Image {
+        let max = self.max_channel_delta().max(1) as u32;
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .deltas
+                .iter()
+                .map(|delta| {
+                    let value = delta.iter().copied().max().unwrap_or(0) as u32 * 255 / max;
+                    Rgba([value as u8, 0, 0, 255])
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Image {
+    /// Compares the image to `other`, channel by channel.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::MismatchedDimensions`] if the images don't have the same dimensions, and
+    /// [`FiltersError::InvalidImageDimensions`] if either of them doesn't have as many pixels as its dimensions say.
+    pub fn diff(&self, other: &Image) -> Result<ImageDiff, FiltersError> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(FiltersError::MismatchedDimensions {
+                expected: (self.width, self.height),
+                actual: (other.width, other.height),
+            });
+        }
+        self.check_pixel_count()?;
+        other.check_pixel_count()?;
+
+        let deltas = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .map(|(Rgba(a), Rgba(b))| [0, 1, 2, 3].map(|channel| a[channel].abs_diff(b[channel])))
+            .collect();
+
+        Ok(ImageDiff {
+            width: self.width,
+            height: self.height,
+            deltas,
+        })
+    }
+
+    /// The structural similarity of the image and `other`, comparing the mean, contrast and structure of their
+    /// luminance around each pixel, with an 11×11 gaussian window of sigma 1.5. The alpha channel is ignored.
+    ///
+    /// Identical images score 1.0, lower values being less similar: a mild blur scores around 0.7 to 0.9 on a
+    /// detailed image, a strong one below 0.3, while unrelated images get close to 0. Near the edges, the window only covers the pixels
+    /// within the image.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`Image::diff`].
+    pub fn ssim(&self, other: &Image) -> Result<f64, FiltersError> {
+        // Checks the dimensions.
+        self.diff(other)?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width == 0 || height == 0 {
+            return Ok(1.0);
+        }
+
+        let x = luminance(self);
+        let y = luminance(other);
+        let product =
+            |a: &[f64], b: &[f64]| -> Vec<f64> { a.iter().zip(b).map(|(a, b)| a * b).collect() };
+        let window = ssim_window();
+        let mean_x = gaussian_mean(&x, width, height, &window);
+        let mean_y = gaussian_mean(&y, width, height, &window);
+        let mean_xx = gaussian_mean(&product(&x, &x), width, height, &window);
+        let mean_yy = gaussian_mean(&product(&y, &y), width, height, &window);
+        let mean_xy = gaussian_mean(&product(&x, &y), width, height, &window);
+
+        let sum: f64 = (0..width * height)
+            .map(|index| {
+                let (mean_x, mean_y) = (mean_x[index], mean_y[index]);
+                let variance_x = mean_xx[index] - mean_x * mean_x;
+                let variance_y = mean_yy[index] - mean_y * mean_y;
+                let covariance = mean_xy[index] - mean_x * mean_y;
+                ((2.0 * mean_x * mean_y + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                    / ((mean_x * mean_x + mean_y * mean_y + SSIM_C1)
+                        * (variance_x + variance_y + SSIM_C2))
+            })
+            .sum();
+        Ok(sum / (width * height) as f64)
+    }
+
+    /// Whether `other` has the same dimensions, with no channel differing by more than `tolerance`.
+    pub fn approx_eq(&self, other: &Image, tolerance: u8) -> bool {
+        self.diff(other)
+            .map(|diff| diff.max_channel_delta() <= tolerance)
+            .unwrap_or(false)
+    }
+}
+
+/// The luminance of each pixel, between 0 and 255, weighted like [`crate::Operation::grayscale`].
+fn luminance(image: &Image) -> Vec<f64> {
+    image
+        .pixels
+        .iter()
+        .map(|Rgba([r, g, b, _])| 0.299 * *r as f64 + 0.587 * *g as f64 + 0.114 * *b as f64)
+        .collect()
+}
+
+/// The weights of the gaussian window, along one dimension.
+fn ssim_window() -> Vec<f64> {
+    (0..=2 * SSIM_RADIUS)
+        .map(|index| {
+            let x = index.abs_diff(SSIM_RADIUS) as f64;
+            (-0.5 * x * x / (SSIM_SIGMA * SSIM_SIGMA)).exp()
+        })
+        .collect()
+}
+
+/// The mean of `values` around each of them, weighted by the gaussian `window` vertically then horizontally, and
+/// renormalized where the window goes past the edges.
+fn gaussian_mean(values: &[f64], width: usize, height: usize, window: &[f64]) -> Vec<f64> {
+    let pass = |input: &[f64], vertical: bool| -> Vec<f64> {
+        (0..width * height)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let (position, length) = if vertical { (y, height) } else { (x, width) };
+                let (mut sum, mut weights) = (0.0, 0.0);
+                for (offset, weight) in window.iter().enumerate() {
+                    let Some(sample) = (position + offset)
+                        .checked_sub(SSIM_RADIUS)
+                        .filter(|&sample| sample < length)
+                    else {
+                        continue;
+                    };
+                    let sample_index = if vertical {
+                        sample * width + x
+                    } else {
+                        y * width + sample
+                    };
+                    sum += weight * input[sample_index];
+                    weights += weight;
+                }
+                sum / weights
+            })
+            .collect()
+    };
+
+    pass(&pass(values, true), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    fn image_a() -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([0, 0, 0, 255]),
+                Rgba([100, 100, 100, 255]),
+                Rgba([255, 0, 0, 255]),
+                Rgba([10, 20, 30, 40]),
+            ],
+        }
+    }
+
+    fn image_b() -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([0, 0, 0, 255]),
+                Rgba([104, 100, 100, 255]),
+                Rgba([255, 0, 0, 255]),
+                Rgba([10, 20, 30, 32]),
+            ],
+        }
+    }
+
+    #[test]
+    fn identical_images() {
+        let diff = image_a().diff(&image_a()).unwrap();
+
+        assert_eq!(0, diff.max_channel_delta());
+        assert_eq!(0.0, diff.mean_absolute_error());
+        assert_eq!(f64::INFINITY, diff.psnr());
+        assert_eq!(vec![Rgba([0, 0, 0, 255]); 4], diff.to_heatmap().pixels);
+    }
+
+    #[test]
+    fn known_metrics() {
+        let diff = image_a().diff(&image_b()).unwrap();
+
+        assert_eq!(8, diff.max_channel_delta());
+        // 4 + 8 over 16 channels.
+        assert_eq!(0.75, diff.mean_absolute_error());
+        // The mean square error is (16 + 64) / 16 = 5.
+        let psnr = 10.0 * (255.0f64 * 255.0 / 5.0).log10();
+        assert!((diff.psnr() - psnr).abs() < 1e-9);
+        assert_eq!(
+            vec![
+                Rgba([0, 0, 0, 255]),
+                Rgba([127, 0, 0, 255]),
+                Rgba([0, 0, 0, 255]),
+                Rgba([255, 0, 0, 255]),
+            ],
+            diff.to_heatmap().pixels
+        );
+    }
+
+    #[test]
+    fn approx_eq_tolerance() {
+        assert!(image_a().approx_eq(&image_b(), 8));
+        assert!(!image_a().approx_eq(&image_b(), 7));
+    }
+
+    #[test]
+    fn diff_mismatched_dimensions() {
+        let other = Image {
+            width: 4,
+            height: 1,
+            pixels: image_b().pixels,
+        };
+
+        assert!(matches!(
+            image_a().diff(&other),
+            Err(FiltersError::MismatchedDimensions {
+                expected: (2, 2),
+                actual: (4, 1)
+            })
+        ));
+        assert!(!image_a().approx_eq(&other, 255));
+    }
+
+    #[test]
+    fn diff_missing_pixels() {
+        let mut other = image_b();
+        other.pixels.pop();
+
+        assert!(matches!(
+            image_a().diff(&other),
+            Err(FiltersError::InvalidImageDimensions {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    /// Stripes and a gradient, with edges for a blur to soften.
+    fn detailed_image() -> Image {
+        Image::from_fn(64, 64, |x, y| {
+            let stripe = if (x / 3 + y / 5) % 2 == 0 { 200 } else { 40 };
+            Rgba([stripe, (x * 4) as u8, (y * 4) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn ssim_of_identical_images() {
+        let image = detailed_image();
+
+        assert_eq!(1.0, image.ssim(&image).unwrap());
+    }
+
+    #[test]
+    fn ssim_of_blurred_image() {
+        let image = detailed_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let ssim = |sigma| {
+            let blurred = image
+                .operation(&filters)
+                .unwrap()
+                .gaussian_blur(sigma)
+                .execute()
+                .block_on();
+            image.ssim(&blurred).unwrap()
+        };
+        let slight = ssim(0.8);
+        let strong = ssim(4.0);
+
+        // Around 0.76 and 0.11.
+        assert!((0.7..0.9).contains(&slight), "{slight}");
+        assert!((0.0..0.3).contains(&strong), "{strong}");
+    }
+
+    #[test]
+    fn ssim_mismatched_dimensions() {
+        let other = Image::new(4, 1, Rgba::new(0, 0, 0, 255));
+
+        assert!(matches!(
+            image_a().ssim(&other),
+            Err(FiltersError::MismatchedDimensions { .. })
+        ));
+    }
+}
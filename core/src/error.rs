@@ -0,0 +1,222 @@
+use std::fmt::Display;
+
+use wgpu::{BufferAsyncError, RequestDeviceError, TextureFormat, TextureUsages};
+
+use crate::PixelFormat;
+
+/// Everything that can go wrong while setting up the gpu or applying filters.
+#[derive(Debug)]
+pub enum FiltersError {
+    /// No gpu adapter could be found, which typically happens in headless environments without drivers.
+    NoAdapter,
+    /// An adapter was found, but it refused to provide a device.
+    DeviceRequestFailed(RequestDeviceError),
+    /// The amount of pixels of an image doesn't match its width and height.
+    InvalidImageDimensions { expected: usize, actual: usize },
+    /// The amount of bytes of raw or deserialized pixels doesn't match the width and height of their image.
+    InvalidPixelBytes { expected: usize, actual: usize },
+    /// An image has an unsupported size, like a zero width or height, or a filter was asked to produce one.
+    UnsupportedSize { width: u32, height: u32 },
+    /// Two images that must have the same dimensions, like an image and its mask, don't.
+    MismatchedDimensions {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// Two operations that must work with the same precision, like the halves of a montage, don't.
+    MismatchedFormats {
+        expected: PixelFormat,
+        actual: PixelFormat,
+    },
+    /// The region to crop doesn't fit in the image.
+    CropOutOfBounds {
+        origin: (u32, u32),
+        dimension: (u32, u32),
+        image: (u32, u32),
+    },
+    /// An image pasted with [`crate::Image::copy_from`] doesn't fit in the target image.
+    PasteOutOfBounds {
+        position: (u32, u32),
+        dimension: (u32, u32),
+        image: (u32, u32),
+    },
+    /// An array doesn't have the `height` × `width` × 4 channels shape of an image, holding its shape.
+    InvalidArrayShape([usize; 3]),
+    /// A pixel was set outside of the image.
+    PixelOutOfBounds {
+        position: (u32, u32),
+        image: (u32, u32),
+    },
+    /// An integer scale factor of 0, or one the filter doesn't support, was requested.
+    InvalidScaleFactor(u32),
+    /// A lut of a size other than 2 to 256 was requested, holding that size.
+    InvalidLutSize(u32),
+    /// A kernel has no values, or an even number of them, holding that number.
+    InvalidKernelSize(usize),
+    /// A level a pyramid doesn't have was requested, along with how many levels the pyramid has.
+    InvalidPyramidLevel { level: usize, levels: usize },
+    /// A string isn't a color formatted as `#rrggbb` or `#rrggbbaa`, holding the string.
+    InvalidColor(String),
+    /// The image is wider or taller than the biggest texture the gpu supports.
+    ImageTooLarge { dimension: (u32, u32), limit: u32 },
+    /// A filter that moves pixels around or depends on their position, like a resize, was used in tiled mode.
+    NotTileable,
+    /// A filter that looks at neighboring pixels, or moves them around, was applied to packed images, which would
+    /// bleed into each other, or baked into a lut, which only maps colors.
+    NotPackable,
+    /// The overlap between tiles is smaller than the radius of the filters, which would show seams.
+    TileOverlapTooSmall { overlap: u32, radius: u32 },
+    /// A texture handed over to the filters doesn't use the Rgba8Unorm format.
+    UnsupportedTextureFormat(TextureFormat),
+    /// A texture handed over to the filters lacks some usages, listed here.
+    MissingTextureUsages(TextureUsages),
+    /// A custom shader doesn't compile, or doesn't have a `main` compute entry point of 16×16 workgroups. Holds the
+    /// compiler message.
+    InvalidShader(String),
+    /// A filter, named here, has no cpu implementation, see the `cpu-reference` feature.
+    NoCpuImplementation(&'static str),
+    /// The work was stopped through a [`crate::CancellationToken`].
+    Cancelled,
+    /// The buffer the result is read back through couldn't be mapped, like when the device is lost.
+    BufferMapFailed(BufferAsyncError),
+    /// Another thread panicked while holding the readback buffer of the filters, which is then dropped.
+    ReadbackBufferPoisoned,
+    /// An image file couldn't be read, decoded, encoded or written, see the `image-interop` feature.
+    #[cfg(feature = "image-interop")]
+    ImageIo(image::ImageError),
+}
+
+impl Display for FiltersError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FiltersError::NoAdapter => write!(f, "No compatible gpu adapter found"),
+            FiltersError::DeviceRequestFailed(error) => {
+                write!(f, "Could not get a device from the gpu adapter: {error}")
+            }
+            FiltersError::InvalidImageDimensions { expected, actual } => write!(
+                f,
+                "The image should contain {expected} pixels according to its dimensions, but contains {actual}"
+            ),
+            FiltersError::InvalidPixelBytes { expected, actual } => write!(
+                f,
+                "The image should contain {expected} bytes of pixels according to its dimensions, but contains {actual}"
+            ),
+            FiltersError::UnsupportedSize { width, height } => {
+                write!(f, "Unsupported image size {width}x{height}")
+            }
+            FiltersError::MismatchedDimensions { expected, actual } => write!(
+                f,
+                "Expected dimensions {}x{}, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            FiltersError::MismatchedFormats { expected, actual } => {
+                write!(f, "Expected the {expected:?} pixel format, got {actual:?}")
+            }
+            FiltersError::CropOutOfBounds {
+                origin,
+                dimension,
+                image,
+            } => write!(
+                f,
+                "Cannot crop {}x{} at {},{} out of an image of {}x{}",
+                dimension.0, dimension.1, origin.0, origin.1, image.0, image.1
+            ),
+            FiltersError::PasteOutOfBounds {
+                position,
+                dimension,
+                image,
+            } => write!(
+                f,
+                "Cannot paste {}x{} at {},{} into an image of {}x{}",
+                dimension.0, dimension.1, position.0, position.1, image.0, image.1
+            ),
+            FiltersError::PixelOutOfBounds { position, image } => write!(
+                f,
+                "The pixel at {},{} is outside of the {}x{} image",
+                position.0, position.1, image.0, image.1
+            ),
+            FiltersError::InvalidArrayShape(shape) => write!(
+                f,
+                "Invalid array shape {}x{}x{}, expecting height x width x 4 channels",
+                shape[0], shape[1], shape[2]
+            ),
+            FiltersError::InvalidScaleFactor(factor) => {
+                write!(f, "Invalid scale factor {factor}")
+            }
+            FiltersError::InvalidLutSize(size) => {
+                write!(f, "Invalid lut size {size}, expecting 2 to 256")
+            }
+            FiltersError::InvalidKernelSize(size) => {
+                write!(f, "Invalid kernel size {size}, it should be odd")
+            }
+            FiltersError::InvalidPyramidLevel { level, levels } => {
+                write!(f, "Invalid pyramid level {level}, the pyramid has {levels} levels")
+            }
+            FiltersError::InvalidColor(input) => write!(
+                f,
+                "Invalid color {input}, expecting #rrggbb or #rrggbbaa hex digits"
+            ),
+            FiltersError::ImageTooLarge { dimension, limit } => write!(
+                f,
+                "Image of {}x{} is too large, the gpu supports at most {limit} pixels per side",
+                dimension.0, dimension.1
+            ),
+            FiltersError::NotTileable => write!(
+                f,
+                "Filters that move pixels or change the image dimensions can't be applied tile by tile"
+            ),
+            FiltersError::NotPackable => write!(
+                f,
+                "Only filters that work on each pixel on its own can be applied to packed images or baked into a lut"
+            ),
+            FiltersError::TileOverlapTooSmall { overlap, radius } => write!(
+                f,
+                "The tile overlap of {overlap} pixels is smaller than the filter radius of {radius} pixels"
+            ),
+            FiltersError::UnsupportedTextureFormat(format) => {
+                write!(f, "Unsupported texture format {format:?}, expecting Rgba8Unorm")
+            }
+            FiltersError::MissingTextureUsages(usages) => {
+                write!(f, "The texture is missing the usages {usages:?}")
+            }
+            FiltersError::InvalidShader(message) => write!(f, "Invalid shader: {message}"),
+            FiltersError::NoCpuImplementation(filter) => {
+                write!(f, "The {filter} filter has no cpu implementation")
+            }
+            FiltersError::Cancelled => write!(f, "Cancelled"),
+            FiltersError::BufferMapFailed(error) => {
+                write!(f, "Could not read the result back from the gpu: {error}")
+            }
+            FiltersError::ReadbackBufferPoisoned => write!(
+                f,
+                "The readback buffer was poisoned by a panic on another thread"
+            ),
+            #[cfg(feature = "image-interop")]
+            FiltersError::ImageIo(error) => write!(f, "Could not read or write the image: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for FiltersError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FiltersError::DeviceRequestFailed(error) => Some(error),
+            FiltersError::BufferMapFailed(error) => Some(error),
+            #[cfg(feature = "image-interop")]
+            FiltersError::ImageIo(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<RequestDeviceError> for FiltersError {
+    fn from(error: RequestDeviceError) -> Self {
+        FiltersError::DeviceRequestFailed(error)
+    }
+}
+
+#[cfg(feature = "image-interop")]
+impl From<image::ImageError> for FiltersError {
+    fn from(error: image::ImageError) -> Self {
+        FiltersError::ImageIo(error)
+    }
+}
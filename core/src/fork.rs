@@ -0,0 +1,121 @@
+use wgpu::CommandEncoderDescriptor;
+
+use crate::{
+    pool::{TexturePool, COPY_TEXTURE_USAGES},
+    profiling::Profiler,
+    Operation,
+};
+
+impl<'a> Operation<'a> {
+    /// Branches the operation: the returned operation starts from a copy of the current image, and both can be
+    /// continued independently, like to compute a blurred and a sharpened version of the same resized image.
+    ///
+    /// Both branches need the passes recorded so far, so they are submitted right away along with the copy,
+    /// which makes a submission of its own rather than being part of the final one.
+    pub fn fork(&mut self) -> Operation<'a> {
+        let mut pool = TexturePool::new(self.pipelines.texture_format(self.format));
+        let texture = pool.take(self.device, self.texture_size, COPY_TEXTURE_USAGES);
+        self.encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            self.texture_size,
+        );
+
+        self.submit_recorded();
+
+        Operation {
+            device: self.device,
+            queue: self.queue,
+            pipelines: self.pipelines,
+            submissions: self.submissions,
+            readback_buffer: self.readback_buffer,
+            encoder: self
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor { label: None }),
+            texture,
+            texture_size: self.texture_size,
+            texture_usage: COPY_TEXTURE_USAGES,
+            format: self.format,
+            color_space: self.color_space,
+            pool,
+            tileable: self.tileable,
+            radius: self.radius,
+            profiler: self
+                .profiler
+                .is_some()
+                .then(|| Profiler::new(self.device))
+                .flatten(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Image16, Resize, Rgba, Rgba16};
+
+    fn test_image() -> Image {
+        Image {
+            width: 12,
+            height: 9,
+            pixels: (0..12 * 9)
+                .map(|index| Rgba([(index * 7 % 256) as u8, (index * 3) as u8, 90, 255]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn fork_after_resize() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let blurred = image
+            .operation(&filters)
+            .unwrap()
+            .resize((6, 5), Resize::Linear)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .execute()
+            .block_on();
+        let sharpened = image
+            .operation(&filters)
+            .unwrap()
+            .resize((6, 5), Resize::Linear)
+            .unwrap()
+            .sharpen(0.5)
+            .hflip()
+            .execute()
+            .block_on();
+
+        let mut operation = image
+            .operation(&filters)
+            .unwrap()
+            .resize((6, 5), Resize::Linear)
+            .unwrap();
+        let branch = operation.fork();
+        let branch_output = branch.sharpen(0.5).hflip().execute().block_on();
+        let output = operation.gaussian_blur(1.0).execute().block_on();
+
+        assert_eq!(blurred, output);
+        assert_eq!(sharpened, branch_output);
+    }
+
+    #[test]
+    fn fork_keeps_pixel_format() {
+        let image = Image16 {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba16([1000, 20000, 65535, 65535]), Rgba16([0; 4])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut operation = image.operation(&filters).unwrap();
+        let branch = operation.fork();
+
+        assert_eq!(
+            operation.execute16().block_on(),
+            branch.execute16().block_on()
+        );
+    }
+}
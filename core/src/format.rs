@@ -0,0 +1,651 @@
+use std::sync::Arc;
+
+use bytemuck::Pod;
+use wgpu::{
+    BindGroupLayoutEntry, BindingType, ComputePipeline, Extent3d, ShaderStages,
+    StorageTextureAccess, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureViewDimension,
+};
+
+use crate::{
+    cache::Bindings,
+    check_texture_size, encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    read_mapped_buffer_into, wait_for_mapping, Filters, FiltersError, Image, Operation,
+};
+
+const FROM_RGBA16_SHADER: &str = include_str!("shaders/from_rgba16.wgsl");
+const FROM_F32_SHADER: &str = include_str!("shaders/from_f32.wgsl");
+const TO_RGBA16_SHADER: &str = include_str!("shaders/to_rgba16.wgsl");
+const TO_F32_SHADER: &str = include_str!("shaders/to_f32.wgsl");
+const TO_RGBA8_SHADER: &str = include_str!("shaders/to_rgba8.wgsl");
+
+/// The precision the passes of an operation work with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// 8 bits per channel, the format of [`Image`].
+    Rgba8,
+    /// 16 bits per channel, the format of [`Image16`]. The passes work on half floats, which keep at least 11 bits
+    /// of precision, so that chaining adjustments doesn't band like it does with 8 bits.
+    Rgba16,
+    /// Floats that can go beyond 1.0, the format of [`ImageF32`], for HDR images.
+    /// The passes work on half floats as well, keeping values up to 65504: 32-bit float textures can't be
+    /// filtered on every gpu, which filters sampling their input rely on. Filters with a clamped output,
+    /// like sharpen or brightness, still clamp to 1.0.
+    Float,
+    /// A single 8-bit channel, the format of [`ImageLuma`], for masks and grayscale data. The passes work on R8Unorm
+    /// textures when the gpu can write them, and fall back to Rgba8Unorm textures otherwise.
+    Luma,
+}
+
+impl PixelFormat {
+    /// The format of the textures the passes read and write.
+    ///
+    /// # Arguments
+    ///
+    /// * `single_channel_storage` - Whether the gpu can write R8Unorm textures from a compute shader.
+    pub(crate) fn texture_format(self, single_channel_storage: bool) -> TextureFormat {
+        match self {
+            PixelFormat::Rgba8 => TextureFormat::Rgba8Unorm,
+            PixelFormat::Rgba16 | PixelFormat::Float => TextureFormat::Rgba16Float,
+            PixelFormat::Luma if single_channel_storage => TextureFormat::R8Unorm,
+            PixelFormat::Luma => TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    /// The name of the texture format in wgsl, as declared by storage textures.
+    pub(crate) fn storage_format(self, single_channel_storage: bool) -> &'static str {
+        match self {
+            PixelFormat::Rgba8 => "rgba8unorm",
+            PixelFormat::Rgba16 | PixelFormat::Float => "rgba16float",
+            PixelFormat::Luma if single_channel_storage => "r8unorm",
+            PixelFormat::Luma => "rgba8unorm",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod, PartialEq, Eq)]
+pub struct Rgba16(pub [u16; 4]);
+
+/// An image with 16 bits per channel, like a 16-bit png.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Image16 {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Rgba16>,
+}
+
+impl Image16 {
+    /// Starts an operation working with 16 bits per channel, see [`PixelFormat::Rgba16`].
+    pub fn operation<'a>(&self, filters: &'a Filters) -> Result<Operation<'a>, FiltersError> {
+        let texture_size = check_pixel_count(self.width, self.height, self.pixels.len())?;
+        let mut operation = Operation::with_uploaded_texture(
+            filters,
+            texture_size,
+            TextureFormat::Rgba16Uint,
+            self.as_raw(),
+            PixelFormat::Rgba16,
+        )?;
+        let pipeline = operation.pipeline("from_rgba16", FROM_RGBA16_SHADER, Bindings::Derived);
+        operation.convert_input("from_rgba16", &pipeline);
+
+        Ok(operation)
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.pixels)
+    }
+}
+
+/// An image with a float per channel, whose values can go beyond 1.0, like an HDR image.
+#[derive(Debug, PartialEq)]
+pub struct ImageF32 {
+    pub width: u32,
+    pub height: u32,
+    /// The red, green, blue and alpha channels of each pixel, one after the other.
+    pub pixels: Vec<f32>,
+}
+
+impl Image {
+    /// Creates a float image, to process with [`PixelFormat::Float`].
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the image.
+    /// * `height` - The height of the image.
+    /// * `pixels` - 4 floats per pixel: red, green, blue and alpha.
+    pub fn from_f32(width: u32, height: u32, pixels: Vec<f32>) -> ImageF32 {
+        ImageF32 {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl ImageF32 {
+    /// Starts an operation working with floats, see [`PixelFormat::Float`].
+    pub fn operation<'a>(&self, filters: &'a Filters) -> Result<Operation<'a>, FiltersError> {
+        if !self.pixels.len().is_multiple_of(4) {
+            return Err(FiltersError::InvalidImageDimensions {
+                expected: self.width as usize * self.height as usize,
+                actual: self.pixels.len() / 4,
+            });
+        }
+        let texture_size = check_pixel_count(self.width, self.height, self.pixels.len() / 4)?;
+        let mut operation = Operation::with_uploaded_texture(
+            filters,
+            texture_size,
+            TextureFormat::Rgba32Float,
+            self.as_raw(),
+            PixelFormat::Float,
+        )?;
+        // The layout derived from the shader would expect a filterable texture, which Rgba32Float isn't.
+        let entries = [
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::StorageTexture {
+                    access: StorageTextureAccess::WriteOnly,
+                    format: operation.pipelines.texture_format(PixelFormat::Float),
+                    view_dimension: TextureViewDimension::D2,
+                },
+                count: None,
+            },
+        ];
+        let pipeline = operation.pipelines.get_with_layout(
+            operation.device,
+            "from_f32",
+            FROM_F32_SHADER,
+            PixelFormat::Float,
+            &entries,
+        );
+        operation.convert_input("from_f32", &pipeline);
+
+        Ok(operation)
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.pixels)
+    }
+}
+
+/// Makes sure that an image has as many pixels as its dimensions say, and that a texture can hold it.
+pub(crate) fn check_pixel_count(
+    width: u32,
+    height: u32,
+    pixel_count: usize,
+) -> Result<Extent3d, FiltersError> {
+    let expected = width as usize * height as usize;
+    if pixel_count != expected {
+        return Err(FiltersError::InvalidImageDimensions {
+            expected,
+            actual: pixel_count,
+        });
+    }
+
+    Ok(Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    })
+}
+
+impl<'a> Operation<'a> {
+    /// Uploads `data` to a texture of `texture_format`, starting an operation working in `format` from it.
+    /// That texture can't be used by the passes as is, and must be converted with [`Operation::convert_input`].
+    pub(crate) fn with_uploaded_texture(
+        filters: &'a Filters,
+        texture_size: Extent3d,
+        texture_format: TextureFormat,
+        data: &[u8],
+        format: PixelFormat,
+    ) -> Result<Self, FiltersError> {
+        check_texture_size(&filters.device, (texture_size.width, texture_size.height))?;
+
+        let usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+        let texture = filters.device.create_texture(&TextureDescriptor {
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: texture_format,
+            usage,
+            label: Some("texture"),
+        });
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "upload",
+            width = texture_size.width,
+            height = texture_size.height,
+            bytes = data.len()
+        )
+        .entered();
+        filters.queue.write_texture(
+            texture.as_image_copy(),
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(
+                    texture_format.describe().block_size as u32 * texture_size.width,
+                ),
+                rows_per_image: None,
+            },
+            texture_size,
+        );
+
+        Ok(Self::with_texture(
+            filters,
+            texture,
+            texture_size,
+            usage,
+            format,
+        ))
+    }
+
+    /// Records the conversion of an uploaded texture to the format of the passes, with `pipeline`.
+    pub(crate) fn convert_input(&mut self, name: &str, pipeline: &ComputePipeline) {
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.encode_simple_pass(name, pipeline, &output_texture);
+        self.texture = output_texture;
+        self.texture_usage = STORAGE_TEXTURE_USAGES;
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Like [`Operation::execute`], but reads the result back with 16 bits per channel, clamped between 0.0 and 1.0.
+    /// An 8-bit operation is widened, each channel value being multiplied by 257.
+    pub async fn execute16(mut self) -> Image16 {
+        if self.format == PixelFormat::Luma {
+            self.convert_to_rgba8();
+        }
+        let (width, height) = self.dimensions();
+        let pipeline = self.pipeline("to_rgba16", TO_RGBA16_SHADER, Bindings::Derived);
+        let pixels = self
+            .read_back("to_rgba16", pipeline, TextureFormat::Rgba16Uint)
+            .await;
+
+        Image16 {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Like [`Operation::execute`], but reads the result back as floats, without clamping them.
+    pub async fn execute_f32(mut self) -> ImageF32 {
+        if self.format == PixelFormat::Luma {
+            self.convert_to_rgba8();
+        }
+        let (width, height) = self.dimensions();
+        let pipeline = self.pipeline("to_f32", TO_F32_SHADER, Bindings::Derived);
+        let pixels: Vec<[f32; 4]> = self
+            .read_back("to_f32", pipeline, TextureFormat::Rgba32Float)
+            .await;
+
+        ImageF32 {
+            width,
+            height,
+            pixels: pixels.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Converts the result to `texture_format` with `pipeline`, then reads it back, with pixels of type `T`.
+    async fn read_back<T: Pod>(
+        mut self,
+        name: &str,
+        pipeline: Arc<ComputePipeline>,
+        texture_format: TextureFormat,
+    ) -> Vec<T> {
+        self.encode_srgb();
+        let (width, height) = self.dimensions();
+        let device = self.device;
+
+        let output_texture = device.create_texture(&TextureDescriptor {
+            size: self.texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: texture_format,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+            label: Some("texture"),
+        });
+        self.encode_simple_pass(name, &pipeline, &output_texture);
+        let output_buffer = encode_texture_to_buffer::<T>(
+            device,
+            &mut self.encoder,
+            width,
+            height,
+            &output_texture,
+        );
+        self.submit();
+
+        wait_for_mapping(device, &output_buffer).await;
+        let mut pixels = Vec::new();
+        read_mapped_buffer_into(width, height, &output_buffer, &mut pixels);
+
+        pixels
+    }
+
+    /// Records the conversion of a 16-bit, float or single channel operation to 8-bit RGBA, for the readbacks and interop producing
+    /// Rgba8Unorm data. Does nothing if the operation already is 8-bit.
+    pub(crate) fn convert_to_rgba8(&mut self) {
+        self.encode_srgb();
+        match self.format {
+            PixelFormat::Rgba8 => {}
+            PixelFormat::Luma => self.convert_luma_to_rgba(),
+            PixelFormat::Rgba16 | PixelFormat::Float => {
+                self.convert_format(PixelFormat::Rgba8, "to_rgba8", TO_RGBA8_SHADER)
+            }
+        }
+    }
+
+    /// Records the conversion of the operation to [`PixelFormat::Float`], for the filters whose intermediate results
+    /// go below 0.0 or beyond 1.0. Does nothing if the operation already works with floats.
+    pub(crate) fn convert_to_float(&mut self) {
+        self.encode_srgb();
+        match self.format {
+            PixelFormat::Float => {}
+            // Both work on half float textures, which only need to be read as floats.
+            PixelFormat::Rgba16 => self.format = PixelFormat::Float,
+            PixelFormat::Rgba8 | PixelFormat::Luma => {
+                self.convert_luma_to_rgba();
+                // The copy of to_rgba8.wgsl writes any format, its storage texture being swapped like for any filter.
+                self.convert_format(PixelFormat::Float, "to_float", TO_RGBA8_SHADER);
+            }
+        }
+    }
+
+    /// Records the conversion of the operation to `format`, like to bring the result of a chain that changed it back
+    /// to the format of the operation it started from. Does nothing if the operation already works with `format`.
+    pub(crate) fn convert_to(&mut self, format: PixelFormat) {
+        match format {
+            _ if self.format == format => {}
+            PixelFormat::Rgba8 => self.convert_to_rgba8(),
+            PixelFormat::Luma => self.convert_to_luma(),
+            // Both work on half float textures, so converting to floats is all it takes.
+            PixelFormat::Rgba16 | PixelFormat::Float => {
+                self.convert_to_float();
+                self.format = format;
+            }
+        }
+    }
+
+    /// Switches the passes to `format`, recording a pass of the shader `name` to convert the current texture.
+    /// The shader must write to a Rgba8Unorm storage texture, swapped for `format` like for any filter.
+    pub(crate) fn convert_format(
+        &mut self,
+        format: PixelFormat,
+        name: &'static str,
+        shader_string: &str,
+    ) {
+        self.format = format;
+        self.pool = TexturePool::new(self.pipelines.texture_format(format));
+        let pipeline = self.pipeline(name, shader_string, Bindings::Textures);
+        self.convert_input(name, &pipeline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Image16, PixelFormat, Resize, Rgba, Rgba16};
+
+    #[test]
+    fn execute16_widens_8_bit_operation() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([0, 1, 128, 255]), Rgba([255, 254, 10, 0])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image.operation(&filters).unwrap().execute16().block_on();
+
+        assert_eq!(
+            Image16 {
+                width: 2,
+                height: 1,
+                pixels: vec![
+                    Rgba16([0, 257, 128 * 257, 65535]),
+                    Rgba16([65535, 254 * 257, 10 * 257, 0])
+                ],
+            },
+            output
+        );
+    }
+
+    #[test]
+    fn image16_round_trip() {
+        let image = Image16 {
+            width: 65,
+            height: 3,
+            pixels: (0..65 * 3)
+                .map(|index| Rgba16([index * 300, 65535 - index * 300, 1000, 65535]))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image.operation(&filters).unwrap();
+        assert_eq!(PixelFormat::Rgba16, operation.pixel_format());
+        let output = operation.execute16().block_on();
+
+        assert_eq!((image.width, image.height), (output.width, output.height));
+        for (expected, actual) in image.pixels.iter().zip(&output.pixels) {
+            for (expected, actual) in expected.0.iter().zip(actual.0) {
+                assert!(expected.abs_diff(actual) <= 32, "{expected} != {actual}");
+            }
+        }
+    }
+
+    #[test]
+    fn image16_execute_matches_8_bit() {
+        let image = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([0, 0, 0, 255]),
+                Rgba([128, 64, 32, 255]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+        let image16 = Image16 {
+            width: 3,
+            height: 1,
+            pixels: image
+                .pixels
+                .iter()
+                .map(|Rgba(channels)| Rgba16(channels.map(|channel| channel as u16 * 257)))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image16
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn image16_filter_chain_close_to_8_bit() {
+        let image = Image {
+            width: 6,
+            height: 4,
+            pixels: (0..24)
+                .map(|index| Rgba([index * 10, 255 - index * 5, 100, 255]))
+                .collect(),
+        };
+        let image16 = Image16 {
+            width: 6,
+            height: 4,
+            pixels: image
+                .pixels
+                .iter()
+                .map(|Rgba(channels)| Rgba16(channels.map(|channel| channel as u16 * 257)))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .sharpen(0.2)
+            .resize((3, 2), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+        let output = image16
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .sharpen(0.2)
+            .resize((3, 2), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((3, 2), (output.width, output.height));
+        for (expected, actual) in expected.pixels.iter().zip(&output.pixels) {
+            for (expected, actual) in expected.0.iter().zip(actual.0) {
+                assert!(expected.abs_diff(actual) <= 2, "{expected} != {actual}");
+            }
+        }
+    }
+
+    #[test]
+    fn adjustments_keep_more_levels_in_16_bit() {
+        let width = 1024;
+        let image = Image {
+            width,
+            height: 1,
+            pixels: (0..width)
+                .map(|x| {
+                    let value = (x / 4) as u8;
+                    Rgba([value, value, value, 255])
+                })
+                .collect(),
+        };
+        let image16 = Image16 {
+            width,
+            height: 1,
+            pixels: (0..width)
+                .map(|x| {
+                    let value = (x * 64) as u16;
+                    Rgba16([value, value, value, 65535])
+                })
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .brightness(-0.2)
+            .contrast(0.5)
+            .contrast(2.0)
+            .execute16()
+            .block_on();
+        let output16 = image16
+            .operation(&filters)
+            .unwrap()
+            .brightness(-0.2)
+            .contrast(0.5)
+            .contrast(2.0)
+            .execute16()
+            .block_on();
+
+        let levels = |image: &Image16| {
+            image
+                .pixels
+                .iter()
+                .map(|pixel| pixel.0[0])
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        assert!(levels(&output16) > levels(&output));
+    }
+
+    #[test]
+    fn float_values_beyond_one_survive() {
+        let image = Image::from_f32(2, 1, vec![4.0, 2.5, 0.5, 1.0, 100.0, 0.0, 1.5, 1.0]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .hflip()
+            .hflip()
+            .execute_f32()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn float_blur_preserves_energy() {
+        let mut pixels = vec![0.0; 9 * 9 * 4];
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 1.0;
+        }
+        pixels[(4 * 9 + 4) * 4] = 4.0;
+        let image = Image::from_f32(9, 9, pixels);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .execute_f32()
+            .block_on();
+
+        let center = output.pixels[(4 * 9 + 4) * 4];
+        let sum: f32 = output.pixels.iter().step_by(4).sum();
+        assert!(center > 0.1 && center < 4.0);
+        assert!((sum - 4.0).abs() < 0.05, "{sum}");
+    }
+
+    #[test]
+    fn float_invalid_pixel_count() {
+        let image = Image::from_f32(2, 1, vec![1.0; 6]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidImageDimensions {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
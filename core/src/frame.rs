@@ -0,0 +1,231 @@
+use wgpu::{Buffer, Extent3d, Maintain, MapMode, Texture};
+
+use crate::{
+    check_pixel_bytes, check_texture_size, create_readback_buffer, encode_copy_to_buffer,
+    pool::{TexturePool, COPY_TEXTURE_USAGES},
+    read_mapped_buffer_into, write_pixels, FilterChain, Filters, FiltersError, Image, Operation,
+    PixelFormat, Rgba,
+};
+
+/// Applies the same chain of filters to frame after frame of the same size, like those of a webcam or a video,
+/// see [`Filters::frame_processor`].
+///
+/// The pipelines, the textures of the passes, the input one included, and the readback buffer are created once,
+/// along with the processor. Each frame then only uploads its pixels, records the passes, whose bind groups can't
+/// outlive a submission in wgpu, and reads the result back.
+pub struct FrameProcessor<'a> {
+    filters: &'a Filters,
+    chain: FilterChain,
+    input_size: Extent3d,
+    /// The dimensions of the results, once the chain is applied.
+    output_size: (u32, u32),
+    /// The textures of the passes, kept between frames.
+    pool: TexturePool,
+    readback_buffer: Buffer,
+}
+
+impl Filters {
+    /// Prepares a [`FrameProcessor`] applying `chain` to frames of `width` by `height` pixels, running the chain
+    /// once to build everything it needs.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`Image::operation`] if the size isn't supported, and the error of a step of `chain` that
+    /// doesn't apply at that size.
+    pub fn frame_processor(
+        &self,
+        width: u32,
+        height: u32,
+        chain: FilterChain,
+    ) -> Result<FrameProcessor<'_>, FiltersError> {
+        check_texture_size(&self.device, (width, height))?;
+        let input_size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let mut processor = FrameProcessor {
+            filters: self,
+            chain,
+            input_size,
+            output_size: (width, height),
+            pool: TexturePool::new(self.pipelines.texture_format(PixelFormat::Rgba8)),
+            readback_buffer: create_readback_buffer::<Rgba>(&self.device, 1, 1),
+        };
+
+        // The input texture starts out blank, which is as good as any frame to build the pipelines and textures.
+        let input = processor.take_input();
+        let operation = processor.record(input)?;
+        let (output_width, output_height) = operation.dimensions();
+        processor.output_size = (output_width, output_height);
+        processor.readback_buffer =
+            create_readback_buffer::<Rgba>(&self.device, output_width, output_height);
+        processor.submit(operation, false);
+
+        Ok(processor)
+    }
+}
+
+impl<'a> FrameProcessor<'a> {
+    /// Applies the chain to `frame`, writing the result into `out`, whose pixels are reused when it already has
+    /// the dimensions of the results. Waits for the gpu to be done.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The rgba pixels of the frame, row by row, 4 bytes each.
+    /// * `out` - The image receiving the result.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidPixelBytes`] if `frame` doesn't have the size the processor was prepared for.
+    pub fn process(&mut self, frame: &[u8], out: &mut Image) -> Result<(), FiltersError> {
+        check_pixel_bytes((self.input_size.width, self.input_size.height), frame.len())?;
+
+        let input = self.take_input();
+        write_pixels(&self.filters.queue, &input, self.input_size, frame);
+        let operation = self.record(input)?;
+        self.submit(operation, true);
+
+        let (width, height) = self.output_size;
+        self.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, |_| {});
+        self.filters.device.poll(Maintain::Wait);
+        read_mapped_buffer_into(width, height, &self.readback_buffer, &mut out.pixels);
+        out.width = width;
+        out.height = height;
+
+        Ok(())
+    }
+
+    /// The dimensions of the results, which differ from those of the frames if the chain resizes them.
+    pub fn output_dimensions(&self) -> (u32, u32) {
+        self.output_size
+    }
+
+    fn take_input(&mut self) -> Texture {
+        self.pool
+            .take(&self.filters.device, self.input_size, COPY_TEXTURE_USAGES)
+    }
+
+    /// Records the passes of the chain on `input`, with the textures of the previous frames.
+    fn record(&mut self, input: Texture) -> Result<Operation<'a>, FiltersError> {
+        let mut operation = Operation::with_texture(
+            self.filters,
+            input,
+            self.input_size,
+            COPY_TEXTURE_USAGES,
+            PixelFormat::Rgba8,
+        );
+        operation.pool = self.pool.take_all();
+        let mut operation = self.chain.apply(operation)?;
+        operation.convert_to_rgba8();
+        Ok(operation)
+    }
+
+    /// Submits the passes, copying the result to the readback buffer if `readback` is set, and keeps the textures
+    /// for the next frame.
+    fn submit(&mut self, mut operation: Operation<'a>, readback: bool) {
+        if readback {
+            let (width, height) = self.output_size;
+            encode_copy_to_buffer::<Rgba>(
+                &mut operation.encoder,
+                width,
+                height,
+                &operation.texture,
+                &self.readback_buffer,
+            );
+        }
+        self.pool = operation.pool.take_all();
+        let usage = operation.texture_usage;
+        let (texture, size) = operation.submit();
+        self.pool.release(size, usage, texture);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{FilterChain, Filters, FiltersError, Image, Rgba};
+
+    fn frame(width: u32, height: u32, seed: u32) -> Image {
+        Image::from_fn(width, height, |x, y| {
+            Rgba::new(
+                (x * 7 + seed * 30) as u8,
+                (y * 5) as u8,
+                ((x ^ y) + seed) as u8,
+                255,
+            )
+        })
+    }
+
+    fn chain() -> FilterChain {
+        FilterChain::parse("grayscale|gaussianblur(2.0)|contrast(1.2)|resize(20,10,linear)")
+            .unwrap()
+    }
+
+    #[test]
+    fn frames_match_one_shot() {
+        let filters = Filters::new().block_on().unwrap();
+        let mut processor = filters.frame_processor(37, 23, chain()).unwrap();
+        let mut output = Image::new(0, 0, Rgba::new(0, 0, 0, 0));
+
+        assert_eq!((20, 10), processor.output_dimensions());
+        for seed in 0..3 {
+            let frame = frame(37, 23, seed);
+            let expected = chain()
+                .apply(frame.operation(&filters).unwrap())
+                .unwrap()
+                .execute()
+                .block_on();
+
+            processor.process(frame.as_raw(), &mut output).unwrap();
+
+            assert_eq!(expected, output, "{seed}");
+        }
+    }
+
+    #[test]
+    fn frames_reuse_textures() {
+        let filters = Filters::new().block_on().unwrap();
+        let mut processor = filters.frame_processor(37, 23, chain()).unwrap();
+        let mut output = Image::new(0, 0, Rgba::new(0, 0, 0, 0));
+        let created = processor.pool.created();
+
+        for seed in 0..3 {
+            processor
+                .process(frame(37, 23, seed).as_raw(), &mut output)
+                .unwrap();
+        }
+
+        assert_eq!(created, processor.pool.created());
+    }
+
+    #[test]
+    fn frame_of_wrong_size() {
+        let filters = Filters::new().block_on().unwrap();
+        let mut processor = filters.frame_processor(4, 4, chain()).unwrap();
+        let mut output = Image::new(0, 0, Rgba::new(0, 0, 0, 0));
+
+        let result = processor.process(frame(4, 3, 0).as_raw(), &mut output);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidPixelBytes {
+                expected: 64,
+                actual: 48
+            })
+        ));
+    }
+
+    #[test]
+    fn frame_processor_errors_with_chain() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let result =
+            filters.frame_processor(4, 4, FilterChain::parse("resize(0,4,linear)").unwrap());
+
+        assert!(matches!(result, Err(FiltersError::UnsupportedSize { .. })));
+    }
+}
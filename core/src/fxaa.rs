@@ -0,0 +1,135 @@
+use wgpu::{AddressMode, FilterMode, SamplerDescriptor};
+
+use crate::{cache::Bindings, Operation, PixelFormat};
+
+const FXAA_SHADER: &str = include_str!("shaders/fxaa.wgsl");
+
+/// The smallest contrast for a pixel to be on an edge, which keeps the noise of dark areas out.
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+/// The smallest contrast for a pixel to be on an edge, relative to its brightest neighbor.
+const EDGE_THRESHOLD: f32 = 0.125;
+/// How much the pixels thinner than a pixel are blended, the default of FXAA 3.11.
+const SUBPIXEL_QUALITY: f32 = 0.75;
+/// How far from a pixel the edge search reads, in pixels: a pixel to reach the edge, 25.5 for the steps of the
+/// search along it, and one for the bilinear sample at its end.
+const SEARCH_RADIUS: u32 = 28;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct FxaaSettings {
+    edge_threshold_min: f32,
+    edge_threshold: f32,
+    subpixel_quality: f32,
+    single_channel: u32,
+}
+
+impl<'a> Operation<'a> {
+    /// Smooths the jagged edges of the image with FXAA 3.11, like those of pixel art upscaled with
+    /// [`crate::Resize::Nearest`] or of rendered content, leaving the flat areas and the soft gradients untouched.
+    ///
+    /// Each pixel on an edge, where the luminance changes sharply, is blended with its neighbor across the edge,
+    /// all the more that it is close to the end of the edge, which turns staircases into smooth slopes.
+    pub fn fxaa(mut self) -> Self {
+        let name = "fxaa";
+        let pipeline = self.pipeline(name, FXAA_SHADER, Bindings::Derived);
+        let sampler = self.device.create_sampler(&SamplerDescriptor {
+            label: Some("Fxaa sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let settings = FxaaSettings {
+            edge_threshold_min: EDGE_THRESHOLD_MIN,
+            edge_threshold: EDGE_THRESHOLD,
+            subpixel_quality: SUBPIXEL_QUALITY,
+            single_channel: u32::from(self.format == PixelFormat::Luma),
+        };
+
+        self.radius += SEARCH_RADIUS;
+        let output_size = self.texture_size;
+        self.settings_pass(
+            name,
+            &pipeline,
+            bytemuck::bytes_of(&settings),
+            Some(&sampler),
+            output_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    /// Whether the red of `pixel` is neither black nor white.
+    fn is_intermediate(pixel: &Rgba) -> bool {
+        (16..240).contains(&pixel.r())
+    }
+
+    #[test]
+    fn smooths_diagonal_edge() {
+        // White below a shallow line, in steps of three pixels.
+        let is_white = |x: u32, y: u32| 3 * y > x + 6;
+        let image = Image::from_fn(48, 24, |x, y| {
+            if is_white(x, y) {
+                Rgba::new(255, 255, 255, 255)
+            } else {
+                Rgba::new(0, 0, 0, 255)
+            }
+        });
+        let filters = Filters::new().block_on().unwrap();
+
+        let smoothed = image
+            .operation(&filters)
+            .unwrap()
+            .fxaa()
+            .execute()
+            .block_on();
+
+        let intermediate = smoothed
+            .pixels
+            .iter()
+            .filter(|pixel| is_intermediate(pixel))
+            .count();
+        assert_eq!(
+            0,
+            image
+                .pixels
+                .iter()
+                .filter(|pixel| is_intermediate(pixel))
+                .count()
+        );
+        assert!(intermediate >= 20, "Only {intermediate} smoothed pixels");
+        for y in 0..24u32 {
+            for x in 0..48u32 {
+                let near_edge = (x.saturating_sub(2)..=x + 2)
+                    .flat_map(|x| (y.saturating_sub(2)..=y + 2).map(move |y| (x, y)))
+                    .any(|(nx, ny)| is_white(nx, ny) != is_white(x, y));
+                let index = (y * 48 + x) as usize;
+                if !near_edge {
+                    assert_eq!(image.pixels[index], smoothed.pixels[index], "{x},{y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flat_image_untouched() {
+        let image = Image::from_fn(20, 10, |x, _| Rgba::new(x as u8 * 10, 100, 50, 200));
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .fxaa()
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+}
@@ -0,0 +1,322 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    Extent3d, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    check_texture_size, compute_work_group_count,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    Filters, FiltersError, Operation, PixelFormat, Rgba,
+};
+
+const GENERATE_SHADER: &str = include_str!("shaders/generate.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct GenerateSettings {
+    first: [f32; 4],
+    second: [f32; 4],
+    direction: [f32; 2],
+    center: [f32; 2],
+    kind: u32,
+    cell: u32,
+    scale: f32,
+    offset: f32,
+}
+
+impl GenerateSettings {
+    fn new(kind: u32, first: Rgba, second: Rgba) -> Self {
+        let unit = |color: Rgba| color.0.map(|channel| f32::from(channel) / 255.0);
+        Self {
+            first: unit(first),
+            second: unit(second),
+            direction: [0.0; 2],
+            center: [0.0; 2],
+            kind,
+            cell: 1,
+            scale: 0.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl Filters {
+    /// Starts an operation on an image of `size` filled with `color`, without uploading any pixels.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`crate::Image::operation`] if the size isn't supported.
+    pub fn solid(&self, size: (u32, u32), color: Rgba) -> Result<Operation<'_>, FiltersError> {
+        self.generate(size, GenerateSettings::new(0, color, color))
+    }
+
+    /// Starts an operation on an image of `size` fading from `start` to `end`, like to build the mask of a
+    /// graduated filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The color of the first pixels along the direction of the gradient, like the left column.
+    /// * `end` - The color of the last pixels along the direction of the gradient, like the right column.
+    /// * `angle` - The direction of the gradient in degrees, clockwise: 0 goes from left to right, 90 from top to
+    ///   bottom. The corners the furthest apart along it get `start` and `end`.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`crate::Image::operation`] if the size isn't supported.
+    pub fn linear_gradient(
+        &self,
+        size: (u32, u32),
+        start: Rgba,
+        end: Rgba,
+        angle: f32,
+    ) -> Result<Operation<'_>, FiltersError> {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (right, bottom) = (
+            size.0.saturating_sub(1) as f32,
+            size.1.saturating_sub(1) as f32,
+        );
+        let projections = [(0.0, 0.0), (right, 0.0), (0.0, bottom), (right, bottom)]
+            .map(|(x, y)| x * cos + y * sin);
+        let first = projections.iter().copied().fold(f32::MAX, f32::min);
+        let last = projections.iter().copied().fold(f32::MIN, f32::max);
+        let length = last - first;
+
+        let mut settings = GenerateSettings::new(1, start, end);
+        settings.direction = [cos, sin];
+        if length > f32::EPSILON {
+            settings.scale = 1.0 / length;
+            settings.offset = -first / length;
+        }
+        self.generate(size, settings)
+    }
+
+    /// Starts an operation on an image of `size` fading from `inner` at its center to `outer` at `radius` pixels
+    /// from it, and beyond, like to build the mask of a vignette.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`crate::Image::operation`] if the size isn't supported.
+    pub fn radial_gradient(
+        &self,
+        size: (u32, u32),
+        inner: Rgba,
+        outer: Rgba,
+        radius: f32,
+    ) -> Result<Operation<'_>, FiltersError> {
+        let mut settings = GenerateSettings::new(2, inner, outer);
+        settings.center = [
+            size.0.saturating_sub(1) as f32 / 2.0,
+            size.1.saturating_sub(1) as f32 / 2.0,
+        ];
+        settings.scale = 1.0 / radius.max(f32::EPSILON);
+        self.generate(size, settings)
+    }
+
+    /// Starts an operation on an image of `size` tiled with squares of `cell` pixels, alternating `a`, in the top
+    /// left corner, and `b`, like to show transparency or to test resizes.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`crate::Image::operation`] if the size isn't supported.
+    pub fn checkerboard(
+        &self,
+        size: (u32, u32),
+        cell: u32,
+        a: Rgba,
+        b: Rgba,
+    ) -> Result<Operation<'_>, FiltersError> {
+        let mut settings = GenerateSettings::new(3, a, b);
+        settings.cell = cell.max(1);
+        self.generate(size, settings)
+    }
+
+    /// Starts an operation on a new texture of `size`, written by the generate shader.
+    fn generate(
+        &self,
+        size: (u32, u32),
+        settings: GenerateSettings,
+    ) -> Result<Operation<'_>, FiltersError> {
+        check_texture_size(&self.device, size)?;
+        let texture_size = Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1,
+        };
+        let texture = TexturePool::new(self.pipelines.texture_format(PixelFormat::Rgba8)).take(
+            &self.device,
+            texture_size,
+            STORAGE_TEXTURE_USAGES,
+        );
+        let mut operation = Operation::with_texture(
+            self,
+            texture,
+            texture_size,
+            STORAGE_TEXTURE_USAGES,
+            PixelFormat::Rgba8,
+        );
+
+        let name = "generate";
+        let pipeline = operation.pipeline(name, GENERATE_SHADER, Bindings::Derived);
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Generate settings"),
+            contents: bytemuck::bytes_of(&settings),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Generate bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: settings.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &operation
+                            .texture
+                            .create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = operation.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(size, self.pipelines.workgroup_size(name));
+            let mut compute_pass = operation
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("Generate pass"),
+                });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        operation.end_pass(pass);
+
+        Ok(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    const RED: Rgba = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba = Rgba([0, 0, 255, 255]);
+
+    /// Whether `pixel` is halfway between red and blue, which gpus round either way.
+    fn is_purple(pixel: Rgba) -> bool {
+        let Rgba([r, g, b, a]) = pixel;
+        r.abs_diff(128) <= 1 && g == 0 && b.abs_diff(128) <= 1 && a == 255
+    }
+
+    fn corners(image: &Image) -> [Rgba; 4] {
+        let (right, bottom) = (image.width - 1, image.height - 1);
+        [(0, 0), (right, 0), (0, bottom), (right, bottom)]
+            .map(|(x, y)| image.pixels[(y * image.width + x) as usize])
+    }
+
+    #[test]
+    fn solid() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let image = filters
+            .solid((5, 3), Rgba([12, 34, 56, 78]))
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(Image::new(5, 3, Rgba([12, 34, 56, 78])), image);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let filters = Filters::new().block_on().unwrap();
+        let gradient = |angle| {
+            filters
+                .linear_gradient((5, 3), RED, BLUE, angle)
+                .unwrap()
+                .execute()
+                .block_on()
+        };
+
+        let horizontal = gradient(0.0);
+        let vertical = gradient(90.0);
+        let diagonal = gradient(45.0);
+
+        assert_eq!([RED, BLUE, RED, BLUE], corners(&horizontal));
+        assert!(
+            is_purple(horizontal.pixels[2]),
+            "{:?}",
+            horizontal.pixels[2]
+        );
+        assert_eq!([RED, RED, BLUE, BLUE], corners(&vertical));
+        assert_eq!(RED, diagonal.pixels[0]);
+        assert_eq!(BLUE, diagonal.pixels[14]);
+    }
+
+    #[test]
+    fn radial_gradient() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let image = filters
+            .radial_gradient((7, 7), RED, BLUE, 2.0)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(RED, image.pixels[3 * 7 + 3]);
+        assert!(
+            is_purple(image.pixels[3 * 7 + 4]),
+            "{:?}",
+            image.pixels[3 * 7 + 4]
+        );
+        assert_eq!(BLUE, image.pixels[3 * 7 + 5]);
+        assert_eq!([BLUE; 4], corners(&image));
+    }
+
+    #[test]
+    fn checkerboard() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let image = filters
+            .checkerboard((5, 4), 2, RED, BLUE)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        let expected = Image::from_fn(
+            5,
+            4,
+            |x, y| {
+                if (x / 2 + y / 2) % 2 == 0 {
+                    RED
+                } else {
+                    BLUE
+                }
+            },
+        );
+        assert_eq!(expected, image);
+        assert_eq!([RED, RED, BLUE, BLUE], corners(&image));
+    }
+
+    #[test]
+    fn unsupported_size() {
+        let filters = Filters::new().block_on().unwrap();
+
+        assert!(matches!(
+            filters.solid((0, 4), RED),
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 4
+            })
+        ));
+    }
+}
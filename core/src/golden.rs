@@ -0,0 +1,221 @@
+//! Golden image tests of the filters: each filter runs on small generated fixtures, and its output is checked
+//! against the one expected, computed by the cpu reference of [`crate::cpu`] or written out in the test, with
+//! [`assert_image_approx_eq`] absorbing the rounding differences of the gpus.
+
+use pollster::FutureExt;
+
+use crate::{
+    blur::box_sizes_for_gaussian,
+    cpu::{self, assert_image_approx_eq},
+    Filters, Image, Operation, Resize, Rgba,
+};
+
+/// The rounding error allowed to filters computing new values, rather than moving pixels around.
+const ROUNDING: u8 = 1;
+
+/// Gradients crossed with a noisy pattern and a varying alpha, with odd dimensions so that the edges of the
+/// workgroups cut through the image.
+fn fixture(width: u32, height: u32) -> Image {
+    Image::from_fn(width, height, |x, y| {
+        let noise = (x * 7919 + y * 104_729) % 61;
+        Rgba::new(
+            (x * 255 / (width - 1).max(1)) as u8,
+            (y * 255 / (height - 1).max(1)) as u8,
+            (noise * 4) as u8,
+            (255 - (x + y) % 5 * 30) as u8,
+        )
+    })
+}
+
+/// Runs `filter` on the gpu and checks its output against `expected`.
+fn assert_filter(
+    filters: &Filters,
+    image: &Image,
+    filter: impl FnOnce(Operation) -> Operation,
+    expected: &Image,
+    tolerance: u8,
+) {
+    let output = filter(image.operation(filters).unwrap())
+        .execute()
+        .block_on();
+    assert_image_approx_eq(expected, &output, tolerance);
+}
+
+#[test]
+fn grayscale() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.grayscale(),
+        &cpu::grayscale(&image),
+        ROUNDING,
+    );
+}
+
+#[test]
+fn inverse() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.inverse(),
+        &cpu::inverse(&image),
+        0,
+    );
+}
+
+#[test]
+fn flips_and_transpose() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+    let transposed = Image::from_fn(13, 19, |x, y| image.pixels[(x * 19 + y) as usize]);
+
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.hflip(),
+        &cpu::hflip(&image),
+        0,
+    );
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.vflip(),
+        &cpu::vflip(&image),
+        0,
+    );
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.transpose(),
+        &transposed,
+        0,
+    );
+}
+
+#[test]
+fn resize_nearest() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    for dimension in [(7, 5), (40, 9), (19, 30)] {
+        assert_filter(
+            &filters,
+            &image,
+            |operation| operation.resize(dimension, Resize::Nearest).unwrap(),
+            &cpu::resize_nearest(&image, dimension).unwrap(),
+            0,
+        );
+    }
+}
+
+#[test]
+fn scale_integer() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(9, 7);
+
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.scale_integer(3).unwrap(),
+        &cpu::resize_nearest(&image, (27, 21)).unwrap(),
+        0,
+    );
+}
+
+#[test]
+fn crop() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.crop((4, 3), (10, 7)).unwrap(),
+        &image.sub_image(4, 3, 10, 7).unwrap(),
+        0,
+    );
+}
+
+#[test]
+fn box_blur() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    for size in [3, 6, 11] {
+        assert_filter(
+            &filters,
+            &image,
+            |operation| operation.box_blur(size),
+            &cpu::box_blur(&image, size),
+            ROUNDING,
+        );
+    }
+}
+
+#[test]
+fn gaussian_blur() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    for sigma in [0.8, 1.5, 3.0] {
+        assert_filter(
+            &filters,
+            &image,
+            |operation| operation.gaussian_blur(sigma),
+            &cpu::gaussian_blur(&image, sigma),
+            ROUNDING,
+        );
+    }
+}
+
+#[test]
+fn fast_gaussian_blur() {
+    let filters = Filters::new().block_on().unwrap();
+    let image = fixture(19, 13);
+
+    let [first, second, third] = box_sizes_for_gaussian(2.0);
+    let expected = cpu::box_blur(&cpu::box_blur(&cpu::box_blur(&image, first), second), third);
+
+    // Each of the three box blurs rounds on its own.
+    assert_filter(
+        &filters,
+        &image,
+        |operation| operation.fast_gaussian_blur(2.0),
+        &expected,
+        3 * ROUNDING,
+    );
+}
+
+#[test]
+fn summary_of_differences() {
+    let expected = Image::new(4, 3, Rgba::new(100, 100, 100, 255));
+    let mut actual = Image::new(4, 3, Rgba::new(101, 100, 100, 255));
+    actual.pixels[1] = Rgba::new(100, 110, 100, 255);
+    actual.pixels[6] = Rgba::new(100, 100, 90, 255);
+
+    let message = std::panic::catch_unwind(|| assert_image_approx_eq(&expected, &actual, 1))
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+    let mismatched = std::panic::catch_unwind(|| {
+        assert_image_approx_eq(&expected, &Image::new(3, 4, Rgba::new(0, 0, 0, 0)), 255)
+    })
+    .unwrap_err()
+    .downcast::<String>()
+    .unwrap();
+
+    assert_eq!(
+        "2 of 12 pixels differ by more than 1, by up to 10, 2.500 on average\n  \
+        at 1,0: expected [100, 100, 100, 255], got [100, 110, 100, 255]\n  \
+        at 2,1: expected [100, 100, 100, 255], got [100, 100, 90, 255]",
+        *message
+    );
+    assert_eq!("Expected an image of 4x3, got 3x4", *mismatched);
+    assert_image_approx_eq(&expected, &actual, 10);
+}
@@ -0,0 +1,223 @@
+use std::f64::consts::PI;
+
+use crate::{Operation, Resize};
+
+/// How [`Operation::perceptual_hash`] sums up an image in 64 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum HashAlgo {
+    /// aHash: whether each pixel of the image shrunk to 8×8 is brighter than their average.
+    Average,
+    /// dHash: whether each pixel of the image shrunk to 9×8 is darker than its right neighbor, which follows the
+    /// gradients of the image and resists changes of brightness and contrast.
+    Difference,
+    /// pHash: whether each of the 8×8 lowest frequencies of the image shrunk to 32×32 is above their median, the
+    /// slowest but the most robust to small edits.
+    Perceptual,
+}
+
+impl HashAlgo {
+    /// The width and height the image is shrunk to before hashing.
+    fn size(self) -> (u32, u32) {
+        match self {
+            HashAlgo::Average => (8, 8),
+            HashAlgo::Difference => (9, 8),
+            HashAlgo::Perceptual => (32, 32),
+        }
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Hashes the current image so that similar images, like the same photo resized or slightly brightened, get
+    /// hashes only a few bits apart, see [`hamming_distance`]. The image is shrunk and turned to grayscale on the
+    /// gpu, and only those few pixels are read back to be hashed.
+    ///
+    /// The hash is computed on a fork of the operation, see [`Operation::fork`], so that it can be continued
+    /// afterwards.
+    pub async fn perceptual_hash(&mut self, algo: HashAlgo) -> u64 {
+        let thumbnail = self
+            .fork()
+            .resize(algo.size(), Resize::Area)
+            .expect("The thumbnail isn't empty, and fits in any texture")
+            .grayscale()
+            .execute()
+            .await;
+        let values = thumbnail
+            .pixels
+            .iter()
+            .map(|pixel| f64::from(pixel.r()))
+            .collect::<Vec<_>>();
+
+        match algo {
+            HashAlgo::Average => {
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                to_bits(values.iter().map(|&value| value > mean))
+            }
+            HashAlgo::Difference => to_bits(
+                values
+                    .chunks_exact(9)
+                    .flat_map(|row| row.windows(2).map(|pair| pair[0] < pair[1])),
+            ),
+            HashAlgo::Perceptual => {
+                let frequencies = low_frequencies(&values, 32);
+                // The first frequency is the average brightness, which would skew the median.
+                let mut sorted = frequencies[1..].to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let median = sorted[sorted.len() / 2];
+                to_bits(frequencies.iter().map(|&frequency| frequency > median))
+            }
+        }
+    }
+}
+
+/// How many bits differ between two hashes of [`Operation::perceptual_hash`]: 0 for the same image, a handful for
+/// variations of it, and around 32 for unrelated images.
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Packs 64 bits, the first one being the most significant.
+fn to_bits(bits: impl Iterator<Item = bool>) -> u64 {
+    bits.take(64)
+        .fold(0, |hash, bit| (hash << 1) | u64::from(bit))
+}
+
+/// The 8×8 lowest frequencies of the discrete cosine transform of `values`, a square of `size` by `size`, row by
+/// row.
+fn low_frequencies(values: &[f64], size: usize) -> Vec<f64> {
+    let cosines = (0..8)
+        .map(|frequency| {
+            (0..size)
+                .map(|index| {
+                    ((2 * index + 1) as f64 * frequency as f64 * PI / (2 * size) as f64).cos()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut frequencies = Vec::with_capacity(64);
+    for vertical in &cosines {
+        for horizontal in &cosines {
+            let sum = values
+                .chunks_exact(size)
+                .zip(vertical)
+                .map(|(row, cosine_y)| {
+                    cosine_y
+                        * row
+                            .iter()
+                            .zip(horizontal)
+                            .map(|(value, cosine_x)| value * cosine_x)
+                            .sum::<f64>()
+                })
+                .sum();
+            frequencies.push(sum);
+        }
+    }
+    frequencies
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::{hamming_distance, to_bits, HashAlgo};
+    use crate::{Filters, Image, Rgba};
+
+    const ALGOS: [HashAlgo; 3] = [
+        HashAlgo::Average,
+        HashAlgo::Difference,
+        HashAlgo::Perceptual,
+    ];
+
+    /// A bright disc on a gradient, with an offset to brighten it.
+    fn landscape(brightness: u8) -> Image {
+        Image::from_fn(120, 90, |x, y| {
+            let (dx, dy) = (x as i32 - 80, y as i32 - 30);
+            let value = if dx * dx + dy * dy < 400 {
+                220
+            } else {
+                (x + y) as u8
+            };
+            Rgba::new(
+                value.saturating_add(brightness),
+                value.saturating_add(brightness),
+                (value / 2).saturating_add(brightness),
+                255,
+            )
+        })
+    }
+
+    /// Stripes, with nothing in common with [`landscape`].
+    fn stripes() -> Image {
+        Image::from_fn(120, 90, |x, y| {
+            let value = if (x / 15 + y / 30) % 2 == 0 { 30 } else { 230 };
+            Rgba::new(value, value, value, 255)
+        })
+    }
+
+    fn hash(filters: &Filters, image: &Image, algo: HashAlgo) -> u64 {
+        image
+            .operation(filters)
+            .unwrap()
+            .perceptual_hash(algo)
+            .block_on()
+    }
+
+    #[test]
+    fn identical_images() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for algo in ALGOS {
+            assert_eq!(
+                hash(&filters, &landscape(0), algo),
+                hash(&filters, &landscape(0), algo),
+                "{algo:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn brightened_is_close_and_unrelated_is_far() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for algo in ALGOS {
+            let original = hash(&filters, &landscape(0), algo);
+            let brightened = hash(&filters, &landscape(12), algo);
+            let unrelated = hash(&filters, &stripes(), algo);
+
+            assert!(
+                hamming_distance(original, brightened) <= 4,
+                "{algo:?}: {original:016x} {brightened:016x}"
+            );
+            assert!(
+                hamming_distance(original, unrelated) >= 16,
+                "{algo:?}: {original:016x} {unrelated:016x}"
+            );
+        }
+    }
+
+    #[test]
+    fn operation_continues_after_hash() {
+        let image = landscape(0);
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut operation = image.operation(&filters).unwrap();
+        operation.perceptual_hash(HashAlgo::Difference).block_on();
+        let inverted = operation.inverse().execute().block_on();
+
+        assert_eq!((120, 90), (inverted.width, inverted.height));
+        assert_eq!(Rgba::new(255, 255, 255, 255), inverted.pixels[0]);
+    }
+
+    #[test]
+    fn bits() {
+        assert_eq!(0b101, to_bits([true, false, true].into_iter()));
+        assert_eq!(u64::MAX, to_bits(std::iter::repeat_n(true, 70)));
+        assert_eq!(0, hamming_distance(42, 42));
+        assert_eq!(64, hamming_distance(0, u64::MAX));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::{check_pixel_bytes, FiltersError, Image, Rgba};
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self([r, g, b, a])
+    }
+
+    pub const fn r(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub const fn g(&self) -> u8 {
+        self.0[1]
+    }
+
+    pub const fn b(&self) -> u8 {
+        self.0[2]
+    }
+
+    pub const fn a(&self) -> u8 {
+        self.0[3]
+    }
+}
+
+impl Image {
+    /// Creates an image of `width` by `height` pixels, all set to `fill`.
+    pub fn new(width: u32, height: u32, fill: Rgba) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width as usize * height as usize],
+        }
+    }
+
+    /// Creates an image of `width` by `height` pixels, calling `pixel` with the coordinates of each of them, row by
+    /// row.
+    pub fn from_fn(width: u32, height: u32, mut pixel: impl FnMut(u32, u32) -> Rgba) -> Self {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| pixel(x, y))
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Creates an image of `width` by `height` pixels from their rgba channels, row by row. The bytes are reused
+    /// as the pixels, without copying them, whenever their allocation allows it.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidPixelBytes`] if `data` isn't `width * height * 4` bytes long.
+    pub fn from_raw(width: u32, height: u32, data: Vec<u8>) -> Result<Self, FiltersError> {
+        check_pixel_bytes((width, height), data.len())?;
+        let pixels = bytemuck::allocation::try_cast_vec(data)
+            .unwrap_or_else(|(_, data)| bytemuck::cast_slice(&data).to_vec());
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// The pixel at `x`, `y`, or `None` if it is outside of the image, or missing from `pixels`.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<&Rgba> {
+        self.index(x, y).and_then(|index| self.pixels.get(index))
+    }
+
+    /// Like [`Image::pixel`], but can change the pixel.
+    pub fn pixel_mut(&mut self, x: u32, y: u32) -> Option<&mut Rgba> {
+        self.index(x, y)
+            .and_then(|index| self.pixels.get_mut(index))
+    }
+
+    /// Sets the pixel at `x`, `y`.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::PixelOutOfBounds`] if the pixel is outside of the image, or missing from `pixels`.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: Rgba) -> Result<(), FiltersError> {
+        let image = (self.width, self.height);
+        let target = self.pixel_mut(x, y).ok_or(FiltersError::PixelOutOfBounds {
+            position: (x, y),
+            image,
+        })?;
+        *target = pixel;
+        Ok(())
+    }
+
+    /// Copies the region of `width` by `height` pixels whose top left corner is at `x`, `y` into a new image, like
+    /// [`crate::Operation::crop`] does on the gpu.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::CropOutOfBounds`] if the region doesn't fit in the image, and
+    /// [`FiltersError::InvalidImageDimensions`] if the image doesn't have as many pixels as its dimensions say.
+    pub fn sub_image(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Image, FiltersError> {
+        self.check_pixel_count()?;
+        if x.saturating_add(width) > self.width || y.saturating_add(height) > self.height {
+            return Err(FiltersError::CropOutOfBounds {
+                origin: (x, y),
+                dimension: (width, height),
+                image: (self.width, self.height),
+            });
+        }
+
+        let columns = x as usize..(x + width) as usize;
+        let pixels = self
+            .rows()
+            .skip(y as usize)
+            .take(height as usize)
+            .flat_map(|row| &row[columns.clone()])
+            .copied()
+            .collect();
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Pastes `other` into the image, its top left corner at `x`, `y`, like to build a sprite sheet filtered in a
+    /// single pass.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::PasteOutOfBounds`] if `other` doesn't fit in the image, and
+    /// [`FiltersError::InvalidImageDimensions`] if either image doesn't have as many pixels as its dimensions say.
+    pub fn copy_from(&mut self, other: &Image, x: u32, y: u32) -> Result<(), FiltersError> {
+        self.check_pixel_count()?;
+        other.check_pixel_count()?;
+        if x.saturating_add(other.width) > self.width
+            || y.saturating_add(other.height) > self.height
+        {
+            return Err(FiltersError::PasteOutOfBounds {
+                position: (x, y),
+                dimension: (other.width, other.height),
+                image: (self.width, self.height),
+            });
+        }
+
+        for (row, source) in other.rows().enumerate() {
+            let start = (y as usize + row) * self.width as usize + x as usize;
+            self.pixels[start..start + source.len()].copy_from_slice(source);
+        }
+        Ok(())
+    }
+
+    /// The rows of the image, from top to bottom. Only the complete rows within `height` are returned when `pixels`
+    /// doesn't match the dimensions.
+    pub fn rows(&self) -> impl Iterator<Item = &[Rgba]> {
+        // An image without width has no pixels per row to split them in.
+        let rows = if self.width == 0 {
+            0
+        } else {
+            self.height as usize
+        };
+        self.pixels
+            .chunks_exact(self.width.max(1) as usize)
+            .take(rows)
+    }
+
+    /// Checks that the image has as many pixels as its dimensions say.
+    pub(crate) fn check_pixel_count(&self) -> Result<(), FiltersError> {
+        let expected = self.width as usize * self.height as usize;
+        if self.pixels.len() != expected {
+            return Err(FiltersError::InvalidImageDimensions {
+                expected,
+                actual: self.pixels.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y as usize * self.width as usize + x as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FiltersError, Image, Rgba};
+
+    fn test_image() -> Image {
+        Image::from_fn(3, 2, |x, y| Rgba::new(x as u8, y as u8, 10, 255))
+    }
+
+    #[test]
+    fn rgba_channels() {
+        let pixel = Rgba::new(1, 2, 3, 4);
+
+        assert_eq!(Rgba([1, 2, 3, 4]), pixel);
+        assert_eq!((1, 2, 3, 4), (pixel.r(), pixel.g(), pixel.b(), pixel.a()));
+    }
+
+    #[test]
+    fn new_fills_pixels() {
+        let image = Image::new(3, 2, Rgba::new(1, 2, 3, 4));
+
+        assert_eq!((3, 2), (image.width, image.height));
+        assert_eq!(vec![Rgba::new(1, 2, 3, 4); 6], image.pixels);
+    }
+
+    #[test]
+    fn from_fn_goes_row_by_row() {
+        let image = test_image();
+
+        assert_eq!(
+            vec![
+                Rgba::new(0, 0, 10, 255),
+                Rgba::new(1, 0, 10, 255),
+                Rgba::new(2, 0, 10, 255),
+                Rgba::new(0, 1, 10, 255),
+                Rgba::new(1, 1, 10, 255),
+                Rgba::new(2, 1, 10, 255),
+            ],
+            image.pixels
+        );
+    }
+
+    #[test]
+    fn from_raw_casts_bytes() {
+        let image = Image::from_raw(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+        assert_eq!(
+            vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)],
+            image.pixels
+        );
+        assert_eq!(
+            image,
+            Image::from_raw(2, 1, image.as_raw().to_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn from_raw_odd_capacity() {
+        let mut data = Vec::with_capacity(9);
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let image = Image::from_raw(1, 2, data).unwrap();
+
+        assert_eq!(
+            vec![Rgba::new(1, 2, 3, 4), Rgba::new(5, 6, 7, 8)],
+            image.pixels
+        );
+    }
+
+    #[test]
+    fn from_raw_wrong_length() {
+        let result = Image::from_raw(2, 2, vec![0; 17]);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidPixelBytes {
+                expected: 16,
+                actual: 17
+            })
+        ));
+    }
+
+    #[test]
+    fn pixel_in_and_out_of_bounds() {
+        let image = test_image();
+
+        assert_eq!(Some(&Rgba::new(2, 1, 10, 255)), image.pixel(2, 1));
+        assert_eq!(None, image.pixel(3, 0));
+        assert_eq!(None, image.pixel(0, 2));
+    }
+
+    #[test]
+    fn pixel_missing_from_pixels() {
+        let mut image = test_image();
+        image.pixels.truncate(4);
+
+        assert_eq!(Some(&Rgba::new(0, 1, 10, 255)), image.pixel(0, 1));
+        assert_eq!(None, image.pixel(1, 1));
+        assert_eq!(None, image.pixel_mut(2, 1));
+    }
+
+    #[test]
+    fn pixel_mut_changes_pixel() {
+        let mut image = test_image();
+
+        image.pixel_mut(1, 0).unwrap().0[2] = 99;
+
+        assert_eq!(Some(&Rgba::new(1, 0, 99, 255)), image.pixel(1, 0));
+    }
+
+    #[test]
+    fn set_pixel_in_and_out_of_bounds() {
+        let mut image = test_image();
+
+        image.set_pixel(0, 1, Rgba::new(7, 7, 7, 7)).unwrap();
+        let result = image.set_pixel(3, 1, Rgba::new(7, 7, 7, 7));
+
+        assert_eq!(Rgba::new(7, 7, 7, 7), image.pixels[3]);
+        assert!(matches!(
+            result,
+            Err(FiltersError::PixelOutOfBounds {
+                position: (3, 1),
+                image: (3, 2)
+            })
+        ));
+    }
+
+    #[test]
+    fn sub_image_and_back() {
+        let gradient = || Image::from_fn(5, 4, |x, y| Rgba::new(x as u8, y as u8, 10, 255));
+        let mut image = gradient();
+
+        let region = image.sub_image(1, 2, 3, 2).unwrap();
+        assert_eq!((3, 2), (region.width, region.height));
+        assert_eq!(Some(&Rgba::new(1, 2, 10, 255)), region.pixel(0, 0));
+        assert_eq!(Some(&Rgba::new(3, 3, 10, 255)), region.pixel(2, 1));
+
+        image
+            .copy_from(&Image::new(3, 2, Rgba::new(0, 0, 0, 0)), 1, 2)
+            .unwrap();
+        assert_eq!(Some(&Rgba::new(0, 0, 0, 0)), image.pixel(3, 3));
+        assert_eq!(Some(&Rgba::new(4, 3, 10, 255)), image.pixel(4, 3));
+        image.copy_from(&region, 1, 2).unwrap();
+        assert_eq!(gradient(), image);
+    }
+
+    #[test]
+    fn sub_image_and_copy_out_of_bounds() {
+        let mut image = test_image();
+
+        assert!(matches!(
+            image.sub_image(1, 0, 3, 1),
+            Err(FiltersError::CropOutOfBounds {
+                origin: (1, 0),
+                dimension: (3, 1),
+                image: (3, 2)
+            })
+        ));
+        assert!(matches!(
+            image.copy_from(&test_image(), 0, 1),
+            Err(FiltersError::PasteOutOfBounds {
+                position: (0, 1),
+                dimension: (3, 2),
+                image: (3, 2)
+            })
+        ));
+        assert!(matches!(
+            image.sub_image(u32::MAX, 0, 2, 1),
+            Err(FiltersError::CropOutOfBounds { .. })
+        ));
+
+        image.pixels.pop();
+        assert!(matches!(
+            image.sub_image(0, 0, 1, 1),
+            Err(FiltersError::InvalidImageDimensions {
+                expected: 6,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn rows_split_pixels() {
+        let image = test_image();
+
+        let rows: Vec<&[Rgba]> = image.rows().collect();
+
+        assert_eq!(2, rows.len());
+        assert_eq!(&image.pixels[3..], rows[1]);
+        assert_eq!(0, Image::new(0, 5, Rgba::new(0, 0, 0, 0)).rows().count());
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use ::image::{DynamicImage, RgbaImage};
+
+use crate::{FiltersError, Image, Rgba};
+
+impl TryFrom<DynamicImage> for Image {
+    type Error = FiltersError;
+
+    /// Converts an image of the `image` crate, whatever its color type, to 8-bit rgba.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::UnsupportedSize`] if the image has no pixels, as it couldn't be filtered.
+    fn try_from(image: DynamicImage) -> Result<Self, Self::Error> {
+        let (width, height) = (image.width(), image.height());
+        if width == 0 || height == 0 {
+            return Err(FiltersError::UnsupportedSize { width, height });
+        }
+
+        Image::from_raw(width, height, image.into_rgba8().into_raw())
+    }
+}
+
+impl From<&Image> for RgbaImage {
+    /// Copies the pixels into an image of the `image` crate. Missing pixels are transparent black, extra ones are
+    /// dropped.
+    fn from(image: &Image) -> Self {
+        let mut raw = image.as_raw().to_vec();
+        raw.resize(image.width as usize * image.height as usize * 4, 0);
+        RgbaImage::from_raw(image.width, image.height, raw)
+            .expect("The buffer was resized to the dimensions")
+    }
+}
+
+impl Image {
+    /// Opens the image at `path`, in any format the `image` crate supports, converted to 8-bit rgba.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::ImageIo`] if the file can't be read or decoded, and [`FiltersError::UnsupportedSize`] if the
+    /// image has no pixels.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FiltersError> {
+        ::image::open(path)?.try_into()
+    }
+
+    /// Saves the image to `path`, in the format matching its extension.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::ImageIo`] if the extension is unknown, or the file can't be encoded or written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FiltersError> {
+        RgbaImage::from(self).save(path)?;
+        Ok(())
+    }
+}
+
+impl From<Rgba> for ::image::Rgba<u8> {
+    fn from(Rgba(channels): Rgba) -> Self {
+        ::image::Rgba(channels)
+    }
+}
+
+impl From<::image::Rgba<u8>> for Rgba {
+    fn from(::image::Rgba(channels): ::image::Rgba<u8>) -> Self {
+        Rgba(channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::image::{DynamicImage, RgbImage, RgbaImage};
+
+    use crate::{FiltersError, Image, Rgba};
+
+    fn test_image() -> Image {
+        Image::from_fn(5, 3, |x, y| {
+            Rgba::new((x * 50) as u8, (y * 100) as u8, 7, (255 - x * 40) as u8)
+        })
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = test_image();
+        let path = std::env::temp_dir().join("filters-image-interop-round-trip.png");
+
+        image.save(&path).unwrap();
+        let opened = Image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image, opened);
+    }
+
+    #[test]
+    fn dynamic_image_round_trip() {
+        let image = test_image();
+
+        let converted = Image::try_from(DynamicImage::ImageRgba8(RgbaImage::from(&image))).unwrap();
+
+        assert_eq!(image, converted);
+    }
+
+    #[test]
+    fn rgb_source_is_opaque() {
+        let rgb = RgbImage::from_fn(2, 1, |x, _| ::image::Rgb([x as u8 * 10, 20, 30]));
+
+        let converted = Image::try_from(DynamicImage::ImageRgb8(rgb)).unwrap();
+
+        assert_eq!(
+            vec![Rgba::new(0, 20, 30, 255), Rgba::new(10, 20, 30, 255)],
+            converted.pixels
+        );
+    }
+
+    #[test]
+    fn empty_dynamic_image() {
+        let empty = DynamicImage::ImageRgba8(RgbaImage::new(0, 4));
+
+        assert!(matches!(
+            Image::try_from(empty),
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_pixels_are_transparent() {
+        let mut image = test_image();
+        image.pixels.truncate(14);
+
+        let converted = RgbaImage::from(&image);
+
+        assert_eq!((5, 3), converted.dimensions());
+        assert_eq!(&::image::Rgba([0, 0, 0, 0]), converted.get_pixel(4, 2));
+        assert_eq!(
+            &::image::Rgba::from(image.pixels[13]),
+            converted.get_pixel(3, 2)
+        );
+    }
+
+    #[test]
+    fn unknown_extension() {
+        let path = std::env::temp_dir().join("filters-image-interop.unknown");
+
+        assert!(matches!(
+            test_image().save(path),
+            Err(FiltersError::ImageIo(_))
+        ));
+    }
+}
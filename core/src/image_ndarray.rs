@@ -0,0 +1,95 @@
+use ndarray::{Array3, ArrayView3};
+
+use crate::{FiltersError, Image};
+
+impl Image {
+    /// Copies the pixels into an array of `height` × `width` × 4 channels. Missing pixels are transparent black,
+    /// extra ones are dropped.
+    pub fn to_array(&self) -> Array3<u8> {
+        let shape = (self.height as usize, self.width as usize, 4);
+        let mut raw = self.as_raw().to_vec();
+        raw.resize(shape.0 * shape.1 * 4, 0);
+        Array3::from_shape_vec(shape, raw).expect("The buffer was resized to the shape")
+    }
+
+    /// Creates an image from an array of `height` × `width` × 4 channels. Views that aren't laid out row by row,
+    /// like a slice of the channels of a bigger array, are copied element by element.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidArrayShape`] if the array doesn't have 4 channels, or is too big for an image.
+    pub fn try_from_array(array: ArrayView3<u8>) -> Result<Self, FiltersError> {
+        let (height, width, channels) = array.dim();
+        let invalid_shape = || FiltersError::InvalidArrayShape([height, width, channels]);
+        if channels != 4 {
+            return Err(invalid_shape());
+        }
+        let width = u32::try_from(width).map_err(|_| invalid_shape())?;
+        let height = u32::try_from(height).map_err(|_| invalid_shape())?;
+
+        let data = match array.as_slice() {
+            Some(data) => data.to_vec(),
+            None => array.iter().copied().collect(),
+        };
+        Image::from_raw(width, height, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{s, Array3};
+
+    use crate::{FiltersError, Image, Rgba};
+
+    fn test_image() -> Image {
+        Image::from_fn(3, 2, |x, y| Rgba::new(x as u8, y as u8, 7, 255))
+    }
+
+    #[test]
+    fn array_round_trip() {
+        let image = test_image();
+
+        let array = image.to_array();
+
+        assert_eq!(&[2, 3, 4], array.shape());
+        assert_eq!([2, 1, 7, 255], [0, 1, 2, 3].map(|c| array[[1, 2, c]]));
+        assert_eq!(image, Image::try_from_array(array.view()).unwrap());
+    }
+
+    #[test]
+    fn channel_sliced_view() {
+        // Two extra channels on each side of the rgba ones.
+        let array = Array3::from_shape_fn((2, 3, 8), |(y, x, c)| match c {
+            2 => x as u8,
+            3 => y as u8,
+            4 => 7,
+            5 => 255,
+            _ => 99,
+        });
+        let view = array.slice(s![.., .., 2..6]);
+        assert!(view.as_slice().is_none());
+
+        assert_eq!(test_image(), Image::try_from_array(view).unwrap());
+    }
+
+    #[test]
+    fn transposed_view() {
+        let image = test_image();
+        let transposed = image.to_array().permuted_axes([1, 0, 2]);
+
+        let converted = Image::try_from_array(transposed.view()).unwrap();
+
+        assert_eq!((2, 3), (converted.width, converted.height));
+        assert_eq!(image.pixel(2, 1), converted.pixel(1, 2));
+    }
+
+    #[test]
+    fn wrong_channel_count() {
+        let array = Array3::<u8>::zeros((2, 3, 3));
+
+        assert!(matches!(
+            Image::try_from_array(array.view()),
+            Err(FiltersError::InvalidArrayShape([2, 3, 3]))
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt::Formatter;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{FiltersError, Image};
+
+/// Serializes the pixels as bytes, or as a base64 string for human readable formats like JSON, instead of a list of
+/// channels.
+impl Serialize for Image {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut image = serializer.serialize_struct("Image", 3)?;
+        image.serialize_field("width", &self.width)?;
+        image.serialize_field("height", &self.height)?;
+        image.serialize_field("pixels", &PixelBytes(bytemuck::cast_slice(&self.pixels)))?;
+        image.end()
+    }
+}
+
+struct PixelBytes<'a>(&'a [u8]);
+
+impl Serialize for PixelBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&BASE64.encode(self.0))
+        } else {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+}
+
+/// An [`Image`] as deserialized, before checking that its pixels match its dimensions.
+#[derive(Deserialize)]
+#[serde(rename = "Image", deny_unknown_fields)]
+pub(crate) struct SerializedImage {
+    width: u32,
+    height: u32,
+    #[serde(deserialize_with = "deserialize_pixels")]
+    pixels: Vec<u8>,
+}
+
+impl TryFrom<SerializedImage> for Image {
+    type Error = FiltersError;
+
+    fn try_from(image: SerializedImage) -> Result<Self, Self::Error> {
+        Image::from_raw(image.width, image.height, image.pixels)
+    }
+}
+
+fn deserialize_pixels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(PixelsVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(PixelsVisitor)
+    }
+}
+
+struct PixelsVisitor;
+
+impl<'de> Visitor<'de> for PixelsVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("pixel bytes, or a base64 string of them")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        BASE64
+            .decode(value)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &"a base64 string"))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(value)
+    }
+
+    /// Some binary formats store bytes as a sequence.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    use crate::{Image, Rgba};
+
+    fn test_image() -> Image {
+        Image::from_fn(3, 2, |x, y| Rgba::new(x as u8, y as u8, 200, 255))
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let image = test_image();
+
+        let json = serde_json::to_string(&image).unwrap();
+        let parsed: Image = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            format!(
+                r#"{{"width":3,"height":2,"pixels":"{}"}}"#,
+                BASE64.encode(image.as_raw())
+            ),
+            json
+        );
+        assert_eq!(image, parsed);
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let image = test_image();
+
+        let bytes = bincode::serialize(&image).unwrap();
+        let parsed: Image = bincode::deserialize(&bytes).unwrap();
+
+        // The width, the height, the length of the pixels and the pixels themselves.
+        assert_eq!(4 + 4 + 8 + 24, bytes.len());
+        assert_eq!(image, parsed);
+    }
+
+    #[test]
+    fn bincode_corrupted_length() {
+        let mut bytes = bincode::serialize(&test_image()).unwrap();
+        // Drops the last pixel and fixes the length of the pixels accordingly.
+        bytes.truncate(bytes.len() - 4);
+        bytes[8..16].copy_from_slice(&20u64.to_le_bytes());
+
+        assert!(bincode::deserialize::<Image>(&bytes).is_err());
+    }
+
+    #[test]
+    fn rgba_json_round_trip() {
+        let json = serde_json::to_string(&Rgba::new(1, 2, 3, 4)).unwrap();
+
+        assert_eq!("[1,2,3,4]", json);
+        assert_eq!(Rgba::new(1, 2, 3, 4), serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn corrupted_length() {
+        let image = test_image();
+        let raw = &image.as_raw()[..23];
+        let json = format!(
+            r#"{{"width":3,"height":2,"pixels":"{}"}}"#,
+            BASE64.encode(raw)
+        );
+
+        let error = serde_json::from_str::<Image>(&json).unwrap_err();
+
+        assert_eq!(
+            "The image should contain 24 bytes of pixels according to its dimensions, but contains 23",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_base64() {
+        let json = r#"{"width":1,"height":1,"pixels":"AAAA*A=="}"#;
+
+        assert!(serde_json::from_str::<Image>(json).is_err());
+    }
+}
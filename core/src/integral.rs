@@ -0,0 +1,257 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    ComputePipeline, TextureFormat, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    read_mapped_buffer_into, wait_for_mapping, Operation, PixelFormat,
+};
+
+const INTEGRAL_ROWS_SHADER: &str = include_str!("shaders/integral_rows.wgsl");
+const INTEGRAL_COLUMNS_SHADER: &str = include_str!("shaders/integral_columns.wgsl");
+
+/// How many rows, or columns, a workgroup of the integral image shaders sums.
+const LINES_PER_WORKGROUP: u32 = 64;
+
+/// The summed-area table of an image, see [`Operation::integral_image`]: the sum of the channels of any rectangle
+/// of the image, in 8-bit levels, in constant time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegralImage {
+    pub width: u32,
+    pub height: u32,
+    /// The sums of each channel over the rectangle from the top left corner to each pixel, included, row by row.
+    /// They wrap around past `u32::MAX`, which [`IntegralImage::sum_of_rect`] cancels out.
+    pub sums: Vec<[u32; 4]>,
+}
+
+impl IntegralImage {
+    /// The sum of each channel over the rectangle of `width` by `height` pixels at `x`, `y`, or `None` if the
+    /// rectangle doesn't fit in the image. Sums are exact for rectangles of up to 16,843,009 pixels, 4104×4104,
+    /// whose sums fit in a `u32` even when every pixel is at 255.
+    pub fn sum_of_rect(&self, x: u32, y: u32, width: u32, height: u32) -> Option<[u32; 4]> {
+        let fits = x.checked_add(width)? <= self.width && y.checked_add(height)? <= self.height;
+        if !fits {
+            return None;
+        }
+        if width == 0 || height == 0 {
+            return Some([0; 4]);
+        }
+
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        let above = y.checked_sub(1);
+        let left = x.checked_sub(1);
+        let corner = |x: Option<u32>, y: Option<u32>| match (x, y) {
+            (Some(x), Some(y)) => self.sums[y as usize * self.width as usize + x as usize],
+            _ => [0; 4],
+        };
+        let (total, top, side, overlap) = (
+            corner(Some(right), Some(bottom)),
+            corner(Some(right), above),
+            corner(left, Some(bottom)),
+            corner(left, above),
+        );
+
+        Some(std::array::from_fn(|channel| {
+            total[channel]
+                .wrapping_sub(top[channel])
+                .wrapping_sub(side[channel])
+                .wrapping_add(overlap[channel])
+        }))
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Computes the summed-area table of the current image on the gpu, summing the rows then the columns, and reads
+    /// it back, like to average any rectangle of the image in constant time for box filters or adaptive thresholds.
+    ///
+    /// The values are the 8-bit levels of the channels, from 0 to 255, HDR values being clamped to 255. Like for
+    /// the filters, they are the decoded linear values after [`Operation::assume_srgb`]. Single channel images have
+    /// their value in red, green and blue, with an opaque alpha.
+    ///
+    /// The passes recorded so far are submitted, and the operation can be continued afterwards.
+    pub async fn integral_image(&mut self) -> IntegralImage {
+        let (width, height) = self.dimensions();
+        let mut sums_pool = TexturePool::new(TextureFormat::Rgba32Uint);
+        let row_sums = sums_pool.take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let sums = sums_pool.take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Integral image settings"),
+            contents: bytemuck::bytes_of(&u32::from(self.format == PixelFormat::Luma)),
+            usage: BufferUsages::UNIFORM,
+        });
+        let rows = self.pipeline("integral rows", INTEGRAL_ROWS_SHADER, Bindings::Derived);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Integral rows bind group"),
+            layout: &rows.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &row_sums.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+        self.encode_scan_pass("integral rows", &rows, &bind_group, height);
+
+        let columns = self.pipeline(
+            "integral columns",
+            INTEGRAL_COLUMNS_SHADER,
+            Bindings::Derived,
+        );
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Integral columns bind group"),
+            layout: &columns.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &row_sums.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &sums.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+        self.encode_scan_pass("integral columns", &columns, &bind_group, width);
+
+        let buffer = encode_texture_to_buffer::<[u32; 4]>(
+            self.device,
+            &mut self.encoder,
+            width,
+            height,
+            &sums,
+        );
+        self.submit_recorded();
+        wait_for_mapping(self.device, &buffer).await;
+        let mut sums = Vec::new();
+        read_mapped_buffer_into(width, height, &buffer, &mut sums);
+
+        IntegralImage {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    /// Records a pass of one of the integral image shaders, with an invocation for each of the `lines`.
+    fn encode_scan_pass(
+        &mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        bind_group: &wgpu::BindGroup,
+        lines: u32,
+    ) {
+        let pass = self.begin_pass(name);
+        {
+            let mut compute_pass = self
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor { label: Some(name) });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(lines.div_ceil(LINES_PER_WORKGROUP), 1, 1);
+        }
+        self.end_pass(pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, ImageLuma, Rgba};
+
+    /// A xorshift generator, so that the test is reproducible.
+    fn random(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    #[test]
+    fn random_rectangles() {
+        let (width, height) = (93, 71);
+        let mut state = 0x1234_5678;
+        let image = Image::from_fn(width, height, |_, _| {
+            let [r, g, b, a] = random(&mut state).to_le_bytes();
+            Rgba::new(r, g, b, a)
+        });
+        let filters = Filters::new().block_on().unwrap();
+
+        let integral = image
+            .operation(&filters)
+            .unwrap()
+            .integral_image()
+            .block_on();
+
+        for _ in 0..200 {
+            let (x, y) = (random(&mut state) % width, random(&mut state) % height);
+            let w = random(&mut state) % (width - x + 1);
+            let h = random(&mut state) % (height - y + 1);
+            let mut expected = [0u32; 4];
+            for row in y..y + h {
+                for column in x..x + w {
+                    let pixel = image.pixels[(row * width + column) as usize];
+                    for (sum, channel) in expected.iter_mut().zip(pixel.0) {
+                        *sum += u32::from(channel);
+                    }
+                }
+            }
+            assert_eq!(
+                Some(expected),
+                integral.sum_of_rect(x, y, w, h),
+                "{w}x{h} at {x},{y}"
+            );
+        }
+
+        let total = image
+            .pixels
+            .iter()
+            .map(|pixel| u32::from(pixel.r()))
+            .sum::<u32>();
+        assert_eq!(total, integral.sum_of_rect(0, 0, width, height).unwrap()[0]);
+        assert_eq!(None, integral.sum_of_rect(90, 0, 4, 1));
+        assert_eq!(None, integral.sum_of_rect(0, 0, 1, u32::MAX));
+    }
+
+    #[test]
+    fn luma_channels() {
+        let luma = ImageLuma {
+            width: 3,
+            height: 2,
+            pixels: vec![10, 20, 30, 40, 50, 60],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let integral = luma
+            .operation(&filters)
+            .unwrap()
+            .integral_image()
+            .block_on();
+
+        assert_eq!(
+            Some([160, 160, 160, 1020]),
+            integral.sum_of_rect(1, 0, 2, 2)
+        );
+    }
+}
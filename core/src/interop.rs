@@ -0,0 +1,197 @@
+use wgpu::{Extent3d, Texture, TextureDescriptor, TextureFormat, TextureUsages};
+
+use crate::{check_texture_size, Filters, FiltersError, Operation, PixelFormat};
+
+impl<'a> Operation<'a> {
+    /// Starts an operation from a texture already living on the gpu, like a frame rendered by an engine,
+    /// without any round trip through the cpu. The texture must belong to the device of `filters`.
+    ///
+    /// wgpu doesn't allow querying a texture for its format or usages, so the descriptor the texture was
+    /// created with is needed as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - The filters owning the device the texture was created on.
+    /// * `texture` - The texture to filter, using the Rgba8Unorm format.
+    /// * `descriptor` - The descriptor of the texture, whose usages must include
+    ///   `TEXTURE_BINDING` and `COPY_SRC`.
+    pub fn from_texture(
+        filters: &'a Filters,
+        texture: Texture,
+        descriptor: &TextureDescriptor,
+    ) -> Result<Self, FiltersError> {
+        if descriptor.format != TextureFormat::Rgba8Unorm {
+            return Err(FiltersError::UnsupportedTextureFormat(descriptor.format));
+        }
+        let required = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC;
+        if !descriptor.usage.contains(required) {
+            return Err(FiltersError::MissingTextureUsages(
+                required - descriptor.usage,
+            ));
+        }
+        let (width, height) = (descriptor.size.width, descriptor.size.height);
+        if width == 0 || height == 0 || descriptor.size.depth_or_array_layers != 1 {
+            return Err(FiltersError::UnsupportedSize { width, height });
+        }
+        check_texture_size(&filters.device, (width, height))?;
+
+        let texture_size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        Ok(Self::with_texture(
+            filters,
+            texture,
+            texture_size,
+            descriptor.usage,
+            PixelFormat::Rgba8,
+        ))
+    }
+
+    /// Finishes the operation while keeping the result on the gpu, skipping the readback of [`Operation::execute`].
+    /// All the recorded passes are submitted before returning.
+    /// The returned texture uses the Rgba8Unorm format and has at least the `TEXTURE_BINDING` and `COPY_SRC`
+    /// usages, so it can be sampled in a render pass or fed to [`Operation::from_texture`].
+    pub fn into_texture(mut self) -> (Texture, Extent3d) {
+        self.convert_to_rgba8();
+        self.submit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+    use wgpu::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages};
+
+    use crate::{Filters, FiltersError, Image, Operation, Rgba};
+
+    fn descriptor(format: TextureFormat, usage: TextureUsages) -> TextureDescriptor<'static> {
+        TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 2,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage,
+        }
+    }
+
+    #[test]
+    fn from_texture_matches_image_operation() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([255, 0, 0, 255]), Rgba([10, 200, 30, 128])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let descriptor = descriptor(
+            TextureFormat::Rgba8Unorm,
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+        );
+        let texture = filters.device.create_texture(&descriptor);
+        filters.queue.write_texture(
+            texture.as_image_copy(),
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * image.width),
+                rows_per_image: None,
+            },
+            descriptor.size,
+        );
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+        let output = Operation::from_texture(&filters, texture, &descriptor)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn from_texture_wrong_format() {
+        let filters = Filters::new().block_on().unwrap();
+        let descriptor = descriptor(
+            TextureFormat::Bgra8Unorm,
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+        );
+        let texture = filters.device.create_texture(&descriptor);
+
+        let result = Operation::from_texture(&filters, texture, &descriptor);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::UnsupportedTextureFormat(
+                TextureFormat::Bgra8Unorm
+            ))
+        ));
+    }
+
+    #[test]
+    fn from_texture_missing_usages() {
+        let filters = Filters::new().block_on().unwrap();
+        let descriptor = descriptor(TextureFormat::Rgba8Unorm, TextureUsages::COPY_SRC);
+        let texture = filters.device.create_texture(&descriptor);
+
+        let result = Operation::from_texture(&filters, texture, &descriptor);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::MissingTextureUsages(usages)) if usages == TextureUsages::TEXTURE_BINDING
+        ));
+    }
+
+    #[test]
+    fn into_texture_round_trip() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([0, 0, 0, 255]),
+                Rgba([255, 255, 255, 255]),
+                Rgba([10, 20, 30, 255]),
+                Rgba([40, 50, 60, 128]),
+                Rgba([70, 80, 90, 0]),
+                Rgba([100, 110, 120, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .hflip()
+            .execute()
+            .block_on();
+        let (texture, size) = image.operation(&filters).unwrap().inverse().into_texture();
+        let descriptor = TextureDescriptor {
+            size,
+            ..descriptor(
+                TextureFormat::Rgba8Unorm,
+                TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+            )
+        };
+        let output = Operation::from_texture(&filters, texture, &descriptor)
+            .unwrap()
+            .hflip()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+}
@@ -1,24 +1,126 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex, PoisonError,
+};
+
+use bytemuck::Pod;
 use wgpu::{
-    AddressMode, Backends, BindGroupDescriptor, BindGroupEntry, BindingResource, BufferDescriptor,
-    BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
-    Device, Extent3d, FilterMode, Instance, PowerPreference, Queue, ShaderModuleDescriptor,
-    ShaderSource, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureViewDescriptor,
+    BindGroupDescriptor, BindGroupEntry, BindingResource, Buffer, BufferAsyncError,
+    BufferDescriptor, BufferUsages, CommandEncoder, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipeline, Device, Extent3d, Features, Queue, Texture,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
 };
 
+mod adjust;
+mod atlas;
+#[cfg(not(target_arch = "wasm32"))]
+mod batch;
 mod blur;
+mod cache;
+mod chain;
+mod channels;
+mod chroma;
+mod clarity;
+mod color;
+mod color_space;
+mod components;
+mod composite;
+#[cfg(any(test, feature = "cpu-reference"))]
+pub mod cpu;
+mod crop;
+mod custom;
+mod dehaze;
+mod diff;
+mod distance;
+mod error;
+mod fork;
+mod format;
+#[cfg(not(target_arch = "wasm32"))]
+mod frame;
+mod fxaa;
+mod generate;
+#[cfg(test)]
+mod golden;
+mod hash;
+mod image;
+#[cfg(feature = "image-interop")]
+mod image_interop;
+#[cfg(feature = "ndarray")]
+mod image_ndarray;
+#[cfg(feature = "serde")]
+mod image_serde;
+mod integral;
+mod interop;
+mod luma;
+mod lut;
+mod mask;
+mod montage;
+mod nonblocking;
+mod options;
+mod parse;
+mod pixel_art;
+mod pool;
+mod profiling;
+mod progress;
+mod pyramid;
+mod resize;
+mod seam;
+mod shadows;
+mod sharpen;
+mod skin;
+mod split_tone;
+mod statistics;
+mod tiled;
+mod tonemap;
+mod workgroup;
+
+pub use blur::Kernel;
+use cache::{Bindings, PipelineCache};
+pub use chain::{FilterChain, FilterStep};
+pub use channels::ChannelMask;
+pub use color_space::ColorSpace;
+pub use components::{Component, LabeledImage};
+pub use diff::ImageDiff;
+pub use error::FiltersError;
+pub use format::{Image16, ImageF32, PixelFormat, Rgba16};
+#[cfg(not(target_arch = "wasm32"))]
+pub use frame::FrameProcessor;
+pub use hash::{hamming_distance, HashAlgo};
+pub use integral::IntegralImage;
+pub use luma::ImageLuma;
+pub use lut::{Lut3d, LutError};
+pub use montage::MontageLayout;
+pub use options::{AvailableAdapter, FiltersOptions};
+pub use parse::{ParseError, ParseErrorKind};
+use pool::{TexturePool, COPY_TEXTURE_USAGES, STORAGE_TEXTURE_USAGES};
+pub use profiling::FilterTiming;
+use profiling::Profiler;
+pub use progress::{BatchError, BatchProgress, CancellationToken};
+pub use pyramid::ImagePyramid;
+pub use resize::{AddressMode, Resize, ResizeOptions};
+pub use statistics::ImageStats;
+pub use tonemap::ToneMapOperator;
+pub use wgpu::{AdapterInfo, Backend, Backends, DeviceType, Limits, PowerPreference};
+pub use workgroup::WorkgroupConfig;
 
 const INVERSE_SHADER: &str = include_str!("shaders/inverse.wgsl");
 const GRAYSCALE_SHADER: &str = include_str!("shaders/grayscale.wgsl");
 const HFLIP_SHADER: &str = include_str!("shaders/hflip.wgsl");
 const VFLIP_SHADER: &str = include_str!("shaders/vflip.wgsl");
-const RESIZE_SHADER: &str = include_str!("shaders/resize.wgsl");
+const TRANSPOSE_SHADER: &str = include_str!("shaders/transpose.wgsl");
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod, PartialEq, Eq)]
-pub struct Rgba([u8; 4]);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rgba(pub [u8; 4]);
 
+/// With the `serde` feature, the pixels are serialized as bytes, or as base64 in human readable formats.
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "image_serde::SerializedImage")
+)]
 pub struct Image {
     pub width: u32,
     pub height: u32,
@@ -26,8 +128,8 @@ pub struct Image {
 }
 
 impl Image {
-    pub fn operation<'a>(&self, filters: &'a Filters) -> Operation<'a> {
-        Operation::new(self, &filters.device, &filters.queue)
+    pub fn operation<'a>(&self, filters: &'a Filters) -> Result<Operation<'a>, FiltersError> {
+        Operation::new(self, filters)
     }
 
     pub fn as_raw(&self) -> &[u8] {
@@ -51,80 +153,192 @@ impl PartialEq for Image {
     }
 }
 
+/// The gpu device along with everything kept between operations. It is `Send` and `Sync`, so a single instance
+/// can be shared in an `Arc` between threads, each building its own [`Operation`].
 pub struct Filters {
-    device: Device,
-    queue: Queue,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    pipelines: PipelineCache,
+    submissions: AtomicUsize,
+    /// The buffer used by [`Operation::execute_into`], kept across operations and grown when needed.
+    readback_buffer: Mutex<Option<Buffer>>,
+    /// Whether the operations time their passes, see [`Filters::with_profiling`].
+    profiling: bool,
 }
 
 impl Filters {
-    pub async fn new() -> Self {
-        let instance = Instance::new(Backends::all());
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptionsBase {
-                power_preference: PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: None,
-            })
-            .await
-            .unwrap();
-        let (device, queue) = adapter
-            .request_device(&Default::default(), None)
-            .await
-            .unwrap();
+    pub async fn new() -> Result<Self, FiltersError> {
+        Self::with_options(FiltersOptions::default()).await
+    }
+
+    /// Creates the filters on a device the application already has, like the one of a game engine,
+    /// instead of requesting a new one.
+    ///
+    /// The device is only borrowed while filters run: reading an image back with [`Operation::execute`]
+    /// calls `device.poll(Maintain::Wait)`, which is safe even if the application polls the device too.
+    /// On wasm32, the device is never polled, as the browser takes care of it.
+    ///
+    /// Single channel operations work on R8Unorm textures if the device was created with
+    /// `Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`, whose adapter must then support writing them from a
+    /// compute shader, and on Rgba8Unorm textures otherwise.
+    ///
+    /// Filters with small settings, like brightness, pass them as push constants if the device was created with
+    /// `Features::PUSH_CONSTANTS` and a big enough `max_push_constant_size`, and in a uniform buffer otherwise.
+    /// Don't enable push constants on the gl backend, which reads them through misaligned pointers.
+    pub fn from_device(device: Arc<Device>, queue: Arc<Queue>) -> Self {
+        let single_channel_storage = device
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let max_push_constant_size = if device.features().contains(Features::PUSH_CONSTANTS) {
+            device.limits().max_push_constant_size
+        } else {
+            0
+        };
+        Self {
+            pipelines: PipelineCache::new(&device, single_channel_storage, max_push_constant_size),
+            device,
+            queue,
+            submissions: AtomicUsize::new(0),
+            readback_buffer: Mutex::new(None),
+            profiling: false,
+        }
+    }
+
+    /// Makes the operations time each of their passes on the gpu, returned by [`Operation::execute_profiled`].
+    ///
+    /// This needs `Features::TIMESTAMP_QUERY`, which [`Filters::new`] enables when the adapter supports it.
+    /// Without it, the timings are empty.
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.profiling = enabled;
+        self
+    }
+
+    /// Whether the device supports `Features::TIMESTAMP_QUERY`, which [`Filters::with_profiling`] needs.
+    pub fn supports_profiling(&self) -> bool {
+        self.device.features().contains(Features::TIMESTAMP_QUERY)
+    }
 
-        Self { device, queue }
+    /// How many command submissions the operations made so far. Every pass of an operation is recorded
+    /// in a single submission, sent when the operation is finished.
+    pub fn submission_count(&self) -> usize {
+        self.submissions.load(Ordering::Relaxed)
+    }
+
+    /// Blocks until the gpu has finished everything submitted so far, like the passes of
+    /// [`Operation::into_texture`]. Does nothing on wasm32, where the gpu can't be waited for.
+    pub fn wait_idle(&self) {
+        wait_idle(&self.device);
+    }
+
+    /// Starts an operation on rgba pixels borrowed from a buffer, like a frame of a capture library, uploaded
+    /// without copying them into an [`Image`] first.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the image, in pixels.
+    /// * `height` - The height of the image, in pixels.
+    /// * `data` - The pixels, row by row, 4 bytes each.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidPixelBytes`] if `data` isn't `width * height * 4` bytes long, and the same as
+    /// [`Image::operation`] if the size isn't supported.
+    pub fn operation_from_raw(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<Operation<'_>, FiltersError> {
+        check_pixel_bytes((width, height), data.len())?;
+        let (texture, texture_size) =
+            texture_from_raw(&self.device, &self.queue, (width, height), data)?;
+
+        Ok(Operation::with_texture(
+            self,
+            texture,
+            texture_size,
+            COPY_TEXTURE_USAGES,
+            PixelFormat::Rgba8,
+        ))
     }
 }
 
 pub struct Operation<'a> {
     pub(crate) device: &'a Device,
     pub(crate) queue: &'a Queue,
+    pub(crate) pipelines: &'a PipelineCache,
+    pub(crate) submissions: &'a AtomicUsize,
+    pub(crate) readback_buffer: &'a Mutex<Option<Buffer>>,
+    /// Records all the passes of the operation, submitted at once when the operation is finished.
+    pub(crate) encoder: CommandEncoder,
     pub(crate) texture: Texture,
     pub(crate) texture_size: Extent3d,
-}
-
-pub enum Resize {
-    Linear,
-    Nearest,
+    pub(crate) texture_usage: TextureUsages,
+    /// The format the passes work in, which the textures of the pool use.
+    pub(crate) format: PixelFormat,
+    /// Whether the passes work on linear values decoded from sRGB, to encode back before reading the result.
+    pub(crate) color_space: ColorSpace,
+    /// Textures of previous passes, reused by the next ones.
+    pub(crate) pool: TexturePool,
+    /// False once a filter moved pixels around or depends on their absolute position,
+    /// as such a filter can't be applied tile by tile.
+    pub(crate) tileable: bool,
+    /// How far around each pixel the filters applied so far had to look, in pixels.
+    pub(crate) radius: u32,
+    /// Times the passes, when profiling is enabled, see [`Filters::with_profiling`].
+    pub(crate) profiler: Option<Profiler>,
 }
 
 impl<'a> Operation<'a> {
-    fn new(image: &Image, device: &'a Device, queue: &'a Queue) -> Operation<'a> {
-        let texture_size = Extent3d {
-            width: image.width,
-            height: image.height,
-            depth_or_array_layers: 1,
-        };
+    fn new(image: &Image, filters: &'a Filters) -> Result<Operation<'a>, FiltersError> {
+        let (texture, texture_size) = texture_from_image(&filters.device, &filters.queue, image)?;
 
-        let texture = device.create_texture(&TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            label: Some("texture"),
-        });
-        queue.write_texture(
-            texture.as_image_copy(),
-            bytemuck::cast_slice(&image.pixels),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4 * image.width),
-                rows_per_image: None,
-            },
+        Ok(Self::with_texture(
+            filters,
+            texture,
             texture_size,
-        );
+            COPY_TEXTURE_USAGES,
+            PixelFormat::Rgba8,
+        ))
+    }
 
+    pub(crate) fn with_texture(
+        filters: &'a Filters,
+        texture: Texture,
+        texture_size: Extent3d,
+        texture_usage: TextureUsages,
+        format: PixelFormat,
+    ) -> Operation<'a> {
         Self {
-            device,
-            queue,
+            device: &filters.device,
+            queue: &filters.queue,
+            pipelines: &filters.pipelines,
+            submissions: &filters.submissions,
+            readback_buffer: &filters.readback_buffer,
+            encoder: filters
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor { label: None }),
             texture,
             texture_size,
+            texture_usage,
+            format,
+            color_space: ColorSpace::Linear,
+            pool: TexturePool::new(filters.pipelines.texture_format(format)),
+            tileable: true,
+            radius: 0,
+            profiler: filters
+                .profiling
+                .then(|| Profiler::new(&filters.device))
+                .flatten(),
         }
     }
 
+    /// Turns the image to grays, weighting the channels by how bright they look. Single channel images already are.
     pub fn grayscale(self) -> Self {
+        if self.format == PixelFormat::Luma {
+            return self;
+        }
+
         self.simple_filter("grayscale", GRAYSCALE_SHADER)
     }
 
@@ -133,158 +347,184 @@ impl<'a> Operation<'a> {
     }
 
     pub fn hflip(self) -> Self {
-        self.simple_filter("hflip", HFLIP_SHADER)
+        let mut operation = self.simple_filter("hflip", HFLIP_SHADER);
+        operation.tileable = false;
+        operation
     }
     pub fn vflip(self) -> Self {
-        self.simple_filter("vflip", VFLIP_SHADER)
+        let mut operation = self.simple_filter("vflip", VFLIP_SHADER);
+        operation.tileable = false;
+        operation
+    }
+
+    /// Swaps the rows and the columns, mirroring the image along the diagonal starting at its top left corner.
+    /// Followed by [`Operation::hflip`], it turns the image a quarter clockwise, and followed by
+    /// [`Operation::vflip`], a quarter counterclockwise.
+    pub fn transpose(mut self) -> Self {
+        let texture_size = Extent3d {
+            width: self.texture_size.height,
+            height: self.texture_size.width,
+            depth_or_array_layers: 1,
+        };
+        let output_texture = self
+            .pool
+            .take(self.device, texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline("transpose", TRANSPOSE_SHADER, Bindings::Textures);
+        self.encode_simple_pass("transpose", &pipeline, &output_texture);
+
+        self.set_texture(output_texture, texture_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+        self
     }
 
     pub fn dimensions(&self) -> (u32, u32) {
         (self.texture_size.width, self.texture_size.height)
     }
 
-    pub fn resize(mut self, new_dimension: (u32, u32), resize: Resize) -> Self {
-        let name = "resize";
-        let capitalized_filter_name = capitalize(name);
-
-        self.texture_size = Extent3d {
-            width: new_dimension.0,
-            height: new_dimension.1,
-            depth_or_array_layers: 1,
-        };
+    /// Submits all the recorded passes and waits for the gpu to finish them, without reading the result back, to
+    /// time the passes on their own. Like [`Filters::wait_idle`], this doesn't wait on wasm32.
+    pub fn submit_only(self) {
+        let device = self.device;
+        self.submit();
+        wait_idle(device);
+    }
 
-        let output_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
+    /// Submits the passes recorded so far and waits for the gpu to finish them, before recording the next ones.
+    /// Splitting an operation in several submissions makes it slower, but lets the cpu time each part of it, like the
+    /// upload or a single filter, on adapters without timestamp queries. Doesn't wait on wasm32.
+    pub fn wait(mut self) -> Self {
+        self.submit_recorded();
+        wait_idle(self.device);
+        self
+    }
 
-        let shader = self.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some(format!("{} shader", capitalized_filter_name).as_str()),
-            source: ShaderSource::Wgsl(RESIZE_SHADER.into()),
-        });
+    /// Submits all the recorded passes, then reads the result back to the cpu, waiting for the device to finish.
+    /// This blocks the calling thread until the gpu is done, see [`Operation::execute_nonblocking`] for async runtimes.
+    /// The result is an 8-bit image, whatever the format of the operation, see [`Operation::execute16`].
+    pub async fn execute(mut self) -> Image {
+        self.convert_to_rgba8();
+        let (width, height) = self.dimensions();
+        let device = self.device;
+        let output_buffer = encode_texture_to_buffer::<Rgba>(
+            device,
+            &mut self.encoder,
+            width,
+            height,
+            &self.texture,
+        );
+        self.submit();
 
-        let pipeline = self
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some(format!("{} pipeline", capitalized_filter_name).as_str()),
-                layout: None,
-                module: &shader,
-                entry_point: "main",
-            });
+        buffer_to_image(device, width, height, &output_buffer).await
+    }
 
-        let filter_mode = match resize {
-            Resize::Linear => FilterMode::Linear,
-            Resize::Nearest => FilterMode::Nearest,
+    /// Like [`Operation::execute`], but writes the result into `target`, reusing its pixels when it already has
+    /// the right dimensions. The readback goes through a buffer kept by [`Filters`] and only grown when a larger
+    /// image comes along, so that executing frame after frame doesn't allocate.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::BufferMapFailed`] if the result couldn't be read back, and
+    /// [`FiltersError::ReadbackBufferPoisoned`] if another thread panicked while reading back through the same
+    /// filters. The poisoned buffer is dropped then, so that the next executions get a new one.
+    pub async fn execute_into(mut self, target: &mut Image) -> Result<(), FiltersError> {
+        self.convert_to_rgba8();
+        let (width, height) = self.dimensions();
+        let device = self.device;
+        let readback_buffer = self.readback_buffer;
+        let required_size = padded_bytes_per_row(width, 4) as u64 * height as u64;
+        let previous_buffer = match readback_buffer.lock() {
+            Ok(mut buffer) => buffer.take(),
+            Err(poisoned) => {
+                poisoned.into_inner().take();
+                readback_buffer.clear_poison();
+                return Err(FiltersError::ReadbackBufferPoisoned);
+            }
+        };
+        let buffer = match previous_buffer {
+            Some(buffer) if buffer.size() >= required_size => buffer,
+            _ => create_readback_buffer::<Rgba>(device, width, height),
         };
 
-        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            address_mode_u: AddressMode::ClampToEdge,
-            address_mode_v: AddressMode::ClampToEdge,
-            address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: filter_mode,
-            min_filter: filter_mode,
-            mipmap_filter: filter_mode,
-            ..Default::default()
-        });
-
-        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Compute constants"),
-            layout: &pipeline.get_bind_group_layout(0),
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Sampler(&sampler),
-            }],
-        });
+        encode_copy_to_buffer::<Rgba>(&mut self.encoder, width, height, &self.texture, &buffer);
+        self.submit();
 
-        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("Texture bind group"),
-            layout: &pipeline.get_bind_group_layout(1),
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(
-                        &self.texture.create_view(&TextureViewDescriptor::default()),
-                    ),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::TextureView(
-                        &output_texture.create_view(&TextureViewDescriptor::default()),
-                    ),
-                },
-            ],
-        });
+        try_wait_for_mapping(device, &buffer)
+            .await
+            .map_err(FiltersError::BufferMapFailed)?;
+        read_mapped_buffer_into(width, height, &buffer, &mut target.pixels);
+        target.width = width;
+        target.height = height;
+        *readback_buffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(buffer);
+
+        Ok(())
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
-        {
-            let (dispatch_with, dispatch_height) = compute_work_group_count(
-                (self.texture_size.width, self.texture_size.height),
-                (16, 16),
-            );
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
-            });
-            compute_pass.set_pipeline(&pipeline);
-            compute_pass.set_bind_group(0, &compute_constants, &[]);
-            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
-            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
-        }
+    /// Makes `texture` the result of the passes recorded so far, handing the previous texture to the pool.
+    pub(crate) fn set_texture(&mut self, texture: Texture, size: Extent3d, usage: TextureUsages) {
+        let previous = std::mem::replace(&mut self.texture, texture);
+        self.pool
+            .release(self.texture_size, self.texture_usage, previous);
+        self.texture_size = size;
+        self.texture_usage = usage;
+    }
 
-        self.queue.submit(Some(encoder.finish()));
-        self.texture = output_texture;
+    /// Sends everything recorded so far to the gpu in a single submission,
+    /// returning the final texture along with its size.
+    pub(crate) fn submit(self) -> (Texture, Extent3d) {
+        submit(self.queue, self.submissions, self.encoder);
+        (self.texture, self.texture_size)
+    }
 
-        self
+    /// Submits the passes recorded so far, and starts recording the next ones in a new encoder, for the results
+    /// read back, or shared, in the middle of an operation.
+    pub(crate) fn submit_recorded(&mut self) {
+        let encoder = std::mem::replace(
+            &mut self.encoder,
+            self.device
+                .create_command_encoder(&CommandEncoderDescriptor { label: None }),
+        );
+        submit(self.queue, self.submissions, encoder);
     }
 
-    pub async fn execute(self) -> Image {
-        texture_to_cpu(
-            self.device,
-            self.queue,
-            self.texture_size.width,
-            self.texture_size.height,
-            &self.texture,
-        )
-        .await
+    /// Returns the pipeline of the filter `name`, for the format of the operation, see [`PipelineCache::get`].
+    pub(crate) fn pipeline(
+        &self,
+        name: &'static str,
+        shader_string: &str,
+        bindings: Bindings,
+    ) -> Arc<ComputePipeline> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if bindings == Bindings::Textures {
+            self.tune_workgroup_size(name, shader_string);
+        }
+        self.pipelines
+            .get(self.device, name, shader_string, self.format, bindings)
     }
 
-    fn simple_filter(mut self, name: &str, shader_string: &str) -> Self {
-        let capitalized_filter_name = capitalize(name);
+    fn simple_filter(mut self, name: &'static str, shader_string: &str) -> Self {
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
 
-        let output_texture = self.device.create_texture(&TextureDescriptor {
-            label: None,
-            size: self.texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC
-                | TextureUsages::STORAGE_BINDING,
-        });
+        let pipeline = self.pipeline(name, shader_string, Bindings::Textures);
+        self.encode_simple_pass(name, &pipeline, &output_texture);
 
-        let shader = self.device.create_shader_module(ShaderModuleDescriptor {
-            label: Some(format!("{} shader", capitalized_filter_name).as_str()),
-            source: ShaderSource::Wgsl(shader_string.into()),
-        });
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
 
-        let pipeline = self
-            .device
-            .create_compute_pipeline(&ComputePipelineDescriptor {
-                label: Some(format!("{} pipeline", capitalized_filter_name).as_str()),
-                layout: None,
-                module: &shader,
-                entry_point: "main",
-            });
+        self
+    }
+
+    /// Records a pass of a shader with no settings, reading the current texture and writing to `output_texture`.
+    pub(crate) fn encode_simple_pass(
+        &mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        output_texture: &Texture,
+    ) {
+        let capitalized_filter_name = capitalize(name);
 
         let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
             label: Some("Texture bind group"),
@@ -305,58 +545,191 @@ impl<'a> Operation<'a> {
             ],
         });
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
+        let pass = self.begin_pass(name);
         {
             let (dispatch_with, dispatch_height) = compute_work_group_count(
                 (self.texture_size.width, self.texture_size.height),
-                (16, 16),
+                self.pipelines.workgroup_size(name),
             );
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
                 label: Some(format!("{} pass", capitalized_filter_name).as_str()),
             });
-            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_pipeline(pipeline);
             compute_pass.set_bind_group(0, &texture_bind_group, &[]);
             compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
         }
+        self.end_pass(pass);
+    }
+}
 
-        self.queue.submit(Some(encoder.finish()));
-        self.texture = output_texture;
-
-        self
+/// Uploads an image to the gpu, returning the texture holding it along with its size.
+pub(crate) fn texture_from_image(
+    device: &Device,
+    queue: &Queue,
+    image: &Image,
+) -> Result<(Texture, Extent3d), FiltersError> {
+    let expected = image.width as usize * image.height as usize;
+    if image.pixels.len() != expected {
+        return Err(FiltersError::InvalidImageDimensions {
+            expected,
+            actual: image.pixels.len(),
+        });
     }
+
+    texture_from_raw(
+        device,
+        queue,
+        (image.width, image.height),
+        bytemuck::cast_slice(&image.pixels),
+    )
 }
 
-/// Copies a texture from the gpu to the cpu. The tricky part here is that the encoder's method `copy_texture_to_buffer`
-/// only works when the image copy buffer's bytes per row are a multiple of 256.
-/// So this operation needs to happen in two faces: First, we copy to a buffer, padding the width so it's a multiple of 256.
-/// Then, we copy the buffer to the final image, slice by slice, by ignoring the extra padded bits of the buffer.
-async fn texture_to_cpu(
+/// Uploads rgba pixels to the gpu, straight from `data`, which must hold `width * height * 4` bytes.
+fn texture_from_raw(
     device: &Device,
     queue: &Queue,
-    width: u32,
-    height: u32,
-    texture: &Texture,
-) -> Image {
-    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    (width, height): (u32, u32),
+    data: &[u8],
+) -> Result<(Texture, Extent3d), FiltersError> {
+    check_texture_size(device, (width, height))?;
+
     let texture_size = Extent3d {
         width,
         height,
         depth_or_array_layers: 1,
     };
 
-    let padded_bytes_per_row = padded_bytes_per_row(width);
-    let unpadded_bytes_per_row = width as usize * 4;
+    let texture = device.create_texture(&TextureDescriptor {
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8Unorm,
+        usage: COPY_TEXTURE_USAGES,
+        label: Some("texture"),
+    });
+    write_pixels(queue, &texture, texture_size, data);
+
+    Ok((texture, texture_size))
+}
+
+/// Writes rgba pixels to a texture of `size`, straight from `data`, which must hold `width * height * 4` bytes.
+pub(crate) fn write_pixels(queue: &Queue, texture: &Texture, size: Extent3d, data: &[u8]) {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!(
+        "upload",
+        width = size.width,
+        height = size.height,
+        bytes = data.len()
+    )
+    .entered();
+    queue.write_texture(
+        texture.as_image_copy(),
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+            rows_per_image: None,
+        },
+        size,
+    );
+}
+
+/// Makes sure that `length` bytes hold exactly the rgba pixels of an image of `width` by `height`.
+pub(crate) fn check_pixel_bytes(
+    (width, height): (u32, u32),
+    length: usize,
+) -> Result<(), FiltersError> {
+    let expected = (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(4);
+    if length != expected {
+        return Err(FiltersError::InvalidPixelBytes {
+            expected,
+            actual: length,
+        });
+    }
+    Ok(())
+}
+
+/// Makes sure that a texture of the given dimension can be created on the device,
+/// as wgpu panics when a texture is empty or exceeds `max_texture_dimension_2d`.
+pub(crate) fn check_texture_size(
+    device: &Device,
+    (width, height): (u32, u32),
+) -> Result<(), FiltersError> {
+    if width == 0 || height == 0 {
+        return Err(FiltersError::UnsupportedSize { width, height });
+    }
+    let limit = device.limits().max_texture_dimension_2d;
+    if width > limit || height > limit {
+        return Err(FiltersError::ImageTooLarge {
+            dimension: (width, height),
+            limit,
+        });
+    }
+
+    Ok(())
+}
+
+/// Blocks until the device has finished its work, except on wasm32, where blocking isn't possible.
+fn wait_idle(device: &Device) {
+    #[cfg(not(target_arch = "wasm32"))]
+    device.poll(wgpu::Maintain::Wait);
+    #[cfg(target_arch = "wasm32")]
+    let _ = device;
+}
+
+/// Submits the passes recorded in `encoder`, counting the submission.
+pub(crate) fn submit(queue: &Queue, submissions: &AtomicUsize, encoder: CommandEncoder) {
+    queue.submit(Some(encoder.finish()));
+    submissions.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Records the copy of a texture into a buffer with rows padded to a multiple of 256 bytes, ready to be mapped.
+/// The tricky part here is that the encoder's method `copy_texture_to_buffer` only works when the image copy buffer's
+/// bytes per row are a multiple of 256. So the width is padded here, and [`buffer_to_image`] later copies the buffer
+/// to the final image slice by slice, ignoring the extra padded bits.
+pub(crate) fn encode_texture_to_buffer<T: Pod>(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    width: u32,
+    height: u32,
+    texture: &Texture,
+) -> Buffer {
+    let output_buffer = create_readback_buffer::<T>(device, width, height);
+    encode_copy_to_buffer::<T>(encoder, width, height, texture, &output_buffer);
+
+    output_buffer
+}
 
+/// Creates a buffer big enough to read back a texture of the given dimensions with pixels of type `T`, padding included.
+pub(crate) fn create_readback_buffer<T: Pod>(device: &Device, width: u32, height: u32) -> Buffer {
     let output_buffer_size =
-        padded_bytes_per_row as u64 * height as u64 * std::mem::size_of::<u8>() as u64;
-    let output_buffer = device.create_buffer(&BufferDescriptor {
+        padded_bytes_per_row(width, std::mem::size_of::<T>()) as u64 * height as u64;
+    device.create_buffer(&BufferDescriptor {
         label: None,
         size: output_buffer_size,
         usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
         mapped_at_creation: false,
-    });
+    })
+}
+
+/// Records the copy of a texture into an existing readback buffer, see [`create_readback_buffer`].
+pub(crate) fn encode_copy_to_buffer<T: Pod>(
+    encoder: &mut CommandEncoder,
+    width: u32,
+    height: u32,
+    texture: &Texture,
+    output_buffer: &Buffer,
+) {
+    let texture_size = Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let padded_bytes_per_row = padded_bytes_per_row(width, std::mem::size_of::<T>());
 
     encoder.copy_texture_to_buffer(
         wgpu::ImageCopyTexture {
@@ -366,7 +739,7 @@ async fn texture_to_cpu(
             origin: wgpu::Origin3d::ZERO,
         },
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row as u32),
@@ -375,22 +748,64 @@ async fn texture_to_cpu(
         },
         texture_size,
     );
-    queue.submit(Some(encoder.finish()));
+}
 
-    let buffer_slice = output_buffer.slice(..);
-    buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
+/// Maps a buffer filled by [`encode_texture_to_buffer`] and copies it to an image, skipping the row padding.
+pub(crate) async fn buffer_to_image(
+    device: &Device,
+    width: u32,
+    height: u32,
+    buffer: &Buffer,
+) -> Image {
+    wait_for_mapping(device, buffer).await;
 
-    device.poll(wgpu::Maintain::Wait);
+    read_mapped_buffer(width, height, buffer)
+}
 
-    let padded_data = buffer_slice.get_mapped_range();
+/// Maps a buffer for reading and waits until it is mapped. This blocks on the device, except on wasm32,
+/// where blocking isn't possible and the browser completes the mapping on its own.
+pub(crate) async fn wait_for_mapping(device: &Device, buffer: &Buffer) {
+    #[cfg(target_arch = "wasm32")]
+    let _ = nonblocking::map_read(device, buffer).await;
 
-    let mut pixels: Vec<Rgba> = vec![Rgba([0, 0, 0, 0]); (width * height) as usize];
-    for (padded, pixels) in padded_data
-        .chunks_exact(padded_bytes_per_row)
-        .zip(pixels.chunks_exact_mut(width as usize))
+    #[cfg(not(target_arch = "wasm32"))]
     {
-        pixels.copy_from_slice(bytemuck::cast_slice(&padded[..unpadded_bytes_per_row]));
+        buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+
+        // Waiting on the whole device rather than a submission index, which also works when the device
+        // is shared with, and polled by, the rest of an application.
+        device.poll(wgpu::Maintain::Wait);
     }
+}
+
+/// Same as [`wait_for_mapping`], but reports a mapping that failed, like one of a lost device, rather than leaving
+/// the reading of the buffer to panic.
+pub(crate) async fn try_wait_for_mapping(
+    device: &Device,
+    buffer: &Buffer,
+) -> Result<(), BufferAsyncError> {
+    #[cfg(target_arch = "wasm32")]
+    return nonblocking::map_read(device, buffer).await;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                // The receiver is still there, as the callback runs within the poll below.
+                let _ = sender.send(result);
+            });
+
+        device.poll(wgpu::Maintain::Wait);
+        receiver.try_recv().unwrap_or(Err(BufferAsyncError))
+    }
+}
+
+/// Copies a mapped readback buffer to an image, skipping the row padding, then unmaps the buffer so it can be reused.
+pub(crate) fn read_mapped_buffer(width: u32, height: u32, buffer: &Buffer) -> Image {
+    let mut pixels = Vec::new();
+    read_mapped_buffer_into(width, height, buffer, &mut pixels);
 
     Image {
         width,
@@ -399,6 +814,46 @@ async fn texture_to_cpu(
     }
 }
 
+/// Same as [`read_mapped_buffer`], but writes to existing pixels, which are only reallocated when they are too few.
+pub(crate) fn read_mapped_buffer_into<T: Pod>(
+    width: u32,
+    height: u32,
+    buffer: &Buffer,
+    pixels: &mut Vec<T>,
+) {
+    let padded_data = buffer.slice(..).get_mapped_range();
+    #[cfg(feature = "tracing")]
+    let _span =
+        tracing::debug_span!("readback", width, height, bytes = padded_data.len()).entered();
+    unpad_rows(&padded_data, width, height, pixels);
+    drop(padded_data);
+    buffer.unmap();
+}
+
+/// Copies rows padded to a multiple of 256 bytes to pixels. When the rows are already a multiple of 256 bytes,
+/// like 8-bit images with a width multiple of 64, there is no padding at all, and the whole image is copied at once.
+fn unpad_rows<T: Pod>(padded_data: &[u8], width: u32, height: u32, pixels: &mut Vec<T>) {
+    let bytes_per_pixel = std::mem::size_of::<T>();
+    let padded_bytes_per_row = padded_bytes_per_row(width, bytes_per_pixel);
+    let unpadded_bytes_per_row = width as usize * bytes_per_pixel;
+
+    if padded_bytes_per_row == unpadded_bytes_per_row {
+        pixels.clear();
+        pixels.extend_from_slice(bytemuck::cast_slice(
+            &padded_data[..unpadded_bytes_per_row * height as usize],
+        ));
+        return;
+    }
+
+    pixels.resize((width * height) as usize, T::zeroed());
+    for (padded, pixels) in padded_data
+        .chunks_exact(padded_bytes_per_row)
+        .zip(pixels.chunks_exact_mut(width as usize))
+    {
+        pixels.copy_from_slice(bytemuck::cast_slice(&padded[..unpadded_bytes_per_row]));
+    }
+}
+
 /// Compute the amount of work groups to be dispatched for an image, based on the work group size.
 /// Chances are, the group will not match perfectly, like an image of width 100, for a workgroup size of 32.
 /// To make sure the that the whole 100 pixels are visited, then we would need a count of 4, as 4 * 32 = 128,
@@ -412,15 +867,17 @@ pub(crate) fn compute_work_group_count(
     (width, height): (u32, u32),
     (workgroup_width, workgroup_height): (u32, u32),
 ) -> (u32, u32) {
-    let width = (width + workgroup_width - 1) / workgroup_width;
-    let height = (height + workgroup_height - 1) / workgroup_height;
+    let width = width.div_ceil(workgroup_width);
+    let height = height.div_ceil(workgroup_height);
+    #[cfg(feature = "tracing")]
+    tracing::trace!(workgroups_x = width, workgroups_y = height, "Dispatching");
 
     (width, height)
 }
 
 /// Compute the next multiple of 256 for texture retrival padding.
-fn padded_bytes_per_row(width: u32) -> usize {
-    let bytes_per_row = width as usize * 4;
+fn padded_bytes_per_row(width: u32, bytes_per_pixel: usize) -> usize {
+    let bytes_per_row = width as usize * bytes_per_pixel;
     let padding = (256 - bytes_per_row % 256) % 256;
     bytes_per_row + padding
 }
@@ -435,27 +892,34 @@ pub(crate) fn capitalize(s: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use pollster::FutureExt;
 
-    use crate::{compute_work_group_count, padded_bytes_per_row, Filters, Image, Rgba};
+    use wgpu::{Backends, TextureDescriptor, TextureDimension, TextureFormat};
+
+    use crate::{
+        compute_work_group_count, padded_bytes_per_row, pool::STORAGE_TEXTURE_USAGES, unpad_rows,
+        Filters, FiltersError, FiltersOptions, Image, Operation, Rgba,
+    };
 
     #[test]
     fn padded_bytes_per_row_width_4() {
-        let padded = padded_bytes_per_row(4);
+        let padded = padded_bytes_per_row(4, 4);
 
         assert_eq!(256, padded)
     }
 
     #[test]
     fn padded_bytes_per_row_width_64() {
-        let padded = padded_bytes_per_row(64);
+        let padded = padded_bytes_per_row(64, 4);
 
         assert_eq!(256, padded)
     }
 
     #[test]
     fn padded_bytes_per_row_width_65() {
-        let padded = padded_bytes_per_row(65);
+        let padded = padded_bytes_per_row(65, 4);
 
         assert_eq!(512, padded)
     }
@@ -468,31 +932,15 @@ mod tests {
     }
 
     #[test]
-    fn grayscale_test() {
-        let image = Image {
-            width: 2,
-            height: 2,
-            pixels: vec![
-                Rgba([0, 0, 0, 0]),
-                Rgba([0, 0, 0, 0]),
-                Rgba([0, 0, 0, 0]),
-                Rgba([0, 0, 0, 0]),
-            ],
-        };
+    fn inverse_test() {
+        let image = Image::from_fn(2, 2, |x, y| Rgba::new(x as u8 * 100, y as u8 * 100, 50, 0));
 
-        let expected = Image {
-            width: 2,
-            height: 2,
-            pixels: vec![
-                Rgba([255, 255, 255, 0]),
-                Rgba([255, 255, 255, 0]),
-                Rgba([255, 255, 255, 0]),
-                Rgba([255, 255, 255, 0]),
-            ],
-        };
-        let filters = Filters::new().block_on();
+        let expected = Image::from_fn(2, 2, |x, y| {
+            Rgba::new(255 - x as u8 * 100, 255 - y as u8 * 100, 205, 0)
+        });
+        let filters = Filters::new().block_on().unwrap();
 
-        let operation = image.operation(&filters).inverse();
+        let operation = image.operation(&filters).unwrap().inverse();
         let output = pollster::block_on(operation.execute());
 
         assert_eq!(expected, output);
@@ -521,11 +969,653 @@ mod tests {
                 Rgba([0, 22, 0, 0]),
             ],
         };
-        let filters = Filters::new().block_on();
+        let filters = Filters::new().block_on().unwrap();
 
-        let operation = image.operation(&filters).hflip();
+        let operation = image.operation(&filters).unwrap().hflip();
         let output = pollster::block_on(operation.execute());
 
         assert_eq!(expected, output);
     }
+
+    #[test]
+    fn transpose_test() {
+        let image = Image::from_fn(3, 2, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let filters = Filters::new().block_on().unwrap();
+
+        let transposed = image.operation(&filters).unwrap().transpose();
+        assert_eq!((2, 3), transposed.dimensions());
+        let output = transposed.execute().block_on();
+        let rotated = image
+            .operation(&filters)
+            .unwrap()
+            .transpose()
+            .hflip()
+            .execute()
+            .block_on();
+
+        assert_eq!(
+            Image::from_fn(2, 3, |x, y| Rgba([y as u8, x as u8, 0, 255])),
+            output
+        );
+        // A quarter turn clockwise brings the bottom left corner to the top left.
+        assert_eq!(Rgba([0, 1, 0, 255]), rotated.pixels[0]);
+        assert_eq!(Rgba([0, 0, 0, 255]), rotated.pixels[1]);
+    }
+
+    #[test]
+    fn operation_mismatched_pixel_length() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 0]); 3],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidImageDimensions {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn operation_image_too_large() {
+        let filters = Filters::new().block_on().unwrap();
+        let limit = filters.device.limits().max_texture_dimension_2d;
+        let image = Image {
+            width: limit + 1,
+            height: 1,
+            pixels: vec![Rgba([0, 0, 0, 0]); limit as usize + 1],
+        };
+
+        let result = image.operation(&filters);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::ImageTooLarge { dimension, limit: actual_limit })
+                if dimension == (limit + 1, 1) && actual_limit == limit
+        ));
+    }
+
+    #[test]
+    fn operation_zero_size() {
+        let filters = Filters::new().block_on().unwrap();
+
+        for (width, height) in [(0, 0), (0, 10), (10, 0)] {
+            let image = Image {
+                width,
+                height,
+                pixels: vec![],
+            };
+
+            let result = image.operation(&filters);
+
+            assert!(
+                matches!(
+                    result,
+                    Err(FiltersError::UnsupportedSize { width: w, height: h })
+                        if (w, h) == (width, height)
+                ),
+                "{width}x{height}"
+            );
+        }
+    }
+
+    #[test]
+    fn operation_from_raw_matches_image() {
+        let filters = Filters::new().block_on().unwrap();
+        let image = Image::from_fn(7, 5, |x, y| Rgba([x as u8 * 30, y as u8 * 50, 90, 200]));
+        let raw = image.as_raw().to_vec();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .box_blur(3)
+            .execute()
+            .block_on();
+        let output = filters
+            .operation_from_raw(7, 5, &raw)
+            .unwrap()
+            .grayscale()
+            .box_blur(3)
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn operation_from_raw_wrong_length() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = filters.operation_from_raw(2, 2, &[0; 15]);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidPixelBytes {
+                expected: 16,
+                actual: 15
+            })
+        ));
+    }
+
+    fn grayscale_and_blur(filters: &Filters, width: u32, height: u32) -> Image {
+        let image = Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| Rgba([(index * 3) as u8, (index * 5) as u8, 200, 255]))
+                .collect(),
+        };
+
+        image
+            .operation(filters)
+            .unwrap()
+            .grayscale()
+            .gaussian_blur(2.0)
+            .box_blur(3)
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn degenerate_images_through_filters() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let single = grayscale_and_blur(&filters, 1, 1);
+        assert_eq!(
+            (1, 1, 1),
+            (single.width, single.height, single.pixels.len())
+        );
+        assert_ne!(Rgba([0, 0, 0, 0]), single.pixels[0]);
+
+        // The blurs are the same both ways, so a column must come out as the transposed row, every pixel of both
+        // going through the padded readback. The passes run in the other order, so the rounding between them can
+        // differ by a step.
+        let column = grayscale_and_blur(&filters, 1, 500);
+        let row = grayscale_and_blur(&filters, 500, 1);
+        assert_eq!((1, 500), (column.width, column.height));
+        assert_eq!((500, 1), (row.width, row.height));
+        let transposed_column = Image {
+            width: 500,
+            height: 1,
+            pixels: column.pixels,
+        };
+        assert!(row.approx_eq(&transposed_column, 1));
+        assert!(row.pixels.iter().all(|pixel| pixel.a() > 0));
+    }
+
+    #[test]
+    fn chained_filters_match_separate_operations() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+                Rgba([10, 20, 30, 128]),
+                Rgba([200, 100, 50, 0]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let chained = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .hflip()
+            .gaussian_blur(1.0)
+            .inverse()
+            .execute()
+            .block_on();
+        let mut separate = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+        separate = separate
+            .operation(&filters)
+            .unwrap()
+            .hflip()
+            .execute()
+            .block_on();
+        separate = separate
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .execute()
+            .block_on();
+        separate = separate
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+
+        assert_eq!(separate, chained);
+    }
+
+    #[test]
+    fn long_chain_single_submission() {
+        let image = Image {
+            width: 4,
+            height: 4,
+            pixels: vec![Rgba([10, 20, 30, 255]); 16],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut operation = image.operation(&filters).unwrap();
+        for _ in 0..20 {
+            operation = operation.inverse().hflip().box_blur(3);
+        }
+        operation.execute().block_on();
+
+        assert_eq!(1, filters.submission_count());
+    }
+
+    #[test]
+    fn submit_only_skips_readback() {
+        let image = Image::new(4, 4, Rgba([10, 20, 30, 255]));
+        let filters = Filters::new().block_on().unwrap();
+
+        image.operation(&filters).unwrap().inverse().submit_only();
+
+        assert_eq!(1, filters.submission_count());
+        assert!(filters.readback_buffer.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn wait_between_filters() {
+        let image = Image::from_fn(6, 4, |x, y| Rgba([x as u8 * 40, y as u8 * 60, 9, 255]));
+        let filters = Filters::new().block_on().unwrap();
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .box_blur(3)
+            .execute()
+            .block_on();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .wait()
+            .inverse()
+            .wait()
+            .box_blur(3)
+            .execute()
+            .block_on();
+
+        // One submission for the expected image, three for the waiting operation.
+        assert_eq!(4, filters.submission_count());
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn wait_idle_then_continue_on_gpu() {
+        let image = Image::from_fn(5, 3, |x, y| Rgba([x as u8 * 40, y as u8 * 80, 7, 255]));
+        let filters = Filters::new().block_on().unwrap();
+
+        let (texture, size) = image.operation(&filters).unwrap().inverse().into_texture();
+        filters.wait_idle();
+        let descriptor = TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: STORAGE_TEXTURE_USAGES,
+        };
+        let output = Operation::from_texture(&filters, texture, &descriptor)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    fn round_trip(width: u32, height: u32) {
+        let image = Image {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|index| Rgba(index.to_le_bytes()))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image.operation(&filters).unwrap().execute().block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn round_trip_width_63() {
+        round_trip(63, 5);
+    }
+
+    #[test]
+    fn round_trip_width_64() {
+        round_trip(64, 5);
+    }
+
+    #[test]
+    fn round_trip_width_65() {
+        round_trip(65, 5);
+    }
+
+    #[test]
+    fn round_trip_single_pixel() {
+        round_trip(1, 1);
+    }
+
+    #[test]
+    fn round_trip_single_column_and_row() {
+        round_trip(1, 500);
+        round_trip(500, 1);
+    }
+
+    #[test]
+    fn unpad_rows_aligned_4096() {
+        let data: Vec<u8> = (0..4096 * 4096 * 4).map(|index| index as u8).collect();
+        let mut pixels = vec![];
+
+        let start = std::time::Instant::now();
+        unpad_rows(&data, 4096, 4096, &mut pixels);
+        let elapsed = start.elapsed();
+
+        assert_eq!(bytemuck::cast_slice::<Rgba, u8>(&pixels), &data[..]);
+        assert!(elapsed < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn unpad_rows_padded() {
+        let mut data = vec![0; 512 * 2];
+        data[..260].fill(1);
+        data[512..772].fill(2);
+        let mut pixels = vec![];
+
+        unpad_rows(&data, 65, 2, &mut pixels);
+
+        assert_eq!(
+            [vec![Rgba([1; 4]); 65], vec![Rgba([2; 4]); 65]].concat(),
+            pixels
+        );
+    }
+
+    #[test]
+    fn execute_into_reuses_pixels() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+                Rgba([10, 20, 30, 128]),
+                Rgba([200, 100, 50, 0]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+
+        let mut target = Image {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute_into(&mut target)
+            .block_on()
+            .unwrap();
+        let pixels = target.pixels.as_ptr();
+        let capacity = target.pixels.capacity();
+        for _ in 0..50 {
+            image
+                .operation(&filters)
+                .unwrap()
+                .grayscale()
+                .execute_into(&mut target)
+                .block_on()
+                .unwrap();
+
+            assert_eq!(expected, target);
+            assert_eq!(pixels, target.pixels.as_ptr());
+            assert_eq!(capacity, target.pixels.capacity());
+        }
+    }
+
+    #[test]
+    fn execute_into_grows_readback_buffer() {
+        let small = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([1, 2, 3, 4])],
+        };
+        let large = Image {
+            width: 2,
+            height: 100,
+            pixels: vec![Rgba([5, 6, 7, 8]); 200],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let readback_size = || {
+            filters
+                .readback_buffer
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|buffer| buffer.size())
+        };
+
+        let mut target = Image {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        large
+            .operation(&filters)
+            .unwrap()
+            .execute_into(&mut target)
+            .block_on()
+            .unwrap();
+        assert_eq!(large, target);
+        assert_eq!(Some(256 * 100), readback_size());
+
+        small
+            .operation(&filters)
+            .unwrap()
+            .execute_into(&mut target)
+            .block_on()
+            .unwrap();
+        assert_eq!(small, target);
+        assert_eq!(Some(256 * 100), readback_size());
+    }
+
+    #[test]
+    fn execute_into_reports_poisoned_readback_buffer() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([1, 2, 3, 4]), Rgba([5, 6, 7, 8])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        std::thread::scope(|scope| {
+            let poisoning = scope.spawn(|| {
+                let _buffer = filters.readback_buffer.lock().unwrap();
+                panic!("poisoning the readback buffer");
+            });
+            assert!(poisoning.join().is_err());
+        });
+
+        let mut target = Image {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .execute_into(&mut target)
+            .block_on();
+        assert!(matches!(result, Err(FiltersError::ReadbackBufferPoisoned)));
+
+        image
+            .operation(&filters)
+            .unwrap()
+            .execute_into(&mut target)
+            .block_on()
+            .unwrap();
+        assert_eq!(image, target);
+    }
+
+    #[test]
+    fn filters_from_existing_device() {
+        let instance = wgpu::Instance::new(Backends::all());
+        let adapter = instance
+            .request_adapter(&Default::default())
+            .block_on()
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(&Default::default(), None)
+            .block_on()
+            .unwrap();
+        let device = Arc::new(device);
+        let queue = Arc::new(queue);
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([0, 64, 255, 255])],
+        };
+
+        let expected = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([255, 191, 0, 255])],
+        };
+        let filters = Filters::from_device(device.clone(), queue.clone());
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        drop(filters);
+
+        assert_eq!(expected, output);
+        assert_eq!(1, Arc::strong_count(&device));
+        assert_eq!(1, Arc::strong_count(&queue));
+    }
+
+    #[test]
+    fn filters_without_adapter() {
+        let result = Filters::with_options(FiltersOptions {
+            backends: Backends::empty(),
+            ..Default::default()
+        })
+        .block_on();
+
+        assert!(matches!(result, Err(FiltersError::NoAdapter)));
+    }
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn filters_and_operation_are_send_and_sync() {
+        assert_send::<Filters>();
+        assert_sync::<Filters>();
+        assert_send::<Operation>();
+        assert_sync::<Operation>();
+    }
+
+    #[test]
+    fn shared_filters_across_threads() {
+        let images: Vec<Image> = (0..8u8)
+            .map(|index| Image {
+                width: 4 + index as u32,
+                height: 3,
+                pixels: vec![Rgba([index * 30, 100, 255 - index, 255]); (4 + index as usize) * 3],
+            })
+            .collect();
+        let filters = Arc::new(Filters::new().block_on().unwrap());
+
+        let outputs: Vec<Image> = std::thread::scope(|scope| {
+            let handles: Vec<_> = images
+                .iter()
+                .map(|image| {
+                    let filters = filters.clone();
+                    scope.spawn(move || {
+                        image
+                            .operation(&filters)
+                            .unwrap()
+                            .grayscale()
+                            .execute()
+                            .block_on()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        for (image, output) in images.iter().zip(outputs) {
+            let expected = image
+                .operation(&filters)
+                .unwrap()
+                .grayscale()
+                .execute()
+                .block_on();
+            assert_eq!(expected, output);
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    use crate::{Filters, Image, Rgba};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn grayscale_in_browser() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([255, 0, 0, 255])],
+        };
+        let filters = Filters::new().await.unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .await;
+
+        assert_eq!(1, output.pixels.len());
+    }
 }
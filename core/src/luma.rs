@@ -0,0 +1,255 @@
+use wgpu::TextureFormat;
+
+use crate::{
+    cache::Bindings, encode_texture_to_buffer, format::check_pixel_count, read_mapped_buffer_into,
+    wait_for_mapping, Filters, FiltersError, Image, Operation, PixelFormat, Rgba,
+};
+
+const TO_LUMA_SHADER: &str = include_str!("shaders/to_luma.wgsl");
+const LUMA_TO_RGBA_SHADER: &str = include_str!("shaders/luma_to_rgba.wgsl");
+
+/// An image with a single 8-bit channel, like a mask or a depth map, taking a quarter of the memory of an [`Image`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImageLuma {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Creates a single channel image, to process with [`PixelFormat::Luma`].
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the image.
+    /// * `height` - The height of the image.
+    /// * `pixels` - One byte per pixel.
+    pub fn new_luma(width: u32, height: u32, pixels: Vec<u8>) -> ImageLuma {
+        ImageLuma {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl ImageLuma {
+    /// Starts an operation working on a single channel, see [`PixelFormat::Luma`].
+    pub fn operation<'a>(&self, filters: &'a Filters) -> Result<Operation<'a>, FiltersError> {
+        let texture_size = check_pixel_count(self.width, self.height, self.pixels.len())?;
+        let mut operation = Operation::with_uploaded_texture(
+            filters,
+            texture_size,
+            TextureFormat::R8Unorm,
+            &self.pixels,
+            PixelFormat::Luma,
+        )?;
+        let pipeline = operation.pipeline("luma_to_rgba", LUMA_TO_RGBA_SHADER, Bindings::Textures);
+        operation.convert_input("luma_to_rgba", &pipeline);
+
+        Ok(operation)
+    }
+
+    pub fn as_raw(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Keeps only the luminance of the image, weighting the channels like [`Operation::grayscale`], so that the
+    /// following filters work on a single channel. The alpha channel is dropped.
+    pub fn to_luma(mut self) -> Self {
+        self.convert_to_luma();
+        self
+    }
+
+    /// Turns a single channel image back to an opaque RGBA one, copying its value to the red, green and blue channels.
+    /// Does nothing if the image isn't single channel.
+    pub fn to_rgba(mut self) -> Self {
+        self.convert_luma_to_rgba();
+        self
+    }
+
+    /// Like [`Operation::execute`], but reads back a single channel, which is the luminance of RGBA images.
+    pub async fn execute_luma(mut self) -> ImageLuma {
+        self.encode_srgb();
+        let mut operation = self.to_luma();
+        let (width, height) = operation.dimensions();
+        let device = operation.device;
+        let single_channel =
+            operation.pipelines.texture_format(PixelFormat::Luma) == TextureFormat::R8Unorm;
+
+        // Without single channel textures, the luminance is in the red channel of an Rgba8Unorm texture.
+        let output_buffer = if single_channel {
+            encode_texture_to_buffer::<u8>(
+                device,
+                &mut operation.encoder,
+                width,
+                height,
+                &operation.texture,
+            )
+        } else {
+            encode_texture_to_buffer::<Rgba>(
+                device,
+                &mut operation.encoder,
+                width,
+                height,
+                &operation.texture,
+            )
+        };
+        operation.submit();
+
+        wait_for_mapping(device, &output_buffer).await;
+        let mut pixels = Vec::new();
+        if single_channel {
+            read_mapped_buffer_into(width, height, &output_buffer, &mut pixels);
+        } else {
+            let mut rgba_pixels: Vec<Rgba> = Vec::new();
+            read_mapped_buffer_into(width, height, &output_buffer, &mut rgba_pixels);
+            pixels = rgba_pixels
+                .into_iter()
+                .map(|Rgba([luma, ..])| luma)
+                .collect();
+        }
+
+        ImageLuma {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Records the conversion of the operation to a single channel, see [`Operation::to_luma`].
+    pub(crate) fn convert_to_luma(&mut self) {
+        if self.format != PixelFormat::Luma {
+            self.convert_format(PixelFormat::Luma, "to_luma", TO_LUMA_SHADER);
+        }
+    }
+
+    /// Records the conversion of a single channel operation to 8-bit RGBA. Does nothing for other formats.
+    pub(crate) fn convert_luma_to_rgba(&mut self) {
+        if self.format == PixelFormat::Luma {
+            self.convert_format(PixelFormat::Rgba8, "luma_to_rgba", LUMA_TO_RGBA_SHADER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, ImageLuma, PixelFormat, Rgba};
+
+    fn mask(width: u32, height: u32) -> ImageLuma {
+        Image::new_luma(
+            width,
+            height,
+            (0..width * height)
+                .map(|index| {
+                    if index % 7 < 3 {
+                        255
+                    } else {
+                        (index * 13 % 200) as u8
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn luma_round_trip() {
+        let image = mask(65, 3);
+        let filters = Filters::new().block_on().unwrap();
+
+        let operation = image.operation(&filters).unwrap();
+        assert_eq!(PixelFormat::Luma, operation.pixel_format());
+        let output = operation.execute_luma().block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn luma_blur_matches_red_channel_of_rgba_blur() {
+        let image = mask(37, 21);
+        let rgba_image = Image {
+            width: image.width,
+            height: image.height,
+            pixels: image
+                .pixels
+                .iter()
+                .map(|&luma| Rgba([luma, 0, 0, 255]))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = rgba_image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(2.0)
+            .inverse()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(2.0)
+            .inverse()
+            .execute_luma()
+            .block_on();
+
+        assert_eq!(
+            expected
+                .pixels
+                .iter()
+                .map(|Rgba([red, ..])| *red)
+                .collect::<Vec<_>>(),
+            output.pixels
+        );
+    }
+
+    #[test]
+    fn to_luma_and_back() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([255, 0, 0, 255]), Rgba([20, 40, 60, 128])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let luma = image
+            .operation(&filters)
+            .unwrap()
+            .to_luma()
+            .execute_luma()
+            .block_on();
+        let rgba = image
+            .operation(&filters)
+            .unwrap()
+            .to_luma()
+            .to_rgba()
+            .execute()
+            .block_on();
+
+        assert_eq!(vec![76, 36], luma.pixels);
+        assert_eq!(
+            vec![Rgba([76, 76, 76, 255]), Rgba([36, 36, 36, 255])],
+            rgba.pixels
+        );
+    }
+
+    #[test]
+    fn luma_invalid_pixel_count() {
+        let image = Image::new_luma(2, 2, vec![0; 3]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::InvalidImageDimensions {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+}
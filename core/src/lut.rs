@@ -0,0 +1,696 @@
+use std::{fmt::Display, path::Path};
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Filters, FiltersError,
+    Image, Operation, PixelFormat,
+};
+
+const LUT_SHADER: &str = include_str!("shaders/lut.wgsl");
+
+/// The largest lut, per side, which is already much finer than the 33 or 65 entries of the usual ones.
+const MAX_LUT_SIZE: u32 = 256;
+
+/// Why a .cube file couldn't be read as a [`Lut3d`]. The lines are counted from 1.
+#[derive(Debug)]
+pub enum LutError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// A value isn't a number, or a size isn't a positive integer.
+    InvalidNumber { line: usize, token: String },
+    /// A line has the wrong number of values, like a color with only two channels.
+    WrongValueCount {
+        line: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// The size of the lut is below 2, or above 256.
+    InvalidSize { line: usize, size: u32 },
+    /// The file is a 1D lut, with a `LUT_1D_SIZE`, which isn't supported.
+    Unsupported1d { line: usize },
+    /// The file has no `LUT_3D_SIZE`.
+    MissingSize,
+    /// A channel of the domain has a minimum that isn't below its maximum.
+    InvalidDomain { min: [f32; 3], max: [f32; 3] },
+    /// A channel of a color of the lut is outside of 0.0 to 1.0.
+    ValueOutOfRange { line: usize, value: f32 },
+    /// The number of colors isn't the cube of the size.
+    WrongEntryCount { expected: usize, actual: usize },
+}
+
+impl Display for LutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LutError::Io(error) => write!(f, "Could not read the lut: {error}"),
+            LutError::InvalidNumber { line, token } => {
+                write!(f, "Invalid number {token} on line {line}")
+            }
+            LutError::WrongValueCount {
+                line,
+                expected,
+                actual,
+            } => write!(f, "Expected {expected} values on line {line}, got {actual}"),
+            LutError::InvalidSize { line, size } => write!(
+                f,
+                "Invalid lut size {size} on line {line}, expecting 2 to {MAX_LUT_SIZE}"
+            ),
+            LutError::Unsupported1d { line } => {
+                write!(
+                    f,
+                    "1D luts aren't supported, found LUT_1D_SIZE on line {line}"
+                )
+            }
+            LutError::MissingSize => write!(f, "Missing LUT_3D_SIZE"),
+            LutError::InvalidDomain { min, max } => write!(
+                f,
+                "Invalid domain from {min:?} to {max:?}, the minimum should be below the maximum"
+            ),
+            LutError::ValueOutOfRange { line, value } => write!(
+                f,
+                "Value {value} on line {line} is out of range, expecting 0.0 to 1.0"
+            ),
+            LutError::WrongEntryCount { expected, actual } => write!(
+                f,
+                "Expected {expected} colors for the size of the lut, got {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LutError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LutError {
+    fn from(error: std::io::Error) -> Self {
+        LutError::Io(error)
+    }
+}
+
+/// A 3D color lookup table, mapping each color to another, like the .cube files of color grading tools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    size: u32,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// The `size`³ colors of the lut, red changing fastest, then green, then blue.
+    entries: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Reads the Adobe .cube file at `path`, see [`Lut3d::from_cube`].
+    ///
+    /// # Errors
+    ///
+    /// [`LutError::Io`] if the file can't be read, and the errors of [`Lut3d::from_cube`].
+    pub fn from_cube_file(path: impl AsRef<Path>) -> Result<Self, LutError> {
+        Self::from_cube(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a lut in the Adobe .cube format: a `LUT_3D_SIZE`, an optional `DOMAIN_MIN` and `DOMAIN_MAX`, and one
+    /// color per line, red changing fastest. Comments, starting with `#`, and other keywords like `TITLE` are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// A [`LutError`] with the line of the first malformed value, or [`LutError::WrongEntryCount`] if the number of
+    /// colors doesn't match the size.
+    pub fn from_cube(input: &str) -> Result<Self, LutError> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut entries = Vec::new();
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = line.split_whitespace();
+            let Some(first) = tokens.next() else {
+                continue;
+            };
+            if first.starts_with('#') {
+                continue;
+            }
+
+            match first {
+                "LUT_3D_SIZE" => {
+                    let [value] = parse_values(tokens, line_number)?;
+                    let parsed = value
+                        .parse()
+                        .map_err(|_| invalid_number(value, line_number))?;
+                    if !(2..=MAX_LUT_SIZE).contains(&parsed) {
+                        return Err(LutError::InvalidSize {
+                            line: line_number,
+                            size: parsed,
+                        });
+                    }
+                    size = Some(parsed);
+                }
+                "LUT_1D_SIZE" => return Err(LutError::Unsupported1d { line: line_number }),
+                "DOMAIN_MIN" => domain_min = parse_color(tokens, line_number)?,
+                "DOMAIN_MAX" => domain_max = parse_color(tokens, line_number)?,
+                _ if first.starts_with(|start: char| start.is_ascii_alphabetic()) => {}
+                _ => {
+                    let color = parse_color(line.split_whitespace(), line_number)?;
+                    if let Some(&value) = color.iter().find(|value| !(0.0..=1.0).contains(*value)) {
+                        return Err(LutError::ValueOutOfRange {
+                            line: line_number,
+                            value,
+                        });
+                    }
+                    entries.push(color);
+                }
+            }
+        }
+
+        let size = size.ok_or(LutError::MissingSize)?;
+        if (0..3).any(|channel| domain_min[channel] >= domain_max[channel]) {
+            return Err(LutError::InvalidDomain {
+                min: domain_min,
+                max: domain_max,
+            });
+        }
+        let expected = size.pow(3) as usize;
+        if entries.len() != expected {
+            return Err(LutError::WrongEntryCount {
+                expected,
+                actual: entries.len(),
+            });
+        }
+
+        Ok(Self {
+            size,
+            domain_min,
+            domain_max,
+            entries,
+        })
+    }
+
+    /// The number of entries of the lut along each channel.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Writes the lut in the Adobe .cube format, which [`Lut3d::from_cube`] reads back, with 6 decimals per value.
+    pub fn to_cube(&self) -> String {
+        let [min_r, min_g, min_b] = self.domain_min;
+        let [max_r, max_g, max_b] = self.domain_max;
+        let mut cube = format!(
+            "LUT_3D_SIZE {}\nDOMAIN_MIN {min_r} {min_g} {min_b}\nDOMAIN_MAX {max_r} {max_g} {max_b}\n",
+            self.size
+        );
+        for [r, g, b] in &self.entries {
+            cube += &format!("{r:.6} {g:.6} {b:.6}\n");
+        }
+        cube
+    }
+
+    /// Writes the lut to a .cube file at `path`, for video editors and color grading tools, see
+    /// [`Lut3d::to_cube`].
+    ///
+    /// # Errors
+    ///
+    /// The error of the file if it can't be written.
+    pub fn write_cube(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_cube())
+    }
+}
+
+impl Filters {
+    /// Bakes the effect of a chain of filters on the colors into a lut of `size`³ entries, which can be applied
+    /// with [`Operation::apply_lut`], or saved with [`Lut3d::write_cube`] for a video editor.
+    ///
+    /// The chain is applied once to a lattice of every color of the lut, laid out as an image of `size` slices of
+    /// blue, which is read back as floats. Only filters that work on each pixel on its own, like brightness,
+    /// contrast or grayscale, can be baked: blurs would mix the colors of the lattice, and flips or resizes would
+    /// move them around, corrupting the lut.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The number of entries per channel, like 17, 33 or 65, from 2 to 256.
+    /// * `chain` - The filter chain, applied to the lattice.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidLutSize`] for a size outside of 2 to 256, and [`FiltersError::NotPackable`] if a
+    /// filter of the chain looks at neighboring pixels or moves them.
+    pub async fn bake_lut<F>(&self, size: u32, chain: F) -> Result<Lut3d, FiltersError>
+    where
+        F: for<'a> Fn(Operation<'a>) -> Operation<'a>,
+    {
+        if !(2..=MAX_LUT_SIZE).contains(&size) {
+            return Err(FiltersError::InvalidLutSize(size));
+        }
+
+        // The slices of blue on a grid as square as possible, 4096² pixels for the largest lut.
+        let columns = (f64::from(size).sqrt().ceil()) as u32;
+        let (width, height) = (size * columns, size * size.div_ceil(columns));
+        let last = (size - 1) as f32;
+        let mut pixels = vec![0.0; width as usize * height as usize * 4];
+        for (index, [r, g, b]) in lattice(size).enumerate() {
+            let offset = lattice_offset(size, columns, width, index);
+            pixels[offset..offset + 4].copy_from_slice(&[
+                r as f32 / last,
+                g as f32 / last,
+                b as f32 / last,
+                1.0,
+            ]);
+        }
+
+        let operation = chain(Image::from_f32(width, height, pixels).operation(self)?);
+        if !operation.tileable || operation.radius > 0 || operation.dimensions() != (width, height)
+        {
+            return Err(FiltersError::NotPackable);
+        }
+        let baked = operation.execute_f32().await;
+
+        let entries = (0..size.pow(3) as usize)
+            .map(|index| {
+                let offset = lattice_offset(size, columns, width, index);
+                let [r, g, b] =
+                    [0, 1, 2].map(|channel| baked.pixels[offset + channel].clamp(0.0, 1.0));
+                [r, g, b]
+            })
+            .collect();
+        Ok(Lut3d {
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            entries,
+        })
+    }
+}
+
+/// The entries of a lut of `size`, red changing fastest, then green, then blue.
+fn lattice(size: u32) -> impl Iterator<Item = [u32; 3]> {
+    (0..size).flat_map(move |b| (0..size).flat_map(move |g| (0..size).map(move |r| [r, g, b])))
+}
+
+/// Where the entry at `index` is in the floats of the lattice image, `width` wide, whose slices of blue are on
+/// `columns` columns.
+fn lattice_offset(size: u32, columns: u32, width: u32, index: usize) -> usize {
+    let size = size as usize;
+    let (r, g, b) = (index % size, index / size % size, index / (size * size));
+    let x = b % columns as usize * size + r;
+    let y = b / columns as usize * size + g;
+    (y * width as usize + x) * 4
+}
+
+fn invalid_number(token: &str, line: usize) -> LutError {
+    LutError::InvalidNumber {
+        line,
+        token: token.to_owned(),
+    }
+}
+
+/// The `N` tokens of a line, or a [`LutError::WrongValueCount`].
+fn parse_values<'a, const N: usize>(
+    tokens: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> Result<[&'a str; N], LutError> {
+    let tokens = tokens.collect::<Vec<_>>();
+    tokens
+        .as_slice()
+        .try_into()
+        .map_err(|_| LutError::WrongValueCount {
+            line,
+            expected: N,
+            actual: tokens.len(),
+        })
+}
+
+fn parse_color<'a>(
+    tokens: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> Result<[f32; 3], LutError> {
+    let [r, g, b] = parse_values(tokens, line)?;
+    let parse = |token: &str| match token.parse::<f32>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(invalid_number(token, line)),
+    };
+    Ok([parse(r)?, parse(g)?, parse(b)?])
+}
+
+/// The bits of the half float closest to `value`, a channel of the lut from 0.0 to 1.0. Values too small for a
+/// normal half float, below 6.1e-5, are flushed to zero.
+fn half_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent <= 0 {
+        return 0;
+    }
+    let mantissa = bits & 0x7f_ffff;
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    // Rounds to the nearest, a carry out of the mantissa moving on to the exponent.
+    (half + ((mantissa >> 12) & 1)) as u16
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct LutSettings {
+    domain_min: [f32; 4],
+    domain_max: [f32; 4],
+    single_channel: u32,
+    _padding: [u32; 3],
+}
+
+impl<'a> Operation<'a> {
+    /// Maps the colors of the image through a 3D lookup table, like a color grade exported as a .cube file.
+    ///
+    /// The lut is uploaded as a 3D texture of half floats, and each pixel is interpolated trilinearly between the 8
+    /// entries around its color. The lut applies to the values as stored, so luts made for sRGB images should be
+    /// applied before [`Operation::assume_srgb`]. Grayscale images go through the lut as grays, and keep the
+    /// luminance of the result.
+    pub fn apply_lut(mut self, lut: &Lut3d) -> Self {
+        let name = "lut";
+        let pipeline = self.pipeline(name, LUT_SHADER, Bindings::Derived);
+
+        let texels = lut
+            .entries
+            .iter()
+            .flat_map(|&[r, g, b]| [half_bits(r), half_bits(g), half_bits(b), half_bits(1.0)])
+            .collect::<Vec<_>>();
+        let lut_texture = self.device.create_texture_with_data(
+            self.queue,
+            &TextureDescriptor {
+                label: Some("Lut texture"),
+                size: Extent3d {
+                    width: lut.size,
+                    height: lut.size,
+                    depth_or_array_layers: lut.size,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D3,
+                format: TextureFormat::Rgba16Float,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            },
+            bytemuck::cast_slice(&texels),
+        );
+
+        let [min_r, min_g, min_b] = lut.domain_min;
+        let [max_r, max_g, max_b] = lut.domain_max;
+        let settings = LutSettings {
+            domain_min: [min_r, min_g, min_b, 0.0],
+            domain_max: [max_r, max_g, max_b, 1.0],
+            single_channel: u32::from(self.format == PixelFormat::Luma),
+            _padding: [0; 3],
+        };
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Lut settings"),
+            contents: bytemuck::bytes_of(&settings),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Lut bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &lut_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Lut pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::{half_bits, Lut3d, LutError};
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    const IDENTITY: &str = "# The smallest identity
+TITLE \"Identity\"
+LUT_3D_SIZE 2
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+
+    const SWIZZLE_SHADER: &str = "
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+    let color = textureLoad(input_texture, vec2<i32>(global_id.xy), 0);
+    textureStore(output_texture, vec2<i32>(global_id.xy), color.bgra);
+}
+";
+
+    /// A .cube file of `size` swapping the red and the blue.
+    fn swap_red_blue(size: u32) -> String {
+        let mut cube = format!("LUT_3D_SIZE {size}\n");
+        let last = (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    cube += &format!(
+                        "{} {} {}\n",
+                        b as f32 / last,
+                        g as f32 / last,
+                        r as f32 / last
+                    );
+                }
+            }
+        }
+        cube
+    }
+
+    fn colors() -> Image {
+        Image::from_fn(32, 16, |x, y| {
+            Rgba::new(
+                (x * 8) as u8,
+                (y * 8 + x) as u8,
+                (255 - x * 3 - y * 5) as u8,
+                200,
+            )
+        })
+    }
+
+    #[test]
+    fn identity_is_identity() {
+        let image = colors();
+        let lut = Lut3d::from_cube(IDENTITY).unwrap();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .apply_lut(&lut)
+            .execute()
+            .block_on();
+
+        assert_eq!(2, lut.size());
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn swap_matches_swizzle() {
+        let image = colors();
+        let lut = Lut3d::from_cube(&swap_red_blue(5)).unwrap();
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .custom(SWIZZLE_SHADER, &[])
+            .unwrap()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .apply_lut(&lut)
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn reads_cube_file() {
+        let path = std::env::temp_dir().join("filters-lut-identity.cube");
+        std::fs::write(&path, IDENTITY).unwrap();
+
+        let lut = Lut3d::from_cube_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Lut3d::from_cube(IDENTITY).unwrap(), lut.unwrap());
+        assert!(matches!(
+            Lut3d::from_cube_file(std::env::temp_dir().join("filters-lut-missing.cube")),
+            Err(LutError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn baked_chain_matches_chain() {
+        let image = colors();
+        let filters = Filters::new().block_on().unwrap();
+
+        let lut = filters
+            .bake_lut(17, |operation| operation.contrast(0.8).brightness(0.1))
+            .block_on()
+            .unwrap();
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .contrast(0.8)
+            .brightness(0.1)
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .apply_lut(&lut)
+            .execute()
+            .block_on();
+
+        assert_eq!(17, lut.size());
+        // The chain doesn't clip, which the trilinear interpolation would round off.
+        assert!(expected.approx_eq(&output, 1));
+    }
+
+    #[test]
+    fn bake_rejects_geometric_filters() {
+        let filters = Filters::new().block_on().unwrap();
+
+        assert!(matches!(
+            filters
+                .bake_lut(9, |operation| operation.hflip())
+                .block_on(),
+            Err(FiltersError::NotPackable)
+        ));
+        assert!(matches!(
+            filters
+                .bake_lut(9, |operation| operation.box_blur(3))
+                .block_on(),
+            Err(FiltersError::NotPackable)
+        ));
+        assert!(matches!(
+            filters.bake_lut(1, |operation| operation).block_on(),
+            Err(FiltersError::InvalidLutSize(1))
+        ));
+    }
+
+    #[test]
+    fn written_cube_reads_back() {
+        let filters = Filters::new().block_on().unwrap();
+        let lut = filters
+            .bake_lut(2, |operation| operation)
+            .block_on()
+            .unwrap();
+        let path = std::env::temp_dir().join("filters-lut-written.cube");
+
+        lut.write_cube(&path).unwrap();
+        let read = Lut3d::from_cube_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Lut3d::from_cube(IDENTITY).unwrap(), read.unwrap());
+    }
+
+    #[test]
+    fn malformed_cubes() {
+        let error = |cube: &str| Lut3d::from_cube(cube).unwrap_err().to_string();
+
+        assert_eq!(
+            "Expected 8 colors for the size of the lut, got 7",
+            error(&IDENTITY.replace("1 1 1\n", ""))
+        );
+        assert_eq!(
+            "Value 1.5 on line 6 is out of range, expecting 0.0 to 1.0",
+            error(&IDENTITY.replace("1 0 0", "1.5 0 0"))
+        );
+        assert_eq!(
+            "Invalid number 0,5 on line 7",
+            error(&IDENTITY.replace("0 1 0", "0,5 1 0"))
+        );
+        assert_eq!(
+            "Expected 3 values on line 8, got 2",
+            error(&IDENTITY.replace("1 1 0", "1 1"))
+        );
+        assert_eq!("Missing LUT_3D_SIZE", error("0 0 0\n1 1 1\n"));
+        assert_eq!(
+            "Invalid lut size 1 on line 3, expecting 2 to 256",
+            error(&IDENTITY.replace("SIZE 2", "SIZE 1"))
+        );
+        assert_eq!(
+            "1D luts aren't supported, found LUT_1D_SIZE on line 1",
+            error("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n")
+        );
+        assert_eq!(
+            "Invalid domain from [0.0, 0.0, 0.0] to [1.0, 0.0, 1.0], the minimum should be below the maximum",
+            error(&format!("DOMAIN_MAX 1 0 1\n{IDENTITY}"))
+        );
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(0x0000, half_bits(0.0));
+        assert_eq!(0x3c00, half_bits(1.0));
+        assert_eq!(0x3800, half_bits(0.5));
+        assert_eq!(0x3555, half_bits(1.0 / 3.0));
+        assert_eq!(0x2e66, half_bits(0.1));
+    }
+}
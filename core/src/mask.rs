@@ -0,0 +1,312 @@
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindingResource, ComputePassDescriptor,
+    TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    capitalize, compute_work_group_count,
+    pool::{TexturePool, COPY_TEXTURE_USAGES, STORAGE_TEXTURE_USAGES},
+    texture_from_image, FiltersError, Image, Operation,
+};
+
+const MASK_SHADER: &str = include_str!("shaders/mask.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Applies the filters chained by `f` only where the mask allows it.
+    ///
+    /// The chain runs on a copy of the current image, then the filtered and original images are blended pixel per pixel,
+    /// weighted by the luminance of the mask multiplied by its alpha: white opaque pixels of the mask take the filtered
+    /// result, black or transparent ones keep the original.
+    ///
+    /// # Arguments
+    ///
+    /// * `mask` - The mask, which must have the same dimensions as the image.
+    /// * `f` - The filters to apply, which must not change the dimensions of the image. Their result is brought back
+    ///   to the pixel format of the image if they change it, like [`Operation::to_luma`] does.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::MismatchedDimensions`] if the mask, or the output of `f`, doesn't match the dimensions of the image.
+    pub fn masked<F>(mut self, mask: &Image, f: F) -> Result<Self, FiltersError>
+    where
+        F: FnOnce(Operation<'a>) -> Operation<'a>,
+    {
+        if self.dimensions() != (mask.width, mask.height) {
+            return Err(FiltersError::MismatchedDimensions {
+                expected: self.dimensions(),
+                actual: (mask.width, mask.height),
+            });
+        }
+
+        let name = "mask";
+        let capitalized_filter_name = capitalize(name);
+
+        let copy_texture = self
+            .pool
+            .take(self.device, self.texture_size, COPY_TEXTURE_USAGES);
+        self.encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            copy_texture.as_image_copy(),
+            self.texture_size,
+        );
+
+        let dimensions = self.dimensions();
+        let mut filtered = f(Operation {
+            device: self.device,
+            queue: self.queue,
+            pipelines: self.pipelines,
+            submissions: self.submissions,
+            readback_buffer: self.readback_buffer,
+            encoder: self.encoder,
+            texture: copy_texture,
+            texture_size: self.texture_size,
+            texture_usage: COPY_TEXTURE_USAGES,
+            format: self.format,
+            color_space: self.color_space,
+            pool: TexturePool::new(self.pipelines.texture_format(self.format)),
+            tileable: self.tileable,
+            radius: self.radius,
+            profiler: self.profiler.take(),
+        });
+        if dimensions != filtered.dimensions() {
+            return Err(FiltersError::MismatchedDimensions {
+                expected: dimensions,
+                actual: filtered.dimensions(),
+            });
+        }
+        // The chain may have changed the format, like with to_luma, while the mask pass blends in the one of the image.
+        filtered.convert_to(self.format);
+        self.encoder = filtered.encoder;
+        self.profiler = filtered.profiler;
+
+        let (mask_texture, _) = texture_from_image(self.device, self.queue, mask)?;
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline(name, MASK_SHADER, Bindings::Derived);
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &filtered
+                            .texture
+                            .create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &mask_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.pool.release(
+            filtered.texture_size,
+            filtered.texture_usage,
+            filtered.texture,
+        );
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    fn test_image() -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([128, 0, 0, 255]),
+                Rgba([0, 0, 54, 255]),
+                Rgba([0, 22, 0, 255]),
+                Rgba([12, 7, 32, 255]),
+            ],
+        }
+    }
+
+    fn mask(pixels: Vec<Rgba>) -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn masked_white_mask_applies_filter() {
+        let image = test_image();
+        let mask = mask(vec![Rgba([255, 255, 255, 255]); 4]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .masked(&mask, |operation| operation.inverse())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn masked_black_mask_is_identity() {
+        let image = test_image();
+        let mask = mask(vec![Rgba([0, 0, 0, 255]); 4]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .masked(&mask, |operation| operation.inverse())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn masked_half_mask() {
+        let image = test_image();
+        let mask = mask(vec![
+            Rgba([255, 255, 255, 255]),
+            Rgba([0, 0, 0, 255]),
+            Rgba([255, 255, 255, 255]),
+            Rgba([0, 0, 0, 255]),
+        ]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([127, 255, 255, 255]),
+                Rgba([0, 0, 54, 255]),
+                Rgba([255, 233, 255, 255]),
+                Rgba([12, 7, 32, 255]),
+            ],
+        };
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .masked(&mask, |operation| operation.inverse())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn masked_to_luma() {
+        let image = test_image();
+        let mask = mask(vec![
+            Rgba([255, 255, 255, 255]),
+            Rgba([0, 0, 0, 255]),
+            Rgba([255, 255, 255, 255]),
+            Rgba([0, 0, 0, 255]),
+        ]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let gray = image
+            .operation(&filters)
+            .unwrap()
+            .to_luma()
+            .to_rgba()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .masked(&mask, |operation| operation.to_luma())
+            .unwrap()
+            .execute()
+            .block_on();
+
+        let expected = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                gray.pixels[0],
+                image.pixels[1],
+                gray.pixels[2],
+                image.pixels[3],
+            ],
+        };
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn masked_mismatched_dimensions() {
+        let image = test_image();
+        let mask = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([255, 255, 255, 255])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .masked(&mask, |operation| operation.inverse());
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::MismatchedDimensions {
+                expected: (2, 2),
+                actual: (1, 1)
+            })
+        ));
+    }
+}
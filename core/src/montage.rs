@@ -0,0 +1,251 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    Extent3d, ImageCopyTexture, Origin3d, TextureAspect, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, check_texture_size, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES,
+    ColorSpace, FiltersError, Operation, Rgba,
+};
+
+const FILL_SHADER: &str = include_str!("shaders/fill.wgsl");
+
+/// Where [`Operation::montage`] places the second image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum MontageLayout {
+    /// To the right of the first image.
+    Horizontal,
+    /// Below the first image.
+    Vertical,
+}
+
+impl<'a> Operation<'a> {
+    /// Places the image of `other` next to the current one, like to show an image before and after filtering.
+    ///
+    /// The passes recorded for `other` are submitted right away, and its image is copied on the gpu, so that even
+    /// images too large to go through the cpu comfortably can be put side by side.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The operation whose image to place, brought to the color space of the current one.
+    /// * `layout` - Whether `other` goes to the right of the current image, or below it.
+    /// * `gutter` - How many pixels separate the two images.
+    /// * `color` - Fills the gutter, along with the space left around the smaller image when they don't have the
+    ///   same height, or width.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::MismatchedFormats`] if the operations don't work with the same precision, and
+    /// [`FiltersError::ImageTooLarge`] if the montage is larger than the gpu supports.
+    pub fn montage(
+        mut self,
+        other: Operation<'a>,
+        layout: MontageLayout,
+        gutter: u32,
+        color: Rgba,
+    ) -> Result<Self, FiltersError> {
+        let other = match self.color_space {
+            ColorSpace::Srgb => other.assume_srgb(),
+            ColorSpace::Linear => other.assume_linear(),
+        };
+        if other.format != self.format {
+            return Err(FiltersError::MismatchedFormats {
+                expected: self.format,
+                actual: other.format,
+            });
+        }
+
+        let (width, height) = self.dimensions();
+        let (other_width, other_height) = other.dimensions();
+        let (canvas, offset) = match layout {
+            MontageLayout::Horizontal => (
+                (
+                    width.saturating_add(gutter).saturating_add(other_width),
+                    height.max(other_height),
+                ),
+                (width + gutter, 0),
+            ),
+            MontageLayout::Vertical => (
+                (
+                    width.max(other_width),
+                    height.saturating_add(gutter).saturating_add(other_height),
+                ),
+                (0, height + gutter),
+            ),
+        };
+        check_texture_size(self.device, canvas)?;
+
+        let other_usage = other.texture_usage;
+        let (other_texture, other_size) = other.submit();
+        let canvas_size = Extent3d {
+            width: canvas.0,
+            height: canvas.1,
+            depth_or_array_layers: 1,
+        };
+        let canvas_usage = STORAGE_TEXTURE_USAGES.union(wgpu::TextureUsages::COPY_DST);
+        let canvas_texture = self.pool.take(self.device, canvas_size, canvas_usage);
+
+        self.fill(&canvas_texture, canvas_size, color);
+        for (texture, size, (x, y)) in [
+            (&self.texture, self.texture_size, (0, 0)),
+            (&other_texture, other_size, offset),
+        ] {
+            self.encoder.copy_texture_to_texture(
+                texture.as_image_copy(),
+                ImageCopyTexture {
+                    texture: &canvas_texture,
+                    mip_level: 0,
+                    origin: Origin3d { x, y, z: 0 },
+                    aspect: TextureAspect::All,
+                },
+                size,
+            );
+        }
+
+        self.pool.release(other_size, other_usage, other_texture);
+        self.set_texture(canvas_texture, canvas_size, canvas_usage);
+        self.tileable = false;
+
+        Ok(self)
+    }
+
+    /// Records a pass setting every pixel of `texture` to `color`, decoded to linear values if the image is.
+    fn fill(&mut self, texture: &wgpu::Texture, size: Extent3d, color: Rgba) {
+        let name = "fill";
+        let color = self.color_values(color);
+
+        let pipeline = self.pipeline(name, FILL_SHADER, Bindings::Derived);
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fill settings"),
+            contents: bytemuck::cast_slice(&color),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Fill bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: settings.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) = compute_work_group_count(
+                (size.width, size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Fill pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, MontageLayout, Rgba};
+
+    fn test_image(width: u32, height: u32) -> Image {
+        Image::from_fn(width, height, |x, y| {
+            Rgba::new(x as u8 * 40, y as u8 * 40, 200, 255)
+        })
+    }
+
+    #[test]
+    fn before_and_after() {
+        let image = test_image(4, 3);
+        let filters = Filters::new().block_on().unwrap();
+        let gutter = Rgba::new(255, 0, 255, 255);
+
+        let mut before = image.operation(&filters).unwrap();
+        let after = before.fork().inverse();
+        let montage = before
+            .montage(after, MontageLayout::Horizontal, 2, gutter)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        let inverted = image
+            .operation(&filters)
+            .unwrap()
+            .inverse()
+            .execute()
+            .block_on();
+        assert_eq!((10, 3), (montage.width, montage.height));
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(image.pixel(x, y), montage.pixel(x, y));
+                assert_eq!(inverted.pixel(x, y), montage.pixel(x + 6, y));
+            }
+            assert_eq!(Some(&gutter), montage.pixel(4, y));
+            assert_eq!(Some(&gutter), montage.pixel(5, y));
+        }
+    }
+
+    #[test]
+    fn vertical_with_different_widths() {
+        let filters = Filters::new().block_on().unwrap();
+        let top = test_image(3, 2);
+        let bottom = test_image(5, 1);
+        let gutter = Rgba::new(0, 0, 0, 0);
+
+        let montage = top
+            .operation(&filters)
+            .unwrap()
+            .montage(
+                bottom.operation(&filters).unwrap(),
+                MontageLayout::Vertical,
+                1,
+                gutter,
+            )
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((5, 4), (montage.width, montage.height));
+        assert_eq!(top.pixel(2, 1), montage.pixel(2, 1));
+        // The space left of the narrower top image is filled like the gutter.
+        assert_eq!(Some(&gutter), montage.pixel(4, 0));
+        assert_eq!(Some(&gutter), montage.pixel(0, 2));
+        assert_eq!(bottom.pixel(4, 0), montage.pixel(4, 3));
+    }
+
+    #[test]
+    fn mismatched_formats() {
+        let filters = Filters::new().block_on().unwrap();
+        let image = test_image(2, 2);
+
+        let result = image.operation(&filters).unwrap().montage(
+            image.operation(&filters).unwrap().to_luma(),
+            MontageLayout::Horizontal,
+            0,
+            Rgba::new(0, 0, 0, 255),
+        );
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::MismatchedFormats { .. })
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+use wgpu::{Buffer, BufferAsyncError, Device};
+
+use crate::{encode_texture_to_buffer, read_mapped_buffer, Image, Operation, Rgba};
+
+impl<'a> Operation<'a> {
+    /// Like [`Operation::execute`], but never blocks the calling thread while the gpu works, so it can be awaited
+    /// inside an async runtime like tokio without stalling the other tasks.
+    ///
+    /// The returned future completes once the readback buffer is mapped. Until then, each time it is polled it
+    /// polls the device without waiting, then yields to the executor, asking to be polled again.
+    pub fn execute_nonblocking(mut self) -> impl Future<Output = Image> + 'a {
+        let (width, height) = self.dimensions();
+        let device = self.device;
+        self.convert_to_rgba8();
+        let output_buffer = encode_texture_to_buffer::<Rgba>(
+            device,
+            &mut self.encoder,
+            width,
+            height,
+            &self.texture,
+        );
+        self.submit();
+
+        async move {
+            let _ = map_read(device, &output_buffer).await;
+            read_mapped_buffer(width, height, &output_buffer)
+        }
+    }
+}
+
+/// Starts mapping `buffer` for reading, returning a future that completes once it is mapped, or failed to be.
+/// Callers that ignore the failure leave it to panic when the buffer is read.
+pub(crate) fn map_read<'a>(device: &'a Device, buffer: &Buffer) -> MapFuture<'a> {
+    let state = Arc::new(Mutex::new(MapState::default()));
+    let callback_state = state.clone();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let mut state = callback_state
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+    MapFuture { device, state }
+}
+
+/// Set by the map_async callback, which may run on whichever thread polls the device.
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Resolves once a buffer is mapped, see [`map_read`].
+pub(crate) struct MapFuture<'a> {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    device: &'a Device,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl<'a> Future for MapFuture<'a> {
+    type Output = Result<(), BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Nobody else is guaranteed to poll the device, so do it here, without waiting on the gpu.
+        // In the browser, there is nothing to poll: the mapping completes on its own and wakes the task.
+        #[cfg(not(target_arch = "wasm32"))]
+        self.device.poll(wgpu::Maintain::Poll);
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(result) = state.result.clone() {
+            return Poll::Ready(result);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        drop(state);
+        #[cfg(not(target_arch = "wasm32"))]
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    fn image(seed: u8) -> Image {
+        Image {
+            width: 3,
+            height: 2,
+            pixels: (0..6)
+                .map(|index| Rgba([seed, index * 40, 0, 255]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn execute_nonblocking_matches_execute() {
+        let image = image(10);
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute_nonblocking()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn concurrent_executes_on_current_thread_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let filters = Filters::new().block_on().unwrap();
+        let (first, second) = (image(10), image(200));
+
+        let (first_output, second_output) = runtime.block_on(async {
+            tokio::join!(
+                first
+                    .operation(&filters)
+                    .unwrap()
+                    .inverse()
+                    .execute_nonblocking(),
+                second
+                    .operation(&filters)
+                    .unwrap()
+                    .inverse()
+                    .execute_nonblocking(),
+            )
+        });
+
+        assert_eq!(
+            first
+                .operation(&filters)
+                .unwrap()
+                .inverse()
+                .execute()
+                .block_on(),
+            first_output
+        );
+        assert_eq!(
+            second
+                .operation(&filters)
+                .unwrap()
+                .inverse()
+                .execute()
+                .block_on(),
+            second_output
+        );
+    }
+}
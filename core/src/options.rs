@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use wgpu::{
+    AdapterInfo, Backend, Backends, DeviceDescriptor, Features, Instance, Limits, PowerPreference,
+    TextureFormat, TextureUsages,
+};
+
+use crate::{Filters, FiltersError};
+
+/// Controls which gpu adapter [`Filters::with_options`] picks.
+#[derive(Debug, Clone)]
+pub struct FiltersOptions {
+    /// The graphics apis that can be used. Defaults to all of them, or to WebGPU only with the `wasm` feature.
+    pub backends: Backends,
+    /// Whether to prefer an integrated or a discrete gpu, when several are available.
+    pub power_preference: PowerPreference,
+    /// Forces a software adapter, if the platform provides one.
+    pub force_fallback_adapter: bool,
+    /// Picks the first adapter whose name contains this string, ignoring case.
+    /// When set, the power preference is ignored. Adapters can't be listed on wasm32, so no adapter is found there.
+    pub adapter_name_filter: Option<String>,
+    /// Passes the settings of filters like brightness as push constants, rather than in a uniform buffer, when the
+    /// adapter supports them. Defaults to true.
+    pub push_constants: bool,
+}
+
+impl Default for FiltersOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(not(feature = "wasm"))]
+            backends: Backends::all(),
+            #[cfg(feature = "wasm")]
+            backends: Backends::BROWSER_WEBGPU,
+            power_preference: PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            adapter_name_filter: None,
+            push_constants: true,
+        }
+    }
+}
+
+/// An adapter found by [`Filters::available_adapters`].
+#[derive(Debug, Clone)]
+pub struct AvailableAdapter {
+    /// The name, backend and device type of the adapter, among others.
+    pub info: AdapterInfo,
+    /// The best limits the adapter supports, like the largest texture it can create.
+    pub limits: Limits,
+}
+
+impl Filters {
+    /// Creates the filters on the adapter matching the given options.
+    pub async fn with_options(options: FiltersOptions) -> Result<Self, FiltersError> {
+        let instance = Instance::new(options.backends);
+        let adapter = match &options.adapter_name_filter {
+            #[cfg(target_arch = "wasm32")]
+            Some(_) => None,
+            #[cfg(not(target_arch = "wasm32"))]
+            Some(name_filter) => {
+                let name_filter = name_filter.to_lowercase();
+                instance
+                    .enumerate_adapters(options.backends)
+                    .filter(|adapter| {
+                        !options.force_fallback_adapter
+                            || adapter.get_info().device_type == wgpu::DeviceType::Cpu
+                    })
+                    .find(|adapter| {
+                        adapter
+                            .get_info()
+                            .name
+                            .to_lowercase()
+                            .contains(&name_filter)
+                    })
+            }
+            None => {
+                instance
+                    .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                        power_preference: options.power_preference,
+                        force_fallback_adapter: options.force_fallback_adapter,
+                        compatible_surface: None,
+                    })
+                    .await
+            }
+        }
+        .ok_or(FiltersError::NoAdapter)?;
+        // Writing single channel textures from a compute shader is an adapter specific feature, see PixelFormat::Luma.
+        let single_channel_storage = adapter
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            && adapter
+                .get_texture_format_features(TextureFormat::R8Unorm)
+                .allowed_usages
+                .contains(TextureUsages::STORAGE_BINDING);
+        let mut features = if single_channel_storage {
+            Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+        } else {
+            Features::empty()
+        };
+        // Timing the passes, see Filters::with_profiling, needs timestamp queries.
+        features |= adapter.features() & Features::TIMESTAMP_QUERY;
+        let mut limits = Limits::default();
+        // The gl backend of wgpu 0.14 reads push constants through misaligned pointers, so it keeps the uniforms.
+        if options.push_constants
+            && adapter.features().contains(Features::PUSH_CONSTANTS)
+            && adapter.get_info().backend != Backend::Gl
+        {
+            features |= Features::PUSH_CONSTANTS;
+            limits.max_push_constant_size = adapter.limits().max_push_constant_size;
+        }
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    features,
+                    limits,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await?;
+
+        Ok(Self::from_device(Arc::new(device), Arc::new(queue)))
+    }
+
+    /// Lists the adapters available on this machine, with their name, backend and device type.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enumerate_adapters() -> Vec<AdapterInfo> {
+        Self::available_adapters()
+            .into_iter()
+            .map(|adapter| adapter.info)
+            .collect()
+    }
+
+    /// Lists the adapters available on this machine, like [`Filters::enumerate_adapters`], along with their limits.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn available_adapters() -> Vec<AvailableAdapter> {
+        Instance::new(Backends::all())
+            .enumerate_adapters(Backends::all())
+            .map(|adapter| AvailableAdapter {
+                info: adapter.get_info(),
+                limits: adapter.limits(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, FiltersOptions, Image, Rgba};
+
+    #[test]
+    fn grayscale_on_fallback_adapter() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([255, 0, 0, 255])],
+        };
+
+        let expected = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([76, 76, 76, 255])],
+        };
+        let filters = Filters::with_options(FiltersOptions {
+            force_fallback_adapter: true,
+            ..Default::default()
+        })
+        .block_on()
+        .unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn available_adapters_have_limits() {
+        let adapters = Filters::available_adapters();
+
+        assert!(!adapters.is_empty());
+        assert!(adapters
+            .iter()
+            .all(|adapter| adapter.limits.max_texture_dimension_2d >= 2048));
+        assert_eq!(
+            Filters::enumerate_adapters()
+                .into_iter()
+                .map(|info| info.name)
+                .collect::<Vec<_>>(),
+            adapters
+                .into_iter()
+                .map(|adapter| adapter.info.name)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn adapter_name_filter_without_match() {
+        let result = Filters::with_options(FiltersOptions {
+            adapter_name_filter: Some(String::from("no such adapter, surely")),
+            ..Default::default()
+        })
+        .block_on();
+
+        assert!(matches!(result, Err(FiltersError::NoAdapter)));
+    }
+}
@@ -0,0 +1,708 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{FilterChain, FilterStep, Resize, Rgba};
+
+/// The size of `boxblur` when it is given no argument.
+const DEFAULT_BOX_BLUR_SIZE: u32 = 15;
+/// The sigma of `gaussianblur` when it is given no argument.
+const DEFAULT_GAUSSIAN_SIGMA: f32 = 3.0;
+
+/// Why a string couldn't be parsed as a [`FilterChain`], see [`ParseError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// There is no filter between two `|`, or at the start or end of the string.
+    EmptyStep,
+    /// No filter has this name.
+    UnknownFilter(String),
+    /// The filter was given too few or too many arguments.
+    WrongArity {
+        filter: &'static str,
+        min: usize,
+        max: usize,
+        actual: usize,
+    },
+    /// An argument isn't a number of the expected type.
+    InvalidNumber(String),
+    /// An argument isn't a color written as `#rrggbb` or `#rrggbbaa`.
+    InvalidColor(String),
+    /// A resize mode isn't one of linear, nearest, cubic, lanczos3 or area.
+    UnknownResizeMode(String),
+    /// The arguments of a filter aren't closed by a `)`.
+    UnclosedParenthesis,
+    /// Nothing follows the `=` of a filter, named here.
+    MissingValue(String),
+    /// The dimensions given after a `=` aren't separated by an `x`, like `800x600`.
+    MissingDimensionSeparator(String),
+    /// Something follows the arguments of a filter, before the next `|`.
+    TrailingCharacters(String),
+}
+
+/// An error of [`FilterChain::parse`], pointing at the offending part of the string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset of the offending token in the parsed string.
+    pub position: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::EmptyStep => write!(f, "Expecting a filter"),
+            ParseErrorKind::UnknownFilter(name) => write!(f, "Unknown filter {name}"),
+            ParseErrorKind::WrongArity {
+                filter,
+                min,
+                max,
+                actual,
+            } => {
+                if min == max {
+                    write!(f, "{filter} expects {min} arguments, got {actual}")
+                } else {
+                    write!(f, "{filter} expects {min} to {max} arguments, got {actual}")
+                }
+            }
+            ParseErrorKind::InvalidNumber(token) => write!(f, "Invalid number {token}"),
+            ParseErrorKind::InvalidColor(token) => {
+                write!(f, "Invalid color {token}, expecting #rrggbb or #rrggbbaa")
+            }
+            ParseErrorKind::UnknownResizeMode(token) => write!(
+                f,
+                "Unknown resize mode {token}, expecting one of linear, nearest, cubic, lanczos3 or area"
+            ),
+            ParseErrorKind::UnclosedParenthesis => write!(f, "Expecting a closing parenthesis"),
+            ParseErrorKind::MissingValue(filter) => write!(f, "Expecting a value after {filter}="),
+            ParseErrorKind::MissingDimensionSeparator(token) => write!(
+                f,
+                "Expecting dimensions formatted as WIDTHxHEIGHT, got {token}"
+            ),
+            ParseErrorKind::TrailingCharacters(token) => {
+                write!(f, "Unexpected {token} after the arguments")
+            }
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A piece of the parsed string, with its position for error messages.
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Trims the whitespace around `text`, which starts at `position`.
+    fn trimmed(text: &'a str, position: usize) -> Self {
+        let trimmed_start = text.trim_start();
+        Self {
+            text: trimmed_start.trim_end(),
+            position: position + text.len() - trimmed_start.len(),
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind,
+        }
+    }
+
+    fn parse<T: FromStr>(&self) -> Result<T, ParseError> {
+        self.text
+            .parse()
+            .map_err(|_| self.error(ParseErrorKind::InvalidNumber(self.text.to_owned())))
+    }
+
+    /// Splits the token at each of the `separators`, trimming the pieces.
+    fn split(&self, separators: &[char]) -> Vec<Token<'a>> {
+        let mut position = self.position;
+        self.text
+            .split(separators)
+            .map(|piece| {
+                let token = Token::trimmed(piece, position);
+                position += piece.len() + 1;
+                token
+            })
+            .collect()
+    }
+
+    fn color(&self) -> Result<Rgba, ParseError> {
+        Rgba::from_hex(self.text)
+            .map_err(|_| self.error(ParseErrorKind::InvalidColor(self.text.to_owned())))
+    }
+
+    fn resize_mode(&self) -> Result<Resize, ParseError> {
+        match self.text.to_ascii_lowercase().as_str() {
+            "linear" => Ok(Resize::Linear),
+            "nearest" => Ok(Resize::Nearest),
+            "cubic" => Ok(Resize::Cubic),
+            "lanczos3" => Ok(Resize::Lanczos3),
+            "area" => Ok(Resize::Area),
+            _ => Err(self.error(ParseErrorKind::UnknownResizeMode(self.text.to_owned()))),
+        }
+    }
+}
+
+impl FilterChain {
+    /// Parses a chain written as filters separated by `|`, their arguments between parentheses, like
+    /// `grayscale|gaussianblur(3.0)|resize(800,600,linear)`. The arguments can also follow a `=`, dimensions being
+    /// separated by an `x` and the resize mode by a `:`, like `gaussianblur=3.0|resize=800x600:linear`.
+    ///
+    /// The filters are named like [`FilterStep::name`]. Resize modes are linear, nearest, cubic, lanczos3 or area,
+    /// and can be left out for resize and fit, which then resize linearly. Without arguments, boxblur has a size
+    /// of 15 and gaussianblur a sigma of 3.0. Colors are written in hex, like `splittone(#008080,#ff8000,0.2)`,
+    /// whose balance is 0.0 when left out.
+    ///
+    /// # Errors
+    ///
+    /// A [`ParseError`] with the position of the first token that can't be parsed.
+    pub fn parse(input: &str) -> Result<FilterChain, ParseError> {
+        let mut steps = Vec::new();
+        let mut position = 0;
+        for step in input.split('|') {
+            steps.push(parse_step(Token::trimmed(step, position))?);
+            position += step.len() + 1;
+        }
+
+        Ok(FilterChain::new(steps))
+    }
+}
+
+impl FromStr for FilterChain {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        FilterChain::parse(input)
+    }
+}
+
+/// Writes the chain in the syntax of [`FilterChain::parse`].
+impl Display for FilterChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                write!(f, "|")?;
+            }
+            write!(f, "{}", step.name())?;
+            match step {
+                FilterStep::Grayscale
+                | FilterStep::Inverse
+                | FilterStep::HFlip
+                | FilterStep::VFlip => {}
+                FilterStep::Resize {
+                    width,
+                    height,
+                    mode,
+                }
+                | FilterStep::Fit {
+                    width,
+                    height,
+                    mode,
+                } => write!(f, "({width},{height},{})", resize_mode_name(*mode))?,
+                FilterStep::Thumbnail { size } | FilterStep::BoxBlur { size } => {
+                    write!(f, "({size})")?
+                }
+                FilterStep::GaussianBlur { sigma: amount }
+                | FilterStep::FastBlur { sigma: amount }
+                | FilterStep::Brightness { amount }
+                | FilterStep::Contrast { amount }
+                | FilterStep::Sharpen { amount } => write!(f, "({amount:?})")?,
+                FilterStep::SplitTone {
+                    shadows,
+                    highlights,
+                    balance,
+                } => write!(
+                    f,
+                    "({},{},{balance:?})",
+                    shadows.to_hex(),
+                    highlights.to_hex()
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn resize_mode_name(mode: Resize) -> &'static str {
+    match mode {
+        Resize::Linear => "linear",
+        Resize::Nearest => "nearest",
+        Resize::Cubic => "cubic",
+        Resize::Lanczos3 => "lanczos3",
+        Resize::Area => "area",
+    }
+}
+
+/// Parses a single filter with its arguments, like `resize(800, 600)`.
+fn parse_step(step: Token) -> Result<FilterStep, ParseError> {
+    if step.text.is_empty() {
+        return Err(step.error(ParseErrorKind::EmptyStep));
+    }
+
+    // The number of values before the resize mode, when the arguments follow a `=`.
+    let (name, arguments, assigned) = match (step.text.find('('), step.text.find('=')) {
+        (None, None) => (step, Vec::new(), None),
+        (None, Some(equals)) => {
+            let name = Token::trimmed(&step.text[..equals], step.position);
+            let value = Token::trimmed(&step.text[equals + 1..], step.position + equals + 1);
+            if value.text.is_empty() {
+                return Err(value.error(ParseErrorKind::MissingValue(name.text.to_owned())));
+            }
+            let mut parts = value.split(&[':']).into_iter();
+            let mut arguments = parts
+                .next()
+                .map_or_else(Vec::new, |values| values.split(&['x']));
+            let values = arguments.len();
+            arguments.extend(parts);
+            (name, arguments, Some(values))
+        }
+        (Some(open), _) => {
+            let name = Token::trimmed(&step.text[..open], step.position);
+            let rest = &step.text[open + 1..];
+            let close = rest.find(')').ok_or_else(|| {
+                Token {
+                    text: "",
+                    position: step.position + step.text.len(),
+                }
+                .error(ParseErrorKind::UnclosedParenthesis)
+            })?;
+            let rest_position = step.position + open + 1;
+            if close + 1 < rest.len() {
+                let trailing = Token::trimmed(&rest[close + 1..], rest_position + close + 1);
+                return Err(
+                    trailing.error(ParseErrorKind::TrailingCharacters(trailing.text.to_owned()))
+                );
+            }
+
+            let arguments = Token {
+                text: &rest[..close],
+                position: rest_position,
+            };
+            let arguments = if arguments.text.trim().is_empty() {
+                Vec::new()
+            } else {
+                arguments.split(&[','])
+            };
+            (name, arguments, None)
+        }
+    };
+
+    let arity = |filter: &'static str, min: usize, max: usize| {
+        if (min..=max).contains(&arguments.len()) {
+            Ok(())
+        } else {
+            Err(name.error(ParseErrorKind::WrongArity {
+                filter,
+                min,
+                max,
+                actual: arguments.len(),
+            }))
+        }
+    };
+    let dimensions = |filter: &'static str| -> Result<(u32, u32), ParseError> {
+        if assigned == Some(1) {
+            return Err(
+                arguments[0].error(ParseErrorKind::MissingDimensionSeparator(
+                    arguments[0].text.to_owned(),
+                )),
+            );
+        }
+        arity(filter, 2, 3)?;
+        Ok((arguments[0].parse()?, arguments[1].parse()?))
+    };
+    let mode = |index: usize| {
+        arguments
+            .get(index)
+            .map_or(Ok(Resize::Linear), Token::resize_mode)
+    };
+
+    Ok(match name.text.to_ascii_lowercase().as_str() {
+        "grayscale" => {
+            arity("grayscale", 0, 0)?;
+            FilterStep::Grayscale
+        }
+        "inverse" => {
+            arity("inverse", 0, 0)?;
+            FilterStep::Inverse
+        }
+        "hflip" => {
+            arity("hflip", 0, 0)?;
+            FilterStep::HFlip
+        }
+        "vflip" => {
+            arity("vflip", 0, 0)?;
+            FilterStep::VFlip
+        }
+        "resize" => {
+            let (width, height) = dimensions("resize")?;
+            FilterStep::Resize {
+                width,
+                height,
+                mode: mode(2)?,
+            }
+        }
+        "fit" => {
+            let (width, height) = dimensions("fit")?;
+            FilterStep::Fit {
+                width,
+                height,
+                mode: mode(2)?,
+            }
+        }
+        "thumbnail" => {
+            arity("thumbnail", 1, 1)?;
+            FilterStep::Thumbnail {
+                size: arguments[0].parse()?,
+            }
+        }
+        "boxblur" => {
+            arity("boxblur", 0, 1)?;
+            FilterStep::BoxBlur {
+                size: arguments
+                    .first()
+                    .map_or(Ok(DEFAULT_BOX_BLUR_SIZE), Token::parse)?,
+            }
+        }
+        "gaussianblur" => {
+            arity("gaussianblur", 0, 1)?;
+            FilterStep::GaussianBlur {
+                sigma: arguments
+                    .first()
+                    .map_or(Ok(DEFAULT_GAUSSIAN_SIGMA), Token::parse)?,
+            }
+        }
+        "fastblur" => {
+            arity("fastblur", 1, 1)?;
+            FilterStep::FastBlur {
+                sigma: arguments[0].parse()?,
+            }
+        }
+        "brightness" => {
+            arity("brightness", 1, 1)?;
+            FilterStep::Brightness {
+                amount: arguments[0].parse()?,
+            }
+        }
+        "contrast" => {
+            arity("contrast", 1, 1)?;
+            FilterStep::Contrast {
+                amount: arguments[0].parse()?,
+            }
+        }
+        "sharpen" => {
+            arity("sharpen", 1, 1)?;
+            FilterStep::Sharpen {
+                amount: arguments[0].parse()?,
+            }
+        }
+        "splittone" => {
+            arity("splittone", 2, 3)?;
+            FilterStep::SplitTone {
+                shadows: arguments[0].color()?,
+                highlights: arguments[1].color()?,
+                balance: arguments.get(2).map_or(Ok(0.0), Token::parse)?,
+            }
+        }
+        _ => return Err(name.error(ParseErrorKind::UnknownFilter(name.text.to_owned()))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FilterChain, FilterStep, ParseError, ParseErrorKind, Resize, Rgba};
+
+    #[test]
+    fn parse_chain() {
+        let chain =
+            FilterChain::parse("grayscale|gaussianblur(3.0)|resize(800,600,linear)").unwrap();
+
+        assert_eq!(
+            FilterChain::new(vec![
+                FilterStep::Grayscale,
+                FilterStep::GaussianBlur { sigma: 3.0 },
+                FilterStep::Resize {
+                    width: 800,
+                    height: 600,
+                    mode: Resize::Linear
+                },
+            ]),
+            chain
+        );
+    }
+
+    #[test]
+    fn parse_whitespace_and_defaults() {
+        let chain =
+            FilterChain::parse(" HFlip | fit( 256 , 128 ) | boxblur(5) | brightness(-0.25)")
+                .unwrap();
+
+        assert_eq!(
+            FilterChain::new(vec![
+                FilterStep::HFlip,
+                FilterStep::Fit {
+                    width: 256,
+                    height: 128,
+                    mode: Resize::Linear
+                },
+                FilterStep::BoxBlur { size: 5 },
+                FilterStep::Brightness { amount: -0.25 },
+            ]),
+            chain
+        );
+    }
+
+    #[test]
+    fn parse_assigned_arguments() {
+        let chain = FilterChain::parse(
+            "gaussianblur=4.5|boxblur = 21|resize=800x600:nearest|fit=64x32|thumbnail=128|brightness=-0.1|fastblur=2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            FilterChain::new(vec![
+                FilterStep::GaussianBlur { sigma: 4.5 },
+                FilterStep::BoxBlur { size: 21 },
+                FilterStep::Resize {
+                    width: 800,
+                    height: 600,
+                    mode: Resize::Nearest
+                },
+                FilterStep::Fit {
+                    width: 64,
+                    height: 32,
+                    mode: Resize::Linear
+                },
+                FilterStep::Thumbnail { size: 128 },
+                FilterStep::Brightness { amount: -0.1 },
+                FilterStep::FastBlur { sigma: 2.0 },
+            ]),
+            chain
+        );
+    }
+
+    #[test]
+    fn parse_blur_defaults() {
+        assert_eq!(
+            Ok(FilterChain::new(vec![
+                FilterStep::BoxBlur { size: 15 },
+                FilterStep::GaussianBlur { sigma: 3.0 },
+                FilterStep::GaussianBlur { sigma: 3.0 },
+            ])),
+            FilterChain::parse("boxblur|gaussianblur|gaussianblur()")
+        );
+    }
+
+    #[test]
+    fn parse_malformed_assigned_arguments() {
+        assert_eq!(
+            Err(ParseError {
+                position: 13,
+                kind: ParseErrorKind::MissingValue("gaussianblur".to_owned())
+            }),
+            FilterChain::parse("gaussianblur=")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 7,
+                kind: ParseErrorKind::MissingDimensionSeparator("800".to_owned())
+            }),
+            FilterChain::parse("resize=800:nearest")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 8,
+                kind: ParseErrorKind::InvalidNumber("4,5".to_owned())
+            }),
+            FilterChain::parse("boxblur=4,5")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 11,
+                kind: ParseErrorKind::InvalidNumber("60o".to_owned())
+            }),
+            FilterChain::parse("resize=800x60o")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 15,
+                kind: ParseErrorKind::UnknownResizeMode("bilinear".to_owned())
+            }),
+            FilterChain::parse("resize=800x600:bilinear")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 0,
+                kind: ParseErrorKind::WrongArity {
+                    filter: "gaussianblur",
+                    min: 0,
+                    max: 1,
+                    actual: 2
+                }
+            }),
+            FilterChain::parse("gaussianblur=3:linear")
+        );
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let chain = FilterChain::new(vec![
+            FilterStep::VFlip,
+            FilterStep::Thumbnail { size: 64 },
+            FilterStep::Resize {
+                width: 10,
+                height: 20,
+                mode: Resize::Lanczos3,
+            },
+            FilterStep::Sharpen { amount: 0.5 },
+            FilterStep::Contrast { amount: 1.0 },
+            FilterStep::FastBlur { sigma: 25.0 },
+            FilterStep::SplitTone {
+                shadows: Rgba::new(0, 128, 128, 255),
+                highlights: Rgba::new(255, 128, 0, 200),
+                balance: 0.25,
+            },
+        ]);
+
+        let text = chain.to_string();
+
+        assert_eq!(
+            "vflip|thumbnail(64)|resize(10,20,lanczos3)|sharpen(0.5)|contrast(1.0)|fastblur(25.0)|splittone(#008080ff,#ff8000c8,0.25)",
+            text
+        );
+        assert_eq!(Ok(chain), FilterChain::parse(&text));
+    }
+
+    #[test]
+    fn parse_colors() {
+        let teal = Rgba::new(0, 128, 128, 255);
+        let orange = Rgba::new(255, 128, 0, 255);
+
+        assert_eq!(
+            Ok(FilterChain::new(vec![
+                FilterStep::SplitTone {
+                    shadows: teal,
+                    highlights: orange,
+                    balance: 0.0,
+                },
+                FilterStep::SplitTone {
+                    shadows: orange,
+                    highlights: teal,
+                    balance: -0.5,
+                },
+            ])),
+            FilterChain::parse("splittone(#008080, #ff8000)|splittone(ff8000ff,#008080,-0.5)")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 18,
+                kind: ParseErrorKind::InvalidColor("#ff80".to_owned())
+            }),
+            FilterChain::parse("splittone(#008080,#ff80)")
+        );
+    }
+
+    #[test]
+    fn parse_unknown_filter() {
+        assert_eq!(
+            Err(ParseError {
+                position: 10,
+                kind: ParseErrorKind::UnknownFilter("sepia".to_owned())
+            }),
+            FilterChain::parse("grayscale|sepia")
+        );
+    }
+
+    #[test]
+    fn parse_wrong_arity() {
+        assert_eq!(
+            Err(ParseError {
+                position: 0,
+                kind: ParseErrorKind::WrongArity {
+                    filter: "resize",
+                    min: 2,
+                    max: 3,
+                    actual: 1
+                }
+            }),
+            FilterChain::parse("resize(800)")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 10,
+                kind: ParseErrorKind::WrongArity {
+                    filter: "grayscale",
+                    min: 0,
+                    max: 0,
+                    actual: 1
+                }
+            }),
+            FilterChain::parse("inverse | grayscale(1)")
+        );
+    }
+
+    #[test]
+    fn parse_bad_numbers() {
+        assert_eq!(
+            Err(ParseError {
+                position: 17,
+                kind: ParseErrorKind::InvalidNumber("1.5".to_owned())
+            }),
+            FilterChain::parse("inverse|boxblur( 1.5)")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 11,
+                kind: ParseErrorKind::InvalidNumber("-600".to_owned())
+            }),
+            FilterChain::parse("resize(800,-600)")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 13,
+                kind: ParseErrorKind::InvalidNumber("three".to_owned())
+            }),
+            FilterChain::parse("gaussianblur(three)")
+        );
+    }
+
+    #[test]
+    fn parse_malformed() {
+        assert_eq!(
+            Err(ParseError {
+                position: 10,
+                kind: ParseErrorKind::EmptyStep
+            }),
+            FilterChain::parse("grayscale|")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 14,
+                kind: ParseErrorKind::UnclosedParenthesis
+            }),
+            FilterChain::parse("gaussianblur(3")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 16,
+                kind: ParseErrorKind::TrailingCharacters("x".to_owned())
+            }),
+            FilterChain::parse("gaussianblur(3) x")
+        );
+        assert_eq!(
+            Err(ParseError {
+                position: 15,
+                kind: ParseErrorKind::UnknownResizeMode("bilinear".to_owned())
+            }),
+            FilterChain::parse("resize(80, 60, bilinear)")
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use wgpu::Extent3d;
+
+use crate::{cache::Bindings, check_texture_size, FiltersError, Operation};
+
+const PIXEL_ART_SHADER: &str = include_str!("shaders/pixel_art.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Upscales pixel art by `factor` with the Scale2x and Scale3x rules, which round the diagonals of the sprites
+    /// while keeping their edges hard and their palette untouched, where [`crate::Resize::Linear`] would blur them
+    /// and [`Operation::scale_integer`] would only grow the staircases.
+    ///
+    /// Each pixel becomes a block of `factor` by `factor` pixels, some taking the color of a neighbor when two of
+    /// its neighbors have the same color, like on a diagonal. Colors are only compared for equality, so the rules
+    /// never create new colors.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - 2 for Scale2x, 3 for Scale3x, or 4 for Scale2x applied twice, the so-called Scale4x.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidScaleFactor`] for any other factor, or [`FiltersError::UnsupportedSize`] if the upscaled
+    /// image is too large.
+    pub fn pixel_art_upscale(self, factor: u32) -> Result<Self, FiltersError> {
+        match factor {
+            2 | 3 => self.scale_x(factor),
+            4 => self.scale_x(2)?.scale_x(2),
+            _ => Err(FiltersError::InvalidScaleFactor(factor)),
+        }
+    }
+
+    /// A pass of Scale2x or Scale3x, for a `factor` of 2 or 3.
+    fn scale_x(mut self, factor: u32) -> Result<Self, FiltersError> {
+        let width = self.texture_size.width.saturating_mul(factor);
+        let height = self.texture_size.height.saturating_mul(factor);
+        check_texture_size(self.device, (width, height))?;
+        let output_size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let name = "pixel art upscale";
+        let pipeline = self.pipeline(name, PIXEL_ART_SHADER, Bindings::Uniform);
+        self.tileable = false;
+        Ok(self.settings_pass(
+            name,
+            &pipeline,
+            bytemuck::bytes_of(&factor),
+            None,
+            output_size,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    const BACKGROUND: Rgba = Rgba([40, 60, 80, 255]);
+    const INK: Rgba = Rgba([250, 200, 20, 255]);
+
+    /// An image from rows of `X` for ink and `.` for the background.
+    fn sprite(rows: &[&str]) -> Image {
+        Image::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            match rows[y as usize].as_bytes()[x as usize] {
+                b'X' => INK,
+                _ => BACKGROUND,
+            }
+        })
+    }
+
+    /// A staircase and a bend, on a background.
+    fn stairs() -> Image {
+        sprite(&[
+            ".....", //
+            ".X...", //
+            "..XX.", //
+            "..X..", //
+            ".....", //
+        ])
+    }
+
+    fn upscale(filters: &Filters, image: &Image, factor: u32) -> Image {
+        image
+            .operation(filters)
+            .unwrap()
+            .pixel_art_upscale(factor)
+            .unwrap()
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn scale2x_reference() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let upscaled = upscale(&filters, &stairs(), 2);
+
+        let expected = sprite(&[
+            "..........", //
+            "..........", //
+            "..XX......", //
+            "..XXX.....", //
+            "...X.XXX..", //
+            "....XXXX..", //
+            "....XXX...", //
+            "....XX....", //
+            "..........", //
+            "..........", //
+        ]);
+        assert_eq!(expected, upscaled);
+    }
+
+    #[test]
+    fn scale3x_reference() {
+        let filters = Filters::new().block_on().unwrap();
+
+        let upscaled = upscale(&filters, &stairs(), 3);
+
+        let expected = sprite(&[
+            "...............", //
+            "...............", //
+            "...............", //
+            "...XXX.........", //
+            "...XXX.........", //
+            "...XXXXX.......", //
+            ".....X..XXXX...", //
+            ".....X.XXXXX...", //
+            "......XXXXXX...", //
+            "......XXXX.....", //
+            "......XXX......", //
+            "......XXX......", //
+            "...............", //
+            "...............", //
+            "...............", //
+        ]);
+        assert_eq!(expected, upscaled);
+    }
+
+    #[test]
+    fn scale4x_is_scale2x_twice() {
+        let image = stairs();
+        let filters = Filters::new().block_on().unwrap();
+
+        let twice = image
+            .operation(&filters)
+            .unwrap()
+            .pixel_art_upscale(2)
+            .unwrap()
+            .pixel_art_upscale(2)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((20, 20), (twice.width, twice.height));
+        assert_eq!(twice, upscale(&filters, &image, 4));
+    }
+
+    #[test]
+    fn unsupported_factors() {
+        let image = stairs();
+        let filters = Filters::new().block_on().unwrap();
+
+        for factor in [0, 1, 5] {
+            assert!(matches!(
+                image.operation(&filters).unwrap().pixel_art_upscale(factor),
+                Err(FiltersError::InvalidScaleFactor(f)) if f == factor
+            ));
+        }
+    }
+}
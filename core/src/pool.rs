@@ -0,0 +1,104 @@
+use wgpu::{
+    Device, Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+
+/// The usages of the textures filters write to from a compute shader.
+pub(crate) const STORAGE_TEXTURE_USAGES: TextureUsages = TextureUsages::TEXTURE_BINDING
+    .union(TextureUsages::COPY_SRC)
+    .union(TextureUsages::STORAGE_BINDING);
+/// The usages of the textures filters copy to.
+pub(crate) const COPY_TEXTURE_USAGES: TextureUsages = TextureUsages::TEXTURE_BINDING
+    .union(TextureUsages::COPY_SRC)
+    .union(TextureUsages::COPY_DST);
+
+/// Textures an operation doesn't need anymore, kept to be written to by later passes instead of
+/// allocating new ones. As all the passes of an operation are recorded in order in a single encoder,
+/// a texture can be reused as soon as the pass reading it has been recorded.
+pub(crate) struct TexturePool {
+    format: TextureFormat,
+    textures: Vec<(Extent3d, TextureUsages, Texture)>,
+    created: usize,
+}
+
+impl TexturePool {
+    /// Creates an empty pool of textures using `format`.
+    pub(crate) fn new(format: TextureFormat) -> Self {
+        Self {
+            format,
+            textures: Vec::new(),
+            created: 0,
+        }
+    }
+
+    /// Returns a texture of the given size and usages, reusing a released one if possible.
+    pub(crate) fn take(
+        &mut self,
+        device: &Device,
+        size: Extent3d,
+        usage: TextureUsages,
+    ) -> Texture {
+        let index = self
+            .textures
+            .iter()
+            .position(|(texture_size, texture_usage, _)| {
+                *texture_size == size && *texture_usage == usage
+            });
+        if let Some(index) = index {
+            return self.textures.swap_remove(index).2;
+        }
+
+        self.created += 1;
+        device.create_texture(&TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.format,
+            usage,
+        })
+    }
+
+    /// Hands a texture back, so that a later [`TexturePool::take`] can reuse it.
+    pub(crate) fn release(&mut self, size: Extent3d, usage: TextureUsages, texture: Texture) {
+        self.textures.push((size, usage, texture));
+    }
+
+    /// Moves the textures to a new pool, leaving this one empty. Lets textures outlive an operation, to be handed to
+    /// the next one.
+    pub(crate) fn take_all(&mut self) -> Self {
+        let empty = Self::new(self.format);
+        std::mem::replace(self, empty)
+    }
+
+    /// How many textures the pool had to allocate so far.
+    #[cfg(test)]
+    pub(crate) fn created(&self) -> usize {
+        self.created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    #[test]
+    fn same_size_filters_reuse_textures() {
+        let image = Image {
+            width: 4,
+            height: 4,
+            pixels: vec![Rgba([10, 20, 30, 255]); 16],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut operation = image.operation(&filters).unwrap();
+        for _ in 0..10 {
+            operation = operation.inverse();
+        }
+
+        assert_eq!(2, operation.pool.created());
+        assert_eq!(image, operation.execute().block_on());
+    }
+}
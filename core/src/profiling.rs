@@ -0,0 +1,280 @@
+use wgpu::{
+    BufferDescriptor, BufferUsages, CommandEncoder, Device, Features, QuerySet, QuerySetDescriptor,
+    QueryType,
+};
+
+use crate::{nonblocking::map_read, Image, Operation};
+
+/// Beyond this many passes in a single operation, the next ones are not timed.
+const MAX_TIMED_PASSES: u32 = 256;
+
+/// The time the gpu spent on a pass, see [`Operation::execute_profiled`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterTiming {
+    /// The name of the filter, or of the conversion, the pass belongs to.
+    pub name: String,
+    pub gpu_micros: f64,
+}
+
+/// Records a timestamp before and after each pass of an operation.
+pub(crate) struct Profiler {
+    query_set: QuerySet,
+    names: Vec<String>,
+}
+
+impl Profiler {
+    /// A profiler for `device`, or `None` if it doesn't support timestamp queries.
+    pub(crate) fn new(device: &Device) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        Some(Self {
+            query_set: device.create_query_set(&QuerySetDescriptor {
+                label: Some("Timestamps"),
+                ty: QueryType::Timestamp,
+                count: MAX_TIMED_PASSES * 2,
+            }),
+            names: Vec::new(),
+        })
+    }
+
+    /// Records the timestamp starting the pass `name`, returning its index if there is still room for it.
+    fn begin(&mut self, encoder: &mut CommandEncoder, name: &str) -> Option<u32> {
+        let index = self.names.len() as u32;
+        if index >= MAX_TIMED_PASSES {
+            return None;
+        }
+
+        encoder.write_timestamp(&self.query_set, index * 2);
+        self.names.push(name.to_owned());
+        Some(index)
+    }
+}
+
+/// Returned by [`Operation::begin_pass`], to hand back to [`Operation::end_pass`] once the pass is recorded.
+pub(crate) struct Pass {
+    /// The index of the pass in the profiler, if it is timed.
+    timing: Option<u32>,
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl<'a> Operation<'a> {
+    /// Starts recording a pass of the filter `name`: times it if the operation is profiled, and, with the
+    /// `tracing` feature, enters a span for it, in which the dispatches are logged.
+    pub(crate) fn begin_pass(&mut self, name: &str) -> Pass {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "filter",
+            name,
+            width = self.texture_size.width,
+            height = self.texture_size.height
+        )
+        .entered();
+
+        Pass {
+            timing: self
+                .profiler
+                .as_mut()
+                .and_then(|profiler| profiler.begin(&mut self.encoder, name)),
+            #[cfg(feature = "tracing")]
+            _span,
+        }
+    }
+
+    /// Ends the pass started by [`Operation::begin_pass`].
+    pub(crate) fn end_pass(&mut self, pass: Pass) {
+        if let (Some(profiler), Some(index)) = (&self.profiler, pass.timing) {
+            self.encoder
+                .write_timestamp(&profiler.query_set, index * 2 + 1);
+        }
+    }
+
+    /// Like [`Operation::execute`], but also returns how long the gpu spent on each pass, in order.
+    ///
+    /// The timings are only measured when the filters were created with [`crate::Filters::with_profiling`],
+    /// on a device supporting `Features::TIMESTAMP_QUERY`. Otherwise, they are empty.
+    pub async fn execute_profiled(mut self) -> (Image, Vec<FilterTiming>) {
+        self.convert_to_rgba8();
+        let Some(profiler) = self
+            .profiler
+            .take()
+            .filter(|profiler| !profiler.names.is_empty())
+        else {
+            return (self.execute().await, Vec::new());
+        };
+
+        let count = profiler.names.len() as u32 * 2;
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        let readback_buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Timestamps readback"),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        self.encoder
+            .resolve_query_set(&profiler.query_set, 0..count, &readback_buffer, 0);
+
+        let (device, queue) = (self.device, self.queue);
+        let image = self.execute().await;
+
+        let _ = map_read(device, &readback_buffer).await;
+        let timestamps: Vec<u64> =
+            bytemuck::cast_slice(&readback_buffer.slice(..).get_mapped_range()).to_vec();
+        readback_buffer.unmap();
+
+        // Timestamps count ticks of the gpu clock, which last this many nanoseconds.
+        let period = queue.get_timestamp_period() as f64;
+        let timings = profiler
+            .names
+            .into_iter()
+            .zip(timestamps.chunks_exact(2))
+            .map(|(name, timestamps)| FilterTiming {
+                name,
+                gpu_micros: timestamps[1].saturating_sub(timestamps[0]) as f64 * period / 1000.0,
+            })
+            .collect();
+
+        (image, timings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+    use wgpu::Features;
+
+    use crate::{Filters, Image, Rgba};
+
+    fn large_image() -> Image {
+        Image {
+            width: 1024,
+            height: 1024,
+            pixels: vec![Rgba([120, 30, 200, 255]); 1024 * 1024],
+        }
+    }
+
+    #[test]
+    fn execute_profiled_times_each_filter() {
+        let image = large_image();
+        let filters = Filters::new().block_on().unwrap().with_profiling(true);
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .gaussian_blur(4.0)
+            .execute()
+            .block_on();
+        let (output, timings) = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .gaussian_blur(4.0)
+            .execute_profiled()
+            .block_on();
+
+        assert_eq!(expected, output);
+        if filters
+            .device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+        {
+            let names: Vec<&str> = timings.iter().map(|timing| timing.name.as_str()).collect();
+            assert_eq!(vec!["grayscale", "gaussian blur"], names);
+            assert!(timings[1].gpu_micros > 0.0);
+        } else {
+            assert!(timings.is_empty());
+        }
+    }
+
+    #[test]
+    fn execute_profiled_without_profiling() {
+        let image = large_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let (_, timings) = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(4.0)
+            .execute_profiled()
+            .block_on();
+
+        assert!(timings.is_empty());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn grayscale_span_has_dimensions() {
+        use std::{
+            collections::HashMap,
+            sync::{Arc, Mutex},
+        };
+
+        use tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id},
+            Subscriber,
+        };
+        use tracing_subscriber::{layer::Context, prelude::*, Layer};
+
+        type Fields = HashMap<&'static str, String>;
+
+        /// Keeps the name and fields of every span created.
+        #[derive(Clone, Default)]
+        struct SpanRecorder(Arc<Mutex<Vec<(&'static str, Fields)>>>);
+
+        struct FieldVisitor<'a>(&'a mut Fields);
+
+        impl Visit for FieldVisitor<'_> {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.insert(field.name(), value.to_owned());
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0.insert(field.name(), format!("{value:?}"));
+            }
+        }
+
+        impl<S: Subscriber> Layer<S> for SpanRecorder {
+            fn on_new_span(&self, attributes: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+                let mut fields = Fields::new();
+                attributes.record(&mut FieldVisitor(&mut fields));
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((attributes.metadata().name(), fields));
+            }
+        }
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let image = Image {
+                width: 3,
+                height: 2,
+                pixels: vec![Rgba([10, 20, 30, 255]); 6],
+            };
+            let filters = Filters::new().block_on().unwrap();
+            image
+                .operation(&filters)
+                .unwrap()
+                .grayscale()
+                .execute()
+                .block_on();
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        let filter = spans
+            .iter()
+            .find(|(name, _)| *name == "filter")
+            .map(|(_, fields)| fields)
+            .unwrap();
+        assert_eq!("grayscale", filter["name"]);
+        assert_eq!("3", filter["width"]);
+        assert_eq!("2", filter["height"]);
+        let names: Vec<&str> = spans.iter().map(|(name, _)| *name).collect();
+        assert_eq!(vec!["upload", "filter", "readback"], names);
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{FiltersError, Image};
+
+/// Cancels a long running batch, or tiled processing, from another thread or from a progress callback.
+///
+/// Clones share the same state: cancelling any of them cancels the work they were handed to.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the work to stop. Nothing new is submitted to the gpu once this is called, and the results finished
+    /// afterwards are dropped.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns [`FiltersError::Cancelled`] if the token was cancelled.
+    pub(crate) fn check(&self) -> Result<(), FiltersError> {
+        if self.is_cancelled() {
+            Err(FiltersError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Reported each time an image of a batch, or a tile of a tiled image, is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// The index of the image that is done, or of the tile, counted row by row.
+    pub index: usize,
+    /// How many images, or tiles, are done so far, this one included.
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// The error of [`crate::Filters::batch_with_progress`], along with the images finished before it happened.
+#[derive(Debug)]
+pub struct BatchError {
+    /// The results of the first images, in order, which can be kept even though the batch stopped.
+    pub completed: Vec<Image>,
+    /// Why the batch stopped, like [`FiltersError::Cancelled`].
+    pub error: FiltersError,
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Batch stopped after {} images: {}",
+            self.completed.len(),
+            self.error
+        )
+    }
+}
+
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,292 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    Extent3d, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Filters, FiltersError,
+    Image, ImageF32, Operation, Resize,
+};
+
+const PYRAMID_SHADER: &str = include_str!("shaders/pyramid.wgsl");
+
+/// A laplacian pyramid, built by [`Operation::decompose_pyramid`]: the details of an image at successive scales,
+/// from the finest to the coarsest, along with the residual, what remains of the image once they are all taken
+/// away. Scaling a level before [`ImagePyramid::reconstruct`] boosts or softens the details of that scale only.
+#[derive(Debug)]
+pub struct ImagePyramid {
+    levels: Vec<ImageF32>,
+    residual: ImageF32,
+    /// How much of the colors of each level the reconstruction adds back.
+    gains: Vec<f32>,
+}
+
+impl ImagePyramid {
+    /// The details of each scale, from the finest, at the size of the image, each next level being half as large,
+    /// rounded up. They are differences between two scales, and as such can be negative.
+    pub fn levels(&self) -> &[ImageF32] {
+        &self.levels
+    }
+
+    /// The coarsest scale of the image, half as large as the last level.
+    pub fn residual(&self) -> &ImageF32 {
+        &self.residual
+    }
+
+    /// Multiplies the details of the colors of `level` by `factor` in the reconstruction, above 1.0 to boost
+    /// them and below to soften them. The details of the alpha are kept as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - The index of the level, 0 being the finest.
+    /// * `factor` - What the details are multiplied by, on top of the previous scalings of the level.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::InvalidPyramidLevel`] if the pyramid doesn't have that level.
+    pub fn scale_level(&mut self, level: usize, factor: f32) -> Result<(), FiltersError> {
+        let levels = self.gains.len();
+        let gain = self
+            .gains
+            .get_mut(level)
+            .ok_or(FiltersError::InvalidPyramidLevel { level, levels })?;
+        *gain *= factor;
+        Ok(())
+    }
+
+    /// Rebuilds the image on the gpu of `filters`, upsampling the residual and adding each level back to it, from
+    /// the coarsest to the finest. An untouched pyramid gives back the image it was decomposed from, up to the
+    /// precision of the half floats the passes work with.
+    ///
+    /// # Errors
+    ///
+    /// The same as [`ImageF32::operation`] if the gpu can't hold the levels.
+    pub async fn reconstruct(&self, filters: &Filters) -> Result<Image, FiltersError> {
+        let mut operation = self.residual.operation(filters)?;
+        for (level, &gain) in self.levels.iter().zip(&self.gains).rev() {
+            let size = Extent3d {
+                width: level.width,
+                height: level.height,
+                depth_or_array_layers: 1,
+            };
+            operation = operation
+                .encode_resize(size, Resize::Linear.into())
+                .add_level(level.operation(filters)?, [gain, gain, gain, 1.0]);
+        }
+
+        Ok(operation.execute().await)
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Decomposes the image into a laplacian pyramid: each level holds the details lost by averaging the image
+    /// down to half its size, the difference between the image and that average scaled back up, the next level
+    /// doing the same with the average. The levels are read back as floats, see [`ImagePyramid`].
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - How many levels of details to build, fewer if the image gets down to a single pixel first.
+    ///   With 0, the residual is the whole image.
+    pub async fn decompose_pyramid(mut self, levels: u32) -> ImagePyramid {
+        self.convert_to_float();
+
+        let mut details = Vec::new();
+        for _ in 0..levels {
+            let size = self.texture_size;
+            if size.width == 1 && size.height == 1 {
+                break;
+            }
+            let half = Extent3d {
+                width: size.width.div_ceil(2),
+                height: size.height.div_ceil(2),
+                depth_or_array_layers: 1,
+            };
+
+            let mut coarser = self.fork().encode_resize(half, Resize::Area.into());
+            let upsampled = coarser.fork().encode_resize(size, Resize::Linear.into());
+            details.push(self.add_level(upsampled, [-1.0; 4]).execute_f32().await);
+            self = coarser;
+        }
+
+        ImagePyramid {
+            gains: vec![1.0; details.len()],
+            levels: details,
+            residual: self.execute_f32().await,
+        }
+    }
+
+    /// Adds `level`, an operation with the same dimensions as this one, to the image, weighted per channel by
+    /// `weights`. The passes of `level` are submitted first.
+    fn add_level(mut self, level: Operation<'a>, weights: [f32; 4]) -> Self {
+        let name = "pyramid";
+        let (level_texture, _) = level.submit();
+
+        let pipeline = self.pipeline(name, PYRAMID_SHADER, Bindings::Derived);
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Pyramid settings"),
+            contents: bytemuck::cast_slice(&weights),
+            usage: BufferUsages::UNIFORM,
+        });
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Pyramid bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &level_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(self.dimensions(), self.pipelines.workgroup_size(name));
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Pyramid pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    /// Smooth gradients under a fine texture and a few hard edges, with odd dimensions so that the levels round up.
+    fn test_image() -> Image {
+        Image::from_fn(37, 29, |x, y| {
+            let texture = ((x * 7 + y * 13) % 5) as u8 * 6;
+            let edge = if (x / 9 + y / 7) % 2 == 0 { 60 } else { 0 };
+            Rgba::new(
+                (x * 4) as u8 + texture + edge,
+                (y * 5) as u8 + texture,
+                150 - texture - edge,
+                255,
+            )
+        })
+    }
+
+    /// The energy of the second derivative of the red channel along the rows, measuring the fine details.
+    fn second_derivative_energy(image: &Image) -> u64 {
+        image
+            .rows()
+            .flat_map(|row| row.windows(3))
+            .map(|window| {
+                let [a, b, c] = [window[0].r(), window[1].r(), window[2].r()].map(i64::from);
+                ((a - 2 * b + c) * (a - 2 * b + c)) as u64
+            })
+            .sum()
+    }
+
+    #[test]
+    fn untouched_pyramid_reconstructs_image() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let pyramid = image
+            .operation(&filters)
+            .unwrap()
+            .decompose_pyramid(4)
+            .block_on();
+        let sizes = pyramid
+            .levels()
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect::<Vec<_>>();
+        let output = pyramid.reconstruct(&filters).block_on().unwrap();
+
+        assert_eq!(vec![(37, 29), (19, 15), (10, 8), (5, 4)], sizes);
+        assert_eq!(
+            (3, 2),
+            (pyramid.residual().width, pyramid.residual().height)
+        );
+        assert!(image.approx_eq(&output, 1));
+    }
+
+    #[test]
+    fn boosted_level_adds_details() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut pyramid = image
+            .operation(&filters)
+            .unwrap()
+            .decompose_pyramid(3)
+            .block_on();
+        pyramid.scale_level(0, 2.0).unwrap();
+        let output = pyramid.reconstruct(&filters).block_on().unwrap();
+
+        assert!(second_derivative_energy(&output) > second_derivative_energy(&image) * 3 / 2);
+    }
+
+    #[test]
+    fn stops_at_single_pixel() {
+        let image = Image::new(4, 3, Rgba::new(10, 200, 30, 255));
+        let filters = Filters::new().block_on().unwrap();
+
+        let pyramid = image
+            .operation(&filters)
+            .unwrap()
+            .decompose_pyramid(10)
+            .block_on();
+
+        assert_eq!(2, pyramid.levels().len());
+        assert_eq!(
+            (1, 1),
+            (pyramid.residual().width, pyramid.residual().height)
+        );
+        assert_eq!(image, pyramid.reconstruct(&filters).block_on().unwrap());
+    }
+
+    #[test]
+    fn scale_missing_level() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut pyramid = image
+            .operation(&filters)
+            .unwrap()
+            .decompose_pyramid(2)
+            .block_on();
+
+        assert!(matches!(
+            pyramid.scale_level(2, 2.0),
+            Err(FiltersError::InvalidPyramidLevel {
+                level: 2,
+                levels: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,1128 @@
+use std::sync::Arc;
+
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::BufferUsages;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindingResource, CommandEncoder, ComputePassDescriptor,
+    ComputePipeline, Device, Extent3d, FilterMode, Sampler, Texture, TextureViewDescriptor,
+};
+
+use crate::{
+    buffer_to_image,
+    cache::{Bindings, PipelineCache},
+    capitalize, check_texture_size, compute_work_group_count, encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    submit, FiltersError, Image, Operation, PixelFormat, Rgba,
+};
+
+const RESIZE_SHADER: &str = include_str!("shaders/resize.wgsl");
+const RESAMPLE_SHADER: &str = include_str!("shaders/resample.wgsl");
+const SCALE_INTEGER_SHADER: &str = include_str!("shaders/scale_integer.wgsl");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Resize {
+    Linear,
+    Nearest,
+    /// Catmull-Rom bicubic interpolation, gathering 4x4 taps when upscaling.
+    Cubic,
+    /// Lanczos interpolation with a window of 3, gathering 6x6 taps when upscaling.
+    Lanczos3,
+    /// Averages all the source pixels covered by each destination pixel, best suited to large downscales.
+    Area,
+}
+
+/// How [`Operation::resize_with`] treats the pixels beyond the edges of the image, which the filters reach for
+/// the pixels along the edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum AddressMode {
+    /// Repeats the pixels of the edges, which is right for most images.
+    #[default]
+    ClampToEdge,
+    /// Wraps around to the opposite edge, for tileable textures, so that their tiles still join seamlessly.
+    Repeat,
+    /// Wraps around like [`AddressMode::Repeat`], mirroring the image every other time.
+    MirrorRepeat,
+}
+
+impl AddressMode {
+    /// The index of the mode in the resample shader.
+    fn index(self) -> u32 {
+        match self {
+            AddressMode::ClampToEdge => 0,
+            AddressMode::Repeat => 1,
+            AddressMode::MirrorRepeat => 2,
+        }
+    }
+}
+
+/// How [`Operation::resize_with`] resizes the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeOptions {
+    /// The interpolation. Defaults to [`Resize::Linear`].
+    pub filter: Resize,
+    /// What the interpolation reads beyond the edges. Defaults to [`AddressMode::ClampToEdge`]. [`Resize::Area`]
+    /// never reads beyond them, so it ignores it.
+    pub address_mode: AddressMode,
+}
+
+impl Default for ResizeOptions {
+    fn default() -> Self {
+        Self {
+            filter: Resize::Linear,
+            address_mode: AddressMode::ClampToEdge,
+        }
+    }
+}
+
+impl From<Resize> for ResizeOptions {
+    fn from(filter: Resize) -> Self {
+        Self {
+            filter,
+            ..Self::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ResampleSettings {
+    filter_type: u32,
+    address_mode: u32,
+    scale: [f32; 2],
+}
+
+/// The pipeline and sampler needed to resize a texture, so that several resize passes can share them.
+struct Resizer {
+    name: String,
+    pipeline: Arc<ComputePipeline>,
+    sampler: Option<Sampler>,
+    filter_type: u32,
+    address_mode: AddressMode,
+    workgroup_size: (u32, u32),
+}
+
+impl Resizer {
+    fn new(
+        device: &Device,
+        pipelines: &PipelineCache,
+        options: ResizeOptions,
+        format: PixelFormat,
+    ) -> Self {
+        let ResizeOptions {
+            filter: resize,
+            address_mode,
+        } = options;
+        let (name, shader_string, bindings, filter_mode, filter_type) = match resize {
+            Resize::Linear => (
+                "resize",
+                RESIZE_SHADER,
+                Bindings::Sampler,
+                FilterMode::Linear,
+                0,
+            ),
+            Resize::Nearest => (
+                "resize",
+                RESIZE_SHADER,
+                Bindings::Sampler,
+                FilterMode::Nearest,
+                0,
+            ),
+            Resize::Cubic => (
+                "resample",
+                RESAMPLE_SHADER,
+                Bindings::Uniform,
+                FilterMode::Nearest,
+                0,
+            ),
+            Resize::Lanczos3 => (
+                "resample",
+                RESAMPLE_SHADER,
+                Bindings::Uniform,
+                FilterMode::Nearest,
+                1,
+            ),
+            Resize::Area => (
+                "resample",
+                RESAMPLE_SHADER,
+                Bindings::Uniform,
+                FilterMode::Nearest,
+                2,
+            ),
+        };
+        let capitalized_filter_name = capitalize(name);
+
+        let pipeline = pipelines.get(device, name, shader_string, format, bindings);
+
+        let sampler_address_mode = match address_mode {
+            AddressMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            AddressMode::Repeat => wgpu::AddressMode::Repeat,
+            AddressMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        };
+        let sampler = match resize {
+            Resize::Linear | Resize::Nearest => {
+                Some(device.create_sampler(&wgpu::SamplerDescriptor {
+                    label: None,
+                    address_mode_u: sampler_address_mode,
+                    address_mode_v: sampler_address_mode,
+                    address_mode_w: sampler_address_mode,
+                    mag_filter: filter_mode,
+                    min_filter: filter_mode,
+                    mipmap_filter: filter_mode,
+                    ..Default::default()
+                }))
+            }
+            Resize::Cubic | Resize::Lanczos3 | Resize::Area => None,
+        };
+
+        Self {
+            name: capitalized_filter_name,
+            pipeline,
+            sampler,
+            filter_type,
+            address_mode,
+            workgroup_size: pipelines.workgroup_size(name),
+        }
+    }
+
+    /// Records a resize pass of `input` to a texture of size `output_size` taken from `pool`, and returns that texture.
+    fn encode(
+        &self,
+        device: &Device,
+        pool: &mut TexturePool,
+        encoder: &mut CommandEncoder,
+        input: &Texture,
+        input_size: Extent3d,
+        output_size: Extent3d,
+    ) -> Texture {
+        let output_texture = pool.take(device, output_size, STORAGE_TEXTURE_USAGES);
+
+        let compute_constants = if let Some(sampler) = &self.sampler {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Compute constants"),
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(sampler),
+                }],
+            })
+        } else {
+            let settings = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Resample settings"),
+                contents: bytemuck::cast_slice(&[ResampleSettings {
+                    filter_type: self.filter_type,
+                    address_mode: self.address_mode.index(),
+                    scale: [
+                        input_size.width as f32 / output_size.width as f32,
+                        input_size.height as f32 / output_size.height as f32,
+                    ],
+                }]),
+                usage: BufferUsages::UNIFORM,
+            });
+
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Compute constants"),
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: settings.as_entire_binding(),
+                }],
+            })
+        };
+
+        let texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &self.pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &input.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (output_size.width, output_size.height),
+                self.workgroup_size,
+            );
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", self.name).as_str()),
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+
+        output_texture
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Resizes the image to `new_dimension` with the interpolation of `resize`, repeating the pixels of the edges
+    /// where it reaches beyond them, see [`Operation::resize_with`] for tileable textures.
+    pub fn resize(self, new_dimension: (u32, u32), resize: Resize) -> Result<Self, FiltersError> {
+        self.resize_with(new_dimension, resize.into())
+    }
+
+    /// Resizes the image to `new_dimension` like [`Operation::resize`], with `options` also choosing what the
+    /// interpolation reads beyond the edges, like the opposite edge to resize a tileable texture without seams.
+    pub fn resize_with(
+        self,
+        new_dimension: (u32, u32),
+        options: ResizeOptions,
+    ) -> Result<Self, FiltersError> {
+        if new_dimension.0 == 0 || new_dimension.1 == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: new_dimension.0,
+                height: new_dimension.1,
+            });
+        }
+        check_texture_size(self.device, new_dimension)?;
+
+        Ok(self.encode_resize(
+            Extent3d {
+                width: new_dimension.0,
+                height: new_dimension.1,
+                depth_or_array_layers: 1,
+            },
+            options,
+        ))
+    }
+
+    /// Records the resize of [`Operation::resize_with`] to `output_size`, which must already be known to fit in a
+    /// texture, like the levels of a pyramid.
+    pub(crate) fn encode_resize(mut self, output_size: Extent3d, options: ResizeOptions) -> Self {
+        let resizer = Resizer::new(self.device, self.pipelines, options, self.format);
+
+        let pass = self.begin_pass("resize");
+        let output_texture = resizer.encode(
+            self.device,
+            &mut self.pool,
+            &mut self.encoder,
+            &self.texture,
+            self.texture_size,
+            output_size,
+        );
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, output_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+
+        self
+    }
+
+    /// Upscales the image by an exact integer factor with nearest neighbor sampling:
+    /// every pixel of the image becomes a block of `factor` by `factor` pixels, which is what pixel art needs.
+    pub fn scale_integer(mut self, factor: u32) -> Result<Self, FiltersError> {
+        if factor == 0 {
+            return Err(FiltersError::InvalidScaleFactor(factor));
+        }
+
+        let name = "scale integer";
+        let capitalized_filter_name = capitalize(name);
+
+        let width = self.texture_size.width.saturating_mul(factor);
+        let height = self.texture_size.height.saturating_mul(factor);
+        check_texture_size(self.device, (width, height))?;
+
+        let output_size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let output_texture = self
+            .pool
+            .take(self.device, output_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline(name, SCALE_INTEGER_SHADER, Bindings::Uniform);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Scale settings"),
+            contents: bytemuck::cast_slice(&[factor]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute constants"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (output_size.width, output_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, output_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+
+        Ok(self)
+    }
+
+    /// Resizes the image so that it fits in `max`, preserving its aspect ratio.
+    /// One of the dimensions will match `max`, the other one will be smaller or equal.
+    pub fn resize_fit(self, max: (u32, u32), resize: Resize) -> Result<Self, FiltersError> {
+        if max.0 == 0 || max.1 == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: max.0,
+                height: max.1,
+            });
+        }
+
+        let new_dimension = fit_dimensions(self.dimensions(), max);
+
+        self.resize(new_dimension, resize)
+    }
+
+    /// Creates a thumbnail whose largest dimension is at most `max_dimension`, preserving the aspect ratio.
+    /// The image is first halved successively until close to the target, then resized to the exact size,
+    /// and finally lightly sharpened. Images already smaller than `max_dimension` are only sharpened.
+    pub fn thumbnail(mut self, max_dimension: u32) -> Result<Self, FiltersError> {
+        if max_dimension == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: max_dimension,
+                height: max_dimension,
+            });
+        }
+
+        let (width, height) = self.dimensions();
+        let target = if width.max(height) > max_dimension {
+            fit_dimensions((width, height), (max_dimension, max_dimension))
+        } else {
+            (width, height)
+        };
+
+        let resizer = Resizer::new(
+            self.device,
+            self.pipelines,
+            Resize::Linear.into(),
+            self.format,
+        );
+        let mut size = self.texture_size;
+        while size.width / 2 >= target.0 && size.height / 2 >= target.1 {
+            let input_size = size;
+            size = Extent3d {
+                width: size.width.div_ceil(2),
+                height: size.height.div_ceil(2),
+                depth_or_array_layers: 1,
+            };
+            let pass = self.begin_pass("thumbnail");
+            let output_texture = resizer.encode(
+                self.device,
+                &mut self.pool,
+                &mut self.encoder,
+                &self.texture,
+                input_size,
+                size,
+            );
+            self.end_pass(pass);
+            self.set_texture(output_texture, size, STORAGE_TEXTURE_USAGES);
+        }
+        if (size.width, size.height) != target {
+            let input_size = size;
+            size = Extent3d {
+                width: target.0,
+                height: target.1,
+                depth_or_array_layers: 1,
+            };
+            let pass = self.begin_pass("thumbnail");
+            let output_texture = resizer.encode(
+                self.device,
+                &mut self.pool,
+                &mut self.encoder,
+                &self.texture,
+                input_size,
+                size,
+            );
+            self.end_pass(pass);
+            self.set_texture(output_texture, size, STORAGE_TEXTURE_USAGES);
+        }
+        self.tileable = false;
+
+        Ok(self.sharpen(0.25))
+    }
+
+    /// Resizes the image so that it covers `target` while preserving its aspect ratio,
+    /// then crops the overflowing part, keeping the center of the image.
+    pub fn resize_fill(self, target: (u32, u32), resize: Resize) -> Result<Self, FiltersError> {
+        if target.0 == 0 || target.1 == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: target.0,
+                height: target.1,
+            });
+        }
+
+        let (width, height) = self.dimensions();
+        let scale = f64::max(
+            target.0 as f64 / width as f64,
+            target.1 as f64 / height as f64,
+        );
+        let new_dimension = (
+            scaled_dimension(width, scale).max(target.0),
+            scaled_dimension(height, scale).max(target.1),
+        );
+        let origin = (
+            (new_dimension.0 - target.0) / 2,
+            (new_dimension.1 - target.1) / 2,
+        );
+
+        self.resize(new_dimension, resize)?.crop(origin, target)
+    }
+
+    /// Generates a mip chain: the current image, followed by successive halvings of it (rounded up),
+    /// until the largest dimension is not bigger than `min_size`.
+    /// All the passes and copies are recorded in a single submission. The levels are always 8-bit images.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_size` - The size under which the halving stops. A value of 0 is treated as 1.
+    pub async fn generate_mipchain(mut self, min_size: u32) -> Vec<Image> {
+        self.convert_to_rgba8();
+        let min_size = min_size.max(1);
+        let resizer = Resizer::new(
+            self.device,
+            self.pipelines,
+            Resize::Linear.into(),
+            self.format,
+        );
+
+        let mut size = self.texture_size;
+        let mut buffers = vec![(
+            size,
+            encode_texture_to_buffer::<Rgba>(
+                self.device,
+                &mut self.encoder,
+                size.width,
+                size.height,
+                &self.texture,
+            ),
+        )];
+        let mut texture = self.texture;
+        while size.width.max(size.height) > min_size {
+            let input_size = size;
+            size = Extent3d {
+                width: size.width.div_ceil(2),
+                height: size.height.div_ceil(2),
+                depth_or_array_layers: 1,
+            };
+            texture = resizer.encode(
+                self.device,
+                &mut self.pool,
+                &mut self.encoder,
+                &texture,
+                input_size,
+                size,
+            );
+            buffers.push((
+                size,
+                encode_texture_to_buffer::<Rgba>(
+                    self.device,
+                    &mut self.encoder,
+                    size.width,
+                    size.height,
+                    &texture,
+                ),
+            ));
+        }
+        submit(self.queue, self.submissions, self.encoder);
+
+        let mut levels = Vec::with_capacity(buffers.len());
+        for (size, buffer) in buffers {
+            levels.push(buffer_to_image(self.device, size.width, size.height, &buffer).await);
+        }
+        levels
+    }
+}
+
+/// The largest dimensions that fit in `max` while preserving the aspect ratio of `(width, height)`.
+fn fit_dimensions((width, height): (u32, u32), max: (u32, u32)) -> (u32, u32) {
+    let scale = f64::min(max.0 as f64 / width as f64, max.1 as f64 / height as f64);
+    (
+        scaled_dimension(width, scale).min(max.0),
+        scaled_dimension(height, scale).min(max.1),
+    )
+}
+
+fn scaled_dimension(dimension: u32, scale: f64) -> u32 {
+    ((dimension as f64 * scale).round() as u32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::{AddressMode, ResizeOptions};
+    use crate::{Filters, FiltersError, Image, Resize, Rgba};
+
+    fn second_derivative_energy(image: &Image) -> u64 {
+        image
+            .pixels
+            .windows(3)
+            .map(|window| {
+                let [a, b, c] = [window[0].0[0], window[1].0[0], window[2].0[0]].map(i64::from);
+                let second_derivative = a - 2 * b + c;
+                (second_derivative * second_derivative) as u64
+            })
+            .sum()
+    }
+
+    #[test]
+    fn resize_exact_dimensions() {
+        let image = Image {
+            width: 7,
+            height: 5,
+            pixels: vec![Rgba([12, 34, 56, 255]); 35],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        for resize in [
+            Resize::Linear,
+            Resize::Nearest,
+            Resize::Cubic,
+            Resize::Lanczos3,
+            Resize::Area,
+        ] {
+            for dimensions in [(13, 3), (3, 11), (1, 1)] {
+                let output = image
+                    .operation(&filters)
+                    .unwrap()
+                    .resize(dimensions, resize)
+                    .unwrap()
+                    .execute()
+                    .block_on();
+
+                assert_eq!(dimensions, (output.width, output.height));
+                assert_eq!(
+                    Rgba([12, 34, 56, 255]),
+                    output.pixels[0],
+                    "A flat image stays flat with {resize:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resize_cubic_smoother_than_linear() {
+        let image = Image {
+            width: 5,
+            height: 1,
+            pixels: [0, 16, 64, 144, 255]
+                .into_iter()
+                .map(|value| Rgba([value, value, value, 255]))
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let linear = image
+            .operation(&filters)
+            .unwrap()
+            .resize((40, 1), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+        let cubic = image
+            .operation(&filters)
+            .unwrap()
+            .resize((40, 1), Resize::Cubic)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert!(second_derivative_energy(&cubic) < second_derivative_energy(&linear));
+    }
+
+    #[test]
+    fn resize_area_averages_checkerboard() {
+        let image = Image {
+            width: 64,
+            height: 64,
+            pixels: (0..64 * 64)
+                .map(|index| {
+                    if (index % 64 + index / 64) % 2 == 0 {
+                        Rgba([0, 0, 0, 255])
+                    } else {
+                        Rgba([255, 255, 255, 255])
+                    }
+                })
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let area = image
+            .operation(&filters)
+            .unwrap()
+            .resize((8, 8), Resize::Area)
+            .unwrap()
+            .execute()
+            .block_on();
+        let linear = image
+            .operation(&filters)
+            .unwrap()
+            .resize((8, 8), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        let is_gray = |pixel: &Rgba| pixel.0[..3].iter().all(|value| value.abs_diff(128) <= 2);
+        assert!(area.pixels.iter().all(is_gray));
+        assert!(!linear.pixels.iter().all(is_gray));
+    }
+
+    #[test]
+    fn resize_repeat_wraps_tileable_texture() {
+        // Black then white, repeating as a texture.
+        let image = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let upscale = |filter, address_mode| {
+            image
+                .operation(&filters)
+                .unwrap()
+                .resize_with(
+                    (8, 1),
+                    ResizeOptions {
+                        filter,
+                        address_mode,
+                    },
+                )
+                .unwrap()
+                .execute()
+                .block_on()
+                .pixels
+                .iter()
+                .map(|pixel| i32::from(pixel.r()))
+                .collect::<Vec<_>>()
+        };
+
+        for filter in [Resize::Linear, Resize::Cubic, Resize::Lanczos3] {
+            // Shifting a tile of black and white by one source pixel swaps them, so the halves of the output add
+            // up to white once the borders wrap around.
+            let repeated = upscale(filter, AddressMode::Repeat);
+            for x in 0..4 {
+                assert!(
+                    (repeated[x] + repeated[x + 4] - 255).abs() <= 2,
+                    "{filter:?}: {repeated:?}"
+                );
+            }
+        }
+
+        // The left border blends with the white of the opposite edge, rather than the black of its own.
+        let clamped = upscale(Resize::Linear, AddressMode::ClampToEdge);
+        let repeated = upscale(Resize::Linear, AddressMode::Repeat);
+        let mirrored = upscale(Resize::Linear, AddressMode::MirrorRepeat);
+        assert!(clamped[0] <= 2, "{clamped:?}");
+        assert!((repeated[0] - 128).abs() <= 2, "{repeated:?}");
+        assert!(mirrored[0] <= 2, "{mirrored:?}");
+        assert_ne!(clamped, repeated);
+    }
+
+    #[test]
+    fn resize_fit_dimensions() {
+        let landscape = Image {
+            width: 400,
+            height: 200,
+            pixels: vec![Rgba([0, 0, 0, 255]); 400 * 200],
+        };
+        let portrait = Image {
+            width: 150,
+            height: 400,
+            pixels: vec![Rgba([0, 0, 0, 255]); 150 * 400],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        assert_eq!(
+            (100, 50),
+            landscape
+                .operation(&filters)
+                .unwrap()
+                .resize_fit((100, 100), Resize::Linear)
+                .unwrap()
+                .dimensions()
+        );
+        assert_eq!(
+            (38, 100),
+            portrait
+                .operation(&filters)
+                .unwrap()
+                .resize_fit((100, 100), Resize::Linear)
+                .unwrap()
+                .dimensions()
+        );
+        assert_eq!(
+            (800, 400),
+            landscape
+                .operation(&filters)
+                .unwrap()
+                .resize_fit((1000, 400), Resize::Linear)
+                .unwrap()
+                .dimensions()
+        );
+    }
+
+    #[test]
+    fn resize_fill_dimensions() {
+        let landscape = Image {
+            width: 400,
+            height: 200,
+            pixels: vec![Rgba([0, 0, 0, 255]); 400 * 200],
+        };
+        let portrait = Image {
+            width: 150,
+            height: 400,
+            pixels: vec![Rgba([0, 0, 0, 255]); 150 * 400],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = landscape
+            .operation(&filters)
+            .unwrap()
+            .resize_fill((100, 100), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+        assert_eq!((100, 100), (output.width, output.height));
+        assert_eq!(100 * 100, output.pixels.len());
+
+        let output = portrait
+            .operation(&filters)
+            .unwrap()
+            .resize_fill((120, 60), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+        assert_eq!((120, 60), (output.width, output.height));
+        assert_eq!(120 * 60, output.pixels.len());
+    }
+
+    #[test]
+    fn resize_fill_keeps_center() {
+        let image = Image {
+            width: 4,
+            height: 2,
+            pixels: vec![
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+                Rgba([255, 255, 255, 255]),
+                Rgba([255, 0, 0, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+                Rgba([255, 255, 255, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+                Rgba([0, 255, 0, 255]),
+                Rgba([0, 0, 255, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .resize_fill((2, 2), Resize::Nearest)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn resize_above_limit() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 255]); 4],
+        };
+        let filters = Filters::new().block_on().unwrap();
+        let limit = filters.device.limits().max_texture_dimension_2d;
+
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .resize((2, limit + 1), Resize::Linear);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::ImageTooLarge { dimension: (2, height), .. }) if height == limit + 1
+        ));
+    }
+
+    #[test]
+    fn resize_fit_zero_dimension() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Rgba([0, 0, 0, 255]); 4],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image
+            .operation(&filters)
+            .unwrap()
+            .resize_fit((0, 10), Resize::Linear);
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::UnsupportedSize {
+                width: 0,
+                height: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn thumbnail_dimensions() {
+        let image = Image {
+            width: 1000,
+            height: 600,
+            pixels: vec![Rgba([0, 0, 0, 255]); 1000 * 600],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .thumbnail(256)
+            .unwrap()
+            .execute()
+            .block_on();
+        assert_eq!((256, 154), (output.width, output.height));
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .thumbnail(2000)
+            .unwrap()
+            .execute()
+            .block_on();
+        assert_eq!((1000, 600), (output.width, output.height));
+    }
+
+    #[test]
+    fn thumbnail_differs_from_naive_resize() {
+        let image = Image {
+            width: 256,
+            height: 256,
+            pixels: (0..256u32 * 256)
+                .map(|index| {
+                    let (x, y) = (index % 256, index / 256);
+                    let value = ((x * 7 + y * 13) % 17 * 15) as u8;
+                    Rgba([value, 255 - value, (x ^ y) as u8, 255])
+                })
+                .collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let thumbnail = image
+            .operation(&filters)
+            .unwrap()
+            .thumbnail(40)
+            .unwrap()
+            .execute()
+            .block_on();
+        let naive = image
+            .operation(&filters)
+            .unwrap()
+            .resize((40, 40), Resize::Linear)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((40, 40), (thumbnail.width, thumbnail.height));
+        assert_ne!(naive, thumbnail);
+    }
+
+    #[test]
+    fn scale_integer_blocks() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Rgba([1, 2, 3, 255]),
+                Rgba([4, 5, 6, 128]),
+                Rgba([7, 8, 9, 0]),
+                Rgba([10, 11, 12, 255]),
+                Rgba([13, 14, 15, 255]),
+                Rgba([16, 17, 18, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .scale_integer(3)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((9, 6), (output.width, output.height));
+        for y in 0..output.height {
+            for x in 0..output.width {
+                assert_eq!(
+                    image.pixels[(y / 3 * image.width + x / 3) as usize],
+                    output.pixels[(y * output.width + x) as usize]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scale_integer_factor_1_is_identity() {
+        let image = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([1, 2, 3, 255]),
+                Rgba([4, 5, 6, 128]),
+                Rgba([7, 8, 9, 0]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .scale_integer(1)
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn scale_integer_factor_0() {
+        let image = Image {
+            width: 1,
+            height: 1,
+            pixels: vec![Rgba([0, 0, 0, 255])],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = image.operation(&filters).unwrap().scale_integer(0);
+
+        assert!(matches!(result, Err(FiltersError::InvalidScaleFactor(0))));
+    }
+
+    #[test]
+    fn generate_mipchain_halves_each_level() {
+        let image = Image {
+            width: 100,
+            height: 60,
+            pixels: vec![Rgba([200, 100, 50, 255]); 6000],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let levels = image
+            .operation(&filters)
+            .unwrap()
+            .generate_mipchain(1)
+            .block_on();
+
+        let dimensions: Vec<(u32, u32)> = levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(
+            vec![
+                (100, 60),
+                (50, 30),
+                (25, 15),
+                (13, 8),
+                (7, 4),
+                (4, 2),
+                (2, 1),
+                (1, 1)
+            ],
+            dimensions
+        );
+        assert_eq!(image, levels[0]);
+        for level in &levels {
+            assert_eq!((level.width * level.height) as usize, level.pixels.len());
+        }
+    }
+
+    #[test]
+    fn generate_mipchain_stops_at_min_size() {
+        let image = Image {
+            width: 64,
+            height: 64,
+            pixels: vec![Rgba([0, 0, 0, 255]); 64 * 64],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let levels = image
+            .operation(&filters)
+            .unwrap()
+            .generate_mipchain(16)
+            .block_on();
+
+        assert_eq!(3, levels.len());
+        assert_eq!((16, 16), (levels[2].width, levels[2].height));
+    }
+}
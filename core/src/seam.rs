@@ -0,0 +1,326 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    ComputePipeline, Extent3d, TextureFormat, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings,
+    compute_work_group_count, encode_texture_to_buffer,
+    pool::{TexturePool, STORAGE_TEXTURE_USAGES},
+    read_mapped_buffer_into, wait_for_mapping, FiltersError, Operation, PixelFormat,
+};
+
+const SEAM_ENERGY_SHADER: &str = include_str!("shaders/seam_energy.wgsl");
+const SEAM_CARVE_SHADER: &str = include_str!("shaders/seam_carve.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Narrows the image to `new_width` by removing the vertical seams of pixels that stand out the least, one pixel
+    /// per row, so that the salient content keeps its proportions while the flat areas around it shrink.
+    ///
+    /// The energy of the pixels, the magnitude of their Sobel gradient, is computed on the gpu and read back, the
+    /// seams are found on the cpu, and the pixels left are gathered on the gpu. The energy is computed once, and the
+    /// seams are only removed from it rather than computing it again after each of them.
+    ///
+    /// The passes recorded so far are submitted, and the operation can be continued afterwards.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::UnsupportedSize`] if `new_width` is 0, or wider than the image, which seam carving can't
+    /// widen.
+    pub async fn seam_carve(mut self, new_width: u32) -> Result<Self, FiltersError> {
+        let (width, height) = self.dimensions();
+        if new_width == 0 || new_width > width {
+            return Err(FiltersError::UnsupportedSize {
+                width: new_width,
+                height,
+            });
+        }
+        if new_width == width {
+            return Ok(self);
+        }
+
+        let energy = self.energy().await;
+        let columns = find_seams(width, height, energy, new_width);
+
+        let texture_size = Extent3d {
+            width: new_width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let output_texture = self
+            .pool
+            .take(self.device, texture_size, STORAGE_TEXTURE_USAGES);
+        let columns = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Seam carving columns"),
+            contents: bytemuck::cast_slice(&columns),
+            usage: BufferUsages::STORAGE,
+        });
+        let carve = self.pipeline("seam carve", SEAM_CARVE_SHADER, Bindings::Derived);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Seam carve bind group"),
+            layout: &carve.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: columns.as_entire_binding(),
+                },
+            ],
+        });
+        self.encode_seam_pass("seam carve", &carve, &bind_group, (new_width, height));
+
+        self.set_texture(output_texture, texture_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+        Ok(self)
+    }
+
+    /// Computes the energy of each pixel on the gpu, and reads it back, row by row.
+    async fn energy(&mut self) -> Vec<f32> {
+        let (width, height) = self.dimensions();
+        let energy = TexturePool::new(TextureFormat::R32Float).take(
+            self.device,
+            self.texture_size,
+            STORAGE_TEXTURE_USAGES,
+        );
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Seam energy settings"),
+            contents: bytemuck::bytes_of(&u32::from(self.format == PixelFormat::Luma)),
+            usage: BufferUsages::UNIFORM,
+        });
+        let pipeline = self.pipeline("seam energy", SEAM_ENERGY_SHADER, Bindings::Derived);
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Seam energy bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &energy.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: settings.as_entire_binding(),
+                },
+            ],
+        });
+        self.encode_seam_pass("seam energy", &pipeline, &bind_group, (width, height));
+
+        let buffer =
+            encode_texture_to_buffer::<f32>(self.device, &mut self.encoder, width, height, &energy);
+        self.submit_recorded();
+        wait_for_mapping(self.device, &buffer).await;
+        let mut values = Vec::new();
+        read_mapped_buffer_into(width, height, &buffer, &mut values);
+        values
+    }
+
+    fn encode_seam_pass(
+        &mut self,
+        name: &str,
+        pipeline: &ComputePipeline,
+        bind_group: &wgpu::BindGroup,
+        dimensions: (u32, u32),
+    ) {
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_width, dispatch_height) =
+                compute_work_group_count(dimensions, self.pipelines.workgroup_size(name));
+            let mut compute_pass = self
+                .encoder
+                .begin_compute_pass(&ComputePassDescriptor { label: Some(name) });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+    }
+}
+
+/// Removes the seams of least energy one after the other, returning the source column of each pixel left, row by
+/// row, `new_width` of them per row.
+///
+/// # Arguments
+///
+/// * `energy` - The energy of each pixel of the image, row by row.
+fn find_seams(width: u32, height: u32, energy: Vec<f32>, new_width: u32) -> Vec<u32> {
+    let mut rows = energy
+        .chunks_exact(width as usize)
+        .map(|row| row.to_vec())
+        .collect::<Vec<_>>();
+    let mut columns = vec![(0..width).collect::<Vec<_>>(); height as usize];
+    // The least energy of a seam from the top row down to each pixel of the current row.
+    let mut costs = Vec::with_capacity(rows.len());
+
+    for current_width in (new_width + 1..=width).rev() {
+        let current_width = current_width as usize;
+        costs.clear();
+        costs.push(rows[0].clone());
+        for row in &rows[1..] {
+            let above = costs.last().unwrap();
+            let cost = (0..current_width)
+                .map(|x| {
+                    let first = x.saturating_sub(1);
+                    let last = (x + 1).min(current_width - 1);
+                    row[x] + above[first..=last].iter().copied().fold(f32::MAX, f32::min)
+                })
+                .collect::<Vec<_>>();
+            costs.push(cost);
+        }
+
+        // Walks back up from the end of the cheapest seam, picking the cheapest of the three pixels above.
+        let mut x = lowest(&costs[costs.len() - 1], 0, current_width - 1);
+        for y in (0..rows.len()).rev() {
+            if y + 1 < rows.len() {
+                x = lowest(
+                    &costs[y],
+                    x.saturating_sub(1),
+                    (x + 1).min(current_width - 1),
+                );
+            }
+            rows[y].remove(x);
+            columns[y].remove(x);
+        }
+    }
+
+    columns.concat()
+}
+
+/// The index of the lowest of `values` from `first` to `last` included, the first one for ties.
+fn lowest(values: &[f32], first: usize, last: usize) -> usize {
+    (first..=last).fold(first, |lowest, index| {
+        if values[index] < values[lowest] {
+            index
+        } else {
+            lowest
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::find_seams;
+    use crate::{Filters, FiltersError, Image, Rgba};
+
+    const BACKGROUND: Rgba = Rgba([120, 140, 160, 255]);
+
+    /// A flat background crossed by a checkered stripe, from column 25 to 29.
+    fn stripe() -> Image {
+        Image::from_fn(40, 20, |x, y| {
+            if (25..30).contains(&x) {
+                let value = if (x + y) % 2 == 0 { 10 } else { 250 };
+                Rgba::new(value, value / 2, 255 - value, 255)
+            } else {
+                BACKGROUND
+            }
+        })
+    }
+
+    #[test]
+    fn stripe_survives() {
+        let image = stripe();
+        let filters = Filters::new().block_on().unwrap();
+
+        let carved = image
+            .operation(&filters)
+            .unwrap()
+            .seam_carve(20)
+            .block_on()
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!((20, 20), (carved.width, carved.height));
+        let column = |image: &Image, x: u32| {
+            (0..image.height)
+                .map(|y| image.pixels[(y * image.width + x) as usize])
+                .collect::<Vec<_>>()
+        };
+        let carved_columns = (0..carved.width)
+            .map(|x| column(&carved, x))
+            .collect::<Vec<_>>();
+        for x in 25..30 {
+            assert!(
+                carved_columns.contains(&column(&image, x)),
+                "Column {x} of the stripe was carved"
+            );
+        }
+        let background = carved_columns
+            .iter()
+            .filter(|column| column.iter().all(|&pixel| pixel == BACKGROUND))
+            .count();
+        // All the seams went through the background, down from 35 columns.
+        assert_eq!(15, background);
+    }
+
+    #[test]
+    fn same_width_and_errors() {
+        let image = stripe();
+        let filters = Filters::new().block_on().unwrap();
+
+        let same = image
+            .operation(&filters)
+            .unwrap()
+            .seam_carve(40)
+            .block_on()
+            .unwrap()
+            .execute()
+            .block_on();
+
+        assert_eq!(image, same);
+        for new_width in [0, 41] {
+            assert!(matches!(
+                image
+                    .operation(&filters)
+                    .unwrap()
+                    .seam_carve(new_width)
+                    .block_on(),
+                Err(FiltersError::UnsupportedSize { width, height: 20 }) if width == new_width
+            ));
+        }
+    }
+
+    #[test]
+    fn seams_follow_the_least_energy() {
+        // A diagonal valley of low energy, from the top right to the bottom left.
+        let energy = vec![
+            9.0, 9.0, 9.0, 0.0, //
+            9.0, 9.0, 0.0, 9.0, //
+            9.0, 0.0, 9.0, 9.0, //
+            0.0, 9.0, 9.0, 9.0, //
+        ];
+
+        let columns = find_seams(4, 4, energy, 3);
+
+        assert_eq!(
+            vec![
+                0, 1, 2, //
+                0, 1, 3, //
+                0, 2, 3, //
+                1, 2, 3, //
+            ],
+            columns
+        );
+    }
+}
@@ -0,0 +1,26 @@
+struct Settings {
+    // 1 for the channels taken from the blurred image, 0 for those kept as they are.
+    channels : vec4<u32>,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var blurred_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let blurred = textureLoad(blurred_texture, position, 0);
+
+    textureStore(output_texture, position, select(color, blurred, settings.channels == vec4<u32>(1u)));
+}
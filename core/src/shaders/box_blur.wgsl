@@ -1,5 +1,7 @@
 struct Settings {
     filter_size : u32,
+    // How many steps 8-bit textures have, 255, for the sums to stay exact. 0 for other formats.
+    steps : f32,
 };
 
 struct Orientation {
@@ -11,35 +13,57 @@ struct Orientation {
 @group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
 @group(1) @binding(2) var<uniform> orientation: Orientation;
 
+// Counts the pixels in whole steps when there are any, so that removing them later gives back the exact same sum.
+fn in_steps(color : vec4<f32>, steps : f32) -> vec4<f32> {
+    if (steps > 0.0) {
+        return round(color * steps);
+    }
+    return color;
+}
+
 @compute
 @workgroup_size(128)
 fn main(
   @builtin(global_invocation_id) global_id : vec3<u32>,
 ) {
     let filter_radius = i32((settings.filter_size - 1u) / 2u);
-    let filter_size = i32(settings.filter_size);
+    let weight = 1.0 / f32(settings.filter_size);
+    var step_size = 1.0;
+    if (settings.steps > 0.0) {
+        step_size = settings.steps;
+    }
     let dimensions = textureDimensions(input_texture);
-    var position = vec2<i32>(global_id.xy);
+    // Each invocation blurs a whole column for the vertical pass, or a whole row for the horizontal one.
+    var start = vec2<i32>(i32(global_id.x), 0);
+    var direction = vec2<i32>(0, 1);
+    var line_length = dimensions.y;
     if (orientation.vertical == 0u) {
-        position = position.yx;
+        start = start.yx;
+        direction = vec2<i32>(1, 0);
+        line_length = dimensions.x;
     }
-    
-    if(position.x >= dimensions.x || position.y >= dimensions.y) {
+
+    if(start.x >= dimensions.x || start.y >= dimensions.y) {
         return;
     }
 
-    let original = textureLoad(input_texture, position, 0);
-    var color : vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
-    
-    if (orientation.vertical > 0u) {
-        for (var i : i32 = position.y - filter_radius; i <= position.y + filter_radius; i = i + 1){
-            color = color + (1.0 / f32(filter_size)) * textureLoad(input_texture, vec2<i32>(position.x, i), 0);
+    // The sum of the window around the first pixel, those outside of the image being transparent black, which then
+    // slides by adding the pixel entering it and removing the one leaving it.
+    var sum : vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    for (var i : i32 = 0; i <= min(filter_radius, line_length - 1); i = i + 1) {
+        sum = sum + in_steps(textureLoad(input_texture, start + direction * i, 0), settings.steps);
+    }
+
+    for (var i : i32 = 0; i < line_length; i = i + 1) {
+        textureStore(output_texture, start + direction * i, weight * sum / step_size);
+
+        let entering = i + filter_radius + 1;
+        if (entering < line_length) {
+            sum = sum + in_steps(textureLoad(input_texture, start + direction * entering, 0), settings.steps);
         }
-    } else {        
-        for (var i : i32 = position.x - filter_radius; i <= position.x + filter_radius; i = i + 1){
-            color = color + (1.0 / f32(filter_size)) * textureLoad(input_texture, vec2<i32>(i, position.y), 0);
+        let leaving = i - filter_radius;
+        if (leaving >= 0) {
+            sum = sum - in_steps(textureLoad(input_texture, start + direction * leaving, 0), settings.steps);
         }
     }
-
-    textureStore(output_texture, position, color);
-}
\ No newline at end of file
+}
@@ -0,0 +1,27 @@
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var chroma_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+fn luma(color : vec3<f32>) -> f32 {
+    return 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let blurred = textureLoad(chroma_texture, position, 0);
+    let chroma = blurred.rgb / max(blurred.a, 0.0001);
+
+    // The blurred chroma is shifted to the luma of the pixel itself, so that only its colors are smoothed.
+    let denoised = chroma + vec3<f32>(luma(color.rgb) - luma(chroma));
+    textureStore(output_texture, position, vec4<f32>(clamp(denoised, vec3<f32>(0.0), vec3<f32>(1.0)), color.a));
+}
@@ -0,0 +1,23 @@
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+// Writes the chroma of the pixel, as how far each channel is above the lowest one: unlike Cb and Cr it is never
+// negative, so it needs no offset to fit in unsigned textures, and grays stay exactly 0 once blurred. The alpha is a
+// weight of 1: once blurred, it is how much of the kernel fell inside the image, which the chroma is divided by to
+// undo the fading of the edges.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0).rgb;
+    let lowest = min(min(color.r, color.g), color.b);
+
+    textureStore(output_texture, position, vec4<f32>(color - vec3<f32>(lowest), 1.0));
+}
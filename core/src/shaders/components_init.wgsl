@@ -0,0 +1,32 @@
+struct Settings {
+    threshold : f32,
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<r32uint, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+// Labels each pixel brighter than the threshold with its index, plus one, and the others with 0.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, vec2<i32>(global_id.xy), 0);
+    var luminance = color.r;
+    if (settings.single_channel == 0u) {
+        luminance = 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+    }
+
+    var label = 0u;
+    if (luminance > settings.threshold) {
+        label = global_id.y * u32(dimensions.x) + global_id.x + 1u;
+    }
+    textureStore(output_texture, vec2<i32>(global_id.xy), vec4<u32>(label, 0u, 0u, 0u));
+}
@@ -0,0 +1,39 @@
+@group(0) @binding(0) var input_texture : texture_2d<u32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<r32uint, write>;
+
+fn label_at(coords : vec2<i32>, dimensions : vec2<i32>) -> u32 {
+    if (coords.x < 0 || coords.y < 0 || coords.x >= dimensions.x || coords.y >= dimensions.y) {
+        return 0u;
+    }
+    return textureLoad(input_texture, coords, 0).r;
+}
+
+// Gives each labeled pixel the smallest label among itself and its four labeled neighbors.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let coords = vec2<i32>(global_id.xy);
+    var label = textureLoad(input_texture, coords, 0).r;
+    if (label != 0u) {
+        var neighbors = array<vec2<i32>, 4>(
+            vec2<i32>(-1, 0),
+            vec2<i32>(1, 0),
+            vec2<i32>(0, -1),
+            vec2<i32>(0, 1),
+        );
+        for (var index = 0; index < 4; index = index + 1) {
+            let neighbor = label_at(coords + neighbors[index], dimensions);
+            if (neighbor != 0u) {
+                label = min(label, neighbor);
+            }
+        }
+    }
+    textureStore(output_texture, coords, vec4<u32>(label, 0u, 0u, 0u));
+}
@@ -0,0 +1,42 @@
+struct Settings {
+    position : vec2<i32>,
+    opacity : f32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var overlay_texture : texture_2d<f32>;
+@group(1) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let destination = textureLoad(input_texture, position, 0);
+
+    let overlay_position = position - settings.position;
+    let overlay_dimensions = textureDimensions(overlay_texture);
+    if(overlay_position.x < 0 || overlay_position.y < 0
+        || overlay_position.x >= overlay_dimensions.x || overlay_position.y >= overlay_dimensions.y) {
+        textureStore(output_texture, position, destination);
+        return;
+    }
+
+    let source = textureLoad(overlay_texture, overlay_position, 0);
+    let source_alpha = source.a * settings.opacity;
+    let alpha = source_alpha + destination.a * (1.0 - source_alpha);
+
+    var color = vec3<f32>(0.0, 0.0, 0.0);
+    if (alpha > 0.0) {
+        color = (source.rgb * source_alpha + destination.rgb * destination.a * (1.0 - source_alpha)) / alpha;
+    }
+
+    textureStore(output_texture, position, vec4<f32>(color, alpha));
+}
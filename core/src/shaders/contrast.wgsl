@@ -0,0 +1,24 @@
+struct Settings {
+    amount : f32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let contrasted = clamp((color.rgb - 0.5) * settings.amount + 0.5, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    textureStore(output_texture, position, vec4<f32>(contrasted, color.a));
+}
@@ -0,0 +1,42 @@
+struct Settings {
+    atmosphere : vec4<f32>,
+    radius : i32,
+    strength : f32,
+    single_channel : u32,
+    _padding : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var dark_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+
+// The least transmission, which keeps the densest haze from amplifying the noise too much.
+let MIN_TRANSMISSION : f32 = 0.1;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    var dark = textureLoad(dark_texture, position, 0).r;
+    for (var offset = 1; offset <= settings.radius; offset = offset + 1) {
+        let above = clamp(position - vec2<i32>(0, offset), vec2<i32>(0, 0), dimensions - 1);
+        let below = clamp(position + vec2<i32>(0, offset), vec2<i32>(0, 0), dimensions - 1);
+        dark = min(dark, min(textureLoad(dark_texture, above, 0).r, textureLoad(dark_texture, below, 0).r));
+    }
+
+    // The haze blends the scene towards the atmospheric light, by 1 - transmission: the blend is undone.
+    let transmission = max(1.0 - settings.strength * dark, MIN_TRANSMISSION);
+    let color = textureLoad(input_texture, position, 0);
+    let atmosphere = settings.atmosphere.rgb;
+    let radiance = clamp((color.rgb - atmosphere) / transmission + atmosphere, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    textureStore(output_texture, position, vec4<f32>(radiance, color.a));
+}
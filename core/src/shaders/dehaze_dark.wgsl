@@ -0,0 +1,42 @@
+struct Settings {
+    atmosphere : vec4<f32>,
+    radius : i32,
+    strength : f32,
+    single_channel : u32,
+    _padding : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+// The smallest channel of a pixel, relative to the atmospheric light, repeating the pixels of the edges.
+fn darkest_channel(position : vec2<i32>, dimensions : vec2<i32>) -> f32 {
+    let color = textureLoad(input_texture, clamp(position, vec2<i32>(0, 0), dimensions - 1), 0);
+    if (settings.single_channel == 1u) {
+        return color.r / settings.atmosphere.r;
+    }
+    let relative = color.rgb / settings.atmosphere.rgb;
+    return min(relative.r, min(relative.g, relative.b));
+}
+
+// The first half of the dark channel: the smallest channel over a row of the window around each pixel.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    var dark = darkest_channel(position, dimensions);
+    for (var offset = 1; offset <= settings.radius; offset = offset + 1) {
+        dark = min(dark, darkest_channel(position - vec2<i32>(offset, 0), dimensions));
+        dark = min(dark, darkest_channel(position + vec2<i32>(offset, 0), dimensions));
+    }
+
+    textureStore(output_texture, position, vec4<f32>(dark, dark, dark, 1.0));
+}
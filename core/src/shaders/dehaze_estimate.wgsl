@@ -0,0 +1,39 @@
+struct Settings {
+    atmosphere : vec4<f32>,
+    radius : i32,
+    strength : f32,
+    single_channel : u32,
+    _padding : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var dark_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba32float, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+
+// Finishes the dark channel over the columns of the window around each pixel, and writes it along with the color
+// of the pixel, to pick the atmospheric light from the haziest pixels.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    var dark = textureLoad(dark_texture, position, 0).r;
+    for (var offset = 1; offset <= settings.radius; offset = offset + 1) {
+        let above = clamp(position - vec2<i32>(0, offset), vec2<i32>(0, 0), dimensions - 1);
+        let below = clamp(position + vec2<i32>(0, offset), vec2<i32>(0, 0), dimensions - 1);
+        dark = min(dark, min(textureLoad(dark_texture, above, 0).r, textureLoad(dark_texture, below, 0).r));
+    }
+
+    var color = textureLoad(input_texture, position, 0).rgb;
+    if (settings.single_channel == 1u) {
+        color = vec3<f32>(color.r);
+    }
+    textureStore(output_texture, position, vec4<f32>(dark, color));
+}
@@ -0,0 +1,41 @@
+struct Settings {
+    spread : f32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<u32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+let NO_SEED : u32 = 0xffffffffu;
+
+// The distance from `position` to the edge of the shape, halfway between `seed` and the pixel next to it.
+fn edge_distance(position : vec2<i32>, seed : vec2<u32>) -> f32 {
+    if (seed.x == NO_SEED) {
+        return 3.4e38;
+    }
+    return distance(vec2<f32>(position), vec2<f32>(seed)) - 0.5;
+}
+
+// Encodes the signed distance to the edge of the shape, positive inside, as 0.5 on the edge, going up to 1.0 at
+// `spread` pixels inside and down to 0.0 at `spread` pixels outside.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let seeds = textureLoad(input_texture, position, 0);
+    // Pixels inside the shape are their own nearest inside pixel.
+    var signed_distance = -edge_distance(position, seeds.xy);
+    if (all(seeds.xy == global_id.xy)) {
+        signed_distance = edge_distance(position, seeds.zw);
+    }
+
+    let value = clamp(0.5 + signed_distance / (2.0 * settings.spread), 0.0, 1.0);
+    textureStore(output_texture, position, vec4<f32>(value, value, value, 1.0));
+}
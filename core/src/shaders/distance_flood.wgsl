@@ -0,0 +1,58 @@
+struct Settings {
+    step : i32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<u32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba32uint, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+let NO_SEED : u32 = 0xffffffffu;
+
+// The squared distance from `position` to `seed`, or the largest distance when there is no seed.
+fn squared_distance(position : vec2<i32>, seed : vec2<u32>) -> i32 {
+    if (seed.x == NO_SEED) {
+        return 0x7fffffff;
+    }
+    let offset = vec2<i32>(seed) - position;
+    return offset.x * offset.x + offset.y * offset.y;
+}
+
+// A jump flooding pass: each pixel keeps the nearest of the seeds of the pixels `step` away from it, for both the
+// inside and the outside of the shape.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    var best = textureLoad(input_texture, position, 0);
+    var inside_distance = squared_distance(position, best.xy);
+    var outside_distance = squared_distance(position, best.zw);
+    for (var y : i32 = -1; y <= 1; y = y + 1) {
+        for (var x : i32 = -1; x <= 1; x = x + 1) {
+            let neighbor = position + vec2<i32>(x, y) * settings.step;
+            if (neighbor.x < 0 || neighbor.y < 0 || neighbor.x >= dimensions.x || neighbor.y >= dimensions.y) {
+                continue;
+            }
+
+            let seeds = textureLoad(input_texture, neighbor, 0);
+            let inside = squared_distance(position, seeds.xy);
+            if (inside < inside_distance) {
+                inside_distance = inside;
+                best = vec4<u32>(seeds.xy, best.zw);
+            }
+            let outside = squared_distance(position, seeds.zw);
+            if (outside < outside_distance) {
+                outside_distance = outside;
+                best = vec4<u32>(best.xy, seeds.zw);
+            }
+        }
+    }
+
+    textureStore(output_texture, position, best);
+}
@@ -0,0 +1,34 @@
+struct Settings {
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba32uint, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+let NO_SEED : u32 = 0xffffffffu;
+
+// Seeds each pixel as the nearest pixel inside the shape, in red and green, if it is inside, and as the nearest
+// pixel outside of it, in blue and alpha, otherwise.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, vec2<i32>(global_id.xy), 0);
+    var coverage = color.a;
+    if (settings.single_channel == 1u) {
+        coverage = color.r;
+    }
+
+    var seeds = vec4<u32>(NO_SEED, NO_SEED, global_id.xy);
+    if (coverage >= 0.5) {
+        seeds = vec4<u32>(global_id.xy, NO_SEED, NO_SEED);
+    }
+    textureStore(output_texture, vec2<i32>(global_id.xy), seeds);
+}
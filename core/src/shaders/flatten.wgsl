@@ -0,0 +1,25 @@
+struct Settings {
+    background : vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+// Draws the pixel over the opaque background color, leaving it opaque.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let flattened = mix(settings.background.rgb, color.rgb, color.a);
+
+    textureStore(output_texture, position, vec4<f32>(flattened, 1.0));
+}
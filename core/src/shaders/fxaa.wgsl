@@ -0,0 +1,188 @@
+struct Settings {
+    // The smallest contrast, on dark areas, and the smallest contrast relative to the brightest neighbor, for a
+    // pixel to be on an edge.
+    edge_threshold_min : f32,
+    edge_threshold : f32,
+    // How much the aliasing of pixels thinner than a pixel is removed, from 0 to 1.
+    subpixel_quality : f32,
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(0) @binding(1) var samp : sampler;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+let ITERATIONS : i32 = 12;
+
+fn luma(color : vec4<f32>) -> f32 {
+    if (settings.single_channel == 1u) {
+        return color.r;
+    }
+    return dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn sample_luma(uv : vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(input_texture, samp, uv, 0.0));
+}
+
+// The luma of the pixel `offset` pixels away from the one at `uv`.
+fn neighbor_luma(uv : vec2<f32>, offset : vec2<f32>, texel : vec2<f32>) -> f32 {
+    return sample_luma(uv + offset * texel);
+}
+
+// How far the edge search moves at each of its iterations, in pixels, longer as it goes.
+fn search_step(iteration : i32) -> f32 {
+    if (iteration < 5) {
+        return 1.0;
+    }
+    if (iteration == 5) {
+        return 1.5;
+    }
+    if (iteration < 10) {
+        return 2.0;
+    }
+    if (iteration == 10) {
+        return 4.0;
+    }
+    return 8.0;
+}
+
+// FXAA 3.11, quality version: finds the direction and the ends of the edge each pixel is on, and samples the
+// image between the pixel and its neighbor across the edge, the closer to the neighbor the closer the pixel is to
+// the end of the edge.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let texel = 1.0 / vec2<f32>(dimensions);
+    let uv = (vec2<f32>(global_id.xy) + 0.5) * texel;
+    let color = textureSampleLevel(input_texture, samp, uv, 0.0);
+
+    let luma_center = luma(color);
+    let luma_up = neighbor_luma(uv, vec2<f32>(0.0, -1.0), texel);
+    let luma_down = neighbor_luma(uv, vec2<f32>(0.0, 1.0), texel);
+    let luma_left = neighbor_luma(uv, vec2<f32>(-1.0, 0.0), texel);
+    let luma_right = neighbor_luma(uv, vec2<f32>(1.0, 0.0), texel);
+    let luma_min = min(luma_center, min(min(luma_up, luma_down), min(luma_left, luma_right)));
+    let luma_max = max(luma_center, max(max(luma_up, luma_down), max(luma_left, luma_right)));
+    let luma_range = luma_max - luma_min;
+    if (luma_range < max(settings.edge_threshold_min, luma_max * settings.edge_threshold)) {
+        textureStore(output_texture, position, color);
+        return;
+    }
+
+    let luma_up_left = neighbor_luma(uv, vec2<f32>(-1.0, -1.0), texel);
+    let luma_up_right = neighbor_luma(uv, vec2<f32>(1.0, -1.0), texel);
+    let luma_down_left = neighbor_luma(uv, vec2<f32>(-1.0, 1.0), texel);
+    let luma_down_right = neighbor_luma(uv, vec2<f32>(1.0, 1.0), texel);
+    let luma_up_down = luma_up + luma_down;
+    let luma_left_right = luma_left + luma_right;
+    let luma_left_corners = luma_up_left + luma_down_left;
+    let luma_right_corners = luma_up_right + luma_down_right;
+    let luma_up_corners = luma_up_left + luma_up_right;
+    let luma_down_corners = luma_down_left + luma_down_right;
+
+    let edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+        + abs(-2.0 * luma_center + luma_up_down) * 2.0
+        + abs(-2.0 * luma_right + luma_right_corners);
+    let edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+        + abs(-2.0 * luma_center + luma_left_right) * 2.0
+        + abs(-2.0 * luma_down + luma_down_corners);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // The neighbors on each side of the edge, and which side differs the most.
+    var luma_1 = luma_left;
+    var luma_2 = luma_right;
+    var step_length = texel.x;
+    if (is_horizontal) {
+        luma_1 = luma_up;
+        luma_2 = luma_down;
+        step_length = texel.y;
+    }
+    let gradient_1 = luma_1 - luma_center;
+    let gradient_2 = luma_2 - luma_center;
+    let gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+    var luma_local_average = 0.5 * (luma_2 + luma_center);
+    if (abs(gradient_1) >= abs(gradient_2)) {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_1 + luma_center);
+    }
+
+    // Walks along the edge, halfway between the pixel and its neighbor, in both directions, until the contrast
+    // changes.
+    var edge_uv = uv;
+    var offset = vec2<f32>(0.0, texel.y);
+    if (is_horizontal) {
+        edge_uv.y = edge_uv.y + step_length * 0.5;
+        offset = vec2<f32>(texel.x, 0.0);
+    } else {
+        edge_uv.x = edge_uv.x + step_length * 0.5;
+    }
+    var uv_1 = edge_uv - offset;
+    var uv_2 = edge_uv + offset;
+    var luma_end_1 = 0.0;
+    var luma_end_2 = 0.0;
+    var reached_1 = false;
+    var reached_2 = false;
+    for (var iteration : i32 = 0; iteration < ITERATIONS; iteration = iteration + 1) {
+        if (!reached_1) {
+            luma_end_1 = sample_luma(uv_1) - luma_local_average;
+            reached_1 = abs(luma_end_1) >= gradient_scaled;
+        }
+        if (!reached_2) {
+            luma_end_2 = sample_luma(uv_2) - luma_local_average;
+            reached_2 = abs(luma_end_2) >= gradient_scaled;
+        }
+        if (reached_1 && reached_2) {
+            break;
+        }
+        if (!reached_1) {
+            uv_1 = uv_1 - offset * search_step(iteration + 1);
+        }
+        if (!reached_2) {
+            uv_2 = uv_2 + offset * search_step(iteration + 1);
+        }
+    }
+
+    var distance_1 = uv.y - uv_1.y;
+    var distance_2 = uv_2.y - uv.y;
+    if (is_horizontal) {
+        distance_1 = uv.x - uv_1.x;
+        distance_2 = uv_2.x - uv.x;
+    }
+    let is_direction_1 = distance_1 < distance_2;
+    let edge_length = distance_1 + distance_2;
+    let pixel_offset = -min(distance_1, distance_2) / edge_length + 0.5;
+
+    // Only moves towards the neighbor if the end of the edge in that direction agrees with the pixel.
+    var luma_end = luma_end_2;
+    if (is_direction_1) {
+        luma_end = luma_end_1;
+    }
+    var final_offset = 0.0;
+    if ((luma_end < 0.0) != (luma_center < luma_local_average)) {
+        final_offset = pixel_offset;
+    }
+
+    // Pixels thinner than a pixel are blended with their neighbors depending on how much they stand out.
+    let luma_average = (2.0 * (luma_up_down + luma_left_right) + luma_left_corners + luma_right_corners) / 12.0;
+    let subpixel_1 = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+    let subpixel_2 = (-2.0 * subpixel_1 + 3.0) * subpixel_1 * subpixel_1;
+    final_offset = max(final_offset, subpixel_2 * subpixel_2 * settings.subpixel_quality);
+
+    var final_uv = uv;
+    if (is_horizontal) {
+        final_uv.y = final_uv.y + final_offset * step_length;
+    } else {
+        final_uv.x = final_uv.x + final_offset * step_length;
+    }
+    textureStore(output_texture, position, textureSampleLevel(input_texture, samp, final_uv, 0.0));
+}
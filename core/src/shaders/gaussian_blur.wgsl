@@ -1,13 +1,14 @@
 struct Settings {
     filter_size : u32,
+    workgroup_length : u32,
 };
 
 struct Orientation {
     vertical : u32,
 };
 
+// Normalized on the cpu, so that the values add up to 1.
 struct Kernel {
-  sum: f32,
   values : array<f32>,
 };
 
@@ -17,36 +18,49 @@ struct Kernel {
 @group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
 @group(1) @binding(2) var<uniform> orientation: Orientation;
 
+// The segment of the row, or column, blurred by the workgroup, along with the pixels the kernel reaches on each side.
+// Its length is swapped for what the device supports.
+var<workgroup> tile : array<vec4<f32>, 1024>;
+
 @compute
 @workgroup_size(128)
 fn main(
   @builtin(global_invocation_id) global_id : vec3<u32>,
+  @builtin(local_invocation_id) local_id : vec3<u32>,
 ) {
     let filter_radius = i32((settings.filter_size - 1u) / 2u);
     let filter_size = i32(settings.filter_size);
     let dimensions = textureDimensions(input_texture);
+    // The invocations of a workgroup follow the direction of the blur, so that they share the pixels they read.
     var position = vec2<i32>(global_id.xy);
-    if (orientation.vertical == 0u) {
+    var direction = vec2<i32>(1, 0);
+    if (orientation.vertical > 0u) {
         position = position.yx;
+        direction = vec2<i32>(0, 1);
+    }
+
+    // Each invocation loads every workgroup_length-th pixel of the segment, those outside of the image being
+    // transparent black.
+    let start = position - direction * (i32(local_id.x) + filter_radius);
+    let tile_length = settings.workgroup_length + settings.filter_size - 1u;
+    for (var i : u32 = local_id.x; i < tile_length; i = i + settings.workgroup_length) {
+        let sample = start + direction * i32(i);
+        if (all(sample >= vec2<i32>(0, 0)) && all(sample < dimensions)) {
+            tile[i] = textureLoad(input_texture, sample, 0);
+        } else {
+            tile[i] = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+        }
     }
-    
+    workgroupBarrier();
+
     if(position.x >= dimensions.x || position.y >= dimensions.y) {
         return;
     }
 
-    let original = textureLoad(input_texture, position, 0);
     var color : vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
-    
     for (var i : i32 = 0; i < filter_size; i = i + 1) {
-        if (orientation.vertical > 0u) {
-            let y = position.y - filter_radius + i;
-            color = color + kernel.values[i] * textureLoad(input_texture, vec2<i32>(position.x, y), 0);
-        } else {
-            let x = position.x - filter_radius + i;
-            color = color + kernel.values[i] * textureLoad(input_texture, vec2<i32>(x, position.y), 0);
-        }
+        color = color + kernel.values[i] * tile[i32(local_id.x) + i];
     }
-    color = color / kernel.sum;
 
     textureStore(output_texture, position, color);
-}
\ No newline at end of file
+}
@@ -0,0 +1,42 @@
+struct Settings {
+    first : vec4<f32>,
+    second : vec4<f32>,
+    direction : vec2<f32>,
+    center : vec2<f32>,
+    // 0 is a solid color, 1 a linear gradient, 2 a radial gradient, and 3 a checkerboard.
+    kind : u32,
+    cell : u32,
+    scale : f32,
+    offset : f32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<f32>(global_id.xy);
+    var color = settings.first;
+    if (settings.kind == 1u) {
+        let t = clamp(dot(position, settings.direction) * settings.scale + settings.offset, 0.0, 1.0);
+        color = mix(settings.first, settings.second, t);
+    } else if (settings.kind == 2u) {
+        let t = clamp(distance(position, settings.center) * settings.scale, 0.0, 1.0);
+        color = mix(settings.first, settings.second, t);
+    } else if (settings.kind == 3u) {
+        let cells = global_id.xy / settings.cell;
+        if ((cells.x + cells.y) % 2u == 1u) {
+            color = settings.second;
+        }
+    }
+
+    textureStore(output_texture, vec2<i32>(global_id.xy), color);
+}
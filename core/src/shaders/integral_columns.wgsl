@@ -0,0 +1,21 @@
+@group(0) @binding(0) var input_texture : texture_2d<u32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba32uint, write>;
+
+// Sums the row sums of each column from top to bottom, one invocation per column.
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    let x = i32(global_id.x);
+    if(x >= dimensions.x) {
+        return;
+    }
+
+    var sum = vec4<u32>(0u);
+    for (var y = 0; y < dimensions.y; y = y + 1) {
+        sum = sum + textureLoad(input_texture, vec2<i32>(x, y), 0);
+        textureStore(output_texture, vec2<i32>(x, y), sum);
+    }
+}
@@ -0,0 +1,31 @@
+struct Settings {
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba32uint, write>;
+@group(0) @binding(2) var<uniform> settings : Settings;
+
+// Sums the 8-bit levels of each row from left to right, one invocation per row. The sums wrap around, which
+// the differences of the summed-area table cancel out.
+@compute
+@workgroup_size(64)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    let y = i32(global_id.x);
+    if(y >= dimensions.y) {
+        return;
+    }
+
+    var sum = vec4<u32>(0u);
+    for (var x = 0; x < dimensions.x; x = x + 1) {
+        var color = textureLoad(input_texture, vec2<i32>(x, y), 0);
+        if (settings.single_channel != 0u) {
+            color = vec4<f32>(color.r, color.r, color.r, 1.0);
+        }
+        sum = sum + vec4<u32>(round(clamp(color, vec4<f32>(0.0), vec4<f32>(1.0)) * 255.0));
+        textureStore(output_texture, vec2<i32>(x, y), sum);
+    }
+}
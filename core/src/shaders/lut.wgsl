@@ -0,0 +1,60 @@
+struct Settings {
+    domain_min : vec4<f32>,
+    domain_max : vec4<f32>,
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var lut_texture : texture_3d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+
+// The color of the lut at `color`, interpolated between the 8 entries around it. The interpolation is done here
+// rather than by a sampler, whose weights only have a few bits of precision on some gpus.
+fn lookup(color : vec3<f32>) -> vec3<f32> {
+    let size = textureDimensions(lut_texture);
+    let domain = (color - settings.domain_min.rgb) / (settings.domain_max.rgb - settings.domain_min.rgb);
+    let scaled = clamp(domain, vec3<f32>(0.0), vec3<f32>(1.0)) * vec3<f32>(size - vec3<i32>(1));
+    // The lowest corner stops one entry before the last, so that the highest one stays in the lut.
+    let low = min(vec3<i32>(floor(scaled)), size - vec3<i32>(2));
+    let t = scaled - vec3<f32>(low);
+
+    let c000 = textureLoad(lut_texture, low, 0).rgb;
+    let c100 = textureLoad(lut_texture, low + vec3<i32>(1, 0, 0), 0).rgb;
+    let c010 = textureLoad(lut_texture, low + vec3<i32>(0, 1, 0), 0).rgb;
+    let c110 = textureLoad(lut_texture, low + vec3<i32>(1, 1, 0), 0).rgb;
+    let c001 = textureLoad(lut_texture, low + vec3<i32>(0, 0, 1), 0).rgb;
+    let c101 = textureLoad(lut_texture, low + vec3<i32>(1, 0, 1), 0).rgb;
+    let c011 = textureLoad(lut_texture, low + vec3<i32>(0, 1, 1), 0).rgb;
+    let c111 = textureLoad(lut_texture, low + vec3<i32>(1, 1, 1), 0).rgb;
+
+    let c00 = mix(c000, c100, t.r);
+    let c10 = mix(c010, c110, t.r);
+    let c01 = mix(c001, c101, t.r);
+    let c11 = mix(c011, c111, t.r);
+    return mix(mix(c00, c10, t.g), mix(c01, c11, t.g), t.b);
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+
+    if (settings.single_channel == 1u) {
+        // The gray goes through the lut as a color, and comes back as its luminance.
+        let mapped = lookup(vec3<f32>(color.r));
+        let gray = 0.299 * mapped.r + 0.587 * mapped.g + 0.114 * mapped.b;
+        textureStore(output_texture, position, vec4<f32>(gray, gray, gray, color.a));
+        return;
+    }
+
+    textureStore(output_texture, position, vec4<f32>(lookup(color.rgb), color.a));
+}
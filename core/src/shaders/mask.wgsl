@@ -0,0 +1,23 @@
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var filtered_texture : texture_2d<f32>;
+@group(0) @binding(2) var mask_texture : texture_2d<f32>;
+@group(0) @binding(3) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let original = textureLoad(input_texture, position, 0);
+    let filtered = textureLoad(filtered_texture, position, 0);
+    let mask = textureLoad(mask_texture, position, 0);
+    let weight = clamp((0.299 * mask.r + 0.587 * mask.g + 0.114 * mask.b) * mask.a, 0.0, 1.0);
+
+    textureStore(output_texture, position, mix(original, filtered, vec4<f32>(weight)));
+}
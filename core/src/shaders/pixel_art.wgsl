@@ -0,0 +1,130 @@
+struct Settings {
+    factor : u32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+fn same(a : vec4<f32>, b : vec4<f32>) -> bool {
+    return all(a == b);
+}
+
+fn load(coordinates : vec2<i32>) -> vec4<f32> {
+    let dimensions = textureDimensions(input_texture);
+    return textureLoad(input_texture, clamp(coordinates, vec2<i32>(0), dimensions - 1), 0);
+}
+
+// The Scale2x rules, for the quarter `sub` of the pixel e, with b above, d on the left, f on the right and h below.
+fn scale2x(sub : vec2<u32>, b : vec4<f32>, d : vec4<f32>, e : vec4<f32>, f : vec4<f32>, h : vec4<f32>) -> vec4<f32> {
+    if (same(b, h) || same(d, f)) {
+        return e;
+    }
+    let top = sub.y == 0u;
+    let left = sub.x == 0u;
+    if (top && left && same(d, b)) {
+        return d;
+    }
+    if (top && !left && same(b, f)) {
+        return f;
+    }
+    if (!top && left && same(d, h)) {
+        return d;
+    }
+    if (!top && !left && same(h, f)) {
+        return f;
+    }
+    return e;
+}
+
+// The Scale3x rules, for the ninth `sub` of the pixel e, in the middle of its neighbors:
+// a b c
+// d e f
+// g h i
+fn scale3x(sub : vec2<u32>, position : vec2<i32>, e : vec4<f32>) -> vec4<f32> {
+    let a = load(position + vec2<i32>(-1, -1));
+    let b = load(position + vec2<i32>(0, -1));
+    let c = load(position + vec2<i32>(1, -1));
+    let d = load(position + vec2<i32>(-1, 0));
+    let f = load(position + vec2<i32>(1, 0));
+    let g = load(position + vec2<i32>(-1, 1));
+    let h = load(position + vec2<i32>(0, 1));
+    let i = load(position + vec2<i32>(1, 1));
+    if (same(b, h) || same(d, f)) {
+        return e;
+    }
+
+    let index = sub.y * 3u + sub.x;
+    switch (index) {
+        case 0u: {
+            if (same(d, b)) {
+                return d;
+            }
+        }
+        case 1u: {
+            if ((same(d, b) && !same(e, c)) || (same(b, f) && !same(e, a))) {
+                return b;
+            }
+        }
+        case 2u: {
+            if (same(b, f)) {
+                return f;
+            }
+        }
+        case 3u: {
+            if ((same(d, b) && !same(e, g)) || (same(d, h) && !same(e, a))) {
+                return d;
+            }
+        }
+        case 5u: {
+            if ((same(b, f) && !same(e, i)) || (same(h, f) && !same(e, c))) {
+                return f;
+            }
+        }
+        case 6u: {
+            if (same(d, h)) {
+                return d;
+            }
+        }
+        case 7u: {
+            if ((same(d, h) && !same(e, i)) || (same(h, f) && !same(e, g))) {
+                return h;
+            }
+        }
+        case 8u: {
+            if (same(h, f)) {
+                return f;
+            }
+        }
+        default: {}
+    }
+    return e;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy / settings.factor);
+    let sub = global_id.xy % settings.factor;
+    let e = load(position);
+
+    var color : vec4<f32>;
+    if (settings.factor == 3u) {
+        color = scale3x(sub, position, e);
+    } else {
+        let b = load(position + vec2<i32>(0, -1));
+        let d = load(position + vec2<i32>(-1, 0));
+        let f = load(position + vec2<i32>(1, 0));
+        let h = load(position + vec2<i32>(0, 1));
+        color = scale2x(sub, b, d, e, f, h);
+    }
+
+    textureStore(output_texture, vec2<i32>(global_id.xy), color);
+}
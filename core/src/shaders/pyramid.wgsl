@@ -0,0 +1,27 @@
+struct Settings {
+    weights : vec4<f32>,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var level_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+
+// Adds the level to the image, weighted per channel: with weights of -1 it takes the upsampled coarser level away to
+// keep the details, and with the gains of the levels it adds them back.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let level = textureLoad(level_texture, position, 0);
+
+    textureStore(output_texture, position, color + settings.weights * level);
+}
@@ -0,0 +1,129 @@
+struct Settings {
+    filter_type : u32,
+    // 0 clamps to the edges, 1 repeats the image, 2 repeats it mirrored.
+    address_mode : u32,
+    scale : vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+let PI : f32 = 3.14159265358979;
+
+// Catmull-Rom cubic, support of 2.
+fn cubic(x : f32) -> f32 {
+    let x = abs(x);
+    if (x < 1.0) {
+        return 1.5 * x * x * x - 2.5 * x * x + 1.0;
+    }
+    if (x < 2.0) {
+        return -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0;
+    }
+    return 0.0;
+}
+
+fn sinc(x : f32) -> f32 {
+    if (abs(x) < 0.00001) {
+        return 1.0;
+    }
+    return sin(PI * x) / (PI * x);
+}
+
+// Lanczos with a = 3, support of 3.
+fn lanczos3(x : f32) -> f32 {
+    if (abs(x) < 3.0) {
+        return sinc(x) * sinc(x / 3.0);
+    }
+    return 0.0;
+}
+
+fn support() -> f32 {
+    if (settings.filter_type == 0u) {
+        return 2.0;
+    }
+    return 3.0;
+}
+
+fn weight(x : f32) -> f32 {
+    if (settings.filter_type == 0u) {
+        return cubic(x);
+    }
+    return lanczos3(x);
+}
+
+// The source pixel at `index` along an axis of `size` pixels, even beyond the edges.
+fn source_index(index : i32, size : i32) -> i32 {
+    if (settings.address_mode == 1u) {
+        return ((index % size) + size) % size;
+    }
+    if (settings.address_mode == 2u) {
+        let period = 2 * size;
+        let wrapped = ((index % period) + period) % period;
+        return min(wrapped, period - 1 - wrapped);
+    }
+    return clamp(index, 0, size - 1);
+}
+
+// Averages the source pixels covered by the footprint of the destination pixel, weighted by how much they overlap it.
+fn area(position : vec2<u32>, input_dimensions : vec2<i32>) -> vec4<f32> {
+    let start = vec2<f32>(position) * settings.scale;
+    let end = start + settings.scale;
+    let first = vec2<i32>(floor(start));
+    let last = min(vec2<i32>(ceil(end)), input_dimensions) - 1;
+
+    var color = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    var weight_sum = 0.0;
+    for (var y : i32 = first.y; y <= last.y; y = y + 1) {
+        let weight_y = min(f32(y + 1), end.y) - max(f32(y), start.y);
+        for (var x : i32 = first.x; x <= last.x; x = x + 1) {
+            let weight_xy = (min(f32(x + 1), end.x) - max(f32(x), start.x)) * weight_y;
+            color = color + weight_xy * textureLoad(input_texture, vec2<i32>(x, y), 0);
+            weight_sum = weight_sum + weight_xy;
+        }
+    }
+
+    return color / weight_sum;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let input_dimensions = textureDimensions(input_texture);
+    let scale = settings.scale;
+
+    if (settings.filter_type == 2u) {
+        textureStore(output_texture, vec2<i32>(global_id.xy), area(global_id.xy, input_dimensions));
+        return;
+    }
+
+    // When downscaling, the kernel is stretched to cover the footprint of the destination pixel.
+    let filter_scale = max(scale, vec2<f32>(1.0, 1.0));
+    let radius = support() * filter_scale;
+    let center = (vec2<f32>(global_id.xy) + 0.5) * scale - 0.5;
+
+    let first = vec2<i32>(floor(center - radius)) + 1;
+    let last = vec2<i32>(floor(center + radius));
+
+    var color = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    var weight_sum = 0.0;
+    for (var y : i32 = first.y; y <= last.y; y = y + 1) {
+        let weight_y = weight((f32(y) - center.y) / filter_scale.y);
+        let source_y = source_index(y, input_dimensions.y);
+        for (var x : i32 = first.x; x <= last.x; x = x + 1) {
+            let weight_xy = weight((f32(x) - center.x) / filter_scale.x) * weight_y;
+            let source_x = source_index(x, input_dimensions.x);
+            color = color + weight_xy * textureLoad(input_texture, vec2<i32>(source_x, source_y), 0);
+            weight_sum = weight_sum + weight_xy;
+        }
+    }
+
+    textureStore(output_texture, vec2<i32>(global_id.xy), clamp(color / weight_sum, vec4<f32>(0.0), vec4<f32>(1.0)));
+}
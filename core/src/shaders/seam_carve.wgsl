@@ -0,0 +1,20 @@
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+// The source column of each pixel of the output, row by row.
+@group(0) @binding(2) var<storage, read> columns : array<u32>;
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(output_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let column = columns[global_id.y * u32(dimensions.x) + global_id.x];
+    let color = textureLoad(input_texture, vec2<i32>(i32(column), i32(global_id.y)), 0);
+
+    textureStore(output_texture, vec2<i32>(global_id.xy), color);
+}
@@ -0,0 +1,38 @@
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<r32float, write>;
+@group(0) @binding(2) var<uniform> single_channel : u32;
+
+fn luminance(position : vec2<i32>, dimensions : vec2<i32>) -> f32 {
+    let color = textureLoad(input_texture, clamp(position, vec2<i32>(0, 0), dimensions - 1), 0);
+    if (single_channel == 1u) {
+        return color.r;
+    }
+    return 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+}
+
+// The magnitude of the Sobel gradient of the luminance, repeating the pixels of the edges.
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let top_left = luminance(position + vec2<i32>(-1, -1), dimensions);
+    let top = luminance(position + vec2<i32>(0, -1), dimensions);
+    let top_right = luminance(position + vec2<i32>(1, -1), dimensions);
+    let left = luminance(position + vec2<i32>(-1, 0), dimensions);
+    let right = luminance(position + vec2<i32>(1, 0), dimensions);
+    let bottom_left = luminance(position + vec2<i32>(-1, 1), dimensions);
+    let bottom = luminance(position + vec2<i32>(0, 1), dimensions);
+    let bottom_right = luminance(position + vec2<i32>(1, 1), dimensions);
+
+    let horizontal = (top_right + 2.0 * right + bottom_right) - (top_left + 2.0 * left + bottom_left);
+    let vertical = (bottom_left + 2.0 * bottom + bottom_right) - (top_left + 2.0 * top + top_right);
+
+    textureStore(output_texture, position, vec4<f32>(sqrt(horizontal * horizontal + vertical * vertical), 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,50 @@
+struct Settings {
+    shadows : f32,
+    highlights : f32,
+    single_channel : u32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var illumination_texture : texture_2d<f32>;
+@group(0) @binding(2) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> settings : Settings;
+// The sums of the weights of the blur kernel before each of them, and of all of them last.
+@group(0) @binding(4) var<storage, read> kernel_sums : array<f32>;
+
+// The sum of the weights of the kernel that fell inside the image around `position`, along a side of `size`, see
+// `blurred_pass` in shadows.rs.
+fn coverage(position : i32, size : i32) -> f32 {
+    let radius = (i32(arrayLength(&kernel_sums)) - 2) / 2;
+    let first = max(-radius, -position);
+    let last = min(radius, size - 1 - position);
+    return kernel_sums[last + radius + 1] - kernel_sums[first + radius];
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let blurred = textureLoad(illumination_texture, position, 0)
+        / max(coverage(position.x, dimensions.x) * coverage(position.y, dimensions.y), 0.0001);
+    var illumination = blurred.r;
+    if (settings.single_channel == 0u) {
+        illumination = 0.299 * blurred.r + 0.587 * blurred.g + 0.114 * blurred.b;
+    }
+    let light = clamp(illumination, 0.0, 1.0);
+
+    // The illumination becomes light + light * (1 - light) * (shadows * (1 - light) - highlights * light), which
+    // lifts the dark areas and pulls the bright ones down while keeping black and white in place, and the pixel is
+    // scaled by the same ratio, keeping its reflectance, the details the blur removed.
+    let gain = 1.0 + (1.0 - light) * (settings.shadows * (1.0 - light) - settings.highlights * light);
+    let adjusted = clamp(color.rgb * gain, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    textureStore(output_texture, position, vec4<f32>(adjusted, color.a));
+}
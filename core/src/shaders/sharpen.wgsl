@@ -0,0 +1,33 @@
+struct Settings {
+    amount : f32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+fn load_clamped(position : vec2<i32>, dimensions : vec2<i32>) -> vec4<f32> {
+    return textureLoad(input_texture, clamp(position, vec2<i32>(0, 0), dimensions - 1), 0);
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let neighbors = load_clamped(position + vec2<i32>(-1, 0), dimensions)
+        + load_clamped(position + vec2<i32>(1, 0), dimensions)
+        + load_clamped(position + vec2<i32>(0, -1), dimensions)
+        + load_clamped(position + vec2<i32>(0, 1), dimensions);
+    let edges = 4.0 * color.rgb - neighbors.rgb;
+    let sharpened = clamp(color.rgb + settings.amount * edges, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    textureStore(output_texture, position, vec4<f32>(sharpened, color.a));
+}
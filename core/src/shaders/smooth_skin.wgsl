@@ -0,0 +1,54 @@
+struct Settings {
+    amount : f32,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var low_texture : texture_2d<f32>;
+@group(0) @binding(2) var smoothed_texture : texture_2d<f32>;
+@group(0) @binding(3) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(4) var<uniform> settings : Settings;
+// The sums of the weights of the kernels of the two blurs before each of them, and of all of them last.
+@group(0) @binding(5) var<storage, read> low_kernel_sums : array<f32>;
+@group(0) @binding(6) var<storage, read> smoothed_kernel_sums : array<f32>;
+
+// The indices of the running sums bounding the weights of a kernel of `radius` that fell inside the image around
+// `position`, along a side of `size`: the blur counts the pixels beyond the edges as black, which their difference
+// undoes.
+fn window(position : i32, size : i32, radius : i32) -> vec2<i32> {
+    let first = max(-radius, -position);
+    let last = min(radius, size - 1 - position);
+    return vec2<i32>(first + radius, last + radius + 1);
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let low_radius = (i32(arrayLength(&low_kernel_sums)) - 2) / 2;
+    let low_x = window(position.x, dimensions.x, low_radius);
+    let low_y = window(position.y, dimensions.y, low_radius);
+    let low_coverage = (low_kernel_sums[low_x.y] - low_kernel_sums[low_x.x])
+        * (low_kernel_sums[low_y.y] - low_kernel_sums[low_y.x]);
+    let smoothed_radius = (i32(arrayLength(&smoothed_kernel_sums)) - 2) / 2;
+    let smoothed_x = window(position.x, dimensions.x, smoothed_radius);
+    let smoothed_y = window(position.y, dimensions.y, smoothed_radius);
+    let smoothed_coverage = (smoothed_kernel_sums[smoothed_x.y] - smoothed_kernel_sums[smoothed_x.x])
+        * (smoothed_kernel_sums[smoothed_y.y] - smoothed_kernel_sums[smoothed_y.x]);
+
+    let color = textureLoad(input_texture, position, 0);
+    let low = textureLoad(low_texture, position, 0) / max(low_coverage, 0.0001);
+    let smoothed = textureLoad(smoothed_texture, position, 0) / max(smoothed_coverage, 0.0001);
+
+    // The image is its low frequencies plus its details: swapping the low frequencies for the smoothed ones keeps
+    // the details.
+    let retouched = clamp(color.rgb + settings.amount * (smoothed.rgb - low.rgb), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    textureStore(output_texture, position, vec4<f32>(retouched, color.a));
+}
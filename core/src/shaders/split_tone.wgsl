@@ -0,0 +1,43 @@
+struct Settings {
+    shadow_color : vec4<f32>,
+    highlight_color : vec4<f32>,
+    crossover : f32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+fn luminance(color : vec3<f32>) -> f32 {
+    return 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+}
+
+// The tint color brought to the luminance of the pixel, so that tinting changes its hue but not how light it is. A
+// gray tint gives back the gray of the same luminance.
+fn tinted(tint : vec3<f32>, light : f32) -> vec3<f32> {
+    return tint * light / max(luminance(tint), 0.0001);
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let light = clamp(luminance(color.rgb), 0.0, 1.0);
+
+    // The shadows are tinted fully on black, fading to nothing at the crossover, and the highlights from the
+    // crossover up to fully on white.
+    let shadow = clamp(1.0 - light / settings.crossover, 0.0, 1.0);
+    let highlight = clamp((light - settings.crossover) / (1.0 - settings.crossover), 0.0, 1.0);
+    var toned = mix(color.rgb, tinted(settings.shadow_color.rgb, light), shadow);
+    toned = mix(toned, tinted(settings.highlight_color.rgb, light), highlight);
+
+    textureStore(output_texture, position, vec4<f32>(clamp(toned, vec3<f32>(0.0), vec3<f32>(1.0)), color.a));
+}
@@ -0,0 +1,58 @@
+struct Partial {
+    sum : vec4<f32>,
+    minimum : vec4<f32>,
+    maximum : vec4<f32>,
+};
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> partials : array<Partial>;
+
+var<workgroup> sums : array<vec4<f32>, 256>;
+var<workgroup> minimums : array<vec4<f32>, 256>;
+var<workgroup> maximums : array<vec4<f32>, 256>;
+
+// Each workgroup reduces a block of 64×64 pixels, each invocation starting with 4×4 of them, to a partial result
+// finished on the cpu. The workgroup size is written in one dimension so that it isn't swapped for the tuned one,
+// which the reduction relies on.
+@compute
+@workgroup_size(256)
+fn main(
+  @builtin(workgroup_id) group : vec3<u32>,
+  @builtin(local_invocation_index) index : u32,
+) {
+    let dimensions = vec2<u32>(textureDimensions(input_texture));
+    let origin = group.xy * 64u + vec2<u32>(index % 16u, index / 16u) * 4u;
+
+    var sum = vec4<f32>(0.0);
+    var minimum = vec4<f32>(65504.0);
+    var maximum = vec4<f32>(-65504.0);
+    for (var y = 0u; y < 4u; y = y + 1u) {
+        for (var x = 0u; x < 4u; x = x + 1u) {
+            let coords = origin + vec2<u32>(x, y);
+            if (coords.x < dimensions.x && coords.y < dimensions.y) {
+                let color = textureLoad(input_texture, vec2<i32>(coords), 0);
+                sum = sum + color;
+                minimum = min(minimum, color);
+                maximum = max(maximum, color);
+            }
+        }
+    }
+    sums[index] = sum;
+    minimums[index] = minimum;
+    maximums[index] = maximum;
+    workgroupBarrier();
+
+    for (var stride = 128u; stride > 0u; stride = stride / 2u) {
+        if (index < stride) {
+            sums[index] = sums[index] + sums[index + stride];
+            minimums[index] = min(minimums[index], minimums[index + stride]);
+            maximums[index] = max(maximums[index], maximums[index + stride]);
+        }
+        workgroupBarrier();
+    }
+
+    if (index == 0u) {
+        let groups_per_row = (dimensions.x + 63u) / 64u;
+        partials[group.y * groups_per_row + group.x] = Partial(sums[0], minimums[0], maximums[0]);
+    }
+}
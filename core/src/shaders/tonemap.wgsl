@@ -0,0 +1,44 @@
+struct Settings {
+    tone_map : u32,
+};
+
+@group(0) @binding(0) var<uniform> settings : Settings;
+@group(1) @binding(0) var input_texture : texture_2d<f32>;
+@group(1) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+
+fn reinhard(color : vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// The fit of the ACES filmic curve by Krzysztof Narkowicz.
+fn aces(color : vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@compute
+@workgroup_size(16, 16)
+fn main(
+  @builtin(global_invocation_id) global_id : vec3<u32>,
+) {
+    let dimensions = textureDimensions(input_texture);
+    if(i32(global_id.x) >= dimensions.x || i32(global_id.y) >= dimensions.y) {
+        return;
+    }
+
+    let position = vec2<i32>(global_id.xy);
+    let color = textureLoad(input_texture, position, 0);
+    let positive = max(color.rgb, vec3<f32>(0.0));
+    var mapped : vec3<f32>;
+    if (settings.tone_map == 0u) {
+        mapped = reinhard(positive);
+    } else {
+        mapped = aces(positive);
+    }
+
+    textureStore(output_texture, position, vec4<f32>(mapped, color.a));
+}
@@ -0,0 +1,309 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferUsages, ComputePassDescriptor,
+    Texture, TextureUsages, TextureViewDescriptor,
+};
+
+use crate::{
+    blur::{kernel_size_for_sigma, Kernel},
+    cache::Bindings,
+    capitalize, compute_work_group_count,
+    pool::{COPY_TEXTURE_USAGES, STORAGE_TEXTURE_USAGES},
+    Operation, PixelFormat,
+};
+
+const SHADOWS_HIGHLIGHTS_SHADER: &str = include_str!("shaders/shadows_highlights.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ShadowsHighlightsSettings {
+    shadows: f32,
+    highlights: f32,
+    single_channel: u32,
+}
+
+impl<'a> Operation<'a> {
+    /// Recovers the details of the shadows and of the highlights, like of a backlit photo, with a single-scale
+    /// retinex: the illumination of each pixel is estimated by blurring the image, then the dark areas are lifted
+    /// and the bright ones pulled down, each pixel being scaled by how much its illumination changed so that the
+    /// local details stay.
+    ///
+    /// # Arguments
+    ///
+    /// * `shadows` - How much the shadows are lifted, 0.0 leaving them untouched and 1.0 brightening the darkest
+    ///   areas up to twice. Negative amounts deepen them.
+    /// * `highlights` - How much the highlights are pulled down, 0.0 leaving them untouched and 1.0 a strong
+    ///   compression. Negative amounts brighten them.
+    /// * `radius` - The sigma of the gaussian blur estimating the illumination, in pixels. Larger radii keep more of
+    ///   the local contrast, smaller ones even out more of it.
+    pub fn shadows_highlights(self, shadows: f32, highlights: f32, radius: f32) -> Self {
+        if shadows == 0.0 && highlights == 0.0 {
+            return self;
+        }
+
+        let settings = ShadowsHighlightsSettings {
+            shadows,
+            highlights,
+            single_channel: u32::from(self.format == PixelFormat::Luma),
+        };
+        self.blurred_pass(
+            "shadows highlights",
+            SHADOWS_HIGHLIGHTS_SHADER,
+            bytemuck::bytes_of(&settings),
+            &[radius],
+        )
+    }
+
+    /// Applies a filter whose shader reads the image along with copies of it blurred by gaussians of each of
+    /// `sigmas`, for filters working on the local averages of the pixels.
+    ///
+    /// The shader takes, at group 0, the image at binding 0, then the blurred copies, then the output texture, then
+    /// its `settings` as a uniform, then, for each copy, the running sums of the weights of its blur, starting at 0,
+    /// to undo the darkening of its edges, where the blur counts the pixels beyond as black. With a single sigma,
+    /// those are bindings 0 to 4.
+    pub(crate) fn blurred_pass(
+        mut self,
+        name: &'static str,
+        shader: &str,
+        settings: &[u8],
+        sigmas: &[f32],
+    ) -> Self {
+        let capitalized_filter_name = capitalize(name);
+
+        let mut blurred_textures = Vec::with_capacity(sigmas.len());
+        let mut kernel_sums = Vec::with_capacity(sigmas.len());
+        for &sigma in sigmas {
+            let (operation, texture, usage) = self.blurred_copy(sigma);
+            self = operation;
+            blurred_textures.push((texture, usage));
+
+            let kernel = if sigma.is_nan() || sigma <= 0.0 {
+                vec![1.0]
+            } else {
+                let size = kernel_size_for_sigma(sigma, self.dimensions());
+                Kernel::gaussian_of_size(sigma, size as usize)
+                    .values()
+                    .to_vec()
+            };
+            let sums = std::iter::once(0.0)
+                .chain(kernel.iter().scan(0.0, |sum, weight| {
+                    *sum += weight;
+                    Some(*sum)
+                }))
+                .collect::<Vec<f32>>();
+            kernel_sums.push(self.device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(format!("{} kernel sums", capitalized_filter_name).as_str()),
+                contents: bytemuck::cast_slice(&sums),
+                usage: BufferUsages::STORAGE,
+            }));
+        }
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some(format!("{} settings", capitalized_filter_name).as_str()),
+            contents: settings,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let pipeline = self.pipeline(name, shader, Bindings::Derived);
+
+        let views = std::iter::once(&self.texture)
+            .chain(blurred_textures.iter().map(|(texture, _)| texture))
+            .chain(std::iter::once(&output_texture))
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()))
+            .collect::<Vec<_>>();
+        let buffers = std::iter::once(&settings).chain(&kernel_sums);
+        let entries = views
+            .iter()
+            .map(BindingResource::TextureView)
+            .chain(buffers.map(|buffer| buffer.as_entire_binding()))
+            .enumerate()
+            .map(|(binding, resource)| BindGroupEntry {
+                binding: binding as u32,
+                resource,
+            })
+            .collect::<Vec<_>>();
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some(format!("{} bind group", capitalized_filter_name).as_str()),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        for (texture, usage) in blurred_textures {
+            self.pool.release(self.texture_size, usage, texture);
+        }
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.tileable = false;
+
+        self
+    }
+
+    /// Records a copy of the image blurred by a gaussian of `sigma`, returning its texture and the usages it was
+    /// created with.
+    pub(crate) fn blurred_copy(self, sigma: f32) -> (Self, Texture, TextureUsages) {
+        self.filtered_copy(|copy| copy.gaussian_blur(sigma))
+    }
+
+    /// Records the filters of `f` on a copy of the image, returning the texture of the filtered copy and the usages
+    /// it was created with. `f` must keep the dimensions of the image.
+    pub(crate) fn filtered_copy<F>(mut self, f: F) -> (Self, Texture, TextureUsages)
+    where
+        F: FnOnce(Operation<'a>) -> Operation<'a>,
+    {
+        let copy_texture = self
+            .pool
+            .take(self.device, self.texture_size, COPY_TEXTURE_USAGES);
+        self.encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            copy_texture.as_image_copy(),
+            self.texture_size,
+        );
+
+        let filtered = f(Operation {
+            device: self.device,
+            queue: self.queue,
+            pipelines: self.pipelines,
+            submissions: self.submissions,
+            readback_buffer: self.readback_buffer,
+            encoder: self.encoder,
+            texture: copy_texture,
+            texture_size: self.texture_size,
+            texture_usage: COPY_TEXTURE_USAGES,
+            format: self.format,
+            color_space: self.color_space,
+            pool: self.pool,
+            tileable: self.tileable,
+            radius: self.radius,
+            profiler: self.profiler.take(),
+        });
+        self.encoder = filtered.encoder;
+        self.pool = filtered.pool;
+        self.profiler = filtered.profiler;
+        self.radius = self.radius.max(filtered.radius);
+
+        (self, filtered.texture, filtered.texture_usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    /// A very dark left half and a very bright right half, both with some texture.
+    fn backlit() -> Image {
+        Image::from_fn(64, 32, |x, y| {
+            let texture = ((x * 7 + y * 13) % 9) as u8;
+            if x < 32 {
+                Rgba::new(12 + texture, 16 + texture, 10 + texture, 255)
+            } else {
+                Rgba::new(235 + texture, 238 + texture, 240 + texture, 255)
+            }
+        })
+    }
+
+    /// The mean red of the columns from `first` to `last` excluded.
+    fn mean(image: &Image, first: u32, last: u32) -> f32 {
+        let values = (0..image.height)
+            .flat_map(|y| (first..last).map(move |x| (x, y)))
+            .map(|(x, y)| f32::from(image.pixels[(y * image.width + x) as usize].r()))
+            .collect::<Vec<_>>();
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn lifts_the_shadows() {
+        let image = backlit();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .shadows_highlights(0.8, 0.0, 4.0)
+            .execute()
+            .block_on();
+
+        // Away from the middle, where the blur mixes both halves.
+        let (dark, bright) = (mean(&image, 0, 20), mean(&image, 44, 64));
+        assert!(
+            mean(&output, 0, 20) > dark * 1.4,
+            "{} from {dark}",
+            mean(&output, 0, 20)
+        );
+        assert!(
+            (mean(&output, 44, 64) - bright).abs() <= 2.0,
+            "{} from {bright}",
+            mean(&output, 44, 64)
+        );
+        assert_eq!(image.pixels[0].a(), output.pixels[0].a());
+    }
+
+    #[test]
+    fn pulls_the_highlights_down() {
+        let image = backlit();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .shadows_highlights(0.0, 1.0, 4.0)
+            .execute()
+            .block_on();
+
+        assert!(mean(&output, 44, 64) < mean(&image, 44, 64) - 3.0);
+        assert!((mean(&output, 0, 20) - mean(&image, 0, 20)).abs() <= 2.0);
+    }
+
+    #[test]
+    fn edges_are_lifted_like_the_middle() {
+        let image = Image::new(24, 16, Rgba::new(40, 40, 40, 255));
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .shadows_highlights(0.5, 0.5, 6.0)
+            .execute()
+            .block_on();
+
+        let middle = output.pixels[8 * 24 + 12];
+        assert!(middle.r() > 40);
+        for pixel in &output.pixels {
+            assert!(pixel.r().abs_diff(middle.r()) <= 1, "{pixel:?}, {middle:?}");
+        }
+    }
+
+    #[test]
+    fn no_amounts_is_identity() {
+        let image = backlit();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .shadows_highlights(0.0, 0.0, 4.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+}
@@ -0,0 +1,145 @@
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::{
+    util::BufferInitDescriptor, BindGroupDescriptor, BindGroupEntry, BindingResource,
+    ComputePassDescriptor, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, capitalize, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Operation,
+};
+
+const SHARPEN_SHADER: &str = include_str!("shaders/sharpen.wgsl");
+
+impl<'a> Operation<'a> {
+    /// Sharpens the image with a laplacian kernel: each pixel is pushed away from the average of its four neighbors.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - How strong the sharpening is. 0.0 leaves the image untouched, 0.25 is a light sharpening,
+    ///   1.0 is already pretty strong.
+    pub fn sharpen(mut self, amount: f32) -> Self {
+        let name = "sharpen";
+        let capitalized_filter_name = capitalize(name);
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline(name, SHARPEN_SHADER, Bindings::Uniform);
+
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Sharpen settings"),
+            contents: bytemuck::cast_slice(&[amount]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute constants"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+        self.radius += 1;
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    #[test]
+    fn sharpen_flat_image_is_identity() {
+        let image = Image {
+            width: 3,
+            height: 3,
+            pixels: vec![Rgba([100, 150, 200, 255]); 9],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .sharpen(0.5)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+
+    #[test]
+    fn sharpen_enhances_edges() {
+        let image = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([100, 100, 100, 255]),
+                Rgba([100, 100, 100, 255]),
+                Rgba([200, 200, 200, 255]),
+            ],
+        };
+
+        let expected = Image {
+            width: 3,
+            height: 1,
+            pixels: vec![
+                Rgba([100, 100, 100, 255]),
+                Rgba([50, 50, 50, 255]),
+                Rgba([250, 250, 250, 255]),
+            ],
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .sharpen(0.5)
+            .execute()
+            .block_on();
+
+        assert_eq!(expected, output);
+    }
+}
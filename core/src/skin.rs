@@ -0,0 +1,124 @@
+use crate::Operation;
+
+const SMOOTH_SKIN_SHADER: &str = include_str!("shaders/smooth_skin.wgsl");
+
+/// How much more the low frequencies are blurred than the details are separated from them.
+const SMOOTHING: f32 = 3.0;
+
+impl<'a> Operation<'a> {
+    /// Smooths the skin of a portrait with a frequency separation: the image is split between its low frequencies,
+    /// a gaussian blur of `sigma`, and its details, what the blur removed. The low frequencies, where the blotches
+    /// and the uneven tones are, are blurred further, then the untouched details are added back onto them, which
+    /// keeps the texture of the skin.
+    ///
+    /// # Arguments
+    ///
+    /// * `sigma` - The size of the details kept, in pixels, like the pores of the skin. The low frequencies are
+    ///   blurred to 3 times it.
+    /// * `amount` - How much the low frequencies are smoothed, from 0.0, leaving the image untouched, to 1.0.
+    pub fn smooth_skin(self, sigma: f32, amount: f32) -> Self {
+        if amount == 0.0 {
+            return self;
+        }
+
+        self.blurred_pass(
+            "smooth skin",
+            SMOOTH_SKIN_SHADER,
+            bytemuck::bytes_of(&amount),
+            &[sigma, sigma * SMOOTHING],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    /// A fine checkerboard, like the texture of the skin, over soft blotches.
+    fn skin() -> Image {
+        Image::from_fn(64, 64, |x, y| {
+            let (fx, fy) = (x as f32, y as f32);
+            let blotches = 30.0 * (fx / 5.0).sin() * (fy / 6.0).sin();
+            let texture = if (x + y) % 2 == 0 { 12.0 } else { -12.0 };
+            let value = 140.0 + blotches + texture;
+            Rgba::new(value as u8, (value * 0.8) as u8, (value * 0.7) as u8, 255)
+        })
+    }
+
+    fn red(image: &Image, x: u32, y: u32) -> f32 {
+        f32::from(image.pixels[(y * image.width + x) as usize].r())
+    }
+
+    /// The average difference between horizontal neighbors, which the checkerboard dominates.
+    fn texture(image: &Image) -> f32 {
+        let differences = (0..image.height)
+            .flat_map(|y| (0..image.width - 1).map(move |x| (x, y)))
+            .map(|(x, y)| (red(image, x, y) - red(image, x + 1, y)).abs())
+            .collect::<Vec<_>>();
+        differences.iter().sum::<f32>() / differences.len() as f32
+    }
+
+    /// The standard deviation of the averages of the blocks of 2×2 pixels, without the checkerboard.
+    fn blotches(image: &Image) -> f32 {
+        let averages = (0..image.height / 2)
+            .flat_map(|y| (0..image.width / 2).map(move |x| (2 * x, 2 * y)))
+            .map(|(x, y)| {
+                (red(image, x, y)
+                    + red(image, x + 1, y)
+                    + red(image, x, y + 1)
+                    + red(image, x + 1, y + 1))
+                    / 4.0
+            })
+            .collect::<Vec<_>>();
+        let mean = averages.iter().sum::<f32>() / averages.len() as f32;
+        let variance = averages
+            .iter()
+            .map(|average| (average - mean).powi(2))
+            .sum::<f32>()
+            / averages.len() as f32;
+        variance.sqrt()
+    }
+
+    #[test]
+    fn keeps_texture_and_smooths_blotches() {
+        let image = skin();
+        let filters = Filters::new().block_on().unwrap();
+
+        let smoothed = image
+            .operation(&filters)
+            .unwrap()
+            .smooth_skin(2.0, 1.0)
+            .execute()
+            .block_on();
+
+        assert!(
+            texture(&smoothed) > texture(&image) * 0.85,
+            "{} from {}",
+            texture(&smoothed),
+            texture(&image)
+        );
+        assert!(
+            blotches(&smoothed) < blotches(&image) * 0.6,
+            "{} from {}",
+            blotches(&smoothed),
+            blotches(&image)
+        );
+    }
+
+    #[test]
+    fn no_amount_is_identity() {
+        let image = skin();
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .smooth_skin(2.0, 0.0)
+            .execute()
+            .block_on();
+
+        assert_eq!(image, output);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{cache::Bindings, Operation, PixelFormat, Rgba};
+
+const SPLIT_TONE_SHADER: &str = include_str!("shaders/split_tone.wgsl");
+
+/// How close to black or white the crossover between the shadows and the highlights gets at most.
+const MIN_CROSSOVER: f32 = 0.01;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct SplitToneSettings {
+    shadow_color: [f32; 4],
+    highlight_color: [f32; 4],
+    crossover: f32,
+    _padding: [f32; 3],
+}
+
+impl<'a> Operation<'a> {
+    /// Tints the shadows and the highlights towards two different colors, like the teal and orange of a movie
+    /// poster. Each pixel is blended towards the tint color brought to its own luminance, so that only its hue
+    /// changes, fully on black and white and fading out towards the crossover between the shadows and the
+    /// highlights, where the pixels are left untouched. Gray tint colors leave gray pixels as they are.
+    ///
+    /// Grayscale images have no hue to tint and are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `shadow_color` - The color the shadows are tinted towards. Its alpha is ignored.
+    /// * `highlight_color` - The color the highlights are tinted towards. Its alpha is ignored.
+    /// * `balance` - Where the crossover is, from -1.0 to 1.0: 0.0 puts it on the midtones, positive values move it
+    ///   towards black, tinting more of the image with the highlight color, and negative ones towards white.
+    pub fn split_tone(self, shadow_color: Rgba, highlight_color: Rgba, balance: f32) -> Self {
+        if self.format == PixelFormat::Luma {
+            return self;
+        }
+
+        let name = "split tone";
+        let pipeline = self.pipeline(name, SPLIT_TONE_SHADER, Bindings::Uniform);
+        let settings = SplitToneSettings {
+            shadow_color: self.color_values(shadow_color),
+            highlight_color: self.color_values(highlight_color),
+            crossover: (0.5 - 0.5 * balance).clamp(MIN_CROSSOVER, 1.0 - MIN_CROSSOVER),
+            _padding: [0.0; 3],
+        };
+        self.settings_filter(name, &pipeline, bytemuck::bytes_of(&settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, Rgba};
+
+    const TEAL: Rgba = Rgba::new(0, 128, 128, 255);
+    const ORANGE: Rgba = Rgba::new(255, 128, 0, 255);
+
+    /// A ramp of grays from black to white, one per column.
+    fn ramp() -> Image {
+        Image::from_fn(256, 4, |x, _| Rgba::new(x as u8, x as u8, x as u8, 255))
+    }
+
+    /// How far towards blue the pixel of the `value` gray went, relative to its luminance.
+    fn blueness(image: &Image, value: u32) -> f32 {
+        let pixel = image.pixels[value as usize];
+        (f32::from(pixel.b()) - f32::from(pixel.r())) / value as f32
+    }
+
+    fn split_tone(filters: &Filters, shadows: Rgba, highlights: Rgba, balance: f32) -> Image {
+        ramp()
+            .operation(filters)
+            .unwrap()
+            .split_tone(shadows, highlights, balance)
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn tints_shadows_and_highlights_proportionally() {
+        let filters = Filters::new().block_on().unwrap();
+        let toned = split_tone(&filters, TEAL, ORANGE, 0.0);
+
+        // The darker the shadow, the closer to teal, with more blue than red.
+        assert!(blueness(&toned, 30) > blueness(&toned, 60));
+        assert!(blueness(&toned, 60) > blueness(&toned, 100));
+        assert!(blueness(&toned, 100) > 0.0);
+        // The brighter the highlight, the closer to orange, with more red than blue.
+        assert!(blueness(&toned, 160) < 0.0);
+        assert!(blueness(&toned, 220) < blueness(&toned, 160));
+        // Around the crossover, the grays are left untouched.
+        let middle = toned.pixels[128];
+        assert!(middle.r().abs_diff(middle.b()) <= 1, "{middle:?}");
+    }
+
+    #[test]
+    fn balance_moves_the_crossover() {
+        let filters = Filters::new().block_on().unwrap();
+        let toned = split_tone(&filters, TEAL, ORANGE, 0.5);
+
+        assert!(blueness(&toned, 40) > 0.0);
+        assert!(blueness(&toned, 100) < 0.0);
+    }
+
+    #[test]
+    fn gray_tints_are_identity() {
+        let filters = Filters::new().block_on().unwrap();
+        let gray = Rgba::new(128, 128, 128, 255);
+
+        assert_eq!(ramp(), split_tone(&filters, gray, gray, 0.3));
+    }
+}
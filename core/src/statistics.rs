@@ -0,0 +1,316 @@
+use std::mem;
+
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindingResource, BufferDescriptor, BufferUsages,
+    ComputePassDescriptor, TextureViewDescriptor,
+};
+
+use crate::{cache::Bindings, wait_for_mapping, Image, Operation, PixelFormat, Rgba};
+
+const STATISTICS_SHADER: &str = include_str!("shaders/statistics.wgsl");
+
+/// The width and height of the block of pixels each workgroup of the statistics shader reduces.
+const BLOCK_SIZE: u32 = 64;
+
+/// The sum, minimum and maximum of each channel, over a block of pixels, as written by the statistics shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct Partial {
+    sum: [f32; 4],
+    min: [f32; 4],
+    max: [f32; 4],
+}
+
+/// The statistics of an image, see [`Operation::statistics`]. Channels are in RGBA order, with values from 0 to 1,
+/// or above 1 for HDR images.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageStats {
+    /// The average of each channel.
+    pub mean: [f32; 4],
+    /// The smallest value of each channel.
+    pub min: [f32; 4],
+    /// The largest value of each channel.
+    pub max: [f32; 4],
+    /// The luminance of the average color, weighting the channels like [`Operation::grayscale`].
+    pub luminance: f32,
+}
+
+impl ImageStats {
+    /// The average color, rounded to 8 bits.
+    pub fn mean_rgba(&self) -> Rgba {
+        Rgba(
+            self.mean
+                .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8),
+        )
+    }
+}
+
+impl<'a> Operation<'a> {
+    /// Computes the average, smallest and largest value of each channel of the current image on the gpu, reading
+    /// back a few values per block of 64×64 pixels rather than the whole image. Like for the filters, the values
+    /// are the decoded linear ones after [`Operation::assume_srgb`]. Single channel images report their value in
+    /// red, green and blue, with an opaque alpha.
+    ///
+    /// The passes recorded so far are submitted, and the operation can be continued afterwards, like to pick an
+    /// adjustment from the statistics.
+    pub async fn statistics(&mut self) -> ImageStats {
+        let name = "statistics";
+        let (width, height) = self.dimensions();
+        let groups = (width.div_ceil(BLOCK_SIZE), height.div_ceil(BLOCK_SIZE));
+        let size = u64::from(groups.0 * groups.1) * mem::size_of::<Partial>() as u64;
+
+        let pipeline = self.pipeline(name, STATISTICS_SHADER, Bindings::Derived);
+        let partials = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Statistics partials"),
+            size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Statistics readback"),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Statistics bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: partials.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Statistics pass"),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(groups.0, groups.1, 1);
+        }
+        self.end_pass(pass);
+        self.encoder
+            .copy_buffer_to_buffer(&partials, 0, &readback, 0, size);
+
+        self.submit_recorded();
+        wait_for_mapping(self.device, &readback).await;
+
+        let mapped = readback.slice(..).get_mapped_range();
+        let stats = reduce(
+            bytemuck::cast_slice(&mapped),
+            u64::from(width) * u64::from(height),
+        );
+        drop(mapped);
+        readback.unmap();
+
+        if self.format == PixelFormat::Luma {
+            let gray = |[value, ..]: [f32; 4]| [value, value, value, 1.0];
+            ImageStats {
+                mean: gray(stats.mean),
+                min: gray(stats.min),
+                max: gray(stats.max),
+                luminance: stats.mean[0],
+            }
+        } else {
+            stats
+        }
+    }
+}
+
+/// Combines the partial results of the blocks, summing in double precision so that large images keep an exact mean.
+fn reduce(partials: &[Partial], pixel_count: u64) -> ImageStats {
+    let mut sum = [0.0f64; 4];
+    let mut min = [f32::INFINITY; 4];
+    let mut max = [f32::NEG_INFINITY; 4];
+    for partial in partials {
+        for channel in 0..4 {
+            sum[channel] += f64::from(partial.sum[channel]);
+            min[channel] = min[channel].min(partial.min[channel]);
+            max[channel] = max[channel].max(partial.max[channel]);
+        }
+    }
+
+    let mean = sum.map(|channel| (channel / pixel_count.max(1) as f64) as f32);
+    ImageStats {
+        mean,
+        min,
+        max,
+        luminance: 0.299 * mean[0] + 0.587 * mean[1] + 0.114 * mean[2],
+    }
+}
+
+impl Image {
+    /// Finds the `count` colors that best represent the image by median cut: the colors are split in two at the
+    /// median of the channel they spread the most over, again and again, and each group is averaged. The colors
+    /// come most common first, and there are fewer of them when the image has fewer distinct ones. Transparent
+    /// pixels are left out, and so is the alpha of the others.
+    pub fn dominant_colors(&self, count: usize) -> Vec<Rgba> {
+        let mut colors = self
+            .pixels
+            .iter()
+            .filter(|pixel| pixel.a() > 0)
+            .map(|&Rgba([r, g, b, _])| [r, g, b])
+            .collect::<Vec<_>>();
+        if colors.is_empty() || count == 0 {
+            return Vec::new();
+        }
+
+        // The boxes are ranges of `colors`, sorted along their widest channel when split.
+        let mut boxes = Vec::with_capacity(count.min(colors.len()));
+        boxes.push(0..colors.len());
+        while boxes.len() < count {
+            let widest = boxes
+                .iter()
+                .enumerate()
+                .map(|(index, range)| {
+                    let (channel, spread) = widest_channel(&colors[range.clone()]);
+                    (index, channel, spread)
+                })
+                .max_by_key(|&(_, _, spread)| spread);
+            let Some((index, channel, spread)) = widest else {
+                break;
+            };
+            if spread == 0 {
+                break;
+            }
+
+            let range = boxes.swap_remove(index);
+            colors[range.clone()].sort_unstable_by_key(|color| color[channel]);
+            // Splits before the median value, or after it when it is the smallest one, so that a color never
+            // ends up in two boxes.
+            let sorted = &colors[range.clone()];
+            let median = sorted[sorted.len() / 2][channel];
+            let below = sorted.partition_point(|color| color[channel] < median);
+            let split = if below > 0 {
+                below
+            } else {
+                sorted.partition_point(|color| color[channel] <= median)
+            };
+            boxes.push(range.start..range.start + split);
+            boxes.push(range.start + split..range.end);
+        }
+
+        boxes.sort_by_key(|range| std::cmp::Reverse(range.len()));
+        boxes
+            .into_iter()
+            .map(|range| {
+                let length = range.len() as u64;
+                let mut sum = [0u64; 3];
+                for color in &colors[range] {
+                    for (total, &channel) in sum.iter_mut().zip(color) {
+                        *total += u64::from(channel);
+                    }
+                }
+                let [r, g, b] = sum.map(|total| ((total + length / 2) / length) as u8);
+                Rgba([r, g, b, u8::MAX])
+            })
+            .collect()
+    }
+}
+
+/// The channel over which `colors` spread the most, and how far apart its smallest and largest values are.
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), color| {
+                (min.min(color[channel]), max.max(color[channel]))
+            });
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, spread)| spread)
+        .expect("There are three channels")
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, ImageLuma, Rgba};
+
+    #[test]
+    fn solid_color() {
+        let color = Rgba::new(200, 100, 50, 255);
+        // Larger than a block, and not a multiple of its size, so that some invocations are out of the image.
+        let image = Image::new(150, 70, color);
+        let filters = Filters::new().block_on().unwrap();
+
+        let stats = image.operation(&filters).unwrap().statistics().block_on();
+
+        let to_bytes =
+            |channels: [f32; 4]| Rgba(channels.map(|channel| (channel * 255.0).round() as u8));
+        assert_eq!(color, to_bytes(stats.mean));
+        assert_eq!(color, to_bytes(stats.min));
+        assert_eq!(color, to_bytes(stats.max));
+        assert_eq!(color, stats.mean_rgba());
+        assert!((stats.luminance - color.luminance()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn two_colors() {
+        // The left quarter dark, the rest bright, with both colors in every block along the top.
+        let image = Image::from_fn(100, 80, |x, _| {
+            if x < 25 {
+                Rgba::new(10, 200, 0, 255)
+            } else {
+                Rgba::new(250, 20, 100, 128)
+            }
+        });
+        let filters = Filters::new().block_on().unwrap();
+
+        let mut operation = image.operation(&filters).unwrap();
+        let stats = operation.statistics().block_on();
+        let inverted = operation.inverse().execute().block_on();
+
+        let to_bytes = |channels: [f32; 4]| channels.map(|channel| (channel * 255.0).round() as u8);
+        assert_eq!([10, 20, 0, 128], to_bytes(stats.min));
+        assert_eq!([250, 200, 100, 255], to_bytes(stats.max));
+        let expected_mean = [190.0, 65.0, 75.0, 159.75].map(|channel| channel / 255.0);
+        for (mean, expected) in stats.mean.iter().zip(expected_mean) {
+            assert!((mean - expected).abs() < 1e-3, "{:?}", stats.mean);
+        }
+        assert_eq!(Rgba::new(245, 55, 255, 255), inverted.pixels[0]);
+    }
+
+    #[test]
+    fn luma_statistics() {
+        let luma = ImageLuma {
+            width: 20,
+            height: 10,
+            pixels: (0..200).map(|index| (index % 20) as u8 * 10).collect(),
+        };
+        let filters = Filters::new().block_on().unwrap();
+
+        let stats = luma.operation(&filters).unwrap().statistics().block_on();
+
+        assert_eq!([0.0, 0.0, 0.0, 1.0], stats.min);
+        let max = 190.0 / 255.0;
+        assert_eq!([max, max, max, 1.0], stats.max);
+    }
+
+    #[test]
+    fn dominant_colors() {
+        let red = Rgba::new(220, 10, 10, 255);
+        let blue = Rgba::new(10, 10, 220, 255);
+        let image = Image::from_fn(10, 10, |x, y| match (x, y) {
+            (0, 0) => Rgba::new(0, 255, 0, 0),
+            (x, _) if x < 7 => red,
+            _ => blue,
+        });
+
+        assert_eq!(vec![red, blue], image.dominant_colors(2));
+        // Only two colors are visible, so asking for more doesn't make any up.
+        assert_eq!(vec![red, blue], image.dominant_colors(5));
+        assert_eq!(1, image.dominant_colors(1).len());
+        assert!(image.dominant_colors(0).is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{BatchProgress, CancellationToken, Filters, FiltersError, Image, Operation, Rgba};
+
+impl Filters {
+    /// Applies a chain of filters to an image tile by tile, which allows processing images larger than
+    /// the biggest texture the gpu supports. Each tile is extended by `overlap` pixels on every side
+    /// before being filtered, so that filters looking at neighboring pixels, like blurs, don't show seams.
+    ///
+    /// Only filters that keep every pixel in place can be used: flips, resizes, crops and the like
+    /// return [`FiltersError::NotTileable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - The image to process.
+    /// * `tile_size` - The width and height of each tile, without the overlap.
+    /// * `overlap` - How many pixels each tile borrows from its neighbors, which must be at least
+    ///   the radius of the filter chain.
+    /// * `f` - The filter chain, applied to each tile.
+    pub async fn process_tiled<F>(
+        &self,
+        image: &Image,
+        tile_size: u32,
+        overlap: u32,
+        f: F,
+    ) -> Result<Image, FiltersError>
+    where
+        F: for<'a> FnMut(Operation<'a>) -> Operation<'a>,
+    {
+        self.process_tiled_with_progress(
+            image,
+            tile_size,
+            overlap,
+            f,
+            &CancellationToken::new(),
+            |_| {},
+        )
+        .await
+    }
+
+    /// Like [`Filters::process_tiled`], but reports each finished tile to `on_progress`, and stops as soon as
+    /// `token` is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// [`FiltersError::Cancelled`] if `token` was cancelled before the last tile was done, along with the errors
+    /// of [`Filters::process_tiled`].
+    pub async fn process_tiled_with_progress<F, P>(
+        &self,
+        image: &Image,
+        tile_size: u32,
+        overlap: u32,
+        mut f: F,
+        token: &CancellationToken,
+        on_progress: P,
+    ) -> Result<Image, FiltersError>
+    where
+        F: for<'a> FnMut(Operation<'a>) -> Operation<'a>,
+        P: Fn(BatchProgress),
+    {
+        if tile_size == 0 {
+            return Err(FiltersError::UnsupportedSize {
+                width: tile_size,
+                height: tile_size,
+            });
+        }
+        image.check_pixel_count()?;
+
+        let total =
+            image.width.div_ceil(tile_size) as usize * image.height.div_ceil(tile_size) as usize;
+        let mut completed = 0;
+        let mut pixels = vec![Rgba([0, 0, 0, 0]); image.pixels.len()];
+        for tile_y in (0..image.height).step_by(tile_size as usize) {
+            for tile_x in (0..image.width).step_by(tile_size as usize) {
+                let tile_width = tile_size.min(image.width - tile_x);
+                let tile_height = tile_size.min(image.height - tile_y);
+
+                let left = tile_x.saturating_sub(overlap);
+                let top = tile_y.saturating_sub(overlap);
+                let right = (tile_x + tile_width)
+                    .saturating_add(overlap)
+                    .min(image.width);
+                let bottom = (tile_y + tile_height)
+                    .saturating_add(overlap)
+                    .min(image.height);
+
+                token.check()?;
+                let region = image.sub_image(left, top, right - left, bottom - top)?;
+                let operation = f(region.operation(self)?);
+                if !operation.tileable {
+                    return Err(FiltersError::NotTileable);
+                }
+                if operation.radius > overlap {
+                    return Err(FiltersError::TileOverlapTooSmall {
+                        overlap,
+                        radius: operation.radius,
+                    });
+                }
+                let output = operation.execute().await;
+
+                for row in 0..tile_height {
+                    let source = ((tile_y - top + row) * output.width + tile_x - left) as usize;
+                    let destination = ((tile_y + row) * image.width + tile_x) as usize;
+                    pixels[destination..destination + tile_width as usize]
+                        .copy_from_slice(&output.pixels[source..source + tile_width as usize]);
+                }
+
+                completed += 1;
+                on_progress(BatchProgress {
+                    index: completed - 1,
+                    completed,
+                    total,
+                });
+            }
+        }
+
+        Ok(Image {
+            width: image.width,
+            height: image.height,
+            pixels,
+        })
+    }
+}
+
+/// Copies a rectangular region of an image, which must fit in the image.
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use pollster::FutureExt;
+
+    use crate::{CancellationToken, Filters, FiltersError, Image, Rgba};
+
+    fn pattern(width: u32, height: u32) -> Image {
+        let pixels = (0..height)
+            .flat_map(|y| {
+                (0..width).map(move |x| {
+                    Rgba([
+                        ((x * 37 + y * 11) % 256) as u8,
+                        ((x * 5 + y * 71) % 256) as u8,
+                        ((x * y * 13) % 256) as u8,
+                        255,
+                    ])
+                })
+            })
+            .collect();
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn tiled_point_filters_match() {
+        let image = pattern(7, 5);
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .grayscale()
+            .inverse()
+            .execute()
+            .block_on();
+        let output = filters
+            .process_tiled(&image, 3, 0, |operation| operation.grayscale().inverse())
+            .block_on()
+            .unwrap();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn tiled_blur_matches() {
+        let image = pattern(20, 13);
+        let filters = Filters::new().block_on().unwrap();
+
+        let expected = image
+            .operation(&filters)
+            .unwrap()
+            .gaussian_blur(1.0)
+            .execute()
+            .block_on();
+        let output = filters
+            .process_tiled(&image, 6, 3, |operation| operation.gaussian_blur(1.0))
+            .block_on()
+            .unwrap();
+
+        assert!(expected.approx_eq(&output, 1));
+    }
+
+    #[test]
+    fn tiled_rejects_geometric_filters() {
+        let image = pattern(4, 4);
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = filters
+            .process_tiled(&image, 2, 0, |operation| operation.hflip())
+            .block_on();
+
+        assert!(matches!(result, Err(FiltersError::NotTileable)));
+    }
+
+    #[test]
+    fn tiled_overlap_too_small() {
+        let image = pattern(4, 4);
+        let filters = Filters::new().block_on().unwrap();
+
+        let result = filters
+            .process_tiled(&image, 2, 1, |operation| operation.gaussian_blur(1.0))
+            .block_on();
+
+        assert!(matches!(
+            result,
+            Err(FiltersError::TileOverlapTooSmall {
+                overlap: 1,
+                radius: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn tiled_reports_each_tile() {
+        let image = pattern(20, 13);
+        let filters = Filters::new().block_on().unwrap();
+        let progress = RefCell::new(Vec::new());
+
+        filters
+            .process_tiled_with_progress(
+                &image,
+                6,
+                0,
+                |operation| operation.inverse(),
+                &CancellationToken::new(),
+                |update| progress.borrow_mut().push((update.completed, update.total)),
+            )
+            .block_on()
+            .unwrap();
+
+        assert_eq!(
+            (1..=12)
+                .map(|completed| (completed, 12))
+                .collect::<Vec<_>>(),
+            progress.into_inner()
+        );
+    }
+
+    #[test]
+    fn tiled_cancelled() {
+        let image = pattern(20, 13);
+        let filters = Filters::new().block_on().unwrap();
+        let token = CancellationToken::new();
+        let completed = RefCell::new(0);
+
+        let result = filters
+            .process_tiled_with_progress(
+                &image,
+                6,
+                0,
+                |operation| operation.inverse(),
+                &token,
+                |update| {
+                    *completed.borrow_mut() = update.completed;
+                    token.cancel();
+                },
+            )
+            .block_on();
+
+        assert!(matches!(result, Err(FiltersError::Cancelled)));
+        assert_eq!(1, completed.into_inner());
+    }
+}
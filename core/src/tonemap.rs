@@ -0,0 +1,146 @@
+use wgpu::util::DeviceExt;
+use wgpu::BufferUsages;
+use wgpu::{
+    util::BufferInitDescriptor, BindGroupDescriptor, BindGroupEntry, BindingResource,
+    ComputePassDescriptor, TextureViewDescriptor,
+};
+
+use crate::{
+    cache::Bindings, capitalize, compute_work_group_count, pool::STORAGE_TEXTURE_USAGES, Operation,
+};
+
+const TONEMAP_SHADER: &str = include_str!("shaders/tonemap.wgsl");
+
+/// How [`Operation::tonemap`] brings colors beyond 1.0 back to a displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// `c / (1 + c)`, which maps 1.0 to 0.5 and never quite reaches 1.0.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve, with more contrast than Reinhard, reaching 1.0 around 16.0.
+    Aces,
+}
+
+impl<'a> Operation<'a> {
+    /// Maps the red, green and blue channels from 0.0 to infinity back to between 0.0 and 1.0.
+    /// Mostly useful with [`crate::PixelFormat::Float`], before reading back an 8 or 16-bit image.
+    ///
+    /// # Arguments
+    ///
+    /// * `operator` - The curve used for the mapping.
+    pub fn tonemap(mut self, operator: ToneMapOperator) -> Self {
+        let name = "tonemap";
+        let capitalized_filter_name = capitalize(name);
+
+        let output_texture = self
+            .pool
+            .take(self.device, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        let pipeline = self.pipeline(name, TONEMAP_SHADER, Bindings::Uniform);
+
+        let tone_map: u32 = match operator {
+            ToneMapOperator::Reinhard => 0,
+            ToneMapOperator::Aces => 1,
+        };
+        let settings = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Tonemap settings"),
+            contents: bytemuck::cast_slice(&[tone_map]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let compute_constants = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Compute constants"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: settings.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Texture bind group"),
+            layout: &pipeline.get_bind_group_layout(1),
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &self.texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &output_texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+            ],
+        });
+
+        let pass = self.begin_pass(name);
+        {
+            let (dispatch_with, dispatch_height) = compute_work_group_count(
+                (self.texture_size.width, self.texture_size.height),
+                self.pipelines.workgroup_size(name),
+            );
+            let mut compute_pass = self.encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some(format!("{} pass", capitalized_filter_name).as_str()),
+            });
+            compute_pass.set_pipeline(&pipeline);
+            compute_pass.set_bind_group(0, &compute_constants, &[]);
+            compute_pass.set_bind_group(1, &texture_bind_group, &[]);
+            compute_pass.dispatch_workgroups(dispatch_with, dispatch_height, 1);
+        }
+        self.end_pass(pass);
+
+        self.set_texture(output_texture, self.texture_size, STORAGE_TEXTURE_USAGES);
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use crate::{Filters, Image, ToneMapOperator};
+
+    #[test]
+    fn reinhard_maps_one_to_half() {
+        let image = Image::from_f32(2, 1, vec![1.0, 0.0, 3.0, 1.0, 0.25, 1.0, 1.0, 0.5]);
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .tonemap(ToneMapOperator::Reinhard)
+            .execute_f32()
+            .block_on();
+
+        let expected = [0.5, 0.0, 0.75, 1.0, 0.2, 0.5, 0.5, 0.5];
+        for (expected, actual) in expected.iter().zip(&output.pixels) {
+            assert!((expected - actual).abs() < 0.001, "{expected} != {actual}");
+        }
+    }
+
+    #[test]
+    fn aces_stays_in_range() {
+        let image = Image::from_f32(
+            3,
+            1,
+            vec![
+                0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 50.0, 50.0, 50.0, 1.0,
+            ],
+        );
+        let filters = Filters::new().block_on().unwrap();
+
+        let output = image
+            .operation(&filters)
+            .unwrap()
+            .tonemap(ToneMapOperator::Aces)
+            .execute_f32()
+            .block_on();
+
+        assert!(output.pixels[0].abs() < 0.001);
+        assert!((output.pixels[4] - 0.8).abs() < 0.01);
+        assert_eq!(1.0, output.pixels[8]);
+    }
+}
@@ -0,0 +1,291 @@
+use wgpu::Limits;
+
+use crate::Filters;
+
+/// The sizes of the workgroups the shaders are dispatched in, see [`Filters::with_workgroups`].
+///
+/// Some adapters run much faster with other sizes than the defaults, which are a safe middle ground.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkgroupConfig {
+    /// The width and height of the workgroups of the filters working on each pixel, like grayscale or resize.
+    /// Defaults to 16×16.
+    pub point: (u32, u32),
+    /// The length of the workgroups of the blur passes, which work along a row or a column. Defaults to 128.
+    pub line: u32,
+    /// Whether to time a few sizes the first time each filter without settings, like grayscale, is used, and keep
+    /// the fastest instead of `point`. Defaults to false. Ignored on wasm32, where the gpu can't be waited for.
+    pub auto_tune: bool,
+}
+
+impl Default for WorkgroupConfig {
+    fn default() -> Self {
+        Self {
+            point: (16, 16),
+            line: 128,
+            auto_tune: false,
+        }
+    }
+}
+
+impl WorkgroupConfig {
+    /// Shrinks the sizes to what a device with `limits` supports, keeping them at least 1.
+    pub(crate) fn clamp(self, limits: &Limits) -> Self {
+        Self {
+            point: clamp_size(self.point, limits),
+            line: self.line.clamp(
+                1,
+                limits
+                    .max_compute_workgroup_size_x
+                    .min(limits.max_compute_invocations_per_workgroup),
+            ),
+            auto_tune: self.auto_tune,
+        }
+    }
+}
+
+/// Clamps a point workgroup size to the limits of each dimension, then halves its largest dimension until the
+/// workgroup has few enough invocations.
+pub(crate) fn clamp_size((width, height): (u32, u32), limits: &Limits) -> (u32, u32) {
+    let mut width = width.clamp(1, limits.max_compute_workgroup_size_x);
+    let mut height = height.clamp(1, limits.max_compute_workgroup_size_y);
+    while width * height > limits.max_compute_invocations_per_workgroup {
+        if width >= height {
+            width /= 2;
+        } else {
+            height /= 2;
+        }
+    }
+    (width, height)
+}
+
+impl Filters {
+    /// Compiles the shaders for the workgroup sizes of `config`, clamped to the limits of the device.
+    /// The pipelines built so far are dropped.
+    pub fn with_workgroups(mut self, config: WorkgroupConfig) -> Self {
+        let config = config.clamp(&self.device.limits());
+        self.pipelines.set_workgroups(config);
+        self
+    }
+
+    /// The workgroup sizes in use, once clamped to the limits of the device.
+    pub fn workgroups(&self) -> WorkgroupConfig {
+        self.pipelines.workgroups()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod tuning {
+    use std::time::{Duration, Instant};
+
+    use wgpu::{
+        BindGroupDescriptor, BindGroupEntry, BindingResource, CommandEncoderDescriptor,
+        ComputePassDescriptor, ComputePipeline, Extent3d, Maintain, TextureDescriptor,
+        TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+    };
+
+    use super::clamp_size;
+    use crate::{cache::Bindings, compute_work_group_count, Operation};
+
+    /// The sizes tried by auto-tuning, along with the configured one.
+    const CANDIDATES: [(u32, u32); 5] = [(8, 8), (16, 16), (32, 8), (8, 32), (32, 32)];
+    /// The width and height of the image the candidates are timed on.
+    const TUNING_SIZE: u32 = 512;
+    /// How many times each candidate runs per measurement.
+    const TUNING_DISPATCHES: u32 = 4;
+
+    impl<'a> Operation<'a> {
+        /// Times the filter `name`, whose shader only binds its input and output textures, with each candidate
+        /// workgroup size, and keeps the fastest for the pipelines built afterwards. Does nothing if auto-tuning is
+        /// off or if the filter was already tuned.
+        pub(crate) fn tune_workgroup_size(&self, name: &'static str, shader_string: &str) {
+            let workgroups = self.pipelines.workgroups();
+            if !workgroups.auto_tune || self.pipelines.is_tuned(name) {
+                return;
+            }
+
+            let limits = self.device.limits();
+            let mut candidates = vec![workgroups.point];
+            for candidate in CANDIDATES.map(|size| clamp_size(size, &limits)) {
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+
+            let size = Extent3d {
+                width: TUNING_SIZE,
+                height: TUNING_SIZE,
+                depth_or_array_layers: 1,
+            };
+            let texture = |format, usage| {
+                self.device
+                    .create_texture(&TextureDescriptor {
+                        label: Some("Tuning texture"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format,
+                        usage,
+                    })
+                    .create_view(&TextureViewDescriptor::default())
+            };
+            let input = texture(TextureFormat::Rgba8Unorm, TextureUsages::TEXTURE_BINDING);
+            let output = texture(
+                self.pipelines.texture_format(self.format),
+                TextureUsages::STORAGE_BINDING,
+            );
+
+            let run = |pipeline: &ComputePipeline, workgroup_size| -> Duration {
+                let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Tuning bind group"),
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(&input),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::TextureView(&output),
+                        },
+                    ],
+                });
+                let start = Instant::now();
+                let mut encoder = self
+                    .device
+                    .create_command_encoder(&CommandEncoderDescriptor { label: None });
+                {
+                    let (dispatch_width, dispatch_height) =
+                        compute_work_group_count((TUNING_SIZE, TUNING_SIZE), workgroup_size);
+                    let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                        label: Some("Tuning pass"),
+                    });
+                    compute_pass.set_pipeline(pipeline);
+                    compute_pass.set_bind_group(0, &bind_group, &[]);
+                    for _ in 0..TUNING_DISPATCHES {
+                        compute_pass.dispatch_workgroups(dispatch_width, dispatch_height, 1);
+                    }
+                }
+                self.queue.submit(Some(encoder.finish()));
+                self.device.poll(Maintain::Wait);
+                start.elapsed()
+            };
+
+            let fastest = candidates
+                .into_iter()
+                .map(|candidate| {
+                    let pipeline = self.pipelines.build_with_workgroup_size(
+                        self.device,
+                        name,
+                        shader_string,
+                        self.format,
+                        Bindings::Textures,
+                        candidate,
+                    );
+                    // The first run includes the compilation of the pipeline by the driver.
+                    run(&pipeline, candidate);
+                    (run(&pipeline, candidate), candidate)
+                })
+                .min()
+                .map(|(_, candidate)| candidate)
+                .unwrap_or(workgroups.point);
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(name, ?fastest, "Tuned workgroup size");
+            self.pipelines.set_tuned_workgroup_size(name, fastest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt;
+
+    use super::WorkgroupConfig;
+    use crate::{Filters, Image, Resize, Rgba};
+
+    fn test_image() -> Image {
+        Image {
+            width: 37,
+            height: 23,
+            pixels: (0..37 * 23u32)
+                .map(|index| {
+                    let value = index * 37 % 256;
+                    Rgba([value as u8, (255 - value) as u8, (index * 7) as u8, 200])
+                })
+                .collect(),
+        }
+    }
+
+    fn process(filters: &Filters, image: &Image) -> Image {
+        image
+            .operation(filters)
+            .unwrap()
+            .grayscale()
+            .brightness(0.1)
+            .sharpen(1.0)
+            .box_blur(5)
+            .gaussian_blur(2.0)
+            .resize((50, 31), Resize::Linear)
+            .unwrap()
+            .resize((20, 12), Resize::Lanczos3)
+            .unwrap()
+            .hflip()
+            .execute()
+            .block_on()
+    }
+
+    #[test]
+    fn workgroup_sizes_give_same_result() {
+        let image = test_image();
+        let mut filters = Filters::new().block_on().unwrap();
+        let expected = process(&filters, &image);
+
+        for (point, line) in [((8, 8), 64), ((16, 16), 128), ((32, 8), 256)] {
+            filters = filters.with_workgroups(WorkgroupConfig {
+                point,
+                line,
+                auto_tune: false,
+            });
+            assert_eq!(point, filters.workgroups().point);
+
+            assert_eq!(expected, process(&filters, &image), "{point:?}");
+        }
+    }
+
+    #[test]
+    fn workgroup_sizes_are_clamped() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+        let expected = process(&filters, &image);
+        let limits = filters.device.limits();
+
+        let filters = filters.with_workgroups(WorkgroupConfig {
+            point: (4096, 0),
+            line: 1 << 20,
+            auto_tune: false,
+        });
+        let WorkgroupConfig { point, line, .. } = filters.workgroups();
+
+        assert!(point.0 <= limits.max_compute_workgroup_size_x);
+        assert_eq!(1, point.1);
+        assert!(line <= limits.max_compute_invocations_per_workgroup);
+        assert_eq!(expected, process(&filters, &image));
+    }
+
+    #[test]
+    fn auto_tune_keeps_result() {
+        let image = test_image();
+        let filters = Filters::new().block_on().unwrap();
+        let expected = process(&filters, &image);
+
+        let filters = filters.with_workgroups(WorkgroupConfig {
+            auto_tune: true,
+            ..Default::default()
+        });
+
+        assert_eq!(expected, process(&filters, &image));
+        assert!(filters.pipelines.is_tuned("grayscale"));
+        assert!(!filters.pipelines.is_tuned("sharpen"));
+    }
+}
@@ -40,13 +40,13 @@ fn main() -> Result<()> {
         GAUSSIAN_BLUR,
     ];
 
-    let filters = Filters::new().block_on();
+    let filters = Filters::new().block_on()?;
 
     for filter in filter_list {
         let output = output_file(input, filter);
 
         let now = Instant::now();
-        let mut operation = image.operation(&filters);
+        let mut operation = image.operation(&filters)?;
 
         operation = match filter {
             GRAYSCALE => operation.grayscale(),
@@ -55,7 +55,7 @@ fn main() -> Result<()> {
             VERTICAL_FLIP => operation.vflip(),
             HALF => {
                 let (width, height) = operation.dimensions();
-                operation.resize((width / 2, height / 2), Resize::Linear)
+                operation.resize((width / 2, height / 2), Resize::Linear)?
             }
             BOX_BLUR => operation.box_blur(9),
             GAUSSIAN_BLUR => operation.gaussian_blur(3.0),
@@ -114,6 +114,9 @@ mod tests {
     fn output_file_name_no_specified() {
         let file_path = output_file("sunflower.png", "grayscale");
 
-        assert_eq!("sunflower_grayscale.png", file_path.to_string_lossy());
+        assert_eq!(
+            "output/sunflower_grayscale.png",
+            file_path.to_string_lossy()
+        );
     }
 }